@@ -1,5 +1,10 @@
 use crate::UniffiCustomTypeConverter;
 
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
 use serde::{Deserialize, Serialize};
 use ssi::{claims::data_integrity::CryptosuiteString, crypto::Algorithm};
 use uniffi::deps::anyhow;
@@ -22,6 +27,68 @@ impl From<CredentialType> for String {
     }
 }
 
+/// Human-readable names of well-known mdoc doctypes, SD-JWT VCTs and VC types.
+const WELL_KNOWN_DISPLAY_NAMES: &[(&str, &str)] = &[
+    ("org.iso.18013.5.1.mDL", "Mobile Driving License"),
+    ("org.iso.23220.photoid.1", "Photo ID"),
+    ("org.iso.7367.1.mVRC", "Mobile Vehicle Registration"),
+    ("eu.europa.ec.eudi.pid.1", "EU Personal ID"),
+    ("urn:eu.europa.ec.eudi:pid:1", "EU Personal ID"),
+    ("OpenBadgeCredential", "Open Badge"),
+];
+
+/// Display names registered by the host, taking precedence over the well-known names.
+fn registered_display_names() -> &'static RwLock<HashMap<String, String>> {
+    static NAMES: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+    NAMES.get_or_init(Default::default)
+}
+
+impl CredentialType {
+    /// A human-readable name for the credential type, e.g. "Mobile Driving License" for
+    /// `org.iso.18013.5.1.mDL`.
+    ///
+    /// Names registered with [register_credential_type_display_name] are used first, then
+    /// well-known doctypes and VCTs. For VC types joined with `+`, the first type with a known
+    /// name is used. Falls back to the raw type.
+    pub fn display_name(&self) -> String {
+        let registered = registered_display_names()
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let lookup = |ty: &str| {
+            registered.get(ty).cloned().or_else(|| {
+                WELL_KNOWN_DISPLAY_NAMES
+                    .iter()
+                    .find(|(known, _)| *known == ty)
+                    .map(|(_, name)| name.to_string())
+            })
+        };
+
+        lookup(&self.0)
+            .or_else(|| self.0.split('+').find_map(lookup))
+            .unwrap_or_else(|| self.0.clone())
+    }
+}
+
+/// A human-readable name for a credential type. See [CredentialType::display_name].
+#[uniffi::export]
+pub fn credential_type_display_name(credential_type: CredentialType) -> String {
+    credential_type.display_name()
+}
+
+/// Register the display name of a credential type, e.g. a doctype or VCT specific to the
+/// host's issuer, or to override a well-known name.
+#[uniffi::export]
+pub fn register_credential_type_display_name(
+    credential_type: CredentialType,
+    display_name: String,
+) {
+    registered_display_names()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(credential_type.0, display_name);
+}
+
 uniffi::custom_type!(Uuid, String);
 impl UniffiCustomTypeConverter for Uuid {
     type Builtin = String;
@@ -114,3 +181,33 @@ impl UniffiCustomTypeConverter for CryptosuiteString {
         suite.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credential_type_display_names() {
+        assert_eq!(
+            CredentialType("org.iso.18013.5.1.mDL".into()).display_name(),
+            "Mobile Driving License"
+        );
+        assert_eq!(
+            CredentialType("VerifiableCredential+OpenBadgeCredential".into()).display_name(),
+            "Open Badge"
+        );
+        assert_eq!(
+            CredentialType("com.example.membership.1".into()).display_name(),
+            "com.example.membership.1"
+        );
+
+        register_credential_type_display_name(
+            CredentialType("com.example.membership.1".into()),
+            "Membership Card".into(),
+        );
+        assert_eq!(
+            credential_type_display_name(CredentialType("com.example.membership.1".into())),
+            "Membership Card"
+        );
+    }
+}
@@ -1,9 +1,12 @@
 use super::{
     status::{BitStringStatusListResolver, Status, StatusListError},
-    Credential, CredentialEncodingError, CredentialFormat, VcdmVersion,
+    Credential, CredentialEncodingError, CredentialFormat, CredentialVerificationError,
+    VcdmVersion,
 };
 use crate::{
     crypto::KeyAlias,
+    did::DidResolutionPolicy,
+    oid4vci::context_loader_from_map,
     oid4vp::{
         error::OID4VPError,
         presentation::{CredentialPresentation, PresentationOptions},
@@ -12,7 +15,7 @@ use crate::{
     CredentialType,
 };
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use openid4vp::{
     core::{
@@ -26,7 +29,10 @@ use ssi::status::bitstring_status_list::BitstringStatusListEntry;
 use ssi::{
     claims::vc::{
         syntax::{IdOr, NonEmptyObject, NonEmptyVec},
-        v1::{Credential as _, JsonPresentation as JsonPresentationV1},
+        v1::{
+            data_integrity::any_credential_from_json_str, Credential as _,
+            JsonPresentation as JsonPresentationV1,
+        },
         v2::{
             syntax::JsonPresentation as JsonPresentationV2, Credential as _,
             JsonCredential as JsonCredentialV2,
@@ -135,6 +141,78 @@ impl JsonVc {
 }
 
 impl JsonVc {
+    /// The `credentialSubject.id` of the credential.
+    pub(crate) fn subject_id(&self) -> Option<String> {
+        super::credential_subject_id(&self.raw)
+    }
+
+    pub(crate) fn issuer_name(&self) -> Option<String> {
+        super::credential_issuer_name(&self.raw)
+    }
+
+    pub(crate) fn issuance_date(&self) -> Option<String> {
+        super::credential_issuance_date(&self.raw)
+    }
+
+    pub(crate) fn expiration_date(&self) -> Option<String> {
+        super::credential_expiration_date(&self.raw)
+    }
+
+    /// Return a copy of the credential bound to a different key alias.
+    pub(crate) fn with_key_alias(&self, key_alias: KeyAlias) -> Self {
+        Self {
+            key_alias: Some(key_alias),
+            ..self.clone()
+        }
+    }
+
+    /// Verify the data integrity proofs of the credential, loading JSON-LD contexts from
+    /// `context_map` in addition to those bundled with the SDK, and only resolving the DIDs
+    /// `did_policy` allows.
+    ///
+    /// A credential secured with a proof set is valid if at least one of its proofs is, so
+    /// that proofs using a cryptosuite the SDK does not support are tolerated.
+    pub(crate) async fn verify(
+        &self,
+        context_map: Option<HashMap<String, String>>,
+        did_policy: &DidResolutionPolicy,
+    ) -> Result<(), CredentialVerificationError> {
+        use ssi::prelude::*;
+
+        let vm_resolver = did_policy.vm_resolver();
+        let params = match context_map {
+            Some(context_map) => VerificationParameters::from_resolver(vm_resolver)
+                .with_json_ld_loader(
+                    context_loader_from_map(context_map)
+                        .map_err(|e| CredentialVerificationError::ContextMap(e.to_string()))?,
+                ),
+            None => VerificationParameters::from_resolver(vm_resolver),
+        };
+
+        let mut error = None;
+        for credential in single_proof_credentials(&self.raw) {
+            let result = async {
+                let vc = any_credential_from_json_str(&credential.to_string())
+                    .map_err(|e| CredentialVerificationError::Parsing(e.to_string()))?;
+
+                vc.verify(&params)
+                    .await
+                    .map_err(|e| CredentialVerificationError::Verification(e.to_string()))?
+                    .map_err(|e| CredentialVerificationError::InvalidSignature(e.to_string()))
+            }
+            .await;
+
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) => error = Some(e),
+            }
+        }
+
+        Err(error.unwrap_or_else(|| {
+            CredentialVerificationError::Verification("the credential has no proof".into())
+        }))
+    }
+
     pub(crate) fn to_json_bytes(&self) -> Result<Vec<u8>, JsonVcEncodingError> {
         serde_json::to_vec(&self.raw).map_err(|_| JsonVcEncodingError::JsonBytesEncoding)
     }
@@ -184,6 +262,10 @@ impl CredentialPresentation for JsonVc {
         &self.raw
     }
 
+    fn credential_types(&self) -> Vec<String> {
+        self.types()
+    }
+
     fn presentation_format(&self) -> Self::PresentationFormat {
         ClaimFormatDesignation::LdpVp
     }
@@ -223,8 +305,15 @@ impl CredentialPresentation for JsonVc {
         let id = UriBuf::new(format!("urn:uuid:{}", Uuid::new_v4()).as_bytes().to_vec())
             .map_err(|e| CredentialEncodingError::VpToken(format!("Error parsing ID: {e:?}")))?;
 
-        // Check the signer supports the requested vp format crypto suite.
-        options.supports_security_method(ClaimFormatDesignation::LdpVp)?;
+        // Data Integrity proofs have no defined place for transaction data hashes.
+        if options.binds_transaction_data() {
+            return Err(OID4VPError::TransactionData(
+                "transaction data cannot be bound to an ldp_vp presentation".into(),
+            ));
+        }
+
+        // Select a crypto suite supported by both the signer and the requested vp format.
+        let suite = options.negotiate_cryptosuite(ClaimFormatDesignation::LdpVp)?;
 
         let unsigned_presentation = match self.parsed.clone() {
             AnyJsonCredential::V1(cred_v1) => {
@@ -278,7 +367,9 @@ impl CredentialPresentation for JsonVc {
             }
         };
 
-        let signed_presentation = options.sign_presentation(unsigned_presentation).await?;
+        let signed_presentation = options
+            .sign_presentation_with_cryptosuite(unsigned_presentation, &suite)
+            .await?;
 
         Ok(VpTokenItem::from(signed_presentation))
     }
@@ -311,6 +402,22 @@ impl BitStringStatusListResolver for JsonVc {
     // NOTE: The remaining methods are default implemented in the trait.
 }
 
+/// Split a credential into one credential per data integrity proof, so that each proof of a
+/// proof set can be verified on its own.
+fn single_proof_credentials(raw: &Json) -> Vec<Json> {
+    match raw.get("proof") {
+        Some(Json::Array(proofs)) => proofs
+            .iter()
+            .map(|proof| {
+                let mut credential = raw.clone();
+                credential["proof"] = proof.clone();
+                credential
+            })
+            .collect(),
+        _ => vec![raw.clone()],
+    }
+}
+
 impl TryFrom<Credential> for Arc<JsonVc> {
     type Error = JsonVcInitError;
 
@@ -350,3 +457,32 @@ fn try_map_subjects<T, U, E: std::fmt::Debug>(
         extra_properties: cred.extra_properties,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserves_proof_sets() {
+        let mut json: Json =
+            serde_json::from_str(include_str!("../../tests/examples/vehicle_title.json")).unwrap();
+        let ecdsa = json["proof"].clone();
+        let mut eddsa = ecdsa.clone();
+        eddsa["cryptosuite"] = "eddsa-rdfc-2022".into();
+        eddsa["verificationMethod"] = "did:web:dmv.utopia.example#key-2".into();
+        json["proof"] = Json::Array(vec![ecdsa, eddsa]);
+
+        let vc = JsonVc::new_from_json(json.to_string()).unwrap();
+
+        let bytes = vc.to_json_bytes().unwrap();
+        let decoded = JsonVc::from_json_bytes(vc.id(), bytes, None).unwrap();
+        assert_eq!(decoded.raw, json);
+
+        let credentials = single_proof_credentials(&decoded.raw);
+        assert_eq!(credentials.len(), 2);
+        assert_eq!(credentials[1]["proof"]["cryptosuite"], "eddsa-rdfc-2022");
+
+        let entry = vc.status_list_entry().unwrap();
+        assert_eq!(entry.status_list_index, 94567);
+    }
+}
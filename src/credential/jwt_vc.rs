@@ -4,7 +4,7 @@ use crate::{
     oid4vp::{
         error::OID4VPError,
         presentation::{CredentialPresentation, PresentationOptions},
-        PresentationError, ResponseOptions,
+        ResponseOptions, TRANSACTION_DATA_HASH_ALG,
     },
     CredentialType,
 };
@@ -27,6 +27,36 @@ use ssi::{
 };
 use uuid::Uuid;
 
+/// Lifetime of a JWT verifiable presentation unless overridden by the response options.
+const DEFAULT_VP_TOKEN_LIFETIME_SECONDS: i64 = 3600;
+
+/// The `jwt_vp_json` signing algorithms accepted by the verifier, or those the wallet
+/// advertises when the verifier does not list any.
+fn supported_vp_algorithms(options: &PresentationOptions<'_>) -> Vec<String> {
+    let algorithms = |formats: serde_json::Value| -> Option<Vec<String>> {
+        let format = formats.get("jwt_vp_json")?;
+        let algorithms = format
+            .get("alg")
+            .or_else(|| format.get("alg_values_supported"))?
+            .as_array()?
+            .iter()
+            .filter_map(|alg| alg.as_str().map(ToOwned::to_owned))
+            .collect::<Vec<_>>();
+        (!algorithms.is_empty()).then_some(algorithms)
+    };
+
+    options
+        .request
+        .and_then(|request| request.vp_formats().ok())
+        .and_then(|formats| serde_json::to_value(formats).ok())
+        .and_then(algorithms)
+        .or_else(|| {
+            let metadata = crate::oid4vp::holder::Holder::metadata().ok()?;
+            algorithms(serde_json::to_value(metadata.vp_formats_supported()).ok()?)
+        })
+        .unwrap_or_default()
+}
+
 #[derive(uniffi::Object, Debug, Clone)]
 /// A verifiable credential secured as a JWT.
 pub struct JwtVc {
@@ -106,6 +136,59 @@ impl JwtVc {
 }
 
 impl JwtVc {
+    /// The `credentialSubject.id` of the credential, falling back to the JWT `sub` claim.
+    pub(crate) fn subject_id(&self) -> Option<String> {
+        self.credential
+            .credential_subjects
+            .iter()
+            .find_map(|obj| obj.get("id")?.as_str().map(ToOwned::to_owned))
+            .or_else(|| {
+                self.payload_json
+                    .get("sub")?
+                    .as_str()
+                    .map(ToOwned::to_owned)
+            })
+    }
+
+    /// The issuer of the credential, falling back to the JWT `iss` claim.
+    pub(crate) fn issuer_name(&self) -> Option<String> {
+        serde_json::to_value(&self.credential)
+            .ok()
+            .as_ref()
+            .and_then(super::credential_issuer_name)
+            .or_else(|| {
+                self.payload_json
+                    .get("iss")?
+                    .as_str()
+                    .map(ToOwned::to_owned)
+            })
+    }
+
+    /// The issuance date of the credential, falling back to the JWT `nbf` or `iat` claims.
+    pub(crate) fn issuance_date(&self) -> Option<String> {
+        serde_json::to_value(&self.credential)
+            .ok()
+            .as_ref()
+            .and_then(super::credential_issuance_date)
+            .or_else(|| super::jwt_issuance_date(&self.payload_json))
+    }
+
+    pub(crate) fn expiration_date(&self) -> Option<String> {
+        serde_json::to_value(&self.credential)
+            .ok()
+            .as_ref()
+            .and_then(super::credential_expiration_date)
+            .or_else(|| super::jwt_expiration_date(&self.payload_json))
+    }
+
+    /// Return a copy of the credential bound to a different key alias.
+    pub(crate) fn with_key_alias(&self, key_alias: KeyAlias) -> Self {
+        Self {
+            key_alias: Some(key_alias),
+            ..self.clone()
+        }
+    }
+
     pub(crate) fn to_compact_jws_bytes(&self) -> Vec<u8> {
         self.jws.as_bytes().to_vec()
     }
@@ -183,6 +266,10 @@ impl CredentialPresentation for JwtVc {
         &self.payload_json
     }
 
+    fn credential_types(&self) -> Vec<String> {
+        self.types()
+    }
+
     fn presentation_format(&self) -> Self::PresentationFormat {
         ClaimFormatDesignation::JwtVpJson
     }
@@ -234,7 +321,13 @@ impl CredentialPresentation for JwtVc {
         }
 
         let iat = time::OffsetDateTime::now_utc().unix_timestamp();
-        let exp = iat + 3600;
+        let lifetime = options
+            .response_options
+            .vp_token_lifetime_seconds
+            .map_or(DEFAULT_VP_TOKEN_LIFETIME_SECONDS, |seconds| {
+                i64::try_from(seconds).unwrap_or(i64::MAX)
+            });
+        let exp = iat.saturating_add(lifetime);
 
         let iss = options.issuer();
         let aud = options.audience();
@@ -242,8 +335,12 @@ impl CredentialPresentation for JwtVc {
         let subject = options.subject();
 
         let key_id = Some(vm);
-        let algorithm = options.signer.algorithm().try_into().map_err(|e| {
-            CredentialEncodingError::VpToken(format!("Invalid Signing Algorithm: {e:?}"))
+        let signing_algorithm = options.signer.algorithm();
+        let algorithm = signing_algorithm.try_into().map_err(|e| {
+            CredentialEncodingError::VpToken(format!(
+                "Signing algorithm {signing_algorithm:?} is not supported for jwt_vp_json presentations ({e:?}). Supported algorithms: {}",
+                supported_vp_algorithms(options).join(", ")
+            ))
         })?;
 
         let header = Header {
@@ -258,16 +355,26 @@ impl CredentialPresentation for JwtVc {
             .map(|b| BASE64_URL_SAFE_NO_PAD.encode(b))
             .map_err(|e| CredentialEncodingError::VpToken(format!("{e:?}")))?;
 
-        let claims = serde_json::json!({
+        let mut claims = serde_json::json!({
             "iat": iat,
             "exp": exp,
             "iss": iss,
             "sub": subject,
-            "aud": aud,
-            "nonce": nonce,
             "vp": vp,
         });
 
+        // A standalone presentation may not be bound to an audience or nonce.
+        if let Some(aud) = aud {
+            claims["aud"] = aud.into();
+        }
+        if let Some(nonce) = nonce {
+            claims["nonce"] = nonce.into();
+        }
+        if options.binds_transaction_data() {
+            claims["transaction_data_hashes"] = options.transaction_data_hashes().into();
+            claims["transaction_data_hashes_alg"] = TRANSACTION_DATA_HASH_ALG.into();
+        }
+
         let body_b64 = serde_json::to_vec(&claims)
             .map(|b| BASE64_URL_SAFE_NO_PAD.encode(b))
             .map_err(|e| CredentialEncodingError::VpToken(format!("{e:?}")))?;
@@ -281,12 +388,7 @@ impl CredentialPresentation for JwtVc {
             .await
             .map_err(|e| CredentialEncodingError::VpToken(format!("{e:?}")))?;
 
-        let signature = options
-            .curve_utils()
-            .map(|utils| utils.ensure_raw_fixed_width_signature_encoding(signature))?
-            .ok_or(OID4VPError::Presentation(PresentationError::Signing(
-                "Unsupported signature encoding.".into(),
-            )))?;
+        let signature = options.encode_signature(signature)?;
 
         let signature_b64 = BASE64_URL_SAFE_NO_PAD.encode(&signature);
 
@@ -369,3 +471,46 @@ pub enum JwtVcInitError {
     #[error("failed to decode JWT payload as base64-encoded JSON")]
     PayloadDecoding,
 }
+
+#[cfg(test)]
+mod tests {
+    use openid4vp::core::authorization_request::AuthorizationRequestObject;
+
+    use super::*;
+    use crate::oid4vp::{holder::tests::test_request, presentation::PresentationSigner};
+
+    fn request_with_metadata(client_metadata: serde_json::Value) -> AuthorizationRequestObject {
+        test_request(
+            serde_json::json!({
+                "id": "jwt-vp-alg-test",
+                "input_descriptors": []
+            }),
+            Some(client_metadata),
+        )
+    }
+
+    #[test]
+    fn supported_vp_algorithms_from_request() {
+        let signer: Arc<Box<dyn PresentationSigner>> =
+            Arc::new(Box::new(crate::tests::load_signer()));
+        let response_options = ResponseOptions::default();
+
+        let request = request_with_metadata(serde_json::json!({
+            "vp_formats": { "jwt_vp_json": { "alg": ["ES256K", "EdDSA"] } }
+        }));
+        let options = PresentationOptions::for_request(
+            &request,
+            signer.clone(),
+            None,
+            &[],
+            &response_options,
+        );
+        assert_eq!(supported_vp_algorithms(&options), vec!["ES256K", "EdDSA"]);
+
+        // Without algorithms from the verifier, those advertised by the wallet are reported.
+        let request = request_with_metadata(serde_json::json!({ "vp_formats": {} }));
+        let options =
+            PresentationOptions::for_request(&request, signer, None, &[], &response_options);
+        assert_eq!(supported_vp_algorithms(&options), vec!["ES256"]);
+    }
+}
@@ -5,15 +5,57 @@ use std::{
 
 use base64::prelude::*;
 use isomdl::{
-    definitions::{IssuerSigned, Mso},
+    definitions::{
+        x509::{
+            trust_anchor::{PemTrustAnchor, TrustAnchorRegistry, TrustPurpose},
+            validation::ValidationRuleset,
+            x5chain::X5CHAIN_COSE_HEADER_LABEL,
+            X5Chain,
+        },
+        CoseKey, DigestAlgorithm, EC2Curve, IssuerSigned, Mso, EC2Y,
+    },
     presentation::{device::Document, Stringify},
 };
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use signature::Verifier;
+use ssi::claims::cose::coset;
 use uuid::Uuid;
+use x509_cert::Certificate;
 
-use crate::{crypto::KeyAlias, CredentialType};
+use crate::{
+    crypto::KeyAlias,
+    oid4vp::iso_18013_7::requested_values,
+    verifier::{
+        helpers::infer_claim_value,
+        outcome::{ClaimValue, CredentialInfo},
+    },
+    CredentialType,
+};
 
 use super::{Credential, CredentialFormat};
 
+/// Data elements that ISO/IEC 18013-5 Table 5 requires in an mDL, by namespace.
+const MDL_REQUIRED_ELEMENTS: &[&str] = &[
+    "family_name",
+    "given_name",
+    "birth_date",
+    "issue_date",
+    "expiry_date",
+    "issuing_country",
+    "issuing_authority",
+    "document_number",
+    "portrait",
+    "driving_privileges",
+    "un_distinguishing_sign",
+];
+
+/// The namespaced data elements required in an mdoc, for the doctypes they are known for.
+const REQUIRED_ELEMENTS: &[(&str, &str, &[&str])] = &[(
+    "org.iso.18013.5.1.mDL",
+    "org.iso.18013.5.1",
+    MDL_REQUIRED_ELEMENTS,
+)];
+
 uniffi::custom_newtype!(Namespace, String);
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 /// A namespace for mdoc data elements.
@@ -63,6 +105,23 @@ impl Mdoc {
         Ok(Arc::new(Self { inner, key_alias }))
     }
 
+    #[uniffi::constructor]
+    /// Construct an MDoc from a stringified spruceid/isomdl `Document`, as
+    /// [Mdoc::from_stringified_document] does, checking that it has the `expected_doctype`
+    /// and the data elements required for that doctype.
+    ///
+    /// Required data elements are only known for the mDL doctype. For other doctypes only
+    /// the doctype is checked.
+    pub fn from_stringified_document_validated(
+        stringified_document: String,
+        key_alias: KeyAlias,
+        expected_doctype: String,
+    ) -> Result<Arc<Self>, MdocInitError> {
+        let mdoc = Self::from_stringified_document(stringified_document, key_alias)?;
+        mdoc.validate(&expected_doctype)?;
+        Ok(mdoc)
+    }
+
     #[uniffi::constructor]
     /// Construct a SpruceKit MDoc from a cbor-encoded
     /// [spruceid/isomdl `Document`](https://github.com/spruceid/isomdl/blob/main/src/presentation/device.rs#L145-L152)
@@ -114,6 +173,112 @@ impl Mdoc {
     pub fn key_alias(&self) -> KeyAlias {
         self.key_alias.clone()
     }
+
+    /// The title, portrait and claims of this mdoc, in the form used for credentials verified
+    /// from a barcode, so that both can be rendered the same way.
+    ///
+    /// Claims are keyed by data element identifier, qualified with their namespace when the
+    /// identifier is used in several namespaces. The `portrait` data element, if any, is used
+    /// as the image.
+    pub fn credential_info(&self) -> CredentialInfo {
+        let mut elements = Vec::new();
+        for (namespace, namespace_elements) in self.inner.namespaces.iter() {
+            for (identifier, tagged) in namespace_elements.iter() {
+                let Some(value) = isomdl::cbor::to_vec(&tagged.as_ref().element_value)
+                    .ok()
+                    .and_then(|cbor| serde_cbor::from_slice(&cbor).ok())
+                else {
+                    continue;
+                };
+                elements.push((namespace.clone(), identifier.clone(), value));
+            }
+        }
+
+        let mut image = Vec::new();
+        let mut claims = HashMap::new();
+        for (namespace, identifier, mut value) in elements.iter().cloned() {
+            // Tags, e.g. `full-date`, are not needed to display the value.
+            while let serde_cbor::Value::Tag(_, tagged) = value {
+                value = *tagged;
+            }
+            let value = match &value {
+                // Arrays, e.g. `driving_privileges`, are rendered as JSON rather than their
+                // debug representation.
+                serde_cbor::Value::Array(_) => match serde_json::to_string(&value) {
+                    Ok(value) => ClaimValue::Text { value },
+                    Err(_) => infer_claim_value(&value),
+                },
+                value => infer_claim_value(value),
+            };
+
+            if identifier == "portrait" && image.is_empty() {
+                if let ClaimValue::Image { value } = &value {
+                    image = value.clone();
+                }
+            }
+
+            let ambiguous = elements
+                .iter()
+                .filter(|(_, other, _)| *other == identifier)
+                .count()
+                > 1;
+            let key = match ambiguous {
+                true => format!("{namespace}/{identifier}"),
+                false => identifier,
+            };
+            claims.insert(key, value);
+        }
+
+        CredentialInfo {
+            title: CredentialType(self.doctype()).display_name(),
+            image,
+            claims,
+            extra: HashMap::new(),
+        }
+    }
+
+    /// Check that every data element matches its digest in the MSO.
+    ///
+    /// This detects tampering or corruption of the stored data elements. It does not verify the
+    /// issuer signature over the MSO, so it needs no network access or trust anchors.
+    pub fn verify_integrity(&self) -> Result<(), MdocIntegrityError> {
+        let mso = &self.inner.mso;
+
+        for (namespace, elements) in self.inner.namespaces.iter() {
+            let digests = mso.value_digests.get(namespace);
+
+            for (identifier, tagged) in elements.iter() {
+                let element = format!("{namespace}/{identifier}");
+
+                let expected = digests
+                    .and_then(|digests| digests.get(&tagged.as_ref().digest_id))
+                    .ok_or_else(|| MdocIntegrityError::MissingDigest(element.clone()))?;
+
+                let encoded = isomdl::cbor::to_vec(tagged)
+                    .map_err(|e| MdocIntegrityError::Encoding(e.to_string()))?;
+                let actual = match mso.digest_algorithm {
+                    DigestAlgorithm::SHA256 => Sha256::digest(&encoded).to_vec(),
+                    DigestAlgorithm::SHA384 => Sha384::digest(&encoded).to_vec(),
+                    DigestAlgorithm::SHA512 => Sha512::digest(&encoded).to_vec(),
+                };
+
+                if actual.as_slice() != expected.as_ref() {
+                    return Err(MdocIntegrityError::DigestMismatch(element));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the holder is over the given age, derived from the `age_over_NN` data elements
+    /// following the rules in ISO/IEC 18013-5 Section 7.2.5.
+    ///
+    /// For example, with `age_over_18: true` and `age_over_21: true`, a request for age 20 is
+    /// answered by `age_over_21`. Returns null if no data element can answer the request.
+    pub fn age_over(&self, age: u8) -> Option<bool> {
+        requested_values::age_over(&self.document().namespaces, age)
+    }
 }
 
 impl Mdoc {
@@ -121,6 +286,149 @@ impl Mdoc {
         &self.inner
     }
 
+    /// The CBOR-encoded document with its local ID cleared, identifying the
+    /// credential material itself.
+    pub(crate) fn content_bytes(&self) -> Result<Vec<u8>, MdocEncodingError> {
+        document_content_bytes(self.inner.clone())
+    }
+
+    /// A `did:jwk` identifying the device key in the MSO.
+    ///
+    /// Returns `None` for device keys on curves other than P-256 and P-384, or with compressed
+    /// coordinates.
+    pub(crate) fn device_key_did(&self) -> Option<String> {
+        let CoseKey::EC2 {
+            crv,
+            x,
+            y: EC2Y::Value(y),
+        } = &self.inner.mso.device_key_info.device_key
+        else {
+            return None;
+        };
+
+        let crv = match crv {
+            EC2Curve::P256 => "P-256",
+            EC2Curve::P384 => "P-384",
+            _ => return None,
+        };
+
+        let jwk: ssi::JWK = serde_json::from_value(serde_json::json!({
+            "kty": "EC",
+            "crv": crv,
+            "x": BASE64_URL_SAFE_NO_PAD.encode(x),
+            "y": BASE64_URL_SAFE_NO_PAD.encode(y),
+        }))
+        .ok()?;
+
+        Some(ssi::dids::DIDJWK::generate(&jwk).to_string())
+    }
+
+    /// Verify the issuer signature over the MSO with the document signer certificate in the
+    /// `x5chain` header, returning the certificate chain.
+    pub(crate) fn verify_issuer_signature(&self) -> Result<X5Chain, MdocVerificationError> {
+        let issuer_auth = &self.inner.issuer_auth;
+        let x5chain = issuer_auth
+            .unprotected
+            .rest
+            .iter()
+            .chain(issuer_auth.protected.header.rest.iter())
+            .find(|(label, _)| *label == coset::Label::Int(X5CHAIN_COSE_HEADER_LABEL))
+            .and_then(|(_, value)| X5Chain::from_cbor(value.clone()).ok())
+            .ok_or(MdocVerificationError::CertificateChainMissing)?;
+
+        let verifying_key = p256_verifying_key(x5chain.end_entity_certificate())?;
+        issuer_auth
+            .verify_signature(&[], |signature, data| {
+                let signature = p256::ecdsa::Signature::from_slice(signature)?;
+                verifying_key.verify(data, &signature)
+            })
+            .map_err(|_| MdocVerificationError::InvalidSignature)?;
+
+        Ok(x5chain)
+    }
+
+    /// When the MSO was signed, as an RFC 3339 timestamp.
+    pub(crate) fn signed_at(&self) -> Option<String> {
+        self.inner
+            .mso
+            .validity_info
+            .signed
+            .format(&time::format_description::well_known::Rfc3339)
+            .ok()
+    }
+
+    /// When the MSO stops being valid, as an RFC 3339 timestamp.
+    pub(crate) fn valid_until(&self) -> Option<String> {
+        self.inner
+            .mso
+            .validity_info
+            .valid_until
+            .format(&time::format_description::well_known::Rfc3339)
+            .ok()
+    }
+
+    /// The namespaces of this mdoc as a JSON object, mapping each namespace to an object of
+    /// its data element identifiers and values.
+    pub(crate) fn namespaces_as_json_string(&self) -> Result<String, MdocEncodingError> {
+        let namespaces = self
+            .document()
+            .namespaces
+            .clone()
+            .into_inner()
+            .into_iter()
+            .map(|(namespace, elements)| {
+                let elements = elements
+                    .into_inner()
+                    .into_values()
+                    .map(|tagged| {
+                        let element = tagged.into_inner();
+                        serde_json::to_value(&element.element_value)
+                            .map(|value| (element.element_identifier, value))
+                    })
+                    .collect::<Result<serde_json::Map<_, _>, _>>()?;
+                Ok((namespace, serde_json::Value::Object(elements)))
+            })
+            .collect::<Result<serde_json::Map<_, _>, serde_json::Error>>()
+            .map_err(|e| MdocEncodingError::JsonEncoding(e.to_string()))?;
+        serde_json::to_string(&namespaces)
+            .map_err(|e| MdocEncodingError::JsonEncoding(e.to_string()))
+    }
+
+    /// Return a copy of the mdoc bound to a different key alias.
+    pub(crate) fn with_key_alias(&self, key_alias: KeyAlias) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            key_alias,
+        }
+    }
+
+    /// Check that the mdoc has the `expected_doctype`, and the data elements required for it.
+    fn validate(&self, expected_doctype: &str) -> Result<(), MdocInitError> {
+        let doctype = &self.inner.mso.doc_type;
+        if doctype != expected_doctype {
+            return Err(MdocInitError::DoctypeMismatch(
+                doctype.clone(),
+                expected_doctype.to_string(),
+            ));
+        }
+
+        for (_, namespace, identifiers) in REQUIRED_ELEMENTS
+            .iter()
+            .filter(|(required_doctype, ..)| required_doctype == doctype)
+        {
+            let elements = self.inner.namespaces.get(*namespace);
+            for identifier in identifiers.iter() {
+                if !elements.is_some_and(|elements| elements.contains_key(*identifier)) {
+                    return Err(MdocInitError::DataElementMissing(format!(
+                        "{namespace}/{identifier}"
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn new_from_parts(inner: Document, key_alias: KeyAlias) -> Self {
         Self { inner, key_alias }
     }
@@ -172,6 +480,17 @@ impl Mdoc {
     }
 }
 
+/// The content bytes, see [Mdoc::content_bytes], of a CBOR-encoded document, or `None` if
+/// it is not a document.
+pub(crate) fn cbor_encoded_document_content_bytes(cbor_encoded_document: &[u8]) -> Option<Vec<u8>> {
+    document_content_bytes(isomdl::cbor::from_slice(cbor_encoded_document).ok()?).ok()
+}
+
+fn document_content_bytes(mut document: Document) -> Result<Vec<u8>, MdocEncodingError> {
+    document.id = Uuid::nil();
+    isomdl::cbor::to_vec(&document).map_err(|_| MdocEncodingError::DocumentCborEncoding)
+}
+
 impl TryFrom<Credential> for Arc<Mdoc> {
     type Error = MdocInitError;
 
@@ -216,10 +535,221 @@ pub enum MdocInitError {
     NamespacesMissing,
     #[error("failed to decode Document from UTF-8 string")]
     DocumentUtf8Decoding,
+    #[error("document type {0} does not match the expected {1}")]
+    DoctypeMismatch(String, String),
+    #[error("required data element {0} is missing")]
+    DataElementMissing(String),
+}
+
+#[derive(Debug, uniffi::Error, thiserror::Error)]
+pub enum MdocIntegrityError {
+    #[error("no digest in the MSO for data element {0}")]
+    MissingDigest(String),
+    #[error("data element {0} does not match its digest in the MSO")]
+    DigestMismatch(String),
+    #[error("failed to encode data element: {0}")]
+    Encoding(String),
+}
+
+#[derive(Debug, uniffi::Error, thiserror::Error)]
+pub enum MdocVerificationError {
+    #[error("IssuerAuth has no x5chain header")]
+    CertificateChainMissing,
+    #[error("unsupported certificate key, only P-256 keys are supported")]
+    UnsupportedKey,
+    #[error("invalid issuer signature over the MSO")]
+    InvalidSignature,
+    #[error("document signer certificate chain is not trusted: {0}")]
+    UntrustedIssuer(String),
+    #[error("invalid trusted root certificates: {0}")]
+    TrustedRoots(String),
 }
 
 #[derive(Debug, uniffi::Error, thiserror::Error)]
 pub enum MdocEncodingError {
     #[error("failed to encode Document to CBOR")]
     DocumentCborEncoding,
+    #[error("failed to encode namespaces as JSON: {0}")]
+    JsonEncoding(String),
+}
+
+/// Check that a document signer certificate chain is valid and issued by one of
+/// `trusted_roots`, given as PEM-encoded IACA certificates, following the mDL validation
+/// rules of isomdl, as the mDL reader does.
+pub(crate) fn verify_signer_certificate(
+    x5chain: &X5Chain,
+    trusted_roots: &[String],
+) -> Result<(), MdocVerificationError> {
+    let registry = TrustAnchorRegistry::from_pem_certificates(
+        trusted_roots
+            .iter()
+            .map(|certificate_pem| PemTrustAnchor {
+                certificate_pem: certificate_pem.clone(),
+                purpose: TrustPurpose::Iaca,
+            })
+            .collect(),
+    )
+    .map_err(|e| MdocVerificationError::TrustedRoots(format!("{e:?}")))?;
+
+    let outcome = ValidationRuleset::Mdl.validate(x5chain, &registry);
+    if !outcome.errors.is_empty() {
+        return Err(MdocVerificationError::UntrustedIssuer(format!(
+            "{:?}",
+            outcome.errors
+        )));
+    }
+
+    Ok(())
+}
+
+fn p256_verifying_key(
+    certificate: &Certificate,
+) -> Result<p256::ecdsa::VerifyingKey, MdocVerificationError> {
+    p256::ecdsa::VerifyingKey::from_sec1_bytes(
+        certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .subject_public_key
+            .raw_bytes(),
+    )
+    .map_err(|_| MdocVerificationError::UnsupportedKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::RustTestKeyManager;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn verify_integrity_detects_tampering() {
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        let key_alias = KeyAlias("integrity".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let mut mdoc = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+        mdoc.verify_integrity().unwrap();
+
+        let digests = mdoc.inner.mso.value_digests.values_mut().next().unwrap();
+        let digest = digests.values_mut().next().unwrap();
+        *digest = vec![0; 32].into();
+
+        assert!(matches!(
+            mdoc.verify_integrity(),
+            Err(MdocIntegrityError::DigestMismatch(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn credential_info_maps_mdl_claims() {
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        let key_alias = KeyAlias("credential-info".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let mdoc = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+        let info = mdoc.credential_info();
+
+        assert_eq!(info.title, "Mobile Driving License");
+        assert!(!info.image.is_empty());
+        assert!(matches!(
+            info.claims.get("portrait"),
+            Some(ClaimValue::Image { value }) if *value == info.image
+        ));
+        assert!(matches!(
+            info.claims.get("family_name"),
+            Some(ClaimValue::Text { value }) if value == "Smith"
+        ));
+        assert!(matches!(
+            info.claims.get("birth_date"),
+            Some(ClaimValue::Date { value }) if value == "1980-01-01"
+        ));
+
+        let Some(ClaimValue::Text { value }) = info.claims.get("driving_privileges") else {
+            panic!("driving_privileges should be rendered as text");
+        };
+        let privileges: serde_json::Value = serde_json::from_str(value).unwrap();
+        assert_eq!(privileges[0]["vehicle_category_code"], "A");
+        assert_eq!(privileges[1]["vehicle_category_code"], "B");
+    }
+
+    #[tokio::test]
+    async fn validates_stringified_documents() {
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        let key_alias = KeyAlias("validated".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let mdl = crate::mdl::util::generate_test_mdl(key_manager.clone(), key_alias.clone())
+            .unwrap()
+            .document()
+            .stringify()
+            .unwrap();
+        let validated = |document: &str, doctype: &str| {
+            Mdoc::from_stringified_document_validated(
+                document.to_string(),
+                key_alias.clone(),
+                doctype.to_string(),
+            )
+        };
+
+        validated(&mdl, "org.iso.18013.5.1.mDL").unwrap();
+        assert!(matches!(
+            validated(&mdl, "eu.europa.ec.eudi.pid.1"),
+            Err(MdocInitError::DoctypeMismatch(..))
+        ));
+
+        let incomplete = crate::mdl::util::generate_test_mdoc(
+            key_manager,
+            key_alias.clone(),
+            crate::mdl::util::TestMdocConfig {
+                doc_type: "org.iso.18013.5.1.mDL".into(),
+                namespaces: serde_json::json!({
+                    "org.iso.18013.5.1": { "family_name": "Smith", "given_name": "Alice" }
+                })
+                .to_string(),
+                valid_from_offset_seconds: 0,
+                valid_until_offset_seconds: 3600,
+            },
+        )
+        .unwrap()
+        .document()
+        .stringify()
+        .unwrap();
+        assert!(matches!(
+            validated(&incomplete, "org.iso.18013.5.1.mDL"),
+            Err(MdocInitError::DataElementMissing(element)) if element == "org.iso.18013.5.1/birth_date"
+        ));
+    }
+
+    #[tokio::test]
+    async fn verify_issuer_signature_against_trusted_roots() {
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        let key_alias = KeyAlias("issuer_signature".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let mdoc = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+        let x5chain = mdoc.verify_issuer_signature().unwrap();
+
+        let iaca = include_str!("../../tests/res/mdl/iaca-certificate.pem").to_string();
+        verify_signer_certificate(&x5chain, &[iaca]).unwrap();
+        assert!(matches!(
+            verify_signer_certificate(&x5chain, &[]),
+            Err(MdocVerificationError::UntrustedIssuer(_))
+        ));
+        assert!(matches!(
+            verify_signer_certificate(&x5chain, &["not a certificate".into()]),
+            Err(MdocVerificationError::TrustedRoots(_))
+        ));
+    }
 }
@@ -3,12 +3,14 @@ pub mod jwt_vc;
 pub mod mdoc;
 pub mod status;
 pub mod status_20240406;
+pub mod type_metadata;
 pub mod vcdm2_sd_jwt;
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     crypto::KeyAlias,
+    did::DidResolutionPolicy,
     oid4vp::{
         error::OID4VPError,
         permission_request::RequestedField,
@@ -19,12 +21,13 @@ use crate::{
 };
 use json_vc::{JsonVc, JsonVcEncodingError, JsonVcInitError};
 use jwt_vc::{JwtVc, JwtVcInitError};
-use mdoc::{Mdoc, MdocEncodingError, MdocInitError};
+use mdoc::{Mdoc, MdocEncodingError, MdocInitError, MdocVerificationError};
 use openid4vp::core::{
     presentation_definition::PresentationDefinition, presentation_submission::DescriptorMap,
     response::parameters::VpTokenItem,
 };
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use status::BitStringStatusListResolver;
 use status_20240406::BitStringStatusListResolver20240406;
 use vcdm2_sd_jwt::{SdJwtError, VCDM2SdJwt};
@@ -51,6 +54,30 @@ impl Credential {
     pub fn try_into_parsed(&self) -> Result<Arc<ParsedCredential>, CredentialDecodingError> {
         self.to_owned().try_into()
     }
+
+    /// A hex-encoded SHA-256 hash of the credential's format and payload.
+    ///
+    /// Unlike the local ID, this is the same for every copy of a credential. The payload of an
+    /// mdoc embeds a local ID generated on import, so it is hashed with that ID cleared.
+    pub fn content_hash(&self) -> String {
+        let mdoc_content = match self.format {
+            CredentialFormat::MsoMdoc => mdoc::cbor_encoded_document_content_bytes(&self.payload),
+            _ => None,
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.format.to_string().as_bytes());
+        hasher.update([0]);
+        hasher.update(mdoc_content.as_deref().unwrap_or(&self.payload));
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Return the content hash of a credential, which identifies copies of the same credential
+/// regardless of their local ID.
+#[uniffi::export]
+pub fn credential_content_hash(credential: Credential) -> String {
+    credential.content_hash()
 }
 
 /// A credential that has been parsed as a known variant.
@@ -58,6 +85,24 @@ impl Credential {
 pub struct ParsedCredential {
     pub(crate) inner: ParsedCredentialInner,
 }
+
+/// A claim value that a presentation discloses to the verifier, see
+/// [PresentableCredential::preview_disclosure].
+///
+/// Values are given as text for JSON strings, and JSON-encoded otherwise.
+#[derive(Debug, Clone, PartialEq, uniffi::Enum)]
+pub enum DisclosedClaim {
+    /// A claim of a VCDM or SD-JWT credential, with the object keys and array indices leading
+    /// to it, e.g. `["credentialSubject", "address", "locality"]`.
+    Json { path: Vec<String>, value: String },
+    /// A data element of an mdoc.
+    Mdoc {
+        namespace: String,
+        element: String,
+        value: String,
+    },
+}
+
 /// A credential that has been parsed as a known variant.
 #[derive(Debug, Clone, uniffi::Object)]
 pub struct PresentableCredential {
@@ -88,6 +133,65 @@ impl PresentableCredential {
         })
     }
 
+    /// Return a copy of the credential carrying the fields selected for presentation, for use
+    /// with [PermissionRequest::create_permission_response_from_selections].
+    ///
+    /// [PermissionRequest::create_permission_response_from_selections]: crate::oid4vp::PermissionRequest::create_permission_response_from_selections
+    pub fn with_selected_fields(&self, fields: Vec<String>) -> Arc<Self> {
+        Arc::new(Self {
+            inner: self.inner.clone(),
+            limit_disclosure: self.limit_disclosure,
+            selected_fields: Some(fields),
+        })
+    }
+
+    /// Return the fields selected for presentation, if any.
+    pub fn selected_fields(&self) -> Option<Vec<String>> {
+        self.selected_fields.clone()
+    }
+
+    /// Return the name of the credential's issuer, to tell apart otherwise identical
+    /// credentials. See [ParsedCredential::issuer_name].
+    pub fn issuer_name(&self) -> Option<String> {
+        self.as_parsed_credential().issuer_name()
+    }
+
+    /// Return when the credential was issued, as an RFC 3339 timestamp. See
+    /// [ParsedCredential::issuance_date].
+    pub fn issuance_date(&self) -> Option<String> {
+        self.as_parsed_credential().issuance_date()
+    }
+
+    /// Return the claim values that presenting the credential with `selected_fields` discloses
+    /// to the verifier, so the user can review them before approving the presentation.
+    ///
+    /// SD-JWTs disclose the selected claims along with those that are not selectively
+    /// disclosable, while JWT and LDP VCs always disclose every claim. Mdocs disclose the data
+    /// elements selected with paths such as `$['org.iso.18013.5.1']['given_name']`.
+    pub fn preview_disclosure(
+        &self,
+        selected_fields: Vec<String>,
+    ) -> Result<Vec<DisclosedClaim>, OID4VPError> {
+        let claims = match &self.inner {
+            ParsedCredentialInner::MsoMdoc(mdoc) => {
+                return mdoc_disclosed_claims(mdoc, &selected_fields)
+            }
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => {
+                sd_jwt.disclosed_claims(selected_fields)?
+            }
+            ParsedCredentialInner::JwtVcJson(_)
+            | ParsedCredentialInner::JwtVcJsonLd(_)
+            | ParsedCredentialInner::LdpVc(_) => {
+                serde_json::from_str(&self.as_parsed_credential().claims_as_json_string()?)
+                    .map_err(|e| OID4VPError::JsonSyntaxParse(e.to_string()))?
+            }
+        };
+
+        let mut disclosed = vec![];
+        collect_disclosed_claims(&claims, &mut vec![], &mut disclosed);
+        Ok(disclosed)
+    }
+
     /// Return if the credential supports selective disclosure
     /// For now only SdJwts are supported
     pub fn selective_disclosable(&self) -> bool {
@@ -139,10 +243,31 @@ impl ParsedCredential {
         }
     }
 
+    /// Parse a credential from the JSON encoding of its generic form, see [Credential].
+    ///
+    /// Each field of the envelope is checked individually, so that the error names the field
+    /// that is missing or invalid.
     #[uniffi::constructor]
     pub fn new_from_json(json_string: String) -> Result<Arc<Self>, CredentialDecodingError> {
-        let credential: Credential = serde_json::from_str(&json_string)
-            .map_err(|e| CredentialDecodingError::Serialization(format!("{e:?}")))?;
+        let envelope: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&json_string)
+                .map_err(|e| CredentialDecodingError::Serialization(format!("{e:?}")))?;
+
+        let format = CredentialFormat::from(envelope_field::<String>(&envelope, "format")?);
+        if let CredentialFormat::Other(format) = format {
+            return Err(CredentialDecodingError::UnknownFormat(format));
+        }
+
+        let credential = Credential {
+            id: envelope_field(&envelope, "id")?,
+            format,
+            r#type: envelope_field(&envelope, "type")?,
+            payload: envelope_field(&envelope, "payload")?,
+            key_alias: match envelope.get("key_alias") {
+                None | Some(serde_json::Value::Null) => None,
+                Some(_) => Some(envelope_field(&envelope, "key_alias")?),
+            },
+        };
         credential.try_into_parsed()
     }
 
@@ -234,6 +359,44 @@ impl ParsedCredential {
         }
     }
 
+    /// Returns whether `other` holds the same credential material as this credential.
+    ///
+    /// The local ID and key alias are ignored, so the same credential received
+    /// through two channels compares equal.
+    pub fn content_equals(&self, other: &Arc<ParsedCredential>) -> bool {
+        match (self.content_bytes(), other.content_bytes()) {
+            (Ok(a), Ok(b)) => self.format() == other.format() && a == b,
+            _ => false,
+        }
+    }
+
+    /// Return a copy of this credential bound to a different key alias, for example after
+    /// migrating the credential to a new hardware-backed key.
+    ///
+    /// The local ID is preserved, so the result can be converted with
+    /// [ParsedCredential::into_generic_form] and saved in place of the original.
+    pub fn with_key_alias(&self, key_alias: KeyAlias) -> Arc<Self> {
+        let inner = match &self.inner {
+            ParsedCredentialInner::MsoMdoc(mdoc) => {
+                ParsedCredentialInner::MsoMdoc(Arc::new(mdoc.with_key_alias(key_alias)))
+            }
+            ParsedCredentialInner::JwtVcJson(vc) => {
+                ParsedCredentialInner::JwtVcJson(Arc::new(vc.with_key_alias(key_alias)))
+            }
+            ParsedCredentialInner::JwtVcJsonLd(vc) => {
+                ParsedCredentialInner::JwtVcJsonLd(Arc::new(vc.with_key_alias(key_alias)))
+            }
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => {
+                ParsedCredentialInner::VCDM2SdJwt(Arc::new(sd_jwt.with_key_alias(key_alias)))
+            }
+            ParsedCredentialInner::LdpVc(vc) => {
+                ParsedCredentialInner::LdpVc(Arc::new(vc.with_key_alias(key_alias)))
+            }
+        };
+
+        Arc::new(Self { inner })
+    }
+
     /// Return the format of the credential.
     pub fn format(&self) -> CredentialFormat {
         match &self.inner {
@@ -267,6 +430,105 @@ impl ParsedCredential {
         }
     }
 
+    /// Return the decoded claims of the credential as a UTF-8 encoded JSON string.
+    ///
+    /// The output depends on the format: the VC for JWT VCs, the revealed claims for
+    /// SD-JWTs, the VC document for LDP VCs, and the namespaces and their data elements for
+    /// mdocs. The storage envelope (see [Credential]) is not included.
+    pub fn claims_as_json_string(&self) -> Result<String, CredentialEncodingError> {
+        match &self.inner {
+            ParsedCredentialInner::MsoMdoc(mdoc) => Ok(mdoc.namespaces_as_json_string()?),
+            ParsedCredentialInner::JwtVcJson(vc) | ParsedCredentialInner::JwtVcJsonLd(vc) => {
+                Ok(vc.credential_as_json_encoded_utf8_string())
+            }
+            ParsedCredentialInner::LdpVc(vc) => Ok(vc.credential_as_json_encoded_utf8_string()),
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => {
+                Ok(sd_jwt.revealed_claims_as_json_string()?)
+            }
+        }
+    }
+
+    /// Return the identifier of the credential subject, used to check whether the credential is
+    /// bound to the current holder.
+    ///
+    /// - VCDM credentials return the `credentialSubject.id`, falling back to the JWT `sub` claim.
+    /// - SD-JWT credentials additionally fall back to the `cnf` holder binding, as its `kid` or
+    ///   as a `did:jwk` derived from its `jwk`.
+    /// - mdocs return a `did:jwk` derived from the device key in the MSO, where supported.
+    pub fn subject_id(&self) -> Option<String> {
+        match &self.inner {
+            ParsedCredentialInner::MsoMdoc(mdoc) => mdoc.device_key_did(),
+            ParsedCredentialInner::JwtVcJson(vc) | ParsedCredentialInner::JwtVcJsonLd(vc) => {
+                vc.subject_id()
+            }
+            ParsedCredentialInner::LdpVc(vc) => vc.subject_id(),
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => sd_jwt.subject_id(),
+        }
+    }
+
+    /// Return whether presenting the credential requires proof of possession of a holder key,
+    /// i.e. whether the wallet must have a [KeyAlias] for it.
+    ///
+    /// - SD-JWT credentials require a key binding JWT when they carry a `cnf` claim.
+    /// - mdocs are always bound to the device key in the MSO.
+    /// - VCDM credentials are bound to their subject when they have a `credentialSubject.id`
+    ///   (or, for JWT VCs, a `sub` claim), which the presentation holder must match.
+    pub fn requires_holder_binding(&self) -> bool {
+        match &self.inner {
+            ParsedCredentialInner::MsoMdoc(_) => true,
+            ParsedCredentialInner::JwtVcJson(vc) | ParsedCredentialInner::JwtVcJsonLd(vc) => {
+                vc.subject_id().is_some()
+            }
+            ParsedCredentialInner::LdpVc(vc) => vc.subject_id().is_some(),
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => sd_jwt.has_holder_binding(),
+        }
+    }
+
+    /// Return the name of the credential's issuer, falling back to its identifier.
+    ///
+    /// VCDM credentials use the `issuer` property, falling back to the JWT `iss` claim. mdocs do
+    /// not identify their issuer in the MSO, so this returns `None` for them.
+    pub fn issuer_name(&self) -> Option<String> {
+        match &self.inner {
+            ParsedCredentialInner::MsoMdoc(_) => None,
+            ParsedCredentialInner::JwtVcJson(vc) | ParsedCredentialInner::JwtVcJsonLd(vc) => {
+                vc.issuer_name()
+            }
+            ParsedCredentialInner::LdpVc(vc) => vc.issuer_name(),
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => sd_jwt.issuer_name(),
+        }
+    }
+
+    /// Return when the credential was issued, as an RFC 3339 timestamp.
+    ///
+    /// VCDM credentials use `issuanceDate` or `validFrom`, falling back to the JWT `nbf` or `iat`
+    /// claims. mdocs use the signing time of the MSO.
+    pub fn issuance_date(&self) -> Option<String> {
+        match &self.inner {
+            ParsedCredentialInner::MsoMdoc(mdoc) => mdoc.signed_at(),
+            ParsedCredentialInner::JwtVcJson(vc) | ParsedCredentialInner::JwtVcJsonLd(vc) => {
+                vc.issuance_date()
+            }
+            ParsedCredentialInner::LdpVc(vc) => vc.issuance_date(),
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => sd_jwt.issuance_date(),
+        }
+    }
+
+    /// Return when the credential expires, as an RFC 3339 timestamp.
+    ///
+    /// VCDM credentials use `expirationDate` or `validUntil`, falling back to the JWT `exp`
+    /// claim. mdocs use the end of the MSO validity period.
+    pub fn expiration_date(&self) -> Option<String> {
+        match &self.inner {
+            ParsedCredentialInner::MsoMdoc(mdoc) => mdoc.valid_until(),
+            ParsedCredentialInner::JwtVcJson(vc) | ParsedCredentialInner::JwtVcJsonLd(vc) => {
+                vc.expiration_date()
+            }
+            ParsedCredentialInner::LdpVc(vc) => vc.expiration_date(),
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => sd_jwt.expiration_date(),
+        }
+    }
+
     /// Return the CredentialType from the parsed credential.
     pub fn r#type(&self) -> CredentialType {
         match &self.inner {
@@ -337,6 +599,21 @@ impl PresentableCredential {
         }
     }
 
+    /// Return the number of signatures the holder key makes when presenting the credential.
+    ///
+    /// VCDM presentations are always signed, while an SD-JWT is only signed through a key
+    /// binding JWT when transaction data is bound to the presentation.
+    pub(crate) fn signature_operations(&self, binds_transaction_data: bool) -> u32 {
+        match &self.inner {
+            ParsedCredentialInner::JwtVcJson(_)
+            | ParsedCredentialInner::JwtVcJsonLd(_)
+            | ParsedCredentialInner::LdpVc(_) => 1,
+            ParsedCredentialInner::VCDM2SdJwt(_) => binds_transaction_data.into(),
+            // Mdocs are not presented over OID4VP, see [PresentableCredential::as_vp_token].
+            ParsedCredentialInner::MsoMdoc(_) => 0,
+        }
+    }
+
     /// Return the descriptor map with the associated format type of the inner credential.
     pub fn create_descriptor_map(
         self: &Arc<Self>,
@@ -364,8 +641,333 @@ impl PresentableCredential {
     }
 }
 
+/// Display a claim value, as text for strings and JSON-encoded otherwise.
+fn claim_value_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Collect every leaf claim of a JSON credential, see [PresentableCredential::preview_disclosure].
+fn collect_disclosed_claims(
+    value: &serde_json::Value,
+    path: &mut Vec<String>,
+    disclosed: &mut Vec<DisclosedClaim>,
+) {
+    let children: Vec<(String, &serde_json::Value)> = match value {
+        serde_json::Value::Object(object) if !object.is_empty() => object
+            .iter()
+            .map(|(key, value)| (key.clone(), value))
+            .collect(),
+        serde_json::Value::Array(array) if !array.is_empty() => array
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (index.to_string(), value))
+            .collect(),
+        value => {
+            disclosed.push(DisclosedClaim::Json {
+                path: path.clone(),
+                value: claim_value_text(value),
+            });
+            return;
+        }
+    };
+
+    for (key, child) in children {
+        path.push(key);
+        collect_disclosed_claims(child, path, disclosed);
+        path.pop();
+    }
+}
+
+/// Resolve the data elements of an mdoc selected by JsonPaths over its namespaces.
+fn mdoc_disclosed_claims(
+    mdoc: &Mdoc,
+    selected_fields: &[String],
+) -> Result<Vec<DisclosedClaim>, OID4VPError> {
+    let namespaces: serde_json::Value = serde_json::from_str(
+        &mdoc
+            .namespaces_as_json_string()
+            .map_err(CredentialEncodingError::from)?,
+    )
+    .map_err(|e| OID4VPError::JsonSyntaxParse(e.to_string()))?;
+
+    let mut disclosed = vec![];
+    for field in selected_fields {
+        let path = vcdm2_sd_jwt::parse_selected_field(field)?;
+        for node in path.query_located(&namespaces) {
+            // Whole data elements are disclosed, even when the path selects part of one.
+            let pointer = node.location().to_json_pointer();
+            let mut segments = pointer
+                .split('/')
+                .skip(1)
+                .map(|segment| segment.replace("~1", "/").replace("~0", "~"));
+            let (Some(namespace), Some(element)) = (segments.next(), segments.next()) else {
+                continue;
+            };
+
+            let claim = DisclosedClaim::Mdoc {
+                value: claim_value_text(&namespaces[&namespace][&element]),
+                namespace,
+                element,
+            };
+            if !disclosed.contains(&claim) {
+                disclosed.push(claim);
+            }
+        }
+    }
+
+    Ok(disclosed)
+}
+
+/// The outcome of verifying the issuer signature of a credential, see [ParsedCredential::verify].
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct CredentialVerificationReport {
+    pub format: CredentialFormat,
+    /// Whether the issuer signature over the credential is valid.
+    pub signature_valid: bool,
+    /// Whether the mdoc document signer certificate was issued by one of the trusted roots.
+    ///
+    /// Null for formats whose issuer is identified by a DID, and when the signature could not
+    /// be verified.
+    pub issuer_trusted: Option<bool>,
+    /// The reason for each failed check.
+    pub errors: Vec<String>,
+}
+
+impl CredentialVerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.signature_valid && self.issuer_trusted != Some(false)
+    }
+}
+
+/// Verify a compact JWS against the public key of the issuer DID, resolved as `did_policy`
+/// allows.
+///
+/// The DID of the header `kid` must be the issuer named by the payload, in `iss`, `issuer` or
+/// `vc.issuer`, so that a JWS signed by any DID cannot claim another issuer.
+pub(crate) async fn verify_issuer_jws(
+    jws: &str,
+    did_policy: &DidResolutionPolicy,
+) -> Result<(), CredentialVerificationError> {
+    use ssi::prelude::*;
+
+    check_issuer_binding(jws)?;
+
+    let jws = JwsString::from_string(jws.to_string())
+        .map_err(|e| CredentialVerificationError::Parsing(e.to_string()))?;
+
+    jws.verify(VerificationParameters::from_resolver(
+        did_policy.vm_resolver(),
+    ))
+    .await
+    .map_err(|e| CredentialVerificationError::Verification(e.to_string()))?
+    .map_err(|e| CredentialVerificationError::InvalidSignature(e.to_string()))
+}
+
+/// Check that the DID of the `kid` a compact JWS is signed with is the issuer its payload names.
+fn check_issuer_binding(jws: &str) -> Result<(), CredentialVerificationError> {
+    use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+
+    let decode = |index: usize| -> Option<serde_json::Value> {
+        let part = BASE64_URL_SAFE_NO_PAD
+            .decode(jws.split('.').nth(index)?)
+            .ok()?;
+        serde_json::from_slice(&part).ok()
+    };
+    let (Some(header), Some(payload)) = (decode(0), decode(1)) else {
+        return Err(CredentialVerificationError::Parsing(
+            "invalid compact JWS".to_string(),
+        ));
+    };
+
+    let signer = header
+        .get("kid")
+        .and_then(serde_json::Value::as_str)
+        .and_then(|kid| kid.split('#').next())
+        .filter(|did| did.starts_with("did:"))
+        .ok_or_else(|| {
+            CredentialVerificationError::IssuerMismatch(
+                "the JWS header has no DID URL `kid`".to_string(),
+            )
+        })?;
+
+    // The issuer may be a string or an object with an `id`.
+    let issuers = [
+        payload.get("iss"),
+        payload.get("issuer"),
+        payload.pointer("/vc/issuer"),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|issuer| issuer.as_str().or_else(|| issuer.get("id")?.as_str()))
+    .collect::<Vec<_>>();
+
+    if issuers.is_empty() {
+        return Err(CredentialVerificationError::IssuerMismatch(
+            "the payload names no issuer".to_string(),
+        ));
+    }
+    if let Some(issuer) = issuers.into_iter().find(|issuer| *issuer != Some(signer)) {
+        return Err(CredentialVerificationError::IssuerMismatch(format!(
+            "signed by {signer}, but issued by {}",
+            issuer.unwrap_or("an issuer without an id")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Whether a credential should be re-issued, see [ParsedCredential::needs_refresh].
+///
+/// Any advice other than [RefreshAdvice::UpToDate] means the credential should be refreshed.
+#[derive(Debug, Clone, PartialEq, uniffi::Enum)]
+pub enum RefreshAdvice {
+    UpToDate,
+    /// The credential expires within the requested threshold.
+    ExpiringSoon,
+    Expired,
+    Suspended,
+    /// The credential has been revoked. This always requires a refresh, regardless of expiry.
+    Revoked,
+}
+
+impl RefreshAdvice {
+    fn from_state(
+        revoked: bool,
+        suspended: bool,
+        expiration: Option<time::OffsetDateTime>,
+        now: time::OffsetDateTime,
+        threshold: time::Duration,
+    ) -> Self {
+        match expiration {
+            _ if revoked => Self::Revoked,
+            _ if suspended => Self::Suspended,
+            Some(expiration) if expiration <= now => Self::Expired,
+            Some(expiration)
+                if now
+                    .checked_add(threshold)
+                    .is_none_or(|limit| expiration <= limit) =>
+            {
+                Self::ExpiringSoon
+            }
+            _ => Self::UpToDate,
+        }
+    }
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl ParsedCredential {
+    /// Return whether the credential should be re-issued, because it is revoked or suspended,
+    /// has expired, or expires within `threshold_secs` seconds.
+    ///
+    /// A revoked credential always needs a refresh, regardless of its expiry. The status list
+    /// is resolved over the network where the format supports it. If it cannot be resolved,
+    /// only the expiration date is considered.
+    pub async fn needs_refresh(&self, threshold_secs: u64) -> RefreshAdvice {
+        let (revoked, suspended) = match &self.inner {
+            ParsedCredentialInner::LdpVc(vc) => match vc.status().await {
+                Ok(status) => (status.is_revoked(), status.is_suspended()),
+                Err(_) => (false, false),
+            },
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => match sd_jwt.status().await {
+                Ok(statuses) => (
+                    statuses.iter().any(|status| status.is_revoked()),
+                    statuses.iter().any(|status| status.is_suspended()),
+                ),
+                Err(_) => (false, false),
+            },
+            _ => (false, false),
+        };
+
+        let expiration = self.expiration_date().and_then(|date| {
+            time::OffsetDateTime::parse(&date, &time::format_description::well_known::Rfc3339).ok()
+        });
+
+        RefreshAdvice::from_state(
+            revoked,
+            suspended,
+            expiration,
+            time::OffsetDateTime::now_utc(),
+            time::Duration::seconds(i64::try_from(threshold_secs).unwrap_or(i64::MAX)),
+        )
+    }
+
+    /// Verify the issuer signature of the credential.
+    ///
+    /// VCDM and SD-JWT credentials are verified against the issuer DID, resolved over the
+    /// network where the DID method requires it. JSON-LD contexts of LDP credentials are
+    /// loaded from `context_map` when it is given. Mdocs are verified with the document signer
+    /// certificate, which must be issued by one of `trusted_roots`, given as PEM-encoded IACA
+    /// certificates.
+    ///
+    /// Failed checks are recorded in the report rather than returned as errors.
+    pub async fn verify(
+        &self,
+        trusted_roots: Vec<String>,
+        context_map: Option<HashMap<String, String>>,
+    ) -> CredentialVerificationReport {
+        self.verify_with_did_policy(trusted_roots, context_map, DidResolutionPolicy::default())
+            .await
+    }
+
+    /// Verify the issuer signature of the credential as [ParsedCredential::verify] does,
+    /// only resolving the issuer DID if `did_policy` allows it.
+    pub async fn verify_with_did_policy(
+        &self,
+        trusted_roots: Vec<String>,
+        context_map: Option<HashMap<String, String>>,
+        did_policy: DidResolutionPolicy,
+    ) -> CredentialVerificationReport {
+        let mut report = CredentialVerificationReport {
+            format: self.format(),
+            signature_valid: false,
+            issuer_trusted: None,
+            errors: vec![],
+        };
+
+        let result = match &self.inner {
+            ParsedCredentialInner::MsoMdoc(mdoc) => match mdoc.verify_issuer_signature() {
+                Ok(x5chain) => {
+                    report.signature_valid = true;
+                    let trusted = mdoc::verify_signer_certificate(&x5chain, &trusted_roots);
+                    report.issuer_trusted = Some(trusted.is_ok());
+                    trusted.map_err(Into::into)
+                }
+                Err(e) => Err(e.into()),
+            },
+            ParsedCredentialInner::JwtVcJson(vc) | ParsedCredentialInner::JwtVcJsonLd(vc) => {
+                match String::from_utf8(vc.to_compact_jws_bytes()) {
+                    Ok(jws) => verify_issuer_jws(&jws, &did_policy).await,
+                    Err(e) => Err(CredentialVerificationError::Parsing(e.to_string())),
+                }
+            }
+            ParsedCredentialInner::LdpVc(vc) => vc.verify(context_map, &did_policy).await,
+            ParsedCredentialInner::VCDM2SdJwt(sd_jwt) => {
+                sd_jwt.verify_issuer_signature(&did_policy).await
+            }
+        };
+
+        match result {
+            Ok(()) => report.signature_valid = true,
+            Err(e) => report.errors.push(e.to_string()),
+        }
+
+        report
+    }
+}
+
 // Internal Parsed Credential methods
 impl ParsedCredential {
+    /// The encoded credential material, excluding local metadata such as the ID and key alias.
+    fn content_bytes(&self) -> Result<Vec<u8>, CredentialEncodingError> {
+        match &self.inner {
+            ParsedCredentialInner::MsoMdoc(mdoc) => Ok(mdoc.content_bytes()?),
+            _ => Ok(self.into_generic_form()?.payload),
+        }
+    }
+
     /// Check if the credential satisfies a presentation definition.
     pub fn satisfies_presentation_definition(&self, definition: &PresentationDefinition) -> bool {
         match &self.inner {
@@ -455,6 +1057,22 @@ pub enum VcdmVersion {
     V2,
 }
 
+#[derive(Debug, uniffi::Error, thiserror::Error)]
+pub enum CredentialVerificationError {
+    #[error("failed to parse the credential: {0}")]
+    Parsing(String),
+    #[error("invalid JSON-LD context map: {0}")]
+    ContextMap(String),
+    #[error("invalid issuer signature: {0}")]
+    InvalidSignature(String),
+    #[error("failed to verify issuer signature: {0}")]
+    Verification(String),
+    #[error("the signer is not the issuer: {0}")]
+    IssuerMismatch(String),
+    #[error(transparent)]
+    MsoMdoc(#[from] MdocVerificationError),
+}
+
 #[derive(Debug, uniffi::Error, thiserror::Error)]
 pub enum CredentialEncodingError {
     #[error("MsoDoc encoding error: {0}")]
@@ -485,6 +1103,12 @@ pub enum CredentialDecodingError {
     Serialization(String),
     #[error("Deserialization error: {0}")]
     Deserialization(String),
+    #[error("The credential is missing the `{0}` field")]
+    MissingField(String),
+    #[error("The `{field}` field of the credential is invalid: {reason}")]
+    InvalidField { field: String, reason: String },
+    #[error("Unknown credential format: {0}")]
+    UnknownFormat(String),
 }
 
 #[derive(Debug, uniffi::Error, thiserror::Error)]
@@ -530,8 +1154,10 @@ impl From<CredentialFormat> for String {
 }
 
 impl From<String> for CredentialFormat {
+    /// Known formats are matched ignoring case and surrounding whitespace, while unknown
+    /// formats keep the original value.
     fn from(value: String) -> Self {
-        match value.as_str() {
+        match value.trim().to_ascii_lowercase().as_str() {
             "mso_mdoc" => CredentialFormat::MsoMdoc,
             "jwt_vc_json" => CredentialFormat::JwtVcJson,
             "jwt_vc_json-ld" => CredentialFormat::JwtVcJsonLd,
@@ -542,6 +1168,88 @@ impl From<String> for CredentialFormat {
     }
 }
 
+/// Deserialize the `field` of a JSON encoded [Credential].
+fn envelope_field<T: serde::de::DeserializeOwned>(
+    envelope: &serde_json::Map<String, serde_json::Value>,
+    field: &str,
+) -> Result<T, CredentialDecodingError> {
+    let value = envelope
+        .get(field)
+        .ok_or_else(|| CredentialDecodingError::MissingField(field.to_string()))?;
+
+    serde_json::from_value(value.clone()).map_err(|e| CredentialDecodingError::InvalidField {
+        field: field.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Return the first `credentialSubject.id` of a JSON encoded VCDM credential.
+pub(crate) fn credential_subject_id(credential: &serde_json::Value) -> Option<String> {
+    match credential.get("credentialSubject")? {
+        serde_json::Value::Array(subjects) => subjects
+            .iter()
+            .find_map(|subject| subject.get("id")?.as_str()),
+        subject => subject.get("id")?.as_str(),
+    }
+    .map(ToOwned::to_owned)
+}
+
+/// Return the issuer name of a JSON encoded VCDM credential, falling back to the issuer ID.
+pub(crate) fn credential_issuer_name(credential: &serde_json::Value) -> Option<String> {
+    match credential.get("issuer")? {
+        serde_json::Value::String(id) => Some(id.as_str()),
+        issuer => issuer
+            .get("name")
+            .and_then(|name| name.as_str())
+            .or_else(|| issuer.get("id")?.as_str()),
+    }
+    .map(ToOwned::to_owned)
+}
+
+/// Return the `issuanceDate` (VCDM 1.1) or `validFrom` (VCDM 2.0) of a JSON encoded VCDM
+/// credential.
+pub(crate) fn credential_issuance_date(credential: &serde_json::Value) -> Option<String> {
+    credential
+        .get("issuanceDate")
+        .or_else(|| credential.get("validFrom"))?
+        .as_str()
+        .map(ToOwned::to_owned)
+}
+
+/// Return the expiration date of a JSON encoded VCDM credential, from `expirationDate` or
+/// `validUntil`.
+pub(crate) fn credential_expiration_date(credential: &serde_json::Value) -> Option<String> {
+    credential
+        .get("expirationDate")
+        .or_else(|| credential.get("validUntil"))?
+        .as_str()
+        .map(ToOwned::to_owned)
+}
+
+/// Return the `exp` claim of a JWT payload, as an RFC 3339 timestamp.
+pub(crate) fn jwt_expiration_date(payload: &serde_json::Value) -> Option<String> {
+    let timestamp = payload.get("exp")?.as_i64()?;
+
+    time::OffsetDateTime::from_unix_timestamp(timestamp)
+        .ok()?
+        .format(&time::format_description::well_known::Rfc3339)
+        .ok()
+}
+
+/// Return the `nbf` claim of a JWT payload, or the `iat` claim when `nbf` is missing or not a
+/// number, as an RFC 3339 timestamp.
+pub(crate) fn jwt_issuance_date(payload: &serde_json::Value) -> Option<String> {
+    let timestamp = payload
+        .get("nbf")
+        .and_then(serde_json::Value::as_i64)
+        .or_else(|| payload.get("iat")?.as_i64())?;
+
+    time::OffsetDateTime::from_unix_timestamp(timestamp)
+        .ok()?
+        .format(&time::format_description::well_known::Rfc3339)
+        .ok()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -573,4 +1281,497 @@ mod test {
 
         assert_eq!(CredentialFormat::MsoMdoc, roundtripped);
     }
+
+    #[rstest::rstest]
+    #[case::mixed_case("Mso_Mdoc", CredentialFormat::MsoMdoc)]
+    #[case::upper_case("JWT_VC_JSON", CredentialFormat::JwtVcJson)]
+    #[case::whitespace(" jwt_vc_json-ld\n", CredentialFormat::JwtVcJsonLd)]
+    #[case::unknown(" Custom_Format ", CredentialFormat::Other(" Custom_Format ".into()))]
+    fn credential_format_from_string_normalizes_known_formats(
+        #[case] value: &str,
+        #[case] expected: CredentialFormat,
+    ) {
+        assert_eq!(CredentialFormat::from(value.to_string()), expected);
+    }
+
+    #[test]
+    fn claims_as_json_string_sd_jwt() {
+        let sd_jwt = VCDM2SdJwt::new_from_compact_sd_jwt(
+            include_str!("../../tests/examples/sd_vc.jwt").to_string(),
+        )
+        .unwrap();
+
+        let claims = ParsedCredential::new_sd_jwt(sd_jwt)
+            .claims_as_json_string()
+            .unwrap();
+        let claims: serde_json::Value = serde_json::from_str(&claims).unwrap();
+
+        assert!(claims.get("credentialSubject").is_some());
+        assert!(claims.get("vc").is_none());
+    }
+
+    #[test]
+    fn new_from_json_reports_invalid_envelope_fields() {
+        let envelope = serde_json::to_value(Credential {
+            id: Uuid::new_v4(),
+            format: CredentialFormat::VCDM2SdJwt,
+            r#type: CredentialType("OpenBadgeCredential".into()),
+            payload: include_bytes!("../../tests/examples/sd_vc.jwt").to_vec(),
+            key_alias: None,
+        })
+        .unwrap();
+        let with = |field: &str, value: Option<serde_json::Value>| {
+            let mut envelope = envelope.clone();
+            let object = envelope.as_object_mut().unwrap();
+            match value {
+                Some(value) => object.insert(field.to_string(), value),
+                None => object.remove(field),
+            };
+            ParsedCredential::new_from_json(envelope.to_string())
+        };
+
+        ParsedCredential::new_from_json(envelope.to_string()).unwrap();
+        assert!(matches!(
+            with("type", None),
+            Err(CredentialDecodingError::MissingField(field)) if field == "type"
+        ));
+        assert!(matches!(
+            with("id", Some("not-a-uuid".into())),
+            Err(CredentialDecodingError::InvalidField { field, .. }) if field == "id"
+        ));
+        assert!(matches!(
+            with("payload", Some("eyJhbGciOi".into())),
+            Err(CredentialDecodingError::InvalidField { field, .. }) if field == "payload"
+        ));
+        assert!(matches!(
+            with("format", Some("jwt_vp".into())),
+            Err(CredentialDecodingError::UnknownFormat(format)) if format == "jwt_vp"
+        ));
+        assert!(matches!(
+            ParsedCredential::new_from_json("[]".into()),
+            Err(CredentialDecodingError::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn content_equals_ignores_local_metadata() {
+        let sd_jwt = include_str!("../../tests/examples/sd_vc.jwt").to_string();
+        let a = ParsedCredential::new_sd_jwt(
+            VCDM2SdJwt::new_from_compact_sd_jwt(sd_jwt.clone()).unwrap(),
+        );
+        let b = ParsedCredential::new_sd_jwt(VCDM2SdJwt::new_from_compact_sd_jwt(sd_jwt).unwrap())
+            .with_key_alias(KeyAlias("other".to_string()));
+        assert_ne!(a.id(), b.id());
+        assert!(a.content_equals(&b));
+
+        let json_vc =
+            JsonVc::new_from_json(include_str!("../../tests/examples/alumni_vc.json").into())
+                .unwrap();
+        assert!(!a.content_equals(&ParsedCredential::new_ldp_vc(json_vc)));
+    }
+
+    #[test]
+    fn signature_operations_per_format() {
+        let presentable = |inner| PresentableCredential {
+            inner,
+            limit_disclosure: false,
+            selected_fields: None,
+        };
+
+        let sd_jwt = presentable(ParsedCredentialInner::VCDM2SdJwt(
+            VCDM2SdJwt::new_from_compact_sd_jwt(
+                include_str!("../../tests/examples/sd_vc.jwt").to_string(),
+            )
+            .unwrap(),
+        ));
+        assert_eq!(sd_jwt.signature_operations(false), 0);
+        assert_eq!(sd_jwt.signature_operations(true), 1);
+
+        let ldp_vc = presentable(ParsedCredentialInner::LdpVc(
+            JsonVc::new_from_json(include_str!("../../tests/examples/alumni_vc.json").into())
+                .unwrap(),
+        ));
+        assert_eq!(ldp_vc.signature_operations(false), 1);
+    }
+
+    #[test]
+    fn refresh_advice_policy() {
+        let now = time::OffsetDateTime::now_utc();
+        let day = time::Duration::days(1);
+
+        assert_eq!(
+            RefreshAdvice::from_state(false, false, None, now, day),
+            RefreshAdvice::UpToDate
+        );
+        assert_eq!(
+            RefreshAdvice::from_state(false, false, Some(now + day * 30), now, day),
+            RefreshAdvice::UpToDate
+        );
+        assert_eq!(
+            RefreshAdvice::from_state(false, false, Some(now + day / 2), now, day),
+            RefreshAdvice::ExpiringSoon
+        );
+        assert_eq!(
+            RefreshAdvice::from_state(false, false, Some(now - day), now, day),
+            RefreshAdvice::Expired
+        );
+        assert_eq!(
+            RefreshAdvice::from_state(false, true, Some(now + day * 30), now, day),
+            RefreshAdvice::Suspended
+        );
+        // Revocation takes precedence over everything else.
+        assert_eq!(
+            RefreshAdvice::from_state(true, true, Some(now - day), now, day),
+            RefreshAdvice::Revoked
+        );
+    }
+
+    #[test]
+    fn expiration_date_from_vcdm_credentials() {
+        assert_eq!(
+            credential_expiration_date(
+                &serde_json::json!({ "validUntil": "2030-01-01T00:00:00Z" })
+            )
+            .as_deref(),
+            Some("2030-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            jwt_expiration_date(&serde_json::json!({ "exp": 0 })).as_deref(),
+            Some("1970-01-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn subject_id_from_vcdm_credentials() {
+        let json_vc =
+            JsonVc::new_from_json(include_str!("../../tests/examples/alumni_vc.json").into())
+                .unwrap();
+        assert_eq!(
+            ParsedCredential::new_ldp_vc(json_vc)
+                .subject_id()
+                .as_deref(),
+            Some("did:example:ebfeb1f712ebc6f1c276e12ec21")
+        );
+
+        // The example SD-JWT has neither a subject id nor a holder binding.
+        let sd_jwt = VCDM2SdJwt::new_from_compact_sd_jwt(
+            include_str!("../../tests/examples/sd_vc.jwt").to_string(),
+        )
+        .unwrap();
+        assert_eq!(ParsedCredential::new_sd_jwt(sd_jwt).subject_id(), None);
+
+        let subjects = serde_json::json!({
+            "credentialSubject": [{ "name": "Alice" }, { "id": "did:example:alice" }]
+        });
+        assert_eq!(
+            credential_subject_id(&subjects).as_deref(),
+            Some("did:example:alice")
+        );
+    }
+
+    #[tokio::test]
+    async fn subject_id_from_mdoc_device_key() {
+        let key_manager = std::sync::Arc::new(crate::crypto::RustTestKeyManager::default());
+        let key_alias = KeyAlias("".to_string());
+
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let mdoc = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+        let subject_id = ParsedCredential::new_mso_mdoc(Arc::new(mdoc))
+            .subject_id()
+            .unwrap();
+
+        assert!(subject_id.starts_with("did:jwk:"));
+    }
+
+    #[tokio::test]
+    async fn requires_holder_binding_per_format() {
+        let key_manager = std::sync::Arc::new(crate::crypto::RustTestKeyManager::default());
+        let key_alias = KeyAlias("".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+        let mdoc = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+        assert!(ParsedCredential::new_mso_mdoc(Arc::new(mdoc)).requires_holder_binding());
+
+        let json_vc =
+            |json: &str| ParsedCredential::new_ldp_vc(JsonVc::new_from_json(json.into()).unwrap());
+        assert!(
+            json_vc(include_str!("../../tests/examples/alumni_vc.json")).requires_holder_binding()
+        );
+        assert!(
+            !json_vc(include_str!("../../tests/examples/vehicle_title.json"))
+                .requires_holder_binding()
+        );
+
+        // The example SD-JWT has no `cnf` claim.
+        let sd_jwt = VCDM2SdJwt::new_from_compact_sd_jwt(
+            include_str!("../../tests/examples/sd_vc.jwt").to_string(),
+        )
+        .unwrap();
+        assert!(!ParsedCredential::new_sd_jwt(sd_jwt).requires_holder_binding());
+    }
+
+    #[tokio::test]
+    async fn preview_disclosure_of_sd_jwt() {
+        let sd_jwt = VCDM2SdJwt::new_from_compact_sd_jwt(
+            vcdm2_sd_jwt::tests::generate_sd_jwt().await.to_string(),
+        )
+        .unwrap();
+        let credential = PresentableCredential {
+            inner: ParsedCredentialInner::VCDM2SdJwt(sd_jwt),
+            limit_disclosure: false,
+            selected_fields: None,
+        };
+        let discloses = |claims: &[DisclosedClaim], expected: &str| {
+            claims.iter().any(
+                |claim| matches!(claim, DisclosedClaim::Json { value, .. } if value == expected),
+            )
+        };
+
+        // Claims that are not selectively disclosable are always disclosed.
+        let claims = credential.preview_disclosure(vec![]).unwrap();
+        assert!(claims.contains(&DisclosedClaim::Json {
+            path: vec![
+                "credentialSubject".into(),
+                "achievement".into(),
+                "name".into()
+            ],
+            value: "Team Membership".into(),
+        }));
+        assert!(!discloses(&claims, "John Smith"));
+
+        let claims = credential
+            .preview_disclosure(vec!["$.credentialSubject.identity[0]".into()])
+            .unwrap();
+        assert!(discloses(&claims, "John Smith"));
+    }
+
+    #[tokio::test]
+    async fn preview_disclosure_of_mdoc() {
+        let key_manager = std::sync::Arc::new(crate::crypto::RustTestKeyManager::default());
+        let key_alias = KeyAlias("".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+        let mdoc = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+        let credential = PresentableCredential {
+            inner: ParsedCredentialInner::MsoMdoc(Arc::new(mdoc)),
+            limit_disclosure: false,
+            selected_fields: None,
+        };
+
+        let claims = credential
+            .preview_disclosure(vec!["$['org.iso.18013.5.1']['given_name']".into()])
+            .unwrap();
+
+        assert_eq!(claims.len(), 1);
+        assert!(matches!(
+            &claims[0],
+            DisclosedClaim::Mdoc { namespace, element, .. }
+                if namespace == "org.iso.18013.5.1" && element == "given_name"
+        ));
+    }
+
+    #[tokio::test]
+    async fn verify_mdoc_reports_trust() {
+        let key_manager = std::sync::Arc::new(crate::crypto::RustTestKeyManager::default());
+        let key_alias = KeyAlias("".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+        let mdoc = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+        let credential = ParsedCredential::new_mso_mdoc(Arc::new(mdoc));
+
+        let iaca = include_str!("../../tests/res/mdl/iaca-certificate.pem").to_string();
+        let report = credential.verify(vec![iaca], None).await;
+        assert!(report.is_valid(), "{:?}", report.errors);
+        assert_eq!(report.issuer_trusted, Some(true));
+
+        let report = credential.verify(vec![], None).await;
+        assert!(report.signature_valid);
+        assert_eq!(report.issuer_trusted, Some(false));
+        assert!(!report.is_valid());
+    }
+
+    /// Sign `claims` as a compact JWS with `jwk`, identified by the `kid` of its `did:jwk`.
+    async fn sign_jws(jwk: &ssi::JWK, claims: serde_json::Value) -> String {
+        use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+        use ssi::claims::jws::JwsSigner;
+
+        let header = serde_json::json!({
+            "alg": "ES256",
+            "kid": ssi::dids::DIDJWK::generate_url(&jwk.to_public()).to_string(),
+        });
+        let signing_input = format!(
+            "{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(header.to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = jwk.sign_bytes(signing_input.as_bytes()).await.unwrap();
+
+        format!(
+            "{signing_input}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    #[tokio::test]
+    async fn verify_jwt_vc_binds_signer_to_issuer() {
+        let jwk = ssi::JWK::generate_p256();
+        let did = ssi::dids::DIDJWK::generate(&jwk.to_public()).to_string();
+        let jwt_vc = |issuer: &str| {
+            serde_json::json!({
+                "iss": issuer,
+                "sub": "did:example:holder",
+                "nbf": 1700000000,
+                "vc": {
+                    "@context": ["https://www.w3.org/2018/credentials/v1"],
+                    "type": ["VerifiableCredential"],
+                    "issuer": issuer,
+                    "issuanceDate": "2023-11-14T22:13:20Z",
+                    "credentialSubject": { "id": "did:example:holder", "name": "Alice" }
+                }
+            })
+        };
+
+        let credential = ParsedCredential::new_jwt_vc_json(
+            JwtVc::new_from_compact_jws(sign_jws(&jwk, jwt_vc(&did)).await).unwrap(),
+        );
+        let report = credential.verify(vec![], None).await;
+        assert!(report.is_valid(), "{:?}", report.errors);
+
+        // A JWT signed by an attacker's key cannot claim another issuer.
+        let forged = ParsedCredential::new_jwt_vc_json(
+            JwtVc::new_from_compact_jws(sign_jws(&jwk, jwt_vc("did:web:issuer.example")).await)
+                .unwrap(),
+        );
+        let report = forged.verify(vec![], None).await;
+        assert!(!report.signature_valid);
+        assert!(!report.is_valid());
+    }
+
+    #[tokio::test]
+    async fn verify_sd_jwt_binds_signer_to_issuer() {
+        use ssi::claims::{sd_jwt::SdAlg, vc_jose_cose::SdJwtVc};
+
+        let mut jwk = ssi::JWK::generate_p256();
+        jwk.key_id = Some(ssi::dids::DIDJWK::generate_url(&jwk.to_public()).into());
+        let sd_jwt = |issuer: String| {
+            let jwk = jwk.clone();
+            async move {
+                let claims: SdJwtVc = serde_json::from_value(serde_json::json!({
+                    "@context": ["https://www.w3.org/ns/credentials/v2"],
+                    "type": ["VerifiableCredential"],
+                    "issuer": issuer,
+                    "credentialSubject": { "name": "Alice" }
+                }))
+                .unwrap();
+                let sd_jwt = claims
+                    .conceal_and_sign(
+                        SdAlg::Sha256,
+                        &[ssi::json_pointer!("/credentialSubject/name")],
+                        &jwk,
+                    )
+                    .await
+                    .unwrap();
+                ParsedCredential::new_sd_jwt(
+                    VCDM2SdJwt::new_from_compact_sd_jwt(sd_jwt.to_string()).unwrap(),
+                )
+            }
+        };
+
+        let did = ssi::dids::DIDJWK::generate(&jwk.to_public()).to_string();
+        let report = sd_jwt(did).await.verify(vec![], None).await;
+        assert!(report.is_valid(), "{:?}", report.errors);
+
+        let report = sd_jwt("did:web:issuer.example".into())
+            .await
+            .verify(vec![], None)
+            .await;
+        assert!(!report.signature_valid);
+        assert!(report
+            .errors
+            .iter()
+            .any(|error| error.contains("did:web:issuer.example")));
+    }
+
+    #[test]
+    fn issuer_and_issuance_date_from_vcdm_credentials() {
+        let json_vc =
+            JsonVc::new_from_json(include_str!("../../tests/examples/alumni_vc.json").into())
+                .unwrap();
+        let parsed = ParsedCredential::new_ldp_vc(json_vc);
+
+        // The issuer has no name, so its ID is used.
+        assert_eq!(
+            parsed.issuer_name().as_deref(),
+            Some("did:key:zDnaeS7MP4xpCcwkgez9FGixW5MwUp5E7QneZ7N63g3nWn5FQ")
+        );
+        assert_eq!(
+            parsed.issuance_date().as_deref(),
+            Some("2018-05-14T12:00:00Z")
+        );
+
+        let issuer_id = serde_json::json!({ "issuer": "did:example:issuer" });
+        assert_eq!(
+            credential_issuer_name(&issuer_id).as_deref(),
+            Some("did:example:issuer")
+        );
+        let named_issuer = serde_json::json!({
+            "issuer": { "id": "did:example:issuer", "name": "Example University" }
+        });
+        assert_eq!(
+            credential_issuer_name(&named_issuer).as_deref(),
+            Some("Example University")
+        );
+        assert_eq!(
+            jwt_issuance_date(&serde_json::json!({ "iat": 1526299200 })).as_deref(),
+            Some("2018-05-14T12:00:00Z")
+        );
+    }
+
+    #[test]
+    fn jwt_issuance_date_prefers_nbf_over_iat() {
+        assert_eq!(
+            jwt_issuance_date(&serde_json::json!({ "nbf": 1526299200, "iat": 1526212800 }))
+                .as_deref(),
+            Some("2018-05-14T12:00:00Z")
+        );
+        assert_eq!(
+            jwt_issuance_date(&serde_json::json!({ "nbf": "soon", "iat": 1526212800 })).as_deref(),
+            Some("2018-05-13T12:00:00Z")
+        );
+        assert_eq!(jwt_issuance_date(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn with_key_alias_rebinds_credential() {
+        let sd_jwt = VCDM2SdJwt::new_from_compact_sd_jwt(
+            include_str!("../../tests/examples/sd_vc.jwt").to_string(),
+        )
+        .unwrap();
+        let credential = ParsedCredential::new_sd_jwt(sd_jwt);
+        assert_eq!(credential.key_alias(), None);
+
+        let key_alias = KeyAlias("rotated-key".into());
+        let rebound = credential.with_key_alias(key_alias.clone());
+        assert_eq!(rebound.id(), credential.id());
+        assert_eq!(rebound.key_alias(), Some(key_alias.clone()));
+        // The original credential is left untouched.
+        assert_eq!(credential.key_alias(), None);
+
+        let stored = rebound.into_generic_form().unwrap();
+        assert_eq!(stored.key_alias, Some(key_alias.clone()));
+        assert_eq!(
+            stored.try_into_parsed().unwrap().key_alias(),
+            Some(key_alias)
+        );
+    }
 }
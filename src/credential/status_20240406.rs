@@ -1,7 +1,7 @@
 use super::status::{StatusListError, StatusMessage};
 use crate::UniffiCustomTypeConverter;
 
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 use futures::stream::{self, StreamExt};
 use reqwest::StatusCode;
@@ -49,6 +49,21 @@ pub struct Status20240406 {
     pub(crate) purpose: StatusPurpose,
     /// List of status messages to include if the purpose is a message.
     pub status_messages: Vec<StatusMessage>,
+    /// Whether the status list could not be resolved in time, in which
+    /// case `value` carries no information.
+    pub(crate) unknown: bool,
+}
+
+impl Status20240406 {
+    /// A status for an entry whose status list could not be resolved.
+    fn unknown(purpose: StatusPurpose) -> Self {
+        Self {
+            value: 0,
+            purpose,
+            status_messages: vec![],
+            unknown: true,
+        }
+    }
 }
 
 #[uniffi::export]
@@ -58,6 +73,12 @@ impl Status20240406 {
         self.purpose
     }
 
+    /// Return whether the status list could not be resolved, e.g. because
+    /// the status server did not respond in time.
+    pub fn is_unknown(&self) -> bool {
+        self.unknown
+    }
+
     /// Return whether the credential status is revoked.
     pub fn is_revoked(&self) -> bool {
         self.purpose == StatusPurpose::Revocation && self.value == 1
@@ -84,40 +105,76 @@ impl Status20240406 {
 ///
 /// Only the `entry` method is required to be implemented.
 #[async_trait::async_trait]
-pub trait BitStringStatusListResolver20240406 {
+pub trait BitStringStatusListResolver20240406: Sync {
     /// Returns the BitstringStatusListEntry of the credential.
     fn status_list_entries(&self) -> Result<Vec<BitstringStatusListEntry>, StatusListError>;
 
+    /// Resolves the status list credential referenced by a single entry.
+    async fn status_list_credential(
+        &self,
+        entry: &BitstringStatusListEntry,
+    ) -> Result<BitstringStatusListCredential, StatusListError> {
+        let url = entry
+            .status_list_credential
+            .parse::<Url>()
+            .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
+
+        let response = reqwest::get(url)
+            .await
+            .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
+
+        if response.status() != StatusCode::OK {
+            return Err(StatusListError::Resolution(format!(
+                "Failed to resolve status list credential: {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| StatusListError::Resolution(format!("{e:?}")))
+    }
+
     /// Resolves the status list as an `BitstringStatusList` type.
     async fn status_list_credentials(
         &self,
     ) -> Result<Vec<BitstringStatusListCredential>, StatusListError> {
         let entries = self.status_list_entries()?;
-        stream::iter(entries)
+        stream::iter(entries.iter())
+            .map(|entry| self.status_list_credential(entry))
+            .buffer_unordered(3)
+            .collect::<Vec<Result<BitstringStatusListCredential, StatusListError>>>()
+            .await
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns the status of each entry, waiting at most `timeout` for each
+    /// status list to be fetched.
+    ///
+    /// Entries whose status list is not resolved in time are reported with an
+    /// unknown status rather than failing the whole check.
+    async fn status_list_values_with_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<Vec<Status20240406>, StatusListError> {
+        let entries = self.status_list_entries()?;
+        stream::iter(entries.iter())
             .map(|entry| async move {
-                let url = entry
-                    .status_list_credential
-                    .parse::<Url>()
-                    .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
-
-                let response = reqwest::get(url)
-                    .await
-                    .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
-
-                if response.status() != StatusCode::OK {
-                    return Err(StatusListError::Resolution(format!(
-                        "Failed to resolve status list credential: {}",
-                        response.status()
-                    )));
+                match tokio::time::timeout(timeout, self.status_list_credential(entry)).await {
+                    Ok(credential) => status_from_credential(entry, credential?),
+                    Err(_) => {
+                        tracing::warn!(
+                            "timed out resolving status list {}",
+                            entry.status_list_credential
+                        );
+                        Ok(Status20240406::unknown(entry.status_purpose))
+                    }
                 }
-
-                response
-                    .json()
-                    .await
-                    .map_err(|e| StatusListError::Resolution(format!("{e:?}")))
             })
-            .buffer_unordered(3)
-            .collect::<Vec<Result<BitstringStatusListCredential, StatusListError>>>()
+            .buffered(3)
+            .collect::<Vec<Result<Status20240406, StatusListError>>>()
             .await
             .into_iter()
             .collect()
@@ -133,40 +190,89 @@ pub trait BitStringStatusListResolver20240406 {
         credentials
             .into_iter()
             .map(|credential| {
-                let bit_string = credential
-                    .credential_subject
-                    .encoded_list
-                    .decode(None)
-                    // TODO: we had to hardcode the status_size to 8 to be able to find the right status.
-                    // We must analyse what is happening and remove it to use the following line:
-                    // .map(|bytes| BitString::from_bytes(credential.credential_subject.status_size, bytes))
-                    .map(|bytes| {
-                        BitString::from_bytes(StatusSize::try_from(8).unwrap_or_default(), bytes)
-                    })
-                    .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
-
-                let value = bit_string
-                    .get(
-                        entries
-                            .first()
-                            .ok_or(StatusListError::Resolution("No entry found".to_string()))?
-                            .status_list_index,
-                    )
-                    .ok_or(StatusListError::Resolution(
-                        "No status found at index".to_string(),
-                    ))?;
-
-                Ok(Status20240406 {
-                    value,
-                    purpose: credential.credential_subject.status_purpose,
-                    status_messages: credential
-                        .credential_subject
-                        .status_message
-                        .into_iter()
-                        .map(Into::into)
-                        .collect(),
-                })
+                let entry = entries
+                    .first()
+                    .ok_or(StatusListError::Resolution("No entry found".to_string()))?;
+                status_from_credential(entry, credential)
             })
             .collect()
     }
 }
+
+/// Read the status of `entry` from its resolved status list credential.
+fn status_from_credential(
+    entry: &BitstringStatusListEntry,
+    credential: BitstringStatusListCredential,
+) -> Result<Status20240406, StatusListError> {
+    let bit_string = credential
+        .credential_subject
+        .encoded_list
+        .decode(None)
+        // TODO: we had to hardcode the status_size to 8 to be able to find the right status.
+        // We must analyse what is happening and remove it to use the following line:
+        // .map(|bytes| BitString::from_bytes(credential.credential_subject.status_size, bytes))
+        .map(|bytes| BitString::from_bytes(StatusSize::try_from(8).unwrap_or_default(), bytes))
+        .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
+
+    let value = bit_string
+        .get(entry.status_list_index)
+        .ok_or(StatusListError::Resolution(
+            "No status found at index".to_string(),
+        ))?;
+
+    Ok(Status20240406 {
+        value,
+        purpose: credential.credential_subject.status_purpose,
+        status_messages: credential
+            .credential_subject
+            .status_message
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        unknown: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A resolver whose status list server never responds.
+    struct HangingResolver;
+
+    #[async_trait::async_trait]
+    impl BitStringStatusListResolver20240406 for HangingResolver {
+        fn status_list_entries(&self) -> Result<Vec<BitstringStatusListEntry>, StatusListError> {
+            let entry = serde_json::from_value(serde_json::json!({
+                "id": "https://example.com/credentials/status/3#94567",
+                "type": "BitstringStatusListEntry",
+                "statusPurpose": "revocation",
+                "statusListIndex": "94567",
+                "statusListCredential": "https://example.com/credentials/status/3"
+            }))
+            .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
+
+            Ok(vec![entry])
+        }
+
+        async fn status_list_credential(
+            &self,
+            _entry: &BitstringStatusListEntry,
+        ) -> Result<BitstringStatusListCredential, StatusListError> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn hanging_status_list_is_unknown() {
+        let statuses = HangingResolver
+            .status_list_values_with_timeout(Duration::from_millis(10))
+            .await
+            .unwrap();
+
+        assert_eq!(statuses.len(), 1);
+        assert!(statuses[0].is_unknown());
+        assert!(!statuses[0].is_revoked());
+        assert_eq!(statuses[0].purpose(), StatusPurpose::Revocation);
+    }
+}
@@ -0,0 +1,313 @@
+//! Type metadata of SD-JWT VCs, as described by the `vct` claim.
+//!
+//! See the [SD-JWT VC specification](https://datatracker.ietf.org/doc/draft-ietf-oauth-sd-jwt-vc/),
+//! Section 6.
+
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use oid4vci::oauth2::http::{header::ACCEPT, Method, Request, StatusCode};
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::oid4vci::IHttpClient;
+
+#[derive(thiserror::Error, uniffi::Error, Debug)]
+pub enum TypeMetadataError {
+    #[error("the credential has no `vct` claim")]
+    MissingVct,
+    #[error("the `vct` is not a URL type metadata can be retrieved from: {0}")]
+    UnresolvableVct(String),
+    #[error("failed to fetch type metadata: {0}")]
+    Fetch(String),
+    #[error("failed to fetch type metadata: HTTP status {0}")]
+    Status(u16),
+    #[error("invalid type metadata: {0}")]
+    Parsing(String),
+    #[error("type metadata does not match `vct#integrity`")]
+    IntegrityMismatch,
+    #[error("unsupported `vct#integrity` value: {0}")]
+    UnsupportedIntegrity(String),
+}
+
+/// Type metadata of an SD-JWT VC, describing how to display the credential and its claims.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct TypeMetadata {
+    pub vct: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// The `vct` of the type this type extends, whose metadata is not retrieved.
+    pub extends: Option<String>,
+    pub display: Vec<TypeDisplay>,
+    pub claims: Vec<ClaimMetadata>,
+}
+
+/// How to display a credential type, in a given locale.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct TypeDisplay {
+    /// The language tag of the display, e.g. `en-US`.
+    pub locale: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    /// The `simple` rendering method.
+    pub logo_uri: Option<String>,
+    pub background_color: Option<String>,
+    pub text_color: Option<String>,
+    /// The `svg_templates` rendering method.
+    pub svg_template_uris: Vec<String>,
+}
+
+/// How to display a claim of a credential type.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct ClaimMetadata {
+    /// The path to the claim, e.g. `["address", "locality"]`. Array elements are selected by
+    /// their index, or `*` for every element.
+    pub path: Vec<String>,
+    pub display: Vec<ClaimDisplay>,
+    /// Whether the claim is selectively disclosable: `always`, `allowed` or `never`.
+    pub sd: Option<String>,
+}
+
+/// The label of a claim, in a given locale.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct ClaimDisplay {
+    /// The language tag of the display, e.g. `en-US`.
+    pub locale: Option<String>,
+    pub label: String,
+    pub description: Option<String>,
+}
+
+impl TypeMetadata {
+    /// Retrieve the type metadata of `vct`, verifying it against `integrity` if provided.
+    pub(crate) async fn fetch(
+        vct: &str,
+        integrity: Option<&str>,
+        http_client: Arc<IHttpClient>,
+    ) -> Result<Self, TypeMetadataError> {
+        if !vct.starts_with("https://") {
+            return Err(TypeMetadataError::UnresolvableVct(vct.to_string()));
+        }
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(vct)
+            .header(ACCEPT, "application/json")
+            .body(Vec::new())
+            .map_err(|_| TypeMetadataError::UnresolvableVct(vct.to_string()))?;
+
+        let response = http_client
+            .call(request)
+            .await
+            .map_err(|e| TypeMetadataError::Fetch(e.to_string()))?;
+
+        if response.status() != StatusCode::OK {
+            return Err(TypeMetadataError::Status(response.status().as_u16()));
+        }
+
+        if let Some(integrity) = integrity {
+            verify_integrity(response.body(), integrity)?;
+        }
+
+        let metadata: Json = serde_json::from_slice(response.body())
+            .map_err(|e| TypeMetadataError::Parsing(e.to_string()))?;
+
+        let metadata = Self::from_json(&metadata)?;
+        if metadata.vct != vct {
+            return Err(TypeMetadataError::Parsing(format!(
+                "`vct` {} does not match the requested {vct}",
+                metadata.vct
+            )));
+        }
+
+        Ok(metadata)
+    }
+
+    fn from_json(metadata: &Json) -> Result<Self, TypeMetadataError> {
+        let vct = string(metadata, "vct")
+            .ok_or_else(|| TypeMetadataError::Parsing("`vct` is missing".into()))?;
+
+        Ok(Self {
+            vct,
+            name: string(metadata, "name"),
+            description: string(metadata, "description"),
+            extends: string(metadata, "extends"),
+            display: array(metadata, "display")
+                .filter_map(|display| {
+                    let simple = display.get("rendering").and_then(|r| r.get("simple"));
+
+                    Some(TypeDisplay {
+                        locale: string(display, "lang").or_else(|| string(display, "locale")),
+                        name: string(display, "name")?,
+                        description: string(display, "description"),
+                        logo_uri: simple
+                            .and_then(|simple| simple.get("logo"))
+                            .and_then(|logo| string(logo, "uri")),
+                        background_color: simple
+                            .and_then(|simple| string(simple, "background_color")),
+                        text_color: simple.and_then(|simple| string(simple, "text_color")),
+                        svg_template_uris: display
+                            .get("rendering")
+                            .map(|rendering| {
+                                array(rendering, "svg_templates")
+                                    .filter_map(|template| string(template, "uri"))
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
+                    })
+                })
+                .collect(),
+            claims: array(metadata, "claims")
+                .filter_map(|claim| {
+                    let path = claim
+                        .get("path")?
+                        .as_array()?
+                        .iter()
+                        .map(|element| match element {
+                            Json::String(name) => name.clone(),
+                            Json::Null => "*".to_string(),
+                            other => other.to_string(),
+                        })
+                        .collect();
+
+                    Some(ClaimMetadata {
+                        path,
+                        display: array(claim, "display")
+                            .filter_map(|display| {
+                                Some(ClaimDisplay {
+                                    locale: string(display, "lang")
+                                        .or_else(|| string(display, "locale")),
+                                    label: string(display, "label")?,
+                                    description: string(display, "description"),
+                                })
+                            })
+                            .collect(),
+                        sd: string(claim, "sd"),
+                    })
+                })
+                .collect(),
+        })
+    }
+}
+
+fn string(value: &Json, key: &str) -> Option<String> {
+    value.get(key)?.as_str().map(ToOwned::to_owned)
+}
+
+fn array<'a>(value: &'a Json, key: &str) -> impl Iterator<Item = &'a Json> {
+    value
+        .get(key)
+        .and_then(Json::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// Verify `document` against a W3C Subresource Integrity value, e.g. `sha256-<base64>`.
+///
+/// The value may list several hashes separated by whitespace, in which case one of the
+/// supported hashes must match.
+fn verify_integrity(document: &[u8], integrity: &str) -> Result<(), TypeMetadataError> {
+    let mut supported = false;
+
+    for hash in integrity.split_whitespace() {
+        let Some((algorithm, expected)) = hash.split_once('-') else {
+            continue;
+        };
+        // Options, e.g. `sha256-<base64>?ct=application/json`, are ignored.
+        let expected = expected.split('?').next().unwrap_or_default();
+
+        let digest = match algorithm {
+            "sha256" => Sha256::digest(document).to_vec(),
+            "sha384" => Sha384::digest(document).to_vec(),
+            "sha512" => Sha512::digest(document).to_vec(),
+            _ => continue,
+        };
+        supported = true;
+
+        if STANDARD.encode(digest) == expected {
+            return Ok(());
+        }
+    }
+
+    match supported {
+        true => Err(TypeMetadataError::IntegrityMismatch),
+        false => Err(TypeMetadataError::UnsupportedIntegrity(
+            integrity.to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::oid4vci::{AsyncHttpClient, HttpClientError, HttpRequest, HttpResponse};
+
+    use super::*;
+
+    const METADATA: &str = r##"{
+        "vct": "https://issuer.example/vct/membership",
+        "name": "Membership",
+        "display": [{
+            "lang": "en-US",
+            "name": "Membership Card",
+            "rendering": {
+                "simple": {
+                    "logo": { "uri": "https://issuer.example/logo.png" },
+                    "background_color": "#12107c",
+                    "text_color": "#ffffff"
+                }
+            }
+        }],
+        "claims": [
+            { "path": ["name"], "display": [{ "lang": "en-US", "label": "Name" }], "sd": "allowed" },
+            { "path": ["roles", null], "display": [{ "lang": "en-US", "label": "Role" }] }
+        ]
+    }"##;
+
+    struct StaticClient(&'static str);
+
+    #[async_trait::async_trait]
+    impl AsyncHttpClient for StaticClient {
+        async fn http_client(&self, _: HttpRequest) -> Result<HttpResponse, HttpClientError> {
+            Ok(HttpResponse {
+                status_code: 200,
+                headers: HashMap::new(),
+                body: self.0.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn fetches_type_metadata_with_integrity() {
+        let client = || {
+            let client: Arc<dyn AsyncHttpClient> = Arc::new(StaticClient(METADATA));
+            Arc::new(IHttpClient::from(client))
+        };
+        let vct = "https://issuer.example/vct/membership";
+        let integrity = format!("sha256-{}", STANDARD.encode(Sha256::digest(METADATA)));
+
+        let metadata = TypeMetadata::fetch(vct, Some(&integrity), client())
+            .await
+            .unwrap();
+
+        assert_eq!(metadata.vct, vct);
+        assert_eq!(metadata.display[0].name, "Membership Card");
+        assert_eq!(
+            metadata.display[0].background_color.as_deref(),
+            Some("#12107c")
+        );
+        assert_eq!(metadata.claims[1].path, vec!["roles", "*"]);
+        assert_eq!(metadata.claims[0].display[0].label, "Name");
+
+        let tampered = format!("sha256-{}", STANDARD.encode(Sha256::digest("{}")));
+        assert!(matches!(
+            TypeMetadata::fetch(vct, Some(&tampered), client()).await,
+            Err(TypeMetadataError::IntegrityMismatch)
+        ));
+
+        assert!(matches!(
+            TypeMetadata::fetch("https://issuer.example/vct/other", None, client()).await,
+            Err(TypeMetadataError::Parsing(_))
+        ));
+    }
+}
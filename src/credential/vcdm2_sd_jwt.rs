@@ -3,23 +3,30 @@ use super::{
     status_20240406::{
         BitStringStatusListResolver20240406 as BitStringStatusListResolver, Status20240406,
     },
-    Credential, CredentialFormat, ParsedCredential, ParsedCredentialInner,
+    type_metadata::{TypeMetadata, TypeMetadataError},
+    verify_issuer_jws, Credential, CredentialFormat, CredentialVerificationError, ParsedCredential,
+    ParsedCredentialInner,
 };
 use crate::{
     crypto::KeyAlias,
+    did::DidResolutionPolicy,
+    oid4vci::IHttpClient,
     oid4vp::{
         error::OID4VPError,
-        presentation::{CredentialPresentation, PresentationOptions},
-        ResponseOptions,
+        iso_18013_7::requested_values,
+        presentation::{CredentialPresentation, PresentationError, PresentationOptions},
+        ResponseOptions, TRANSACTION_DATA_HASH_ALG,
     },
     CredentialType,
 };
 
 use core::str;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use base64::{engine::general_purpose::URL_SAFE, Engine as _};
-use futures::stream::{self, StreamExt};
+use base64::{
+    engine::general_purpose::{URL_SAFE, URL_SAFE_NO_PAD},
+    Engine as _,
+};
 use openid4vp::{
     core::{
         credential_format::ClaimFormatDesignation, presentation_submission::DescriptorMap,
@@ -28,6 +35,7 @@ use openid4vp::{
     JsonPath,
 };
 use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
 use ssi::{
     claims::{
         jwt::AnyClaims,
@@ -44,7 +52,7 @@ use ssi::{
 use url::Url;
 use uuid::Uuid;
 
-#[derive(Debug, uniffi::Object)]
+#[derive(Debug, Clone, uniffi::Object)]
 pub struct VCDM2SdJwt {
     pub(crate) id: Uuid,
     pub(crate) key_alias: Option<KeyAlias>,
@@ -79,6 +87,180 @@ impl VCDM2SdJwt {
     fn format() -> CredentialFormat {
         CredentialFormat::VCDM2SdJwt
     }
+
+    /// Return the compact SD-JWT as presented, keeping only the disclosures needed to reveal
+    /// `selected_fields`, or every disclosure without a selection.
+    fn presented(&self, selected_fields: Option<Vec<String>>) -> Result<String, OID4VPError> {
+        let Some(selected_fields) = selected_fields else {
+            return Ok(self.inner.as_str().to_string());
+        };
+
+        let selected_fields_pointers =
+            with_parent_pointers(self.disclosures_for_paths(selected_fields)?)?;
+
+        Ok(self
+            .inner
+            .decode_reveal::<AnyClaims>()
+            .map_err(|e| OID4VPError::Debug(e.to_string()))?
+            .retaining(&selected_fields_pointers)
+            .into_encoded()
+            .as_str()
+            .to_string())
+    }
+
+    /// Resolve selected fields, as JsonPaths or in the holder encoding, to the pointers of the
+    /// claims to disclose, without duplicates.
+    ///
+    /// Selections can be merged by concatenating their paths. Fails if a path does not
+    /// resolve to a claim of the credential.
+    pub fn disclosures_for_paths(
+        &self,
+        paths: Vec<String>,
+    ) -> Result<Vec<JsonPointerBuf>, OID4VPError> {
+        let json = self.revealed_claims_as_json().map_err(|e| {
+            OID4VPError::CredentialEncoding(super::CredentialEncodingError::SdJwt(e))
+        })?;
+
+        let mut pointers: Vec<JsonPointerBuf> = Vec::new();
+        for sfield in paths {
+            let path = parse_selected_field(&sfield)?;
+            let located_node = path.query_located(&json);
+
+            let pointer = match located_node.first() {
+                Some(node) => JsonPointerBuf::new(node.location().to_json_pointer())
+                    .map_err(|e| OID4VPError::JsonPathToPointer(e.to_string()))?,
+                // A request for an `age_over_NN` claim the credential does not have may be
+                // answered by another `age_over_NN` claim.
+                None => self.age_over_fallback(&path).ok_or_else(|| {
+                    OID4VPError::JsonPathResolve(format!("Unable to resolve JsonPath: {}", path))
+                })?,
+            };
+
+            if !pointers.contains(&pointer) {
+                pointers.push(pointer);
+            }
+        }
+
+        Ok(pointers)
+    }
+
+    /// The pointer of the claim answering a selected `credentialSubject.age_over_NN` field,
+    /// see [VCDM2SdJwt::age_over_claim].
+    fn age_over_fallback(&self, path: &JsonPath) -> Option<JsonPointerBuf> {
+        let path = path.to_string();
+        let (parent, name) = split_last_name_segment(&path)?;
+        if split_last_name_segment(parent) != Some(("$", "credentialSubject")) {
+            return None;
+        }
+
+        let age = requested_values::age_from_str(name.strip_prefix("age_over_")?)?;
+        JsonPointerBuf::new(self.age_over_claim(age)?).ok()
+    }
+
+    /// Return the claims revealed by presenting `selected_fields`, including the claims that
+    /// are not selectively disclosable.
+    pub(crate) fn disclosed_claims(
+        &self,
+        selected_fields: Vec<String>,
+    ) -> Result<serde_json::Value, OID4VPError> {
+        let presented = SdJwtBuf::new(self.presented(Some(selected_fields))?)
+            .map_err(|e| OID4VPError::Debug(format!("{e:?}")))?;
+        let SdJwtVc(vc) = SdJwtVc::decode_reveal_any(&presented)
+            .map_err(|e| OID4VPError::Debug(e.to_string()))?
+            .into_claims()
+            .private;
+
+        serde_json::to_value(vc).map_err(|e| OID4VPError::Debug(e.to_string()))
+    }
+
+    /// The `credentialSubject.id` of the credential, falling back to the `sub` claim and then
+    /// to the `cnf` holder binding of the issuer-signed JWT.
+    pub(crate) fn subject_id(&self) -> Option<String> {
+        if let Some(id) = self
+            .revealed_claims_as_json()
+            .ok()
+            .as_ref()
+            .and_then(super::credential_subject_id)
+        {
+            return Some(id);
+        }
+
+        let payload = self.issuer_signed_payload()?;
+
+        if let Some(sub) = payload.get("sub").and_then(|sub| sub.as_str()) {
+            return Some(sub.to_owned());
+        }
+
+        let cnf = payload.get("cnf")?;
+        if let Some(kid) = cnf.get("kid").and_then(|kid| kid.as_str()) {
+            return Some(kid.to_owned());
+        }
+
+        let jwk: ssi::JWK = serde_json::from_value(cnf.get("jwk")?.clone()).ok()?;
+        Some(ssi::dids::DIDJWK::generate(&jwk).to_string())
+    }
+
+    /// Whether the issuer-signed JWT binds the credential to a holder key through `cnf`.
+    pub(crate) fn has_holder_binding(&self) -> bool {
+        self.issuer_signed_payload()
+            .is_some_and(|payload| payload.get("cnf").is_some())
+    }
+
+    /// The issuer of the credential, falling back to the `iss` claim of the issuer-signed JWT.
+    pub(crate) fn issuer_name(&self) -> Option<String> {
+        self.revealed_claims_as_json()
+            .ok()
+            .as_ref()
+            .and_then(super::credential_issuer_name)
+            .or_else(|| {
+                self.issuer_signed_payload()?
+                    .get("iss")?
+                    .as_str()
+                    .map(ToOwned::to_owned)
+            })
+    }
+
+    /// The issuance date of the credential, falling back to the `nbf` or `iat` claims of the
+    /// issuer-signed JWT.
+    pub(crate) fn issuance_date(&self) -> Option<String> {
+        self.revealed_claims_as_json()
+            .ok()
+            .as_ref()
+            .and_then(super::credential_issuance_date)
+            .or_else(|| super::jwt_issuance_date(&self.issuer_signed_payload()?))
+    }
+
+    pub(crate) fn expiration_date(&self) -> Option<String> {
+        self.revealed_claims_as_json()
+            .ok()
+            .as_ref()
+            .and_then(super::credential_expiration_date)
+            .or_else(|| super::jwt_expiration_date(&self.issuer_signed_payload()?))
+    }
+
+    /// The decoded payload of the issuer-signed JWT, without disclosures applied.
+    fn issuer_signed_payload(&self) -> Option<serde_json::Value> {
+        let compact: &str = self.inner.as_ref();
+        let payload = compact.split('~').next()?.split('.').nth(1)?;
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()
+    }
+
+    /// Verify the signature of the issuer-signed JWT against the issuer DID.
+    pub(crate) async fn verify_issuer_signature(
+        &self,
+        did_policy: &DidResolutionPolicy,
+    ) -> Result<(), CredentialVerificationError> {
+        let compact: &str = self.inner.as_ref();
+        verify_issuer_jws(compact.split('~').next().unwrap_or_default(), did_policy).await
+    }
+
+    /// Return a copy of the credential bound to a different key alias.
+    pub(crate) fn with_key_alias(&self, key_alias: KeyAlias) -> Self {
+        Self {
+            key_alias: Some(key_alias),
+            ..self.clone()
+        }
+    }
 }
 
 #[uniffi::export(async_runtime = "tokio")]
@@ -95,6 +277,19 @@ impl VCDM2SdJwt {
         Ok(Arc::new(sd_jwt))
     }
 
+    /// Create a new SdJwt instance from a compact SD-JWS string, checked against `limits`
+    /// instead of [SdJwtLimits::default].
+    #[uniffi::constructor]
+    pub fn new_from_compact_sd_jwt_with_limits(
+        input: String,
+        limits: SdJwtLimits,
+    ) -> Result<Arc<Self>, SdJwtError> {
+        let inner: SdJwtBuf =
+            SdJwtBuf::new(input).map_err(|e| SdJwtError::InvalidSdJwt(format!("{e:?}")))?;
+
+        Ok(Arc::new(VCDM2SdJwt::try_from_with_limits(inner, &limits)?))
+    }
+
     /// Create a new SdJwt instance from a compact SD-JWS string with a provided key alias.
     #[uniffi::constructor]
     pub fn new_from_compact_sd_jwt_with_key(
@@ -132,6 +327,72 @@ impl VCDM2SdJwt {
             .map_err(|e| SdJwtError::Serialization(format!("{e:?}")))
     }
 
+    /// Return the JSON pointers of the claims disclosed when presenting the selected `paths`,
+    /// see [VCDM2SdJwt::disclosures_for_paths]. The disclosures of objects containing these
+    /// claims are presented as well.
+    ///
+    /// This allows a selection to be validated and previewed before the presentation is built.
+    pub fn disclosure_pointers_for_paths(
+        &self,
+        paths: Vec<String>,
+    ) -> Result<Vec<String>, OID4VPError> {
+        Ok(with_parent_pointers(self.disclosures_for_paths(paths)?)?
+            .iter()
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    /// Whether the credential has a claim at the JSON pointer, e.g.
+    /// `/credentialSubject/age_over_18`, whether or not it is selectively disclosable.
+    pub fn has_claim(&self, pointer: String) -> bool {
+        self.revealed_claims_as_json()
+            .is_ok_and(|json| json.pointer(&pointer).is_some())
+    }
+
+    /// The JSON pointer of the boolean `credentialSubject.age_over_NN` claim that answers
+    /// whether the holder is over `age`, following the rules in ISO/IEC 18013-5 Section 7.2.5.
+    ///
+    /// For example, with `age_over_18: true` and `age_over_21: true`, a request for age 20 is
+    /// answered by `age_over_21`. Returns null if no claim can answer the request, e.g. when
+    /// the credential only has a birth date.
+    pub fn age_over_claim(&self, age: u8) -> Option<String> {
+        let json = self.revealed_claims_as_json().ok()?;
+        let mut age_over_x_claims: Vec<(u8, bool)> = json
+            .get("credentialSubject")?
+            .as_object()?
+            .iter()
+            .filter_map(|(name, value)| {
+                name.strip_prefix("age_over_")
+                    .and_then(requested_values::age_from_str)
+                    .zip(value.as_bool())
+            })
+            .collect();
+        age_over_x_claims.sort_by_key(|(age, _)| *age);
+
+        requested_values::responding_age(age_over_x_claims, age)
+            .map(|age| format!("/credentialSubject/age_over_{age:02}"))
+    }
+
+    /// Retrieve the type metadata referenced by the `vct` claim, with the display names of the
+    /// credential and its claims, verified against `vct#integrity` when present.
+    pub async fn fetch_type_metadata(
+        &self,
+        http_client: Arc<IHttpClient>,
+    ) -> Result<TypeMetadata, TypeMetadataError> {
+        let claims = self
+            .issuer_signed_payload()
+            .ok_or(TypeMetadataError::MissingVct)?;
+        let vct = claims
+            .get("vct")
+            .and_then(serde_json::Value::as_str)
+            .ok_or(TypeMetadataError::MissingVct)?;
+        let integrity = claims
+            .get("vct#integrity")
+            .and_then(serde_json::Value::as_str);
+
+        TypeMetadata::fetch(vct, integrity, http_client).await
+    }
+
     /// Returns the status of the credential, resolving the value in the status list,
     /// along with the purpose of the status.
     pub async fn status(&self) -> Result<Vec<Arc<Status20240406>>, StatusListError> {
@@ -139,6 +400,20 @@ impl VCDM2SdJwt {
             .await
             .map(|v| v.into_iter().map(Arc::new).collect())
     }
+
+    /// Returns the status of the credential, waiting at most `timeout_ms` milliseconds
+    /// for each status list to be fetched.
+    ///
+    /// Status lists that are not fetched in time are reported with an unknown status,
+    /// see [Status20240406::is_unknown].
+    pub async fn status_with_timeout(
+        &self,
+        timeout_ms: u64,
+    ) -> Result<Vec<Arc<Status20240406>>, StatusListError> {
+        self.status_list_values_with_timeout(Duration::from_millis(timeout_ms))
+            .await
+            .map(|v| v.into_iter().map(Arc::new).collect())
+    }
 }
 
 impl CredentialPresentation for VCDM2SdJwt {
@@ -150,6 +425,10 @@ impl CredentialPresentation for VCDM2SdJwt {
         &self.credential
     }
 
+    fn credential_types(&self) -> Vec<String> {
+        self.types()
+    }
+
     fn presentation_format(&self) -> Self::PresentationFormat {
         ClaimFormatDesignation::Other(Self::format().to_string())
     }
@@ -161,7 +440,7 @@ impl CredentialPresentation for VCDM2SdJwt {
     /// Return the credential as a VpToken
     async fn as_vp_token_item<'a>(
         &self,
-        _options: &'a PresentationOptions<'a>,
+        options: &'a PresentationOptions<'a>,
         selected_fields: Option<Vec<String>>,
         limit_disclosure: bool,
     ) -> Result<VpTokenItem, OID4VPError> {
@@ -171,59 +450,14 @@ impl CredentialPresentation for VCDM2SdJwt {
             ));
         }
 
-        let compact: &str = self.inner.as_ref();
-        let vp_token = if let Some(selected_fields) = selected_fields {
-            let json = self.revealed_claims_as_json().map_err(|e| {
-                OID4VPError::CredentialEncoding(super::CredentialEncodingError::SdJwt(e))
-            })?;
-
-            let selected_fields_pointers = selected_fields
-                .into_iter()
-                .map(|sfield| {
-                    // TODO: Remove hotfix encoding and improve path usage
-                    // SAFETY: encoded by client (sprucekit-mobile@holder)
-                    let path = sfield.split(",").next().unwrap().to_owned();
-                    let path = match URL_SAFE.decode(path) {
-                        Ok(path) => path,
-                        Err(err) => return Err(OID4VPError::JsonPathParse(err.to_string())),
-                    };
-                    let path = match str::from_utf8(&path) {
-                        Ok(path) => path,
-                        Err(err) => return Err(OID4VPError::JsonPathParse(err.to_string())),
-                    };
-                    let path = match JsonPath::parse(path) {
-                        Ok(path) => path,
-                        Err(err) => return Err(OID4VPError::JsonPathParse(err.to_string())),
-                    };
-                    let located_node = path.query_located(&json);
-
-                    if located_node.is_empty() {
-                        Err(OID4VPError::JsonPathResolve(format!(
-                            "Unable to resolve JsonPath: {}",
-                            path
-                        )))
-                    } else {
-                        // SAFETY: Empty check above
-                        JsonPointerBuf::new(
-                            located_node.first().unwrap().location().to_json_pointer(),
-                        )
-                        .map_err(|e| OID4VPError::JsonPathToPointer(e.to_string()))
-                    }
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-
-            let ret = self
-                .inner
-                .decode_reveal::<AnyClaims>()
-                .map_err(|e| OID4VPError::Debug(e.to_string()))?
-                .retaining(&selected_fields_pointers)
-                .into_encoded()
-                .as_str()
-                .to_string();
-            ret
-        } else {
-            compact.to_string()
-        };
+        let vp_token = self.presented(selected_fields)?;
+
+        if !options.binds_transaction_data() {
+            return Ok(VpTokenItem::String(vp_token));
+        }
+
+        // Transaction data is bound to the presentation through a key binding JWT.
+        let vp_token = append_key_binding(vp_token, options).await?;
 
         Ok(VpTokenItem::String(vp_token))
     }
@@ -249,6 +483,41 @@ impl CredentialPresentation for VCDM2SdJwt {
     }
 }
 
+/// Parse a selected field into a JsonPath.
+///
+/// Fields starting with `$` are taken as plain JsonPaths. Anything else is
+/// expected to use the holder encoding, where the path is base64url encoded
+/// and followed by a comma separated suffix.
+pub(crate) fn parse_selected_field(field: &str) -> Result<JsonPath, PresentationError> {
+    let invalid = |reason: String| PresentationError::SelectedField {
+        field: field.to_string(),
+        reason,
+    };
+
+    if field.starts_with('$') {
+        return JsonPath::parse(field).map_err(|e| invalid(e.to_string()));
+    }
+
+    // TODO: Remove hotfix encoding and improve path usage
+    let encoded = field.split(',').next().unwrap_or_default();
+    let decoded = URL_SAFE
+        .decode(encoded)
+        .map_err(|e| invalid(format!("not a base64url encoded JsonPath: {e}")))?;
+    let path = str::from_utf8(&decoded)
+        .map_err(|e| invalid(format!("decoded JsonPath is not UTF-8: {e}")))?;
+
+    JsonPath::parse(path).map_err(|e| invalid(e.to_string()))
+}
+
+/// Split a JsonPath ending with a name selector, e.g. `$.credentialSubject.name` or
+/// `$['credentialSubject']['name']`, into the path of its parent and the name.
+fn split_last_name_segment(path: &str) -> Option<(&str, &str)> {
+    match path.strip_suffix("']").or_else(|| path.strip_suffix("\"]")) {
+        Some(path) => path.rsplit_once("['").or_else(|| path.rsplit_once("[\"")),
+        None => path.rsplit_once('.'),
+    }
+}
+
 #[async_trait::async_trait]
 impl BitStringStatusListResolver for VCDM2SdJwt {
     fn status_list_entries(&self) -> Result<Vec<BitstringStatusListEntry>, StatusListError> {
@@ -276,53 +545,45 @@ impl BitStringStatusListResolver for VCDM2SdJwt {
         Ok(entries)
     }
 
-    async fn status_list_credentials(
+    async fn status_list_credential(
         &self,
-    ) -> Result<Vec<BitstringStatusListCredential>, StatusListError> {
-        let entries = self.status_list_entries()?;
-        stream::iter(entries)
-            .map(|entry| async move {
-                let url = entry
-                    .status_list_credential
-                    .parse::<Url>()
-                    .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
-
-                let response = reqwest::get(url)
-                    .await
-                    .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
-
-                if response.status() != StatusCode::OK {
-                    return Err(StatusListError::Resolution(format!(
-                        "Failed to resolve status list credential: {}",
-                        response.status()
-                    )));
-                }
-
-                let sd_jwt_buf = SdJwtBuf::new(
-                    response
-                        .text()
-                        .await
-                        .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?,
-                )
-                .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
-
-                let credential = sd_jwt_buf
-                    .decode_reveal_any()
-                    .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?
-                    .into_claims()
-                    .private;
-
-                serde_json::from_value(
-                    serde_json::to_value(credential)
-                        .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?,
-                )
-                .map_err(|e| StatusListError::Resolution(format!("{e:?}")))
-            })
-            .buffer_unordered(3)
-            .collect::<Vec<Result<BitstringStatusListCredential, StatusListError>>>()
+        entry: &BitstringStatusListEntry,
+    ) -> Result<BitstringStatusListCredential, StatusListError> {
+        let url = entry
+            .status_list_credential
+            .parse::<Url>()
+            .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
+
+        let response = reqwest::get(url)
             .await
-            .into_iter()
-            .collect()
+            .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
+
+        if response.status() != StatusCode::OK {
+            return Err(StatusListError::Resolution(format!(
+                "Failed to resolve status list credential: {}",
+                response.status()
+            )));
+        }
+
+        let sd_jwt_buf = SdJwtBuf::new(
+            response
+                .text()
+                .await
+                .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?,
+        )
+        .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?;
+
+        let credential = sd_jwt_buf
+            .decode_reveal_any()
+            .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?
+            .into_claims()
+            .private;
+
+        serde_json::from_value(
+            serde_json::to_value(credential)
+                .map_err(|e| StatusListError::Resolution(format!("{e:?}")))?,
+        )
+        .map_err(|e| StatusListError::Resolution(format!("{e:?}")))
     }
 
     // NOTE: The remaining methods are default implemented in the trait.
@@ -384,6 +645,18 @@ impl TryFrom<SdJwtBuf> for VCDM2SdJwt {
     type Error = SdJwtError;
 
     fn try_from(value: SdJwtBuf) -> Result<Self, Self::Error> {
+        Self::try_from_with_limits(value, &SdJwtLimits::default())
+    }
+}
+
+impl VCDM2SdJwt {
+    /// Decode an SD-JWT, rejecting it before its disclosures are decoded if it exceeds `limits`.
+    pub(crate) fn try_from_with_limits(
+        value: SdJwtBuf,
+        limits: &SdJwtLimits,
+    ) -> Result<Self, SdJwtError> {
+        limits.check(value.as_str())?;
+
         let SdJwtVc(credential) = SdJwtVc::decode_reveal_any(&value)
             .map_err(|e| SdJwtError::SdJwtDecoding(format!("{e:?}")))?
             .into_claims()
@@ -430,6 +703,214 @@ pub fn list_sd_fields(input: Arc<VCDM2SdJwt>) -> Result<Vec<String>, SdJwtError>
     inner_list_sd_fields(&input)
 }
 
+/// The result of verifying an SD-JWT presentation, with the outcome of each check.
+#[derive(Debug, Clone, Default, uniffi::Record)]
+pub struct SdJwtVerificationReport {
+    /// Whether the issuer-signed JWT has a valid signature from the issuer DID.
+    pub issuer_signature_valid: bool,
+    /// Whether every disclosure is referenced by the issuer-signed JWT.
+    pub disclosures_valid: bool,
+    /// Whether the KB-JWT is signed by the holder key in `cnf.jwk`, its `sd_hash` covers the
+    /// presented SD-JWT, and its `iat` is within the last [KB_JWT_MAX_AGE_SECS] seconds.
+    pub key_binding_valid: bool,
+    /// Whether the KB-JWT `aud` is the expected audience.
+    pub audience_matches: bool,
+    /// Whether the KB-JWT `nonce` is the expected nonce.
+    pub nonce_matches: bool,
+    /// The revealed claims as a JSON string, when the disclosures could be decoded.
+    pub claims: Option<String>,
+    /// The reason for each failed check.
+    pub errors: Vec<String>,
+}
+
+impl SdJwtVerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issuer_signature_valid
+            && self.disclosures_valid
+            && self.key_binding_valid
+            && self.audience_matches
+            && self.nonce_matches
+    }
+}
+
+/// How long after its `iat` a KB-JWT is accepted, in seconds.
+pub const KB_JWT_MAX_AGE_SECS: i64 = 300;
+
+/// How far in the future a KB-JWT `iat` may be, to tolerate clock skew, in seconds.
+const KB_JWT_CLOCK_SKEW_SECS: i64 = 60;
+
+/// Verify a received SD-JWT presentation: the issuer signature, the disclosures, and that
+/// the KB-JWT binds the presentation to the holder key, `expected_audience` and
+/// `expected_nonce`.
+///
+/// Failed checks are recorded in the report rather than returned as errors, so that a
+/// verifier can show every problem with the presentation at once.
+#[uniffi::export]
+pub async fn verify_sd_jwt_presentation(
+    sd_jwt: String,
+    expected_audience: String,
+    expected_nonce: String,
+) -> SdJwtVerificationReport {
+    verify_sd_jwt_presentation_with_did_policy(
+        sd_jwt,
+        expected_audience,
+        expected_nonce,
+        DidResolutionPolicy::default(),
+    )
+    .await
+}
+
+/// Verify a received SD-JWT presentation like [verify_sd_jwt_presentation], only resolving
+/// the issuer DIDs allowed by `did_policy`.
+#[uniffi::export]
+pub async fn verify_sd_jwt_presentation_with_did_policy(
+    sd_jwt: String,
+    expected_audience: String,
+    expected_nonce: String,
+    did_policy: DidResolutionPolicy,
+) -> SdJwtVerificationReport {
+    use ssi::prelude::*;
+
+    let mut report = SdJwtVerificationReport::default();
+
+    // The KB-JWT follows the last separator, and everything up to it is covered by `sd_hash`.
+    let Some(separator) = sd_jwt.rfind('~') else {
+        report
+            .errors
+            .push("SD-JWT presentation has no `~` separator".to_string());
+        return report;
+    };
+    let (presented, kb_jwt) = sd_jwt.split_at(separator + 1);
+    let issuer_jwt = presented.split('~').next().unwrap_or_default();
+
+    match verify_issuer_jws(issuer_jwt, &did_policy).await {
+        Ok(()) => report.issuer_signature_valid = true,
+        Err(e) => report.errors.push(e.to_string()),
+    }
+
+    let mut holder_jwk = None;
+    match SdJwtBuf::new(presented.to_string())
+        .map_err(|e| format!("{e:?}"))
+        .and_then(|sd_jwt| {
+            sd_jwt
+                .decode_reveal::<AnyClaims>()
+                .map_err(|e| e.to_string())
+        }) {
+        Ok(revealed) => {
+            report.disclosures_valid = true;
+            let claims = serde_json::to_value(revealed.into_claims()).unwrap_or_default();
+            holder_jwk = claims
+                .pointer("/cnf/jwk")
+                .and_then(|jwk| serde_json::from_value::<ssi::JWK>(jwk.clone()).ok());
+            report.claims = serde_json::to_string(&claims).ok();
+        }
+        Err(e) => report.errors.push(format!("invalid disclosures: {e}")),
+    }
+
+    if kb_jwt.is_empty() {
+        report
+            .errors
+            .push("SD-JWT presentation has no key binding JWT".to_string());
+        return report;
+    }
+
+    let (Some(header), Some(kb_claims)) = (decode_jwt_part(kb_jwt, 0), decode_jwt_part(kb_jwt, 1))
+    else {
+        report.errors.push("invalid key binding JWT".to_string());
+        return report;
+    };
+
+    let signature_valid = match (holder_jwk, JwsString::from_string(kb_jwt.to_string())) {
+        (None, _) => {
+            report
+                .errors
+                .push("SD-JWT has no `cnf.jwk` holder key".to_string());
+            false
+        }
+        (Some(_), Err(e)) => {
+            report.errors.push(format!("invalid key binding JWT: {e}"));
+            false
+        }
+        (Some(jwk), Ok(jws)) => {
+            match jws.verify(VerificationParameters::from_resolver(jwk)).await {
+                Ok(Ok(())) => true,
+                Ok(Err(e)) => {
+                    report
+                        .errors
+                        .push(format!("invalid key binding signature: {e}"));
+                    false
+                }
+                Err(e) => {
+                    report
+                        .errors
+                        .push(format!("failed to verify key binding signature: {e}"));
+                    false
+                }
+            }
+        }
+    };
+
+    let typ_valid = header.get("typ").and_then(serde_json::Value::as_str) == Some("kb+jwt");
+    if !typ_valid {
+        report
+            .errors
+            .push("key binding JWT `typ` is not `kb+jwt`".to_string());
+    }
+
+    let sd_hash = URL_SAFE_NO_PAD.encode(Sha256::digest(presented.as_bytes()));
+    let sd_hash_valid =
+        kb_claims.get("sd_hash").and_then(serde_json::Value::as_str) == Some(sd_hash.as_str());
+    if !sd_hash_valid {
+        report
+            .errors
+            .push("key binding JWT `sd_hash` does not match the presentation".to_string());
+    }
+
+    // An old KB-JWT may have been captured from another presentation and replayed.
+    let now = time::OffsetDateTime::now_utc().unix_timestamp();
+    let iat_valid = kb_claims
+        .get("iat")
+        .and_then(serde_json::Value::as_i64)
+        .is_some_and(|iat| iat <= now + KB_JWT_CLOCK_SKEW_SECS && now - iat <= KB_JWT_MAX_AGE_SECS);
+    if !iat_valid {
+        report
+            .errors
+            .push("key binding JWT `iat` is missing or not recent".to_string());
+    }
+
+    report.key_binding_valid = signature_valid && typ_valid && sd_hash_valid && iat_valid;
+
+    // `aud` may be a single string or an array of audiences.
+    report.audience_matches = match kb_claims.get("aud") {
+        Some(serde_json::Value::String(aud)) => *aud == expected_audience,
+        Some(serde_json::Value::Array(auds)) => auds
+            .iter()
+            .any(|aud| aud.as_str() == Some(expected_audience.as_str())),
+        _ => false,
+    };
+    if !report.audience_matches {
+        report
+            .errors
+            .push(format!("key binding JWT `aud` is not {expected_audience}"));
+    }
+
+    report.nonce_matches =
+        kb_claims.get("nonce").and_then(serde_json::Value::as_str) == Some(expected_nonce.as_str());
+    if !report.nonce_matches {
+        report
+            .errors
+            .push(format!("key binding JWT `nonce` is not {expected_nonce}"));
+    }
+
+    report
+}
+
+/// Decode a base64url-encoded JSON part of a compact JWT.
+fn decode_jwt_part(jwt: &str, index: usize) -> Option<serde_json::Value> {
+    let part = URL_SAFE_NO_PAD.decode(jwt.split('.').nth(index)?).ok()?;
+    serde_json::from_slice(&part).ok()
+}
+
 #[derive(Debug, uniffi::Error, thiserror::Error)]
 pub enum SdJwtError {
     #[error("failed to initialize SD-JWT: {0}")]
@@ -446,11 +927,155 @@ pub enum SdJwtError {
     CredentialClaimMissing,
 }
 
+/// Limits on the size and structure of an SD-JWT, bounding the resources a maliciously
+/// crafted credential can consume when its disclosures are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Record)]
+pub struct SdJwtLimits {
+    /// Maximum length of the compact SD-JWT, in bytes.
+    pub max_size: u64,
+    /// Maximum number of disclosures.
+    pub max_disclosures: u32,
+    /// Maximum nesting of arrays and objects in the JWT payload and in each disclosure.
+    pub max_depth: u32,
+}
+
+impl Default for SdJwtLimits {
+    fn default() -> Self {
+        Self {
+            // Leaves room for claims such as portraits.
+            max_size: 1024 * 1024,
+            max_disclosures: 1024,
+            max_depth: 32,
+        }
+    }
+}
+
+impl SdJwtLimits {
+    /// Check a compact SD-JWT against the limits. Parts that cannot be decoded are left for
+    /// the SD-JWT parser to reject.
+    pub(crate) fn check(&self, compact: &str) -> Result<(), SdJwtError> {
+        if compact.len() as u64 > self.max_size {
+            return Err(SdJwtError::InvalidSdJwt(format!(
+                "SD-JWT is {} bytes, more than the limit of {}",
+                compact.len(),
+                self.max_size
+            )));
+        }
+
+        let mut parts = compact.split('~');
+        let jwt = parts.next().unwrap_or_default();
+        // A trailing KB-JWT is the only part containing a `.`.
+        let disclosures: Vec<&str> = parts
+            .filter(|part| !part.is_empty() && !part.contains('.'))
+            .collect();
+
+        if disclosures.len() as u64 > self.max_disclosures as u64 {
+            return Err(SdJwtError::InvalidSdJwt(format!(
+                "SD-JWT has {} disclosures, more than the limit of {}",
+                disclosures.len(),
+                self.max_disclosures
+            )));
+        }
+
+        for encoded in jwt.split('.').nth(1).into_iter().chain(disclosures) {
+            let Some(value) = URL_SAFE_NO_PAD
+                .decode(encoded)
+                .ok()
+                .and_then(|decoded| serde_json::from_slice::<serde_json::Value>(&decoded).ok())
+            else {
+                continue;
+            };
+
+            if json_depth(&value) > self.max_depth as usize {
+                return Err(SdJwtError::InvalidSdJwt(format!(
+                    "SD-JWT nests claims deeper than the limit of {}",
+                    self.max_depth
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The nesting of arrays and objects in a JSON value, e.g. 1 for `{"a": 1}`.
+fn json_depth(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Array(items) => 1 + items.iter().map(json_depth).max().unwrap_or(0),
+        serde_json::Value::Object(entries) => {
+            1 + entries.values().map(json_depth).max().unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+/// Append a key binding JWT (KB-JWT) to a presented SD-JWT, binding it to the
+/// audience, nonce and transaction data of the presentation.
+async fn append_key_binding(
+    mut sd_jwt: String,
+    options: &PresentationOptions<'_>,
+) -> Result<String, OID4VPError> {
+    // The SD-JWT hashed for `sd_hash` ends with the separator preceding the KB-JWT.
+    if !sd_jwt.ends_with('~') {
+        sd_jwt.push('~');
+    }
+
+    let mut claims = serde_json::json!({
+        "iat": time::OffsetDateTime::now_utc().unix_timestamp(),
+        "sd_hash": URL_SAFE_NO_PAD.encode(Sha256::digest(sd_jwt.as_bytes())),
+        "transaction_data_hashes": options.transaction_data_hashes(),
+        "transaction_data_hashes_alg": TRANSACTION_DATA_HASH_ALG,
+    });
+    if let Some(aud) = options.audience() {
+        claims["aud"] = aud.into();
+    }
+    if let Some(nonce) = options.nonce() {
+        claims["nonce"] = nonce.into();
+    }
+
+    let kb_jwt = options
+        .sign_jwt(serde_json::json!({ "typ": "kb+jwt" }), &claims)
+        .await?;
+
+    Ok(format!("{sd_jwt}{kb_jwt}"))
+}
+
+/// Add the ancestors of `pointers`, so that a selected claim nested in selectively disclosable
+/// objects is retained along with the disclosures of the objects containing it, e.g.
+/// `/credentialSubject/address` for `/credentialSubject/address/city`.
+fn with_parent_pointers(pointers: Vec<JsonPointerBuf>) -> Result<Vec<JsonPointerBuf>, OID4VPError> {
+    let mut retained: Vec<JsonPointerBuf> = Vec::new();
+    for pointer in pointers {
+        let pointer = pointer.to_string();
+        // Reference tokens escape `/` as `~1`, so every `/` but the first ends an ancestor.
+        let ancestors = pointer
+            .match_indices('/')
+            .skip(1)
+            .map(|(index, _)| &pointer[..index])
+            .chain(std::iter::once(pointer.as_str()));
+
+        for ancestor in ancestors {
+            let ancestor = JsonPointerBuf::new(ancestor.to_owned())
+                .map_err(|e| OID4VPError::JsonPathToPointer(e.to_string()))?;
+            if !retained.contains(&ancestor) {
+                retained.push(ancestor);
+            }
+        }
+    }
+
+    Ok(retained)
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
 
-    use ssi::{claims::sd_jwt::SdAlg, json_pointer, JWK};
+    use ssi::{claims::sd_jwt::SdAlg, dids::DIDJWK, json_pointer, JWK};
+
+    use crate::oid4vp::{
+        holder::tests::{test_request, KeySigner},
+        presentation::PresentationSigner,
+    };
 
     #[test]
     fn test_decode_static() {
@@ -516,6 +1141,164 @@ pub(crate) mod tests {
             .unwrap()
     }
 
+    #[tokio::test]
+    async fn rejects_sd_jwts_exceeding_limits() {
+        let sd_jwt = generate_sd_jwt().await;
+        let limits = SdJwtLimits::default();
+
+        VCDM2SdJwt::new_from_compact_sd_jwt_with_limits(sd_jwt.to_string(), limits).unwrap();
+        assert!(matches!(
+            VCDM2SdJwt::new_from_compact_sd_jwt_with_limits(
+                sd_jwt.to_string(),
+                SdJwtLimits {
+                    max_size: 64,
+                    ..limits
+                }
+            ),
+            Err(SdJwtError::InvalidSdJwt(_))
+        ));
+
+        let jwt = sd_jwt.as_str().split('~').next().unwrap();
+        let disclosure = URL_SAFE_NO_PAD.encode(r#"["salt","name","value"]"#);
+        let many_disclosures = format!("{jwt}~{}", format!("{disclosure}~").repeat(5_000));
+        assert!(matches!(
+            limits.check(&many_disclosures),
+            Err(SdJwtError::InvalidSdJwt(reason)) if reason.contains("disclosures")
+        ));
+
+        let nested = format!("{}1{}", "[".repeat(100), "]".repeat(100));
+        let deep_disclosure = URL_SAFE_NO_PAD.encode(format!(r#"["salt","name",{nested}]"#));
+        assert!(matches!(
+            limits.check(&format!("{jwt}~{deep_disclosure}~")),
+            Err(SdJwtError::InvalidSdJwt(reason)) if reason.contains("deeper")
+        ));
+    }
+
+    #[tokio::test]
+    async fn discloses_age_over_claim_answering_request() {
+        let jwk: JWK = JWK::generate_ed25519().unwrap();
+        let claims: SdJwtVc = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/ns/credentials/v2"],
+            "type": ["VerifiableCredential"],
+            "issuer": "did:example:issuer",
+            "credentialSubject": {
+                "birth_date": "1990-01-31",
+                "age_over_18": true,
+                "age_over_21": true
+            }
+        }))
+        .unwrap();
+        let sd_jwt = claims
+            .conceal_and_sign(
+                SdAlg::Sha256,
+                &[
+                    json_pointer!("/credentialSubject/birth_date"),
+                    json_pointer!("/credentialSubject/age_over_18"),
+                    json_pointer!("/credentialSubject/age_over_21"),
+                ],
+                &jwk,
+            )
+            .await
+            .unwrap();
+        let sd_jwt = VCDM2SdJwt::new_from_compact_sd_jwt(sd_jwt.to_string()).unwrap();
+
+        assert!(sd_jwt.has_claim("/credentialSubject/age_over_18".into()));
+        assert!(!sd_jwt.has_claim("/credentialSubject/age_over_65".into()));
+        assert_eq!(
+            sd_jwt.age_over_claim(20).as_deref(),
+            Some("/credentialSubject/age_over_21")
+        );
+        assert_eq!(sd_jwt.age_over_claim(30), None);
+
+        let pointers = sd_jwt
+            .disclosure_pointers_for_paths(vec![
+                "$.credentialSubject.birth_date".into(),
+                "$.credentialSubject.age_over_20".into(),
+                "$.credentialSubject.birth_date".into(),
+            ])
+            .unwrap();
+        assert_eq!(
+            pointers,
+            vec![
+                "/credentialSubject",
+                "/credentialSubject/birth_date",
+                "/credentialSubject/age_over_21"
+            ]
+        );
+        assert!(matches!(
+            sd_jwt.disclosures_for_paths(vec!["$.credentialSubject.name".into()]),
+            Err(OID4VPError::JsonPathResolve(_))
+        ));
+
+        // Holders select fields by the encoded ids of `RequestedField::path`.
+        let encoded = format!(
+            "{},0",
+            URL_SAFE.encode("$.credentialSubject.age_over_20".as_bytes())
+        );
+        assert_eq!(
+            sd_jwt.disclosure_pointers_for_paths(vec![encoded]).unwrap(),
+            vec!["/credentialSubject", "/credentialSubject/age_over_21"]
+        );
+        assert!(matches!(
+            sd_jwt.disclosures_for_paths(vec!["$.age_over_20".into()]),
+            Err(OID4VPError::JsonPathResolve(_))
+        ));
+
+        let disclosed = sd_jwt
+            .disclosed_claims(vec!["$.credentialSubject.age_over_20".into()])
+            .unwrap();
+        assert_eq!(
+            disclosed["credentialSubject"]["age_over_21"],
+            serde_json::json!(true)
+        );
+        assert!(disclosed["credentialSubject"].get("birth_date").is_none());
+    }
+
+    #[tokio::test]
+    async fn retains_parents_of_nested_disclosures() {
+        let jwk: JWK = JWK::generate_ed25519().unwrap();
+        let claims: SdJwtVc = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/ns/credentials/v2"],
+            "type": ["VerifiableCredential"],
+            "issuer": "did:example:issuer",
+            "credentialSubject": {
+                "name": "Alice",
+                "address": {
+                    "street": "123 Main St",
+                    "city": "Anytown"
+                }
+            }
+        }))
+        .unwrap();
+        let sd_jwt = claims
+            .conceal_and_sign(
+                SdAlg::Sha256,
+                &[
+                    json_pointer!("/credentialSubject/address/street"),
+                    json_pointer!("/credentialSubject/address/city"),
+                    json_pointer!("/credentialSubject/address"),
+                    json_pointer!("/credentialSubject/name"),
+                ],
+                &jwk,
+            )
+            .await
+            .unwrap();
+        let sd_jwt = VCDM2SdJwt::new_from_compact_sd_jwt(sd_jwt.to_string()).unwrap();
+
+        let disclosed = sd_jwt
+            .disclosed_claims(vec!["$.credentialSubject.address.city".into()])
+            .unwrap();
+
+        assert_eq!(
+            disclosed["credentialSubject"]["address"]["city"],
+            serde_json::json!("Anytown")
+        );
+        assert!(disclosed["credentialSubject"]["address"]
+            .get("street")
+            .is_none());
+        assert!(disclosed["credentialSubject"].get("name").is_none());
+    }
+
     #[tokio::test]
     async fn test_sd_jwt() -> Result<(), SdJwtError> {
         let input = generate_sd_jwt().await;
@@ -540,4 +1323,171 @@ pub(crate) mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn verify_sd_jwt_presentation_checks_key_binding() {
+        let mut issuer_jwk = JWK::generate_p256();
+        issuer_jwk.key_id = Some(DIDJWK::generate_url(&issuer_jwk.to_public()).into());
+        let holder_jwk = JWK::generate_p256();
+
+        let claims: SdJwtVc = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/ns/credentials/v2"],
+            "type": ["VerifiableCredential"],
+            "issuer": DIDJWK::generate(&issuer_jwk.to_public()).to_string(),
+            "credentialSubject": { "name": "John Smith" },
+            "cnf": { "jwk": holder_jwk.to_public() }
+        }))
+        .unwrap();
+        let sd_jwt = claims
+            .conceal_and_sign(
+                SdAlg::Sha256,
+                &[json_pointer!("/credentialSubject/name")],
+                &issuer_jwk,
+            )
+            .await
+            .unwrap();
+
+        let request = test_request(
+            serde_json::json!({
+                "id": "kb-jwt-test",
+                "input_descriptors": []
+            }),
+            None,
+        );
+        let signer: Arc<Box<dyn PresentationSigner>> =
+            Arc::new(Box::new(KeySigner { jwk: holder_jwk }));
+        let response_options = ResponseOptions::default();
+        let options =
+            PresentationOptions::for_request(&request, signer, None, &[], &response_options);
+        let presentation = append_key_binding(sd_jwt.as_str().to_string(), &options)
+            .await
+            .unwrap();
+
+        let report = verify_sd_jwt_presentation(
+            presentation.clone(),
+            "https://verifier.example/response".into(),
+            "n-0S6_WzA2Mj".into(),
+        )
+        .await;
+        assert!(report.is_valid(), "{:?}", report.errors);
+        assert!(report.claims.unwrap().contains("John Smith"));
+
+        let report = verify_sd_jwt_presentation(
+            presentation,
+            "https://verifier.example/response".into(),
+            "replayed-nonce".into(),
+        )
+        .await;
+        assert!(report.key_binding_valid);
+        assert!(!report.nonce_matches);
+        assert!(!report.is_valid());
+
+        // A presentation without a KB-JWT is not bound to the holder.
+        let report = verify_sd_jwt_presentation(
+            format!("{}~", sd_jwt.as_str().trim_end_matches('~')),
+            "https://verifier.example/response".into(),
+            "n-0S6_WzA2Mj".into(),
+        )
+        .await;
+        assert!(report.issuer_signature_valid);
+        assert!(report.disclosures_valid);
+        assert!(!report.key_binding_valid);
+
+        // A KB-JWT issued too long ago is not fresh.
+        let presented = format!("{}~", sd_jwt.as_str().trim_end_matches('~'));
+        let stale_kb_jwt = options
+            .sign_jwt(
+                serde_json::json!({ "typ": "kb+jwt" }),
+                &serde_json::json!({
+                    "iat": time::OffsetDateTime::now_utc().unix_timestamp()
+                        - KB_JWT_MAX_AGE_SECS
+                        - 1,
+                    "sd_hash": URL_SAFE_NO_PAD.encode(Sha256::digest(presented.as_bytes())),
+                    "aud": "https://verifier.example/response",
+                    "nonce": "n-0S6_WzA2Mj",
+                }),
+            )
+            .await
+            .unwrap();
+        let report = verify_sd_jwt_presentation(
+            format!("{presented}{stale_kb_jwt}"),
+            "https://verifier.example/response".into(),
+            "n-0S6_WzA2Mj".into(),
+        )
+        .await;
+        assert!(report.audience_matches && report.nonce_matches);
+        assert!(!report.key_binding_valid);
+
+        // An SD-JWT signed by one DID cannot claim to be issued by another.
+        let forged: SdJwtVc = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/ns/credentials/v2"],
+            "type": ["VerifiableCredential"],
+            "issuer": "did:web:issuer.example",
+            "credentialSubject": { "name": "John Smith" },
+            "cnf": { "jwk": options.jwk().unwrap() }
+        }))
+        .unwrap();
+        let forged = forged
+            .conceal_and_sign(
+                SdAlg::Sha256,
+                &[json_pointer!("/credentialSubject/name")],
+                &issuer_jwk,
+            )
+            .await
+            .unwrap();
+        let presentation = append_key_binding(forged.as_str().to_string(), &options)
+            .await
+            .unwrap();
+        let report = verify_sd_jwt_presentation(
+            presentation,
+            "https://verifier.example/response".into(),
+            "n-0S6_WzA2Mj".into(),
+        )
+        .await;
+        assert!(!report.issuer_signature_valid);
+        assert!(report.key_binding_valid);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn parse_selected_field_accepts_both_encodings() {
+        let plain = parse_selected_field("$.credentialSubject.name").unwrap();
+        let encoded = format!(
+            "{},0",
+            URL_SAFE.encode("$.credentialSubject.name".as_bytes())
+        );
+
+        assert_eq!(
+            parse_selected_field(&encoded).unwrap().to_string(),
+            plain.to_string()
+        );
+    }
+
+    #[test]
+    fn splits_last_name_segment() {
+        assert_eq!(
+            split_last_name_segment("$.credentialSubject.age_over_21"),
+            Some(("$.credentialSubject", "age_over_21"))
+        );
+        assert_eq!(
+            split_last_name_segment("$['credentialSubject']['age_over_21']"),
+            Some(("$['credentialSubject']", "age_over_21"))
+        );
+        assert_eq!(split_last_name_segment("$"), None);
+    }
+
+    #[test]
+    fn parse_selected_field_names_invalid_field() {
+        let err = parse_selected_field("not base64!").unwrap_err();
+        assert!(matches!(
+            err,
+            PresentationError::SelectedField { ref field, .. } if field == "not base64!"
+        ));
+
+        let err = parse_selected_field("$..[").unwrap_err();
+        assert!(matches!(
+            err,
+            PresentationError::SelectedField { ref field, .. } if field == "$..["
+        ));
+    }
 }
@@ -45,6 +45,19 @@ pub trait SigningKey: Send + Sync {
     fn sign(&self, payload: Vec<u8>) -> Result<Vec<u8>>;
 }
 
+#[uniffi::export]
+/// Computes the RFC 7638 thumbprint of a JWK, returned as an unpadded base64url-encoded
+/// SHA-256 digest.
+///
+/// Only the required members of the key are hashed, so members such as `kid`, `alg` or the
+/// private key parameters do not affect the result.
+pub fn jwk_thumbprint(jwk_json: String) -> Result<String> {
+    let jwk: ssi::jwk::JWK = serde_json::from_str(&jwk_json)
+        .map_err(|e| CryptoError::General(format!("failed to parse JWK: {e}")))?;
+    jwk.thumbprint()
+        .map_err(|e| CryptoError::General(format!("failed to compute JWK thumbprint: {e}")))
+}
+
 #[derive(uniffi::Object)]
 /// Utility functions for cryptographic curves
 pub struct CryptoCurveUtils(Curve);
@@ -73,6 +86,24 @@ impl CryptoCurveUtils {
             }
         }
     }
+
+    /// Normalize an ECDSA signature to its low-S form, as required by verifiers that reject
+    /// malleable (high-S) signatures.
+    ///
+    /// Accepts a raw fixed-width or DER encoded signature and returns it raw fixed-width. A
+    /// signature in an unrecognized encoding is returned unchanged.
+    pub fn normalize_low_s(&self, signature: Vec<u8>) -> Vec<u8> {
+        match self.0 {
+            Curve::SecP256R1 => {
+                use p256::ecdsa::Signature;
+                match Signature::from_slice(&signature).or_else(|_| Signature::from_der(&signature))
+                {
+                    Ok(s) => s.normalize_s().unwrap_or(s).to_vec(),
+                    Err(_) => signature,
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -145,4 +176,50 @@ mod test {
             Ok(signature.to_vec())
         }
     }
+
+    #[test]
+    fn jwk_thumbprint_ignores_optional_members() {
+        let jwk = p256::SecretKey::random(&mut ssi::crypto::rand::thread_rng())
+            .public_key()
+            .to_jwk_string();
+
+        let mut with_kid: serde_json::Value = serde_json::from_str(&jwk).unwrap();
+        with_kid["kid"] = "device-key".into();
+        with_kid["alg"] = "ES256".into();
+
+        let thumbprint = jwk_thumbprint(jwk).unwrap();
+        assert_eq!(thumbprint, jwk_thumbprint(with_kid.to_string()).unwrap());
+        // 32 bytes of SHA-256, base64url-encoded without padding.
+        assert_eq!(thumbprint.len(), 43);
+    }
+
+    #[test]
+    fn normalize_low_s_signatures() {
+        let utils = CryptoCurveUtils::secp256r1();
+
+        // r = 1, s = n - 1, where n is the order of the P-256 group.
+        let high_s = hex::decode(concat!(
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632550",
+        ))
+        .unwrap();
+        // r = 1, s = 1.
+        let low_s = hex::decode(concat!(
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+        ))
+        .unwrap();
+
+        assert_eq!(utils.normalize_low_s(high_s.clone()), low_s);
+        assert_eq!(utils.normalize_low_s(low_s.clone()), low_s);
+
+        let der = p256::ecdsa::Signature::from_slice(&high_s)
+            .unwrap()
+            .to_der()
+            .as_bytes()
+            .to_vec();
+        assert_eq!(utils.normalize_low_s(der), low_s);
+
+        assert_eq!(utils.normalize_low_s(vec![1, 2, 3]), vec![1, 2, 3]);
+    }
 }
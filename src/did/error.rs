@@ -10,6 +10,21 @@ pub enum DidError {
     #[error("{_0}")]
     ResolutionError(#[from] ssi::dids::resolution::Error),
 
+    #[error("{_0}")]
+    JwkError(#[from] ssi::jwk::Error),
+
+    #[error("invalid DID: {_0}")]
+    InvalidDid(String),
+
     #[error("DID document is missing a verification method")]
     MissingVerificationMethod,
+
+    #[error("failed to resolve verification method: {_0}")]
+    VerificationMethodResolution(String),
+
+    #[error("verification method has no public key in a supported format: {_0}")]
+    UnsupportedVerificationMethod(String),
+
+    #[error("resolving {_0} is not allowed by the DID resolution policy")]
+    ResolutionDenied(String),
 }
@@ -1,8 +1,11 @@
+use base64::prelude::*;
 use ssi::dids::DIDResolver;
 
 pub use error::*;
+pub use policy::*;
 
 mod error;
+mod policy;
 
 #[derive(Debug, uniffi::Enum)]
 pub enum DidMethod {
@@ -20,6 +23,29 @@ impl DidMethod {
         Ok(did.to_string())
     }
 
+    /// Checks that `did` was derived from the public key in `jwk`.
+    ///
+    /// For `did:jwk` the embedded key is compared by its RFC 7638 thumbprint, so optional
+    /// members (e.g. `kid`) and member ordering do not cause a mismatch. A DID URL fragment,
+    /// if present, is ignored.
+    pub fn did_matches_jwk(&self, did: &str, jwk: &str) -> Result<bool, DidError> {
+        let key: ssi::jwk::JWK = serde_json::from_str(jwk)?;
+        let did = did.split_once('#').map_or(did, |(did, _)| did);
+        match &self {
+            DidMethod::Jwk => {
+                let encoded = did
+                    .strip_prefix("did:jwk:")
+                    .ok_or_else(|| DidError::InvalidDid(format!("not a did:jwk: {did}")))?;
+                let decoded = BASE64_URL_SAFE_NO_PAD
+                    .decode(encoded)
+                    .map_err(|e| DidError::InvalidDid(format!("{e}")))?;
+                let embedded: ssi::jwk::JWK = serde_json::from_slice(&decoded)?;
+                Ok(embedded.thumbprint()? == key.thumbprint()?)
+            }
+            DidMethod::Key => Ok(ssi::dids::DIDKey::generate(&key)?.as_str() == did),
+        }
+    }
+
     pub async fn vm_from_jwk(&self, jwk: &str) -> Result<String, DidError> {
         let key: ssi::jwk::JWK = serde_json::from_str(jwk)?;
         let vm = match &self {
@@ -44,6 +70,38 @@ impl DidMethod {
     }
 }
 
+/// Resolve the verification method identified by a DID URL, e.g. `did:key:z6Mk...#z6Mk...`,
+/// returning its public key as a JWK. Only DIDs `did_policy` allows are resolved.
+pub async fn resolve_verification_method(
+    did_url: &str,
+    did_policy: &DidResolutionPolicy,
+) -> Result<String, DidError> {
+    use ssi::{
+        json_ld::iref::Iri,
+        prelude::*,
+        verification_methods::{ReferenceOrOwnedRef, VerificationMethodResolver},
+    };
+
+    if !did_url.starts_with("did:") || !did_url.contains('#') {
+        return Err(DidError::InvalidDid(format!(
+            "not a DID URL with a fragment: {did_url}"
+        )));
+    }
+    let id = Iri::new(did_url).map_err(|e| DidError::InvalidDid(format!("{e}")))?;
+
+    did_policy.check(did_url)?;
+    let method = did_policy
+        .vm_resolver()
+        .resolve_verification_method(None, Some(ReferenceOrOwnedRef::Reference(id)))
+        .await
+        .map_err(|e| DidError::VerificationMethodResolution(e.to_string()))?;
+
+    let jwk = method
+        .public_key_jwk()
+        .ok_or_else(|| DidError::UnsupportedVerificationMethod(did_url.to_string()))?;
+    Ok(serde_json::to_string(&jwk)?)
+}
+
 #[derive(Debug, uniffi::Object)]
 pub struct DidMethodUtils {
     inner: DidMethod,
@@ -60,7 +118,97 @@ impl DidMethodUtils {
         self.inner.did_from_jwk(jwk)
     }
 
+    /// Returns whether `did` corresponds to the public key in `jwk`, e.g. to confirm that a
+    /// credential's `cnf` claim binds it to a device key the wallet controls.
+    pub fn did_matches_jwk(&self, did: &str, jwk: &str) -> Result<bool, DidError> {
+        self.inner.did_matches_jwk(did, jwk)
+    }
+
     pub async fn vm_from_jwk(&self, jwk: &str) -> Result<String, DidError> {
         self.inner.vm_from_jwk(jwk).await
     }
+
+    /// Resolves the verification method identified by `did_url`, e.g. the `kid` of a
+    /// presentation proof, and returns its public JWK.
+    ///
+    /// Unlike [DidMethodUtils::vm_from_jwk], the fragment selects the verification method,
+    /// which may not be the first of a DID document with several keys. Any supported DID
+    /// method can be resolved, regardless of the method of this instance.
+    pub async fn resolve_verification_method(&self, did_url: String) -> Result<String, DidError> {
+        resolve_verification_method(&did_url, &DidResolutionPolicy::default()).await
+    }
+
+    /// Resolves the verification method identified by `did_url` as
+    /// [DidMethodUtils::resolve_verification_method] does, failing with
+    /// [DidError::ResolutionDenied] if `did_policy` forbids resolving its DID.
+    pub async fn resolve_verification_method_with_did_policy(
+        &self,
+        did_url: String,
+        did_policy: DidResolutionPolicy,
+    ) -> Result<String, DidError> {
+        resolve_verification_method(&did_url, &did_policy).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resolves_verification_method_by_fragment() {
+        let jwk = crate::tests::load_jwk();
+        let public_jwk = serde_json::to_string(&jwk.to_public()).unwrap();
+        let utils = DidMethodUtils::new(DidMethod::Key);
+
+        let vm = utils.vm_from_jwk(&public_jwk).await.unwrap();
+        let resolved: ssi::jwk::JWK =
+            serde_json::from_str(&utils.resolve_verification_method(vm).await.unwrap()).unwrap();
+        assert_eq!(resolved.thumbprint().unwrap(), jwk.thumbprint().unwrap());
+
+        let did = utils.did_from_jwk(&public_jwk).unwrap();
+        assert!(matches!(
+            utils.resolve_verification_method(did).await,
+            Err(DidError::InvalidDid(_))
+        ));
+    }
+
+    #[test]
+    fn did_matches_jwk() {
+        let jwk = serde_json::to_string(&crate::tests::load_jwk().to_public()).unwrap();
+        let other = serde_json::to_string(&ssi::JWK::generate_p256().to_public()).unwrap();
+
+        for method in [DidMethod::Jwk, DidMethod::Key] {
+            let utils = DidMethodUtils::new(method);
+            let did = utils.did_from_jwk(&jwk).unwrap();
+
+            assert!(utils.did_matches_jwk(&did, &jwk).unwrap());
+            assert!(utils.did_matches_jwk(&format!("{did}#0"), &jwk).unwrap());
+            assert!(!utils.did_matches_jwk(&did, &other).unwrap());
+        }
+
+        // Optional members of the embedded key do not cause a mismatch.
+        let mut with_kid: serde_json::Value = serde_json::from_str(&jwk).unwrap();
+        with_kid["kid"] = "key-1".into();
+        let utils = DidMethodUtils::new(DidMethod::Jwk);
+        let did = utils.did_from_jwk(&with_kid.to_string()).unwrap();
+        assert!(utils.did_matches_jwk(&did, &jwk).unwrap());
+    }
+
+    #[tokio::test]
+    async fn resolve_verification_method_enforces_did_policy() {
+        let utils = DidMethodUtils::new(DidMethod::Key);
+
+        assert!(matches!(
+            utils
+                .resolve_verification_method_with_did_policy(
+                    "did:web:169%2e254%2e169%2e254#key-1".into(),
+                    DidResolutionPolicy {
+                        allowed_web_hosts: Some(vec!["issuer.example".into()]),
+                        denied_web_hosts: vec![],
+                    },
+                )
+                .await,
+            Err(DidError::ResolutionDenied(_))
+        ));
+    }
 }
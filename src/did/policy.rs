@@ -0,0 +1,180 @@
+use ssi::{
+    dids::{resolution, AnyDidMethod, DIDResolver, VerificationMethodDIDResolver, DID},
+    verification_methods::AnyMethod,
+};
+use url::Host;
+
+use super::DidError;
+
+/// Restricts the hosts `did:web` DIDs may be resolved from, e.g. so that a credential cannot
+/// make the wallet send requests to internal hosts.
+///
+/// Hosts are compared as they appear in the URL the DID document is fetched from, i.e.
+/// percent-decoded, lowercased and with IP addresses in canonical form, regardless of the port.
+/// An entry starting with `*.` matches every subdomain of the domain. The default policy
+/// resolves every host, so internal hosts, e.g. IP addresses of cloud metadata endpoints, must
+/// be denied or left out of `allowed_web_hosts`. DIDs of other methods are not restricted.
+#[derive(uniffi::Record, Clone, Debug, Default)]
+pub struct DidResolutionPolicy {
+    /// Hosts `did:web` DIDs may be resolved from. Every host that is not denied is allowed if
+    /// null.
+    pub allowed_web_hosts: Option<Vec<String>>,
+    /// Hosts `did:web` DIDs must not be resolved from.
+    pub denied_web_hosts: Vec<String>,
+}
+
+impl DidResolutionPolicy {
+    /// Fail with [DidError::ResolutionDenied] if the policy forbids resolving `did`.
+    pub fn check(&self, did: &str) -> Result<(), DidError> {
+        let Some(id) = did.strip_prefix("did:web:") else {
+            return Ok(());
+        };
+        let denied = || DidError::ResolutionDenied(did.to_string());
+
+        let host = web_did_host(id).ok_or_else(denied)?;
+        let listed = |hosts: &[String]| hosts.iter().any(|entry| host_matches(entry, &host));
+        let allowed = match &self.allowed_web_hosts {
+            Some(allowed) => listed(allowed),
+            None => true,
+        };
+        if !allowed || listed(&self.denied_web_hosts) {
+            return Err(denied());
+        }
+
+        Ok(())
+    }
+
+    /// A verification method resolver for every supported DID method, enforcing the policy.
+    pub(crate) fn vm_resolver(
+        &self,
+    ) -> VerificationMethodDIDResolver<PolicyDidResolver, AnyMethod> {
+        PolicyDidResolver {
+            policy: self.clone(),
+            inner: AnyDidMethod::default(),
+        }
+        .into_vm_resolver()
+    }
+}
+
+/// Resolves DIDs of any supported method, refusing those forbidden by a [DidResolutionPolicy].
+pub(crate) struct PolicyDidResolver {
+    policy: DidResolutionPolicy,
+    inner: AnyDidMethod,
+}
+
+impl DIDResolver for PolicyDidResolver {
+    async fn resolve_representation<'a>(
+        &'a self,
+        did: &'a DID,
+        options: resolution::Options,
+    ) -> Result<resolution::Output<Vec<u8>>, resolution::Error> {
+        self.policy
+            .check(did.as_str())
+            .map_err(|e| resolution::Error::Internal(e.to_string()))?;
+
+        self.inner.resolve_representation(did, options).await
+    }
+}
+
+/// The host the DID document of the `did:web` DID with method-specific `id` is fetched from,
+/// or `None` if it is not a valid host.
+fn web_did_host(id: &str) -> Option<Host> {
+    // Path segments are separated by `:`, and the port is encoded as `%3A`, which is the only
+    // escape decoded before the URL is built, e.g. `did:web:localhost%3A8443`.
+    let domain = id
+        .split([':', '#', '?', '/'])
+        .next()?
+        .replacen("%3A", ":", 1);
+
+    // Decodes the remaining escapes and normalizes the host as the URL parser does.
+    Host::parse(without_port(&domain)).ok()
+}
+
+fn without_port(host: &str) -> &str {
+    match host.find(']') {
+        Some(end) if host.starts_with('[') => &host[..=end],
+        _ => host.split(':').next().unwrap_or_default(),
+    }
+}
+
+fn host_matches(entry: &str, host: &Host) -> bool {
+    match entry.strip_prefix("*.") {
+        Some(domain) => match (host, Host::parse(without_port(domain))) {
+            (Host::Domain(host), Ok(Host::Domain(domain))) => host
+                .strip_suffix(&domain)
+                .is_some_and(|subdomain| subdomain.ends_with('.')),
+            _ => false,
+        },
+        None => Host::parse(without_port(entry)).is_ok_and(|entry| entry == *host),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restricts_web_did_hosts() {
+        let permissive = DidResolutionPolicy::default();
+        permissive.check("did:web:issuer.example").unwrap();
+        permissive.check("did:web:localhost%3A8443").unwrap();
+        permissive.check("did:web:169.254.169.254").unwrap();
+
+        let metadata_denied = DidResolutionPolicy {
+            allowed_web_hosts: None,
+            denied_web_hosts: vec!["169.254.169.254".into(), "[::1]".into()],
+        };
+        metadata_denied.check("did:web:issuer.example").unwrap();
+        for did in [
+            "did:web:169.254.169.254",
+            "did:web:169%2e254%2e169%2e254",
+            "did:web:2852039166",
+            "did:web:%5B%3A%3A1%5D",
+        ] {
+            assert!(
+                matches!(
+                    metadata_denied.check(did),
+                    Err(DidError::ResolutionDenied(_))
+                ),
+                "{did} should be denied"
+            );
+        }
+
+        let ip_allowed = DidResolutionPolicy {
+            allowed_web_hosts: Some(vec!["127.0.0.1".into()]),
+            denied_web_hosts: vec![],
+        };
+        ip_allowed.check("did:web:0x7f000001%3A8443").unwrap();
+
+        let policy = DidResolutionPolicy {
+            allowed_web_hosts: Some(vec!["issuer.example".into(), "*.trusted.example".into()]),
+            denied_web_hosts: vec!["internal.trusted.example".into()],
+        };
+
+        policy.check("did:web:issuer.example").unwrap();
+        policy
+            .check("did:web:Issuer.Example%3A8443:users:alice")
+            .unwrap();
+        policy.check("did:web:dmv.trusted.example#key-1").unwrap();
+        policy
+            .check("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK")
+            .unwrap();
+
+        for did in [
+            "did:web:169.254.169.254",
+            "did:web:localhost%3A8080",
+            "did:web:trusted.example",
+            "did:web:nottrusted.example",
+            "did:web:internal.trusted.example:keys",
+            "did:web:issuer.example.attacker.example",
+            "did:web:internal%2etrusted%2eexample",
+            "did:web:INTERNAL.trusted.example%3A443",
+            "did:web:not%20a%20host",
+        ] {
+            assert!(
+                matches!(policy.check(did), Err(DidError::ResolutionDenied(_))),
+                "{did} should be denied"
+            );
+        }
+    }
+}
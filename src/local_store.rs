@@ -1,10 +1,19 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
 use async_trait::async_trait;
 
 use crate::common::*;
 use crate::storage_manager::*;
 
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Length of the AES-GCM nonce prepended to the encrypted file contents.
+const NONCE_LEN: usize = 12;
 
 /// A version of secure storage for debugging purposes, and as a minimal interface example.  Do not
 /// use in production!  This encrypts nothing, uses a path relative to the current working directory,
@@ -18,13 +27,21 @@ impl LocalStore {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Lock the entries, failing instead of panicking if another operation panicked while
+    /// holding the lock.
+    fn entries(&self) -> Result<MutexGuard<'_, HashMap<Key, Value>>, StorageManagerError> {
+        self.store
+            .lock()
+            .map_err(|_| StorageManagerError::InternalError)
+    }
 }
 
 #[async_trait]
 impl StorageManagerInterface for LocalStore {
     /// Add a key/value pair to storage.
     async fn add(&self, key: Key, value: Value) -> Result<(), StorageManagerError> {
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.entries()?;
 
         store.insert(key, value);
 
@@ -33,7 +50,7 @@ impl StorageManagerInterface for LocalStore {
 
     /// Retrieve the value associated with a key.
     async fn get(&self, key: Key) -> Result<Option<Value>, StorageManagerError> {
-        let store = self.store.lock().unwrap();
+        let store = self.entries()?;
 
         match store.get(&key) {
             Some(x) => Ok(Some(Value(x.0.clone()))),
@@ -43,17 +60,381 @@ impl StorageManagerInterface for LocalStore {
 
     /// List the available key/value pairs.
     async fn list(&self) -> Result<Vec<Key>, StorageManagerError> {
-        let store = self.store.lock().unwrap();
+        let store = self.entries()?;
 
         Ok(store.keys().map(|x| x.to_owned()).collect())
     }
 
     /// Delete a given key/value pair from storage.
     async fn remove(&self, key: Key) -> Result<(), StorageManagerError> {
-        let mut store = self.store.lock().unwrap();
+        let mut store = self.entries()?;
 
         _ = store.remove(&key);
 
         Ok(())
     }
 }
+
+#[async_trait]
+impl StorageBatchInterface for LocalStore {
+    /// Apply the operations under a single lock.
+    async fn batch(&self, ops: Vec<StorageOp>) -> Result<(), StorageManagerError> {
+        let mut store = self.entries()?;
+
+        for op in ops {
+            match op {
+                StorageOp::Add { key, value } => _ = store.insert(key, value),
+                StorageOp::Remove { key } => _ = store.remove(&key),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A file-backed storage manager that encrypts its contents at rest with AES-256-GCM, for
+/// desktop and CLI usage where no platform secure storage is available.
+///
+/// All entries are kept in a single file, which is re-encrypted with a fresh nonce and atomically
+/// replaced on every write. The 32-byte encryption key is supplied by the host and is never
+/// persisted by the store.
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    cipher: Aes256Gcm,
+    /// Serializes read-modify-write cycles on the backing file.
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl std::fmt::Debug for EncryptedFileStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedFileStore")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptedFileStore {
+    pub fn new(path: impl Into<PathBuf>, key: &[u8]) -> Result<Self, StorageManagerError> {
+        let cipher =
+            Aes256Gcm::new_from_slice(key).map_err(|_| StorageManagerError::CouldNotMakeKey)?;
+
+        Ok(Self {
+            path: path.into(),
+            cipher,
+            lock: Default::default(),
+        })
+    }
+
+    /// Read and decrypt all entries. A missing file is treated as an empty store.
+    async fn load(&self) -> Result<BTreeMap<String, Vec<u8>>, StorageManagerError> {
+        let contents = match tokio::fs::read(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(_) => return Err(StorageManagerError::InternalError),
+        };
+
+        if contents.len() < NONCE_LEN {
+            return Err(StorageManagerError::CouldNotDecryptValue);
+        }
+
+        let (nonce, ciphertext) = contents.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| StorageManagerError::CouldNotDecryptValue)?;
+
+        serde_cbor::from_slice(&plaintext).map_err(|_| StorageManagerError::CouldNotDecryptValue)
+    }
+
+    /// Encrypt and persist all entries, replacing the backing file.
+    async fn store(&self, entries: &BTreeMap<String, Vec<u8>>) -> Result<(), StorageManagerError> {
+        let plaintext =
+            serde_cbor::to_vec(entries).map_err(|_| StorageManagerError::InternalError)?;
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_ref())
+            .map_err(|_| StorageManagerError::InternalError)?;
+
+        let mut contents = nonce.to_vec();
+        contents.extend(ciphertext);
+
+        // Write to a temporary file first so that a crash cannot leave a truncated store. The
+        // suffix keeps the file name intact and is unique, so stores sharing a directory, or
+        // whose names only differ by extension, never write to the same temporary file.
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(format!(".{}.tmp", Uuid::new_v4()));
+        let tmp_path = PathBuf::from(tmp_path);
+
+        tokio::fs::write(&tmp_path, contents)
+            .await
+            .map_err(|_| StorageManagerError::InternalError)?;
+        if tokio::fs::rename(&tmp_path, &self.path).await.is_err() {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(StorageManagerError::InternalError);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageManagerInterface for EncryptedFileStore {
+    /// Add a key/value pair to storage.
+    async fn add(&self, key: Key, value: Value) -> Result<(), StorageManagerError> {
+        let _guard = self.lock.lock().await;
+
+        let mut entries = self.load().await?;
+        entries.insert(key.0, value.0);
+        self.store(&entries).await
+    }
+
+    /// Retrieve the value associated with a key.
+    async fn get(&self, key: Key) -> Result<Option<Value>, StorageManagerError> {
+        let _guard = self.lock.lock().await;
+
+        Ok(self.load().await?.remove(&key.0).map(Value))
+    }
+
+    /// List the available key/value pairs.
+    async fn list(&self) -> Result<Vec<Key>, StorageManagerError> {
+        let _guard = self.lock.lock().await;
+
+        Ok(self.load().await?.into_keys().map(Key).collect())
+    }
+
+    /// Delete a given key/value pair from storage.
+    async fn remove(&self, key: Key) -> Result<(), StorageManagerError> {
+        let _guard = self.lock.lock().await;
+
+        let mut entries = self.load().await?;
+        if entries.remove(&key.0).is_some() {
+            self.store(&entries).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBatchInterface for EncryptedFileStore {
+    /// Apply the operations with a single replacement of the backing file, so a crash leaves
+    /// either all or none of them applied.
+    async fn batch(&self, ops: Vec<StorageOp>) -> Result<(), StorageManagerError> {
+        let _guard = self.lock.lock().await;
+
+        let mut entries = self.load().await?;
+        for op in ops {
+            match op {
+                StorageOp::Add { key, value } => _ = entries.insert(key.0, value.0),
+                StorageOp::Remove { key } => _ = entries.remove(&key.0),
+            }
+        }
+        self.store(&entries).await
+    }
+}
+
+/// Create a storage manager persisted to the file at `path`, encrypted at rest with AES-256-GCM
+/// using the provided 32-byte `key`.
+///
+/// This is intended for desktop and CLI consumers; mobile hosts should prefer their platform's
+/// secure storage.
+#[uniffi::export]
+pub fn new_encrypted_file_store(
+    path: String,
+    key: Vec<u8>,
+) -> Result<Arc<dyn StorageManagerInterface>, StorageManagerError> {
+    Ok(Arc::new(EncryptedFileStore::new(path, &key)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_store_concurrent_operations() {
+        let store = LocalStore::new();
+
+        let tasks = (0..32).map(|task| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                for i in 0..50 {
+                    let key = Key::from(format!("{task}-{i}").as_str());
+                    store.add(key.clone(), Value(vec![task])).await.unwrap();
+                    assert_eq!(
+                        store.get(key.clone()).await.unwrap(),
+                        Some(Value(vec![task]))
+                    );
+                    if i % 2 == 0 {
+                        store.remove(key).await.unwrap();
+                    }
+                }
+            })
+        });
+        for task in futures::future::join_all(tasks).await {
+            task.unwrap();
+        }
+
+        assert_eq!(store.list().await.unwrap().len(), 32 * 25);
+    }
+
+    #[tokio::test]
+    async fn local_store_survives_poisoned_lock() {
+        let store = LocalStore::new();
+        store
+            .add(Key::from("a"), Value(b"first".to_vec()))
+            .await
+            .unwrap();
+
+        let poisoner = store.clone();
+        std::thread::spawn(move || {
+            let _guard = poisoner.store.lock().unwrap();
+            panic!("operation panicked while holding the lock");
+        })
+        .join()
+        .unwrap_err();
+
+        assert!(matches!(
+            store.get(Key::from("a")).await,
+            Err(StorageManagerError::InternalError)
+        ));
+        assert!(matches!(
+            store.add(Key::from("b"), Value(vec![])).await,
+            Err(StorageManagerError::InternalError)
+        ));
+        assert!(matches!(
+            store.batch(vec![]).await,
+            Err(StorageManagerError::InternalError)
+        ));
+    }
+
+    fn store_path() -> PathBuf {
+        std::env::temp_dir().join(format!("encrypted-file-store-{}", Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn encrypted_file_store_roundtrip() {
+        let path = store_path();
+        let key = [7u8; 32];
+        let store = EncryptedFileStore::new(&path, &key).unwrap();
+
+        store
+            .add(Key::from("a"), Value(b"first".to_vec()))
+            .await
+            .unwrap();
+        store
+            .add(Key::from("b"), Value(b"second".to_vec()))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get(Key::from("a")).await.unwrap(),
+            Some(Value(b"first".to_vec()))
+        );
+
+        store.remove(Key::from("a")).await.unwrap();
+        // Removing a missing key is not an error.
+        store.remove(Key::from("a")).await.unwrap();
+        assert_eq!(store.get(Key::from("a")).await.unwrap(), None);
+
+        // The contents are encrypted and persisted across instances.
+        let contents = std::fs::read(&path).unwrap();
+        assert!(!contents.windows(6).any(|w| w == b"second"));
+
+        let reopened = EncryptedFileStore::new(&path, &key).unwrap();
+        assert_eq!(reopened.list().await.unwrap(), vec![Key::from("b")]);
+
+        let wrong_key = EncryptedFileStore::new(&path, &[8u8; 32]).unwrap();
+        assert!(matches!(
+            wrong_key.get(Key::from("b")).await,
+            Err(StorageManagerError::CouldNotDecryptValue)
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn encrypted_file_store_batch() {
+        let path = store_path();
+        let store = EncryptedFileStore::new(&path, &[2u8; 32]).unwrap();
+        store
+            .add(Key::from("old"), Value(b"old".to_vec()))
+            .await
+            .unwrap();
+
+        store
+            .batch(vec![
+                StorageOp::Add {
+                    key: Key::from("a"),
+                    value: Value(b"first".to_vec()),
+                },
+                StorageOp::Add {
+                    key: Key::from("b"),
+                    value: Value(b"second".to_vec()),
+                },
+                StorageOp::Remove {
+                    key: Key::from("old"),
+                },
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.list().await.unwrap(),
+            vec![Key::from("a"), Key::from("b")]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn encrypted_file_store_concurrent_writes() {
+        let path = store_path();
+        let store = Arc::new(EncryptedFileStore::new(&path, &[1u8; 32]).unwrap());
+
+        futures::future::try_join_all((0..32).map(|i| {
+            let store = store.clone();
+            async move {
+                store
+                    .add(Key(format!("key-{i}")), Value(vec![i as u8]))
+                    .await
+            }
+        }))
+        .await
+        .unwrap();
+
+        assert_eq!(store.list().await.unwrap().len(), 32);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn encrypted_file_stores_differing_by_extension() {
+        let dir = store_path();
+        std::fs::create_dir(&dir).unwrap();
+        let first = EncryptedFileStore::new(dir.join("store.a"), &[3u8; 32]).unwrap();
+        let second = EncryptedFileStore::new(dir.join("store.b"), &[3u8; 32]).unwrap();
+
+        futures::future::try_join(
+            first.add(Key::from("first"), Value(b"first".to_vec())),
+            second.add(Key::from("second"), Value(b"second".to_vec())),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(first.list().await.unwrap(), vec![Key::from("first")]);
+        assert_eq!(second.list().await.unwrap(), vec![Key::from("second")]);
+        // No temporary file is left behind.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn encrypted_file_store_rejects_invalid_key() {
+        assert!(matches!(
+            EncryptedFileStore::new(store_path(), &[0u8; 16]),
+            Err(StorageManagerError::CouldNotMakeKey)
+        ));
+    }
+}
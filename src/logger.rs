@@ -1,6 +1,127 @@
+use std::sync::Arc;
+
+/// Verbosity of the SDK logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(value: LogLevel) -> Self {
+        match value {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+impl From<log::Level> for LogLevel {
+    fn from(value: log::Level) -> Self {
+        match value {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug => LogLevel::Debug,
+            log::Level::Trace => LogLevel::Trace,
+        }
+    }
+}
+
+#[derive(thiserror::Error, uniffi::Error, Debug)]
+pub enum LoggingError {
+    #[error("The global logger has already been initialized")]
+    AlreadyInitialized,
+}
+
+/// A host-provided destination for the SDK logs, e.g. `os_log` on iOS.
+#[uniffi::export(with_foreign)]
+pub trait LogSink: Send + Sync {
+    fn log(&self, level: LogLevel, target: String, message: String);
+}
+
+/// Routes `log` records, including those emitted through `tracing`, to a [LogSink].
+struct SinkLogger {
+    sink: Arc<dyn LogSink>,
+    level: log::LevelFilter,
+}
+
+impl log::Log for SinkLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            self.sink.log(
+                record.level().into(),
+                record.target().to_string(),
+                record.args().to_string(),
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Writes log records to stderr, for platforms without a native logger.
+#[cfg(not(target_os = "android"))]
+#[derive(Debug)]
+struct StderrSink;
+
+#[cfg(not(target_os = "android"))]
+impl LogSink for StderrSink {
+    fn log(&self, level: LogLevel, target: String, message: String) {
+        eprintln!("[{level:?} {target}] {message}");
+    }
+}
+
 /// Initiate the global logger for the mobile SDK.
 ///
-/// This method should be called once per application lifecycle.
+/// Logs at or above `level` are sent to `sink`. Without a sink, logs go to logcat on Android
+/// and to stderr on other platforms.
+///
+/// The logger can only be initialized once per application lifecycle.
+#[uniffi::export]
+pub fn init_logging(level: LogLevel, sink: Option<Arc<dyn LogSink>>) -> Result<(), LoggingError> {
+    let sink = match sink {
+        Some(sink) => sink,
+        #[cfg(target_os = "android")]
+        None => {
+            android_logger::init_once(
+                android_logger::Config::default()
+                    .with_max_level(level.into())
+                    .with_tag("MOBILE_SDK_RS"),
+            );
+            return Ok(());
+        }
+        #[cfg(not(target_os = "android"))]
+        None => Arc::new(StderrSink),
+    };
+
+    log::set_boxed_logger(Box::new(SinkLogger {
+        sink,
+        level: level.into(),
+    }))
+    .map_err(|_| LoggingError::AlreadyInitialized)?;
+    log::set_max_level(level.into());
+
+    Ok(())
+}
+
+/// Initiate the global logger for the mobile SDK.
+///
+/// On Android, logs of every level go to logcat. Other platforms are left without a logger,
+/// so that [init_logging] can still choose one.
+///
+/// This method should be called once per application lifecycle. Prefer [init_logging] to
+/// choose the level and destination of the logs.
 #[uniffi::export]
 pub fn init_global_logger() {
     #[cfg(target_os = "android")]
@@ -10,3 +131,48 @@ pub fn init_global_logger() {
             .with_tag("MOBILE_SDK_RS"),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingSink(Mutex<Vec<(LogLevel, String)>>);
+
+    impl LogSink for RecordingSink {
+        fn log(&self, level: LogLevel, _target: String, message: String) {
+            self.0.lock().unwrap().push((level, message));
+        }
+    }
+
+    #[test]
+    fn sink_logger_filters_by_level() {
+        let sink = Arc::new(RecordingSink::default());
+        let logger = SinkLogger {
+            sink: sink.clone(),
+            level: LogLevel::Info.into(),
+        };
+
+        log::Log::log(
+            &logger,
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .args(format_args!("kept"))
+                .build(),
+        );
+        log::Log::log(
+            &logger,
+            &log::Record::builder()
+                .level(log::Level::Debug)
+                .args(format_args!("dropped"))
+                .build(),
+        );
+
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            vec![(LogLevel::Warn, "kept".to_string())]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Fragmentation and reassembly of mDL messages sent over BLE.
+//!
+//! ISO 18013-5 transfers each message as a series of characteristic writes.
+//! The first byte of every write is a status byte: `0x01` when more chunks
+//! follow and `0x00` for the last chunk of the message.
+
+use std::sync::{Arc, Mutex};
+
+/// Status byte marking the last chunk of a message.
+const LAST_CHUNK: u8 = 0x00;
+/// Status byte marking a chunk that is followed by more chunks.
+const MORE_CHUNKS: u8 = 0x01;
+/// Bytes of each ATT write taken up by the opcode and attribute handle.
+const ATT_HEADER_LEN: u16 = 3;
+
+#[derive(thiserror::Error, uniffi::Error, Debug)]
+pub enum BleMessageError {
+    #[error("received an empty BLE chunk")]
+    EmptyChunk,
+    #[error("invalid BLE chunk status byte: {0:#04x}")]
+    InvalidStatus(u8),
+    #[error("MTU of {0} is too small to carry message data")]
+    MtuTooSmall(u16),
+    #[error("Could not lock mutex")]
+    Lock,
+}
+
+/// Reassembles BLE characteristic writes into complete messages.
+#[derive(uniffi::Object, Default)]
+pub struct BleMessageAssembler {
+    buffer: Mutex<Vec<u8>>,
+}
+
+#[uniffi::export]
+impl BleMessageAssembler {
+    #[uniffi::constructor]
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Add a chunk received from a characteristic write.
+    ///
+    /// Returns the complete message once its last chunk arrives, ready to be
+    /// passed to [MdlPresentationSession::handle_request](super::holder::MdlPresentationSession::handle_request)
+    /// or `handle_response`, and `None` while more chunks are expected.
+    pub fn push(&self, chunk: Vec<u8>) -> Result<Option<Vec<u8>>, BleMessageError> {
+        let (status, data) = chunk.split_first().ok_or(BleMessageError::EmptyChunk)?;
+        let mut buffer = self.buffer.lock().map_err(|_| BleMessageError::Lock)?;
+
+        match *status {
+            MORE_CHUNKS => {
+                buffer.extend_from_slice(data);
+                Ok(None)
+            }
+            LAST_CHUNK => {
+                buffer.extend_from_slice(data);
+                Ok(Some(std::mem::take(&mut *buffer)))
+            }
+            status => {
+                buffer.clear();
+                Err(BleMessageError::InvalidStatus(status))
+            }
+        }
+    }
+
+    /// Discard any partially received message, e.g. after a disconnect.
+    pub fn reset(&self) {
+        if let Ok(mut buffer) = self.buffer.lock() {
+            buffer.clear();
+        }
+    }
+}
+
+/// Splits outgoing messages into chunks that fit in a single characteristic write.
+#[derive(uniffi::Object, Debug)]
+pub struct BleMessageChunker {
+    chunk_data_len: usize,
+}
+
+#[uniffi::export]
+impl BleMessageChunker {
+    /// Create a chunker for a connection with the negotiated ATT `mtu`.
+    #[uniffi::constructor]
+    pub fn new(mtu: u16) -> Result<Arc<Self>, BleMessageError> {
+        // Each write loses the ATT header and the status byte.
+        let chunk_data_len = mtu
+            .checked_sub(ATT_HEADER_LEN + 1)
+            .filter(|len| *len > 0)
+            .ok_or(BleMessageError::MtuTooSmall(mtu))?;
+
+        Ok(Arc::new(Self {
+            chunk_data_len: chunk_data_len.into(),
+        }))
+    }
+
+    /// Split a message into chunks, each prefixed with its status byte.
+    pub fn chunk(&self, message: Vec<u8>) -> Vec<Vec<u8>> {
+        if message.is_empty() {
+            return vec![vec![LAST_CHUNK]];
+        }
+
+        let mut chunks = message.chunks(self.chunk_data_len).peekable();
+        let mut out = Vec::new();
+        while let Some(data) = chunks.next() {
+            let status = if chunks.peek().is_some() {
+                MORE_CHUNKS
+            } else {
+                LAST_CHUNK
+            };
+            let mut chunk = Vec::with_capacity(data.len() + 1);
+            chunk.push(status);
+            chunk.extend_from_slice(data);
+            out.push(chunk);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_and_reassembles_messages() {
+        let message: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let chunker = BleMessageChunker::new(185).unwrap();
+        let chunks = chunker.chunk(message.clone());
+
+        assert_eq!(chunks.len(), 6);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 182));
+        assert_eq!(chunks.last().unwrap()[0], LAST_CHUNK);
+
+        let assembler = BleMessageAssembler::new();
+        let (last, rest) = chunks.split_last().unwrap();
+        for chunk in rest {
+            assert_eq!(assembler.push(chunk.clone()).unwrap(), None);
+        }
+        assert_eq!(assembler.push(last.clone()).unwrap(), Some(message));
+
+        // The assembler is ready for the next message.
+        assert_eq!(assembler.push(vec![LAST_CHUNK, 1]).unwrap(), Some(vec![1]));
+    }
+
+    #[test]
+    fn rejects_invalid_chunks() {
+        let assembler = BleMessageAssembler::new();
+        assert!(matches!(
+            assembler.push(vec![]),
+            Err(BleMessageError::EmptyChunk)
+        ));
+        assert!(matches!(
+            assembler.push(vec![0x02, 1]),
+            Err(BleMessageError::InvalidStatus(0x02))
+        ));
+        assert!(matches!(
+            BleMessageChunker::new(4),
+            Err(BleMessageError::MtuTooSmall(4))
+        ));
+    }
+}
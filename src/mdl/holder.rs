@@ -12,9 +12,8 @@
 use crate::common::*;
 use crate::credential::mdoc::Mdoc;
 use crate::{storage_manager::StorageManagerInterface, vdc_collection::VdcCollection};
-use std::ops::DerefMut;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
 };
 
@@ -88,8 +87,9 @@ pub async fn initialize_mdl_presentation(
             value: format!("Could not generate qr engagement: {e:?}"),
         })?;
     Ok(MdlPresentationSession {
-        engaged: Mutex::new(engaged_state),
-        in_process: Mutex::new(None),
+        stage: Mutex::new(PresentationStage::Engaged(engaged_state)),
+        disclosure_policy: Mutex::new(None),
+        session_transcript: Mutex::new(None),
         qr_code_uri,
         ble_ident,
     })
@@ -138,8 +138,9 @@ pub fn initialize_mdl_presentation_from_bytes(
             value: format!("Could not generate qr engagement: {e:?}"),
         })?;
     Ok(MdlPresentationSession {
-        engaged: Mutex::new(engaged_state),
-        in_process: Mutex::new(None),
+        stage: Mutex::new(PresentationStage::Engaged(engaged_state)),
+        disclosure_policy: Mutex::new(None),
+        session_transcript: Mutex::new(None),
         qr_code_uri,
         ble_ident,
     })
@@ -147,8 +148,9 @@ pub fn initialize_mdl_presentation_from_bytes(
 
 #[derive(uniffi::Object)]
 pub struct MdlPresentationSession {
-    engaged: Mutex<device::SessionManagerEngaged>,
-    in_process: Mutex<Option<InProcessRecord>>,
+    stage: Mutex<PresentationStage>,
+    disclosure_policy: Mutex<Option<Arc<DisclosurePolicy>>>,
+    session_transcript: Mutex<Option<Vec<u8>>>,
     pub qr_code_uri: String,
     pub ble_ident: Vec<u8>,
 }
@@ -159,6 +161,112 @@ struct InProcessRecord {
     items_request: device::RequestedItems,
 }
 
+/// The state of an mDL presentation session.
+///
+/// A session moves through the states in order; calls made out of order are rejected with an
+/// `InvalidState` error carrying the current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum MdlPresentationState {
+    /// Waiting for the request from the reader.
+    Engaged,
+    /// The request was received and is waiting for the user's consent.
+    RequestReceived,
+    /// The response was generated and is waiting for its signature.
+    AwaitingSignature,
+    /// The signed response was produced.
+    Responded,
+    /// The session was terminated.
+    Terminated,
+}
+
+/// The session data held in each [MdlPresentationState], kept behind a single lock so that
+/// state transitions cannot interleave.
+enum PresentationStage {
+    Engaged(device::SessionManagerEngaged),
+    RequestReceived(InProcessRecord),
+    AwaitingSignature(InProcessRecord),
+    Responded,
+    Terminated,
+}
+
+impl PresentationStage {
+    fn state(&self) -> MdlPresentationState {
+        match self {
+            PresentationStage::Engaged(_) => MdlPresentationState::Engaged,
+            PresentationStage::RequestReceived(_) => MdlPresentationState::RequestReceived,
+            PresentationStage::AwaitingSignature(_) => MdlPresentationState::AwaitingSignature,
+            PresentationStage::Responded => MdlPresentationState::Responded,
+            PresentationStage::Terminated => MdlPresentationState::Terminated,
+        }
+    }
+}
+
+/// A wallet-side policy listing data elements that must never be disclosed,
+/// regardless of what the reader requests or the user permits.
+#[derive(uniffi::Object, Debug, Default)]
+pub struct DisclosurePolicy {
+    /// Forbidden element identifiers, keyed by namespace.
+    forbidden: HashMap<String, HashSet<String>>,
+}
+
+#[uniffi::export]
+impl DisclosurePolicy {
+    /// Create a policy from a map of namespace to forbidden element identifiers.
+    #[uniffi::constructor]
+    pub fn new(forbidden_elements: HashMap<String, Vec<String>>) -> Arc<Self> {
+        Arc::new(Self {
+            forbidden: forbidden_elements
+                .into_iter()
+                .map(|(namespace, elements)| (namespace, elements.into_iter().collect()))
+                .collect(),
+        })
+    }
+
+    /// Returns whether the policy forbids disclosing `element` from `namespace`.
+    pub fn is_forbidden(&self, namespace: String, element: String) -> bool {
+        self.forbids(&namespace, &element)
+    }
+}
+
+impl DisclosurePolicy {
+    fn forbids(&self, namespace: &str, element: &str) -> bool {
+        self.forbidden
+            .get(namespace)
+            .is_some_and(|elements| elements.contains(element))
+    }
+
+    /// Remove forbidden elements from a set of permitted items, logging each one dropped.
+    fn apply(
+        &self,
+        permitted_items: HashMap<String, HashMap<String, Vec<String>>>,
+    ) -> HashMap<String, HashMap<String, Vec<String>>> {
+        permitted_items
+            .into_iter()
+            .map(|(doc_type, namespaces)| {
+                let namespaces = namespaces
+                    .into_iter()
+                    .map(|(namespace, elements)| {
+                        let elements = elements
+                            .into_iter()
+                            .filter(|element| {
+                                let forbidden = self.forbids(&namespace, element);
+                                if forbidden {
+                                    tracing::warn!(
+                                        "dropping {namespace}/{element} from {doc_type}: forbidden by disclosure policy"
+                                    );
+                                }
+                                !forbidden
+                            })
+                            .collect();
+                        (namespace, elements)
+                    })
+                    .collect();
+                (doc_type, namespaces)
+            })
+            .collect()
+    }
+}
+
 #[uniffi::export]
 impl MdlPresentationSession {
     /// Handle a request from a reader that is seeking information from the mDL holder.
@@ -167,33 +275,34 @@ impl MdlPresentationSession {
     /// technology. Returns a Vector of information items requested by the reader, or an
     /// error.
     pub fn handle_request(&self, request: Vec<u8>) -> Result<Vec<ItemsRequest>, RequestError> {
-        let (session_manager, items_requests) = {
-            let session_establishment: SessionEstablishment = isomdl::cbor::from_slice(&request)
-                .map_err(|e| RequestError::Generic {
-                    value: format!("Could not deserialize request: {e:?}"),
-                })?;
-            self.engaged
-                .lock()
-                .map_err(|_| RequestError::Generic {
-                    value: "Could not lock mutex".to_string(),
-                })?
-                .clone()
-                .process_session_establishment(
-                    session_establishment,
-                    TrustAnchorRegistry::default(),
-                )
-                .map_err(|e| RequestError::Generic {
-                    value: format!("Could not process process session establishment: {e:?}"),
-                })?
-        };
+        let session_establishment: SessionEstablishment = isomdl::cbor::from_slice(&request)
+            .map_err(|e| RequestError::Generic {
+                value: format!("Could not deserialize request: {e:?}"),
+            })?;
 
-        let mut in_process = self.in_process.lock().map_err(|_| RequestError::Generic {
+        let mut stage = self.stage.lock().map_err(|_| RequestError::Generic {
             value: "Could not lock mutex".to_string(),
         })?;
-        *in_process = Some(InProcessRecord {
+        let PresentationStage::Engaged(engaged) = &*stage else {
+            return Err(RequestError::InvalidState {
+                state: stage.state(),
+            });
+        };
+        let session_transcript =
+            super::qr_session_transcript(&self.qr_code_uri, &session_establishment);
+        let (session_manager, items_requests) = engaged
+            .clone()
+            .process_session_establishment(session_establishment, TrustAnchorRegistry::default())
+            .map_err(|e| RequestError::Generic {
+                value: format!("Could not process process session establishment: {e:?}"),
+            })?;
+        *stage = PresentationStage::RequestReceived(InProcessRecord {
             session: session_manager,
             items_request: items_requests.items_request.clone(),
         });
+        if let Ok(mut transcript) = self.session_transcript.lock() {
+            *transcript = session_transcript;
+        }
 
         Ok(items_requests
             .items_request
@@ -213,6 +322,30 @@ impl MdlPresentationSession {
             .collect())
     }
 
+    /// The CBOR-encoded `SessionTranscript` of the session, once the reader's request has
+    /// been handled.
+    ///
+    /// The device signature of the response is made over this transcript, so it can be logged
+    /// for audit or passed to external verification tooling.
+    pub fn session_transcript_bytes(&self) -> Option<Vec<u8>> {
+        self.session_transcript.lock().ok()?.clone()
+    }
+
+    /// Attach a [DisclosurePolicy] to the session.
+    ///
+    /// Elements forbidden by the policy are removed from the permitted items
+    /// passed to [MdlPresentationSession::generate_response].
+    pub fn set_disclosure_policy(&self, policy: Arc<DisclosurePolicy>) -> Result<(), SessionError> {
+        *self
+            .disclosure_policy
+            .lock()
+            .map_err(|_| SessionError::Generic {
+                value: "Could not lock mutex".to_string(),
+            })? = Some(policy);
+
+        Ok(())
+    }
+
     /// Constructs the response to be sent from the holder to the reader containing
     /// the items of information the user has consented to share.
     ///
@@ -224,6 +357,17 @@ impl MdlPresentationSession {
         &self,
         permitted_items: HashMap<String, HashMap<String, Vec<String>>>,
     ) -> Result<Vec<u8>, SignatureError> {
+        let policy = self
+            .disclosure_policy
+            .lock()
+            .map_err(|_| SignatureError::Generic {
+                value: "Could not lock mutex".to_string(),
+            })?
+            .clone();
+        let permitted_items = match policy {
+            Some(policy) => policy.apply(permitted_items),
+            None => permitted_items,
+        };
         let permitted = permitted_items
             .into_iter()
             .map(|(doc_type, namespaces)| {
@@ -231,53 +375,82 @@ impl MdlPresentationSession {
                 (doc_type, ns)
             })
             .collect();
-        if let Some(ref mut in_process) = self.in_process.lock().unwrap().deref_mut() {
-            in_process
-                .session
-                .prepare_response(&in_process.items_request, permitted);
-            Ok(in_process
-                .session
-                .get_next_signature_payload()
-                .map(|(_, payload)| payload)
-                .ok_or(SignatureError::Generic {
-                    value: "Failed to get next signature payload".to_string(),
-                })?
-                .to_vec())
-        } else {
-            Err(SignatureError::Generic {
-                value: "Could not get lock on session".to_string(),
-            })
+
+        let mut stage = self.stage.lock().map_err(|_| SignatureError::Generic {
+            value: "Could not lock mutex".to_string(),
+        })?;
+        let PresentationStage::RequestReceived(in_process) = &mut *stage else {
+            return Err(SignatureError::InvalidState {
+                state: stage.state(),
+            });
+        };
+        in_process
+            .session
+            .prepare_response(&in_process.items_request, permitted);
+        let payload = in_process
+            .session
+            .get_next_signature_payload()
+            .map(|(_, payload)| payload)
+            .ok_or(SignatureError::Generic {
+                value: "Failed to get next signature payload".to_string(),
+            })?
+            .to_vec();
+
+        if let PresentationStage::RequestReceived(in_process) =
+            std::mem::replace(&mut *stage, PresentationStage::Terminated)
+        {
+            *stage = PresentationStage::AwaitingSignature(in_process);
         }
+        Ok(payload)
     }
 
+    /// Completes the response with the signature of the payload returned by
+    /// [MdlPresentationSession::generate_response].
+    ///
+    /// Returns the response to be transmitted to the reader.
     pub fn submit_response(&self, signature: Vec<u8>) -> Result<Vec<u8>, SignatureError> {
         let signature = p256::ecdsa::Signature::from_slice(&signature).map_err(|e| {
             SignatureError::InvalidSignature {
                 value: e.to_string(),
             }
         })?;
-        if let Some(ref mut in_process) = self.in_process.lock().unwrap().deref_mut() {
-            in_process
-                .session
-                .submit_next_signature(signature.to_bytes().to_vec())
-                .map_err(|e| SignatureError::Generic {
-                    value: format!("Could not submit next signature: {e:?}"),
-                })?;
-            in_process
-                .session
-                .retrieve_response()
-                .ok_or(SignatureError::TooManyDocuments)
-        } else {
-            Err(SignatureError::Generic {
-                value: "Could not get lock on session".to_string(),
-            })
-        }
+
+        let mut stage = self.stage.lock().map_err(|_| SignatureError::Generic {
+            value: "Could not lock mutex".to_string(),
+        })?;
+        let PresentationStage::AwaitingSignature(in_process) = &mut *stage else {
+            return Err(SignatureError::InvalidState {
+                state: stage.state(),
+            });
+        };
+        in_process
+            .session
+            .submit_next_signature(signature.to_bytes().to_vec())
+            .map_err(|e| SignatureError::Generic {
+                value: format!("Could not submit next signature: {e:?}"),
+            })?;
+        let response = in_process
+            .session
+            .retrieve_response()
+            .ok_or(SignatureError::TooManyDocuments)?;
+
+        *stage = PresentationStage::Responded;
+        Ok(response)
     }
 
     /// Terminates the mDL exchange session.
     ///
     /// Returns the termination message to be transmitted to the reader.
     pub fn terminate_session(&self) -> Result<Vec<u8>, TerminationError> {
+        let mut stage = self.stage.lock().map_err(|_| TerminationError::Generic {
+            value: "Could not lock mutex".to_string(),
+        })?;
+        if let PresentationStage::Terminated = *stage {
+            return Err(TerminationError::InvalidState {
+                state: MdlPresentationState::Terminated,
+            });
+        }
+
         let msg = session::SessionData {
             data: None,
             status: Some(session::Status::SessionTermination),
@@ -285,9 +458,21 @@ impl MdlPresentationSession {
         let msg_bytes = isomdl::cbor::to_vec(&msg).map_err(|e| TerminationError::Generic {
             value: format!("Could not serialize message bytes: {e:?}"),
         })?;
+
+        *stage = PresentationStage::Terminated;
         Ok(msg_bytes)
     }
 
+    /// Returns the current state of the session.
+    ///
+    /// A session whose lock was poisoned by a panic is reported as terminated.
+    pub fn state(&self) -> MdlPresentationState {
+        self.stage
+            .lock()
+            .map(|stage| stage.state())
+            .unwrap_or(MdlPresentationState::Terminated)
+    }
+
     /// Returns the generated QR code
     pub fn get_qr_code_uri(&self) -> String {
         self.qr_code_uri.clone()
@@ -309,6 +494,8 @@ pub enum SessionError {
 pub enum RequestError {
     #[error("{value}")]
     Generic { value: String },
+    #[error("the session is {state:?}")]
+    InvalidState { state: MdlPresentationState },
 }
 
 #[derive(uniffi::Record, Clone)]
@@ -333,12 +520,16 @@ pub enum SignatureError {
     TooManyDocuments,
     #[error("{value}")]
     Generic { value: String },
+    #[error("the session is {state:?}")]
+    InvalidState { state: MdlPresentationState },
 }
 
 #[derive(thiserror::Error, uniffi::Error, Debug)]
 pub enum TerminationError {
     #[error("{value}")]
     Generic { value: String },
+    #[error("the session is {state:?}")]
+    InvalidState { state: MdlPresentationState },
 }
 
 #[derive(thiserror::Error, uniffi::Error, Debug)]
@@ -372,6 +563,117 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn disclosure_policy_drops_forbidden_elements() {
+        let policy = DisclosurePolicy::new(
+            [(
+                "org.iso.18013.5.1".to_string(),
+                vec!["portrait".to_string()],
+            )]
+            .into_iter()
+            .collect(),
+        );
+        assert!(policy.is_forbidden("org.iso.18013.5.1".into(), "portrait".into()));
+        assert!(!policy.is_forbidden("org.iso.18013.5.1".into(), "given_name".into()));
+
+        let permitted_items = [(
+            "org.iso.18013.5.1.mDL".to_string(),
+            [(
+                "org.iso.18013.5.1".to_string(),
+                vec!["given_name".to_string(), "portrait".to_string()],
+            )]
+            .into_iter()
+            .collect(),
+        )]
+        .into_iter()
+        .collect();
+
+        let filtered = policy.apply(permitted_items);
+        assert_eq!(
+            filtered["org.iso.18013.5.1.mDL"]["org.iso.18013.5.1"],
+            vec!["given_name".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn presentation_session_rejects_out_of_order_calls() {
+        let key_alias = KeyAlias(Uuid::new_v4().to_string());
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+        let mdl = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+        let presentation_session =
+            initialize_mdl_presentation_from_bytes(Arc::new(mdl), Uuid::new_v4()).unwrap();
+        assert_eq!(presentation_session.state(), MdlPresentationState::Engaged);
+
+        assert!(matches!(
+            presentation_session.generate_response(HashMap::new()),
+            Err(SignatureError::InvalidState {
+                state: MdlPresentationState::Engaged
+            })
+        ));
+        assert!(matches!(
+            presentation_session.submit_response(vec![1; 64]),
+            Err(SignatureError::InvalidState {
+                state: MdlPresentationState::Engaged
+            })
+        ));
+
+        presentation_session.terminate_session().unwrap();
+        assert_eq!(
+            presentation_session.state(),
+            MdlPresentationState::Terminated
+        );
+        assert!(matches!(
+            presentation_session.terminate_session(),
+            Err(TerminationError::InvalidState {
+                state: MdlPresentationState::Terminated
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn presentation_session_survives_poisoned_lock() {
+        let key_alias = KeyAlias(Uuid::new_v4().to_string());
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+        let mdl = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+        let presentation_session =
+            initialize_mdl_presentation_from_bytes(Arc::new(mdl), Uuid::new_v4()).unwrap();
+
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let _stage = presentation_session.stage.lock().unwrap();
+                    panic!("operation panicked while holding the lock");
+                })
+                .join()
+                .unwrap_err();
+        });
+
+        assert!(matches!(
+            presentation_session.generate_response(HashMap::new()),
+            Err(SignatureError::Generic { .. })
+        ));
+        assert!(matches!(
+            presentation_session.submit_response(vec![1; 64]),
+            Err(SignatureError::Generic { .. })
+        ));
+        assert!(matches!(
+            presentation_session.terminate_session(),
+            Err(TerminationError::Generic { .. })
+        ));
+        assert_eq!(
+            presentation_session.state(),
+            MdlPresentationState::Terminated
+        );
+    }
+
     #[tokio::test]
     async fn end_to_end_ble_presentment_holder() {
         let key_alias = KeyAlias(Uuid::new_v4().to_string());
@@ -426,7 +728,7 @@ mod tests {
             "org.iso.18013.5.1.mDL".to_string(),
             [(
                 "org.iso.18013.5.1".to_string(),
-                vec!["given_name".to_string()],
+                vec!["given_name".to_string(), "family_name".to_string()],
             )]
             .into_iter()
             .collect(),
@@ -439,11 +741,43 @@ mod tests {
         let key = key_manager.get_signing_key(key_alias).unwrap();
         let signature = key.sign(signing_payload).unwrap();
         let response = presentation_session.submit_response(signature).unwrap();
+        assert_eq!(
+            presentation_session.state(),
+            MdlPresentationState::Responded
+        );
         let res = reader_session_manager.handle_response(&response);
         vdc_collection.delete(mdl.id).await.unwrap();
         assert_eq!(res.errors, BTreeMap::new());
     }
 
+    /// Extract the CBOR-encoded `SessionTranscript` from the COSE `Sig_structure` of the
+    /// device signature, `["Signature1", protected, external_aad, DeviceAuthenticationBytes]`,
+    /// where `DeviceAuthenticationBytes` is the tagged encoding of
+    /// `["DeviceAuthentication", SessionTranscript, DocType, DeviceNameSpacesBytes]`.
+    fn signed_session_transcript(signing_payload: &[u8]) -> Vec<u8> {
+        use ciborium::Value;
+
+        let sig_structure: Value = ciborium::from_reader(signing_payload).unwrap();
+        let Value::Bytes(device_authentication_bytes) = &sig_structure.as_array().unwrap()[3]
+        else {
+            panic!("payload is not a byte string: {sig_structure:?}")
+        };
+        let Value::Tag(24, device_authentication) =
+            ciborium::from_reader(device_authentication_bytes.as_slice()).unwrap()
+        else {
+            panic!("payload is not tagged as encoded CBOR")
+        };
+        let device_authentication: Value =
+            ciborium::from_reader(device_authentication.as_bytes().unwrap().as_slice()).unwrap();
+        let device_authentication = device_authentication.as_array().unwrap();
+        assert_eq!(
+            device_authentication[0],
+            Value::Text("DeviceAuthentication".into())
+        );
+
+        isomdl::cbor::to_vec(&device_authentication[1]).unwrap()
+    }
+
     #[tokio::test]
     async fn end_to_end_ble_presentment_holder_reader() {
         let key_alias = KeyAlias(Uuid::new_v4().to_string());
@@ -486,6 +820,8 @@ mod tests {
             .to_string()]),
         )
         .unwrap();
+        let reader_transcript = reader_session_data.state.session_transcript_bytes();
+        assert_eq!(presentation_session.session_transcript_bytes(), None);
         let _request_data = presentation_session
             .handle_request(reader_session_data.request)
             .unwrap();
@@ -503,10 +839,36 @@ mod tests {
         let signing_payload = presentation_session
             .generate_response(permitted_items)
             .unwrap();
+
+        // The exposed transcripts are the one isomdl has the device sign.
+        let signed_transcript = signed_session_transcript(&signing_payload);
+        assert_eq!(
+            presentation_session.session_transcript_bytes(),
+            Some(signed_transcript.clone())
+        );
+        assert_eq!(reader_transcript, Some(signed_transcript));
+
         let key = key_manager.get_signing_key(key_alias).unwrap();
         let signature = key.sign(signing_payload).unwrap();
         let response = presentation_session.submit_response(signature).unwrap();
-        let _ = crate::reader::handle_response(reader_session_data.state, response).unwrap();
+        let response_data =
+            crate::reader::handle_response(reader_session_data.state, response).unwrap();
+        assert_eq!(response_data.validity, crate::reader::ValidityStatus::Valid);
+        assert_eq!(response_data.trust_anchor_diagnostic, None);
+        assert_eq!(
+            response_data.intent_to_retain,
+            [(
+                "org.iso.18013.5.1".to_string(),
+                [
+                    ("given_name".to_string(), true),
+                    ("family_name".to_string(), false),
+                ]
+                .into_iter()
+                .collect(),
+            )]
+            .into_iter()
+            .collect()
+        );
 
         vdc_collection.delete(mdl.id).await.unwrap();
     }
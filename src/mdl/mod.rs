@@ -1,11 +1,30 @@
+pub mod ble;
 pub mod holder;
 pub mod reader;
 pub mod util;
 
-use ssi::{
-    claims::vc::v1::{data_integrity::any_credential_from_json_str, ToJwtClaims},
-    dids::{AnyDidMethod, DIDResolver},
-};
+use isomdl::definitions::{helpers::Tag24, DeviceEngagement, SessionEstablishment};
+use ssi::claims::vc::v1::{data_integrity::any_credential_from_json_str, ToJwtClaims};
+
+use crate::did::DidResolutionPolicy;
+
+/// The CBOR-encoded `SessionTranscript` of a session engaged by QR code, as defined in
+/// ISO/IEC 18013-5 Section 9.1.5.1: the device engagement, the reader key and a null handover.
+///
+/// The handover is null only for QR code engagement, the only engagement holder and reader
+/// sessions support; NFC and OID4VP sessions have other handovers.
+pub(crate) fn qr_session_transcript(
+    qr_code_uri: &str,
+    session_establishment: &SessionEstablishment,
+) -> Option<Vec<u8>> {
+    let device_engagement = Tag24::<DeviceEngagement>::from_qr_code_uri(qr_code_uri).ok()?;
+    let transcript = ciborium::Value::Array(vec![
+        ciborium::Value::serialized(&device_engagement).ok()?,
+        ciborium::Value::serialized(&session_establishment.e_reader_key).ok()?,
+        ciborium::Value::Null,
+    ]);
+    isomdl::cbor::to_vec(&transcript).ok()
+}
 
 #[derive(thiserror::Error, uniffi::Error, Debug)]
 pub enum VCVerificationError {
@@ -15,14 +34,23 @@ pub enum VCVerificationError {
 
 #[uniffi::export]
 pub async fn verify_json_vc_string(json: String) -> Result<(), VCVerificationError> {
+    verify_json_vc_string_with_did_policy(json, DidResolutionPolicy::default()).await
+}
+
+/// Verify a JSON-LD credential like [verify_json_vc_string], only resolving the DIDs allowed
+/// by `did_policy`.
+#[uniffi::export]
+pub async fn verify_json_vc_string_with_did_policy(
+    json: String,
+    did_policy: DidResolutionPolicy,
+) -> Result<(), VCVerificationError> {
     use ssi::prelude::VerificationParameters;
 
     let vc = any_credential_from_json_str(&json).map_err(|e| VCVerificationError::Generic {
         value: e.to_string(),
     })?;
 
-    let vm_resolver = AnyDidMethod::default().into_vm_resolver();
-    let params = VerificationParameters::from_resolver(vm_resolver);
+    let params = VerificationParameters::from_resolver(did_policy.vm_resolver());
 
     vc.verify(&params)
         .await
@@ -71,15 +99,23 @@ pub async fn vc_to_signed_vp(vc: String, key_str: String) -> Result<String, VPEr
 
 #[uniffi::export]
 pub async fn verify_jwt_vp(jwt_vp: String) -> Result<(), VPError> {
+    verify_jwt_vp_with_did_policy(jwt_vp, DidResolutionPolicy::default()).await
+}
+
+/// Verify a JWT presentation like [verify_jwt_vp], only resolving the DIDs allowed by
+/// `did_policy`.
+#[uniffi::export]
+pub async fn verify_jwt_vp_with_did_policy(
+    jwt_vp: String,
+    did_policy: DidResolutionPolicy,
+) -> Result<(), VPError> {
     use ssi::prelude::*;
 
     let jwt = JwsString::from_string(jwt_vp.to_string()).map_err(|e| VPError::Parsing {
         value: e.to_string(),
     })?;
 
-    let vm_resolver: ssi::dids::VerificationMethodDIDResolver<AnyDidMethod, AnyMethod> =
-        AnyDidMethod::default().into_vm_resolver();
-    let params = VerificationParameters::from_resolver(vm_resolver);
+    let params = VerificationParameters::from_resolver(did_policy.vm_resolver());
 
     jwt.verify(params)
         .await
@@ -1,20 +1,29 @@
 use std::{
     collections::{BTreeMap, HashMap},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use isomdl::{
     definitions::{
-        device_request,
-        helpers::{non_empty_map, NonEmptyMap},
+        device_request::{self, DeviceRequest, DocRequest},
+        device_response::DeviceResponse,
+        helpers::{non_empty_map, NonEmptyMap, NonEmptyVec, Tag24},
         x509::{
             self,
             trust_anchor::{PemTrustAnchor, TrustAnchorRegistry},
+            x5chain::X5CHAIN_COSE_HEADER_LABEL,
         },
+        DeviceEngagement, DeviceRetrievalMethod, Mso, ValidityInfo,
     },
     presentation::{authentication::AuthenticationStatus as IsoMdlAuthenticationStatus, reader},
 };
+use ssi::claims::cose::coset;
+use time::OffsetDateTime;
 use uuid::Uuid;
+use x509_cert::{
+    der::{Decode, DecodePem},
+    Certificate,
+};
 
 #[derive(thiserror::Error, uniffi::Error, Debug)]
 pub enum MDLReaderSessionError {
@@ -22,8 +31,179 @@ pub enum MDLReaderSessionError {
     Generic { value: String },
 }
 
+/// A device retrieval method advertised in a device engagement.
+#[derive(uniffi::Enum, Debug, Clone, PartialEq)]
+pub enum DeviceRetrievalMethodInfo {
+    /// BLE with the mdoc acting as the central client.
+    BleCentralClient {
+        uuid: Uuid,
+    },
+    /// BLE with the mdoc acting as the peripheral server.
+    BlePeripheralServer {
+        uuid: Uuid,
+    },
+    Nfc,
+    WifiAware,
+}
+
+/// The contents of a device engagement, as advertised by the holder's QR code.
+#[derive(uniffi::Record, Debug, Clone)]
+pub struct DeviceEngagementInfo {
+    /// Version of the device engagement structure.
+    pub version: String,
+    /// Cipher suite identifier from the engagement security structure.
+    pub cipher_suite: u64,
+    /// Retrieval methods supported by the device, in the order advertised.
+    pub retrieval_methods: Vec<DeviceRetrievalMethodInfo>,
+}
+
+/// Parse the device engagement from a holder's QR code URI (`mdoc:...`) without
+/// establishing a session.
+///
+/// This allows a reader to inspect the advertised transports before calling
+/// [establish_session], and to reject malformed QR codes early.
+#[uniffi::export]
+pub fn parse_device_engagement(
+    qr_uri: String,
+) -> Result<DeviceEngagementInfo, MDLReaderSessionError> {
+    let device_engagement = Tag24::<DeviceEngagement>::from_qr_code_uri(&qr_uri)
+        .map_err(|e| MDLReaderSessionError::Generic {
+            value: format!("unable to parse device engagement: {e:?}"),
+        })?
+        .into_inner();
+
+    let retrieval_methods =
+        device_engagement
+            .device_retrieval_methods
+            .iter()
+            .flat_map(|methods| methods.iter())
+            .flat_map(|method| match method {
+                DeviceRetrievalMethod::BLE(options) => options
+                    .central_client_mode
+                    .iter()
+                    .map(|mode| DeviceRetrievalMethodInfo::BleCentralClient { uuid: mode.uuid })
+                    .chain(options.peripheral_server_mode.iter().map(|mode| {
+                        DeviceRetrievalMethodInfo::BlePeripheralServer { uuid: mode.uuid }
+                    }))
+                    .collect(),
+                DeviceRetrievalMethod::NFC(_) => vec![DeviceRetrievalMethodInfo::Nfc],
+                DeviceRetrievalMethod::WIFI(_) => vec![DeviceRetrievalMethodInfo::WifiAware],
+            })
+            .collect();
+
+    Ok(DeviceEngagementInfo {
+        version: device_engagement.version,
+        cipher_suite: device_engagement.security.0,
+        retrieval_methods,
+    })
+}
+
+/// Builds a request for specific data elements of a single document type.
+///
+/// The requested elements can be passed to [establish_session] with
+/// [MdlRequestBuilder::requested_items], or encoded directly as a CBOR `DeviceRequest` with
+/// [MdlRequestBuilder::build].
+#[derive(uniffi::Object, Debug)]
+pub struct MdlRequestBuilder {
+    doc_type: String,
+    namespaces: Mutex<BTreeMap<String, BTreeMap<String, bool>>>,
+}
+
+#[uniffi::export]
+impl MdlRequestBuilder {
+    #[uniffi::constructor]
+    /// Start a request for a document type, e.g. `org.iso.18013.5.1.mDL`.
+    pub fn new(doc_type: String) -> Arc<Self> {
+        Arc::new(Self {
+            doc_type,
+            namespaces: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Request a data element, replacing any earlier request for the same element.
+    pub fn add_element(
+        &self,
+        namespace: String,
+        element: String,
+        intent_to_retain: bool,
+    ) -> Result<(), MDLReaderSessionError> {
+        self.namespaces
+            .lock()
+            .map_err(|_| MDLReaderSessionError::Generic {
+                value: "Could not lock mutex".to_string(),
+            })?
+            .entry(namespace)
+            .or_default()
+            .insert(element, intent_to_retain);
+        Ok(())
+    }
+
+    /// The requested data elements and their intent to retain, keyed by namespace.
+    pub fn requested_items(
+        &self,
+    ) -> Result<HashMap<String, HashMap<String, bool>>, MDLReaderSessionError> {
+        Ok(self
+            .namespaces
+            .lock()
+            .map_err(|_| MDLReaderSessionError::Generic {
+                value: "Could not lock mutex".to_string(),
+            })?
+            .iter()
+            .map(|(namespace, elements)| {
+                (namespace.clone(), elements.clone().into_iter().collect())
+            })
+            .collect())
+    }
+
+    /// Encode the request as a CBOR `DeviceRequest`.
+    pub fn build(&self) -> Result<Vec<u8>, MDLReaderSessionError> {
+        let items_request = device_request::ItemsRequest {
+            doc_type: self.doc_type.clone(),
+            namespaces: build_namespaces(self.requested_items()?)?,
+            request_info: None,
+        };
+
+        let doc_request = DocRequest {
+            items_request: Tag24::new(items_request).map_err(|e| {
+                MDLReaderSessionError::Generic {
+                    value: format!("Unable to encode items request: {e:?}"),
+                }
+            })?,
+            reader_auth: None,
+        };
+
+        let device_request = DeviceRequest {
+            version: DeviceRequest::VERSION.to_string(),
+            doc_requests: NonEmptyVec::new(doc_request),
+        };
+
+        isomdl::cbor::to_vec(&device_request).map_err(|e| MDLReaderSessionError::Generic {
+            value: format!("Unable to encode device request: {e:?}"),
+        })
+    }
+}
+
 #[derive(uniffi::Object)]
-pub struct MDLSessionManager(reader::SessionManager);
+pub struct MDLSessionManager {
+    session: reader::SessionManager,
+    /// Subjects of the configured trust anchors, used to diagnose untrusted issuers.
+    trust_anchor_subjects: Vec<String>,
+    /// The CBOR-encoded session transcript.
+    session_transcript: Option<Vec<u8>>,
+    /// The requested data elements and their intent to retain, keyed by namespace.
+    requested_items: HashMap<String, HashMap<String, bool>>,
+}
+
+#[uniffi::export]
+impl MDLSessionManager {
+    /// The CBOR-encoded `SessionTranscript` of the session.
+    ///
+    /// The device signature of the response is made over this transcript, so it can be logged
+    /// for audit or passed to external verification tooling.
+    pub fn session_transcript_bytes(&self) -> Option<Vec<u8>> {
+        self.session_transcript.clone()
+    }
+}
 
 impl std::fmt::Debug for MDLSessionManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -45,30 +225,18 @@ pub fn establish_session(
     requested_items: HashMap<String, HashMap<String, bool>>,
     trust_anchor_registry: Option<Vec<String>>,
 ) -> Result<MDLReaderSessionData, MDLReaderSessionError> {
-    let namespaces: Result<BTreeMap<_, NonEmptyMap<_, _>>, non_empty_map::Error> = requested_items
-        .into_iter()
-        .map(|(doc_type, namespaces)| {
-            let namespaces: BTreeMap<_, _> = namespaces.into_iter().collect();
-            match namespaces.try_into() {
-                Ok(n) => Ok((doc_type, n)),
-                Err(e) => Err(e),
-            }
-        })
+    let namespaces = build_namespaces(requested_items.clone())?;
+
+    let trust_anchor_pems = trust_anchor_registry.unwrap_or_default();
+    let trust_anchor_subjects = trust_anchor_pems
+        .iter()
+        .filter_map(|pem| Certificate::from_pem(pem).ok())
+        .map(|certificate| certificate.tbs_certificate.subject.to_string())
         .collect();
-    let namespaces = namespaces.map_err(|e| MDLReaderSessionError::Generic {
-        value: format!("Unable to build data elements: {e:?}"),
-    })?;
-    let namespaces: device_request::Namespaces =
-        namespaces
-            .try_into()
-            .map_err(|e| MDLReaderSessionError::Generic {
-                value: format!("Unable to build namespaces: {e:?}"),
-            })?;
 
     let registry = TrustAnchorRegistry::from_pem_certificates(
-        trust_anchor_registry
+        trust_anchor_pems
             .into_iter()
-            .flat_map(|v| v.into_iter())
             .map(|certificate_pem| PemTrustAnchor {
                 certificate_pem,
                 purpose: x509::trust_anchor::TrustPurpose::Iaca,
@@ -85,6 +253,12 @@ pub fn establish_session(
                 value: format!("unable to establish session: {e:?}"),
             },
         )?;
+    let session_transcript =
+        isomdl::cbor::from_slice(&request)
+            .ok()
+            .and_then(|session_establishment| {
+                super::qr_session_transcript(&uri, &session_establishment)
+            });
     let manager2 = manager.clone();
     let uuid =
         manager2
@@ -94,13 +268,42 @@ pub fn establish_session(
             })?;
 
     Ok(MDLReaderSessionData {
-        state: Arc::new(MDLSessionManager(manager)),
+        state: Arc::new(MDLSessionManager {
+            session: manager,
+            trust_anchor_subjects,
+            session_transcript,
+            requested_items,
+        }),
         request,
         ble_ident: ble_ident.to_vec(),
         uuid: *uuid,
     })
 }
 
+/// Convert requested data elements, keyed by namespace, into non-empty request namespaces.
+fn build_namespaces(
+    requested_items: HashMap<String, HashMap<String, bool>>,
+) -> Result<device_request::Namespaces, MDLReaderSessionError> {
+    let namespaces: Result<BTreeMap<_, NonEmptyMap<_, _>>, non_empty_map::Error> = requested_items
+        .into_iter()
+        .map(|(namespace, elements)| {
+            let elements: BTreeMap<_, _> = elements.into_iter().collect();
+            match elements.try_into() {
+                Ok(n) => Ok((namespace, n)),
+                Err(e) => Err(e),
+            }
+        })
+        .collect();
+    let namespaces = namespaces.map_err(|e| MDLReaderSessionError::Generic {
+        value: format!("Unable to build data elements: {e:?}"),
+    })?;
+    namespaces
+        .try_into()
+        .map_err(|e| MDLReaderSessionError::Generic {
+            value: format!("Unable to build namespaces: {e:?}"),
+        })
+}
+
 #[derive(thiserror::Error, uniffi::Error, Debug, PartialEq)]
 pub enum MDLReaderResponseError {
     #[error("Invalid decryption")]
@@ -168,17 +371,172 @@ impl From<IsoMdlAuthenticationStatus> for AuthenticationStatus {
         }
     }
 }
+
+/// Whether the mdocs in a response were within their MSO `validityInfo` period when received.
+#[derive(Debug, Clone, PartialEq, uniffi::Enum)]
+pub enum ValidityStatus {
+    Valid,
+    /// The current time is after `validUntil` of at least one mdoc.
+    Expired,
+    /// The current time is before `validFrom` of at least one mdoc.
+    NotYetValid,
+    /// The validity period could not be read from the response.
+    Unchecked,
+}
+
+impl ValidityStatus {
+    fn from_validity_info(validity_info: &ValidityInfo, now: OffsetDateTime) -> Self {
+        if now < validity_info.valid_from {
+            Self::NotYetValid
+        } else if now > validity_info.valid_until {
+            Self::Expired
+        } else {
+            Self::Valid
+        }
+    }
+}
+
+/// Check the validity period in the MSO of every mdoc in a decrypted response.
+fn response_validity(device_response: &DeviceResponse) -> ValidityStatus {
+    let Some(documents) = &device_response.documents else {
+        return ValidityStatus::Unchecked;
+    };
+
+    let now = OffsetDateTime::now_utc();
+    let mut status = ValidityStatus::Valid;
+    for document in documents.iter() {
+        let mso: Option<Mso> = document
+            .issuer_signed
+            .issuer_auth
+            .payload
+            .as_ref()
+            .and_then(|payload| isomdl::cbor::from_slice(payload).ok());
+
+        match mso {
+            Some(mso) => match ValidityStatus::from_validity_info(&mso.validity_info, now) {
+                ValidityStatus::Valid => {}
+                invalid => return invalid,
+            },
+            None => status = ValidityStatus::Unchecked,
+        }
+    }
+    status
+}
+
+/// Describe why a response's issuer is not trusted, when no certificate of the chain that
+/// signed a document was issued by one of the configured trust anchors.
+fn trust_anchor_diagnostic(
+    device_response: &DeviceResponse,
+    trust_anchor_subjects: &[String],
+) -> Option<String> {
+    if trust_anchor_subjects.is_empty() {
+        return None;
+    }
+
+    let documents = device_response.documents.as_ref()?;
+
+    documents.iter().find_map(|document| {
+        let issuer_auth = &document.issuer_signed.issuer_auth;
+        let chain_issuers: Vec<String> = issuer_auth
+            .unprotected
+            .rest
+            .iter()
+            .chain(issuer_auth.protected.header.rest.iter())
+            .find(|(label, _)| *label == coset::Label::Int(X5CHAIN_COSE_HEADER_LABEL))
+            .map(|(_, value)| x5chain_certificates(value))
+            .unwrap_or_default()
+            .iter()
+            .map(|certificate| certificate.tbs_certificate.issuer.to_string())
+            .collect();
+
+        if chain_issuers.is_empty() {
+            return Some("document is missing the issuer certificate chain".to_string());
+        }
+
+        untrusted_issuer_message(&chain_issuers, trust_anchor_subjects)
+    })
+}
+
+/// The certificates of an `x5chain` header, starting with the document signer certificate.
+fn x5chain_certificates(value: &ciborium::Value) -> Vec<Certificate> {
+    let certificates = match value {
+        ciborium::Value::Bytes(der) => vec![der],
+        ciborium::Value::Array(items) => items.iter().filter_map(|item| item.as_bytes()).collect(),
+        _ => vec![],
+    };
+
+    certificates
+        .into_iter()
+        .filter_map(|der| Certificate::from_der(der).ok())
+        .collect()
+}
+
+/// Describe a certificate chain, given by the issuers of its certificates starting with the
+/// document signer certificate, that none of the trust anchors issued.
+fn untrusted_issuer_message(
+    chain_issuers: &[String],
+    trust_anchor_subjects: &[String],
+) -> Option<String> {
+    if chain_issuers
+        .iter()
+        .any(|issuer| trust_anchor_subjects.contains(issuer))
+    {
+        return None;
+    }
+
+    Some(format!(
+        "signer certificate chain was not issued by a trusted root:\n\texpected one of:\n\t\t{}\n\tfound: {}",
+        trust_anchor_subjects.join("\n\t\t"),
+        chain_issuers.last()?
+    ))
+}
+
 #[derive(uniffi::Record, Debug)]
 pub struct MDLReaderResponseData {
     state: Arc<MDLSessionManager>,
     /// Contains the namespaces for the mDL directly, without top-level doc types
     verified_response: HashMap<String, HashMap<String, MDocItem>>,
+    /// Whether the reader requested to retain each disclosed data element, keyed by namespace.
+    ///
+    /// Elements requested with `intent_to_retain` set to false, or not requested at all, must
+    /// not be stored beyond the transaction, e.g. they should be kept in memory only.
+    pub intent_to_retain: HashMap<String, HashMap<String, bool>>,
     /// Outcome of issuer authentication.
     pub issuer_authentication: AuthenticationStatus,
     /// Outcome of device authentication.
     pub device_authentication: AuthenticationStatus,
+    /// Whether the mdocs were within their validity period. Expired or not yet valid
+    /// mdocs should be rejected.
+    pub validity: ValidityStatus,
     /// Errors that occurred during response processing.
     pub errors: Option<String>,
+    /// Why the issuer is not trusted, naming the issuer of the signer certificate and the
+    /// configured trust anchors, when it does not chain to any of them.
+    pub trust_anchor_diagnostic: Option<String>,
+}
+
+/// Look up the intent to retain of each disclosed data element in the request.
+fn intent_to_retain(
+    verified_response: &HashMap<String, HashMap<String, MDocItem>>,
+    requested_items: &HashMap<String, HashMap<String, bool>>,
+) -> HashMap<String, HashMap<String, bool>> {
+    verified_response
+        .iter()
+        .map(|(namespace, elements)| {
+            let requested = requested_items.get(namespace);
+            let elements = elements
+                .keys()
+                .map(|element| {
+                    let retain = requested
+                        .and_then(|requested| requested.get(element))
+                        .copied()
+                        .unwrap_or(false);
+                    (element.clone(), retain)
+                })
+                .collect();
+            (namespace.clone(), elements)
+        })
+        .collect()
 }
 
 #[uniffi::export]
@@ -186,8 +544,17 @@ pub fn handle_response(
     state: Arc<MDLSessionManager>,
     response: Vec<u8>,
 ) -> Result<MDLReaderResponseData, MDLReaderResponseError> {
-    let mut state = state.0.clone();
-    let validated_response = state.handle_response(&response);
+    let mut session = state.session.clone();
+    // Decrypting advances the session, so the response is inspected with a copy of the
+    // session from before it is handled.
+    let (validity, trust_anchor_diagnostic) = match session.clone().decrypt_response(&response) {
+        Ok(device_response) => (
+            response_validity(&device_response),
+            trust_anchor_diagnostic(&device_response, &state.trust_anchor_subjects),
+        ),
+        Err(_) => (ValidityStatus::Unchecked, None),
+    };
+    let validated_response = session.handle_response(&response);
     let errors = if !validated_response.errors.is_empty() {
         Some(
             serde_json::to_string(&validated_response.errors).map_err(|e| {
@@ -219,11 +586,144 @@ pub fn handle_response(
     let verified_response = verified_response.map_err(|e| MDLReaderResponseError::Generic {
         value: format!("Unable to parse response: {e:?}"),
     })?;
+    let intent_to_retain = intent_to_retain(&verified_response, &state.requested_items);
     Ok(MDLReaderResponseData {
-        state: Arc::new(MDLSessionManager(state)),
+        state: Arc::new(MDLSessionManager {
+            session,
+            trust_anchor_subjects: state.trust_anchor_subjects.clone(),
+            session_transcript: state.session_transcript.clone(),
+            requested_items: state.requested_items.clone(),
+        }),
         verified_response,
+        intent_to_retain,
         issuer_authentication: AuthenticationStatus::from(validated_response.issuer_authentication),
         device_authentication: AuthenticationStatus::from(validated_response.device_authentication),
+        validity,
         errors,
+        trust_anchor_diagnostic,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        crypto::{KeyAlias, RustTestKeyManager},
+        mdl::holder::initialize_mdl_presentation_from_bytes,
+    };
+
+    #[tokio::test]
+    async fn parse_device_engagement_from_holder_qr_code() {
+        let key_alias = KeyAlias(Uuid::new_v4().to_string());
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+        let mdoc = Arc::new(crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap());
+
+        let uuid = Uuid::new_v4();
+        let session = initialize_mdl_presentation_from_bytes(mdoc, uuid).unwrap();
+
+        let engagement = parse_device_engagement(session.get_qr_code_uri()).unwrap();
+        assert_eq!(
+            engagement.retrieval_methods,
+            vec![DeviceRetrievalMethodInfo::BleCentralClient { uuid }]
+        );
+    }
+
+    #[test]
+    fn parse_device_engagement_rejects_malformed_qr_code() {
+        assert!(parse_device_engagement("mdoc:not-an-engagement".into()).is_err());
+        assert!(parse_device_engagement("https://example.com".into()).is_err());
+    }
+
+    #[test]
+    fn validity_status_from_validity_info() {
+        let now = OffsetDateTime::now_utc();
+        let day = time::Duration::days(1);
+        let validity_info = ValidityInfo {
+            signed: now - day,
+            valid_from: now - day,
+            valid_until: now + day,
+            expected_update: None,
+        };
+
+        assert_eq!(
+            ValidityStatus::from_validity_info(&validity_info, now),
+            ValidityStatus::Valid
+        );
+        assert_eq!(
+            ValidityStatus::from_validity_info(&validity_info, now + day * 2),
+            ValidityStatus::Expired
+        );
+        assert_eq!(
+            ValidityStatus::from_validity_info(&validity_info, now - day * 2),
+            ValidityStatus::NotYetValid
+        );
+    }
+
+    #[test]
+    fn untrusted_issuer_names_issuer_and_anchors() {
+        let anchors = vec!["CN=Example IACA,C=US".to_string()];
+
+        assert_eq!(
+            untrusted_issuer_message(&["CN=Example IACA,C=US".to_string()], &anchors),
+            None
+        );
+
+        // A document signer certificate issued by an intermediate of a trust anchor.
+        assert_eq!(
+            untrusted_issuer_message(
+                &[
+                    "CN=Example Intermediate,C=US".to_string(),
+                    "CN=Example IACA,C=US".to_string()
+                ],
+                &anchors
+            ),
+            None
+        );
+
+        let message = untrusted_issuer_message(
+            &[
+                "CN=Other Intermediate,C=US".to_string(),
+                "CN=Other IACA,C=US".to_string(),
+            ],
+            &anchors,
+        )
+        .unwrap();
+        assert!(message.contains("CN=Example IACA,C=US"));
+        assert!(message.contains("found: CN=Other IACA,C=US"));
+    }
+
+    #[test]
+    fn request_builder_encodes_device_request() {
+        let builder = MdlRequestBuilder::new("org.iso.18013.5.1.mDL".into());
+        builder
+            .add_element("org.iso.18013.5.1".into(), "given_name".into(), true)
+            .unwrap();
+        builder
+            .add_element("org.iso.18013.5.1".into(), "family_name".into(), false)
+            .unwrap();
+
+        let requested_items = builder.requested_items().unwrap();
+        assert!(requested_items["org.iso.18013.5.1"]["given_name"]);
+        assert!(!requested_items["org.iso.18013.5.1"]["family_name"]);
+
+        let device_request: DeviceRequest =
+            isomdl::cbor::from_slice(&builder.build().unwrap()).unwrap();
+        let items_request = &device_request.doc_requests.first().items_request.as_ref();
+
+        assert_eq!(items_request.doc_type, "org.iso.18013.5.1.mDL");
+        let elements = items_request.namespaces.get("org.iso.18013.5.1").unwrap();
+        assert_eq!(elements.get("given_name"), Some(&true));
+        assert_eq!(elements.get("family_name"), Some(&false));
+    }
+
+    #[test]
+    fn request_builder_rejects_empty_request() {
+        let builder = MdlRequestBuilder::new("org.iso.18013.5.1.mDL".into());
+        assert!(builder.build().is_err());
+    }
+}
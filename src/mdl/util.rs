@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::Arc;
 use std::time::Duration;
@@ -64,24 +65,107 @@ impl fmt::Display for MdlUtilError {
     }
 }
 
+/// Namespaces of a test mdoc, mapping each namespace to its data elements.
+type TestNamespaces = BTreeMap<String, BTreeMap<String, ciborium::Value>>;
+
+/// The contents of a test mdoc generated with [generate_test_mdoc].
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct TestMdocConfig {
+    /// The document type, e.g. `eu.europa.ec.eudi.pid.1`.
+    pub doc_type: String,
+    /// A JSON object mapping each namespace to an object of its data elements.
+    pub namespaces: String,
+    /// Start of the validity period, in seconds relative to now. May be negative.
+    pub valid_from_offset_seconds: i64,
+    /// End of the validity period, in seconds relative to now. May be negative.
+    pub valid_until_offset_seconds: i64,
+}
+
 #[uniffi::export]
 /// Generate a new test mDL with hardcoded values, using the supplied key as the DeviceKey.
 pub fn generate_test_mdl(
     key_manager: Arc<dyn KeyStore>,
     key_alias: KeyAlias,
 ) -> Result<crate::credential::mdoc::Mdoc, MdlUtilError> {
-    Ok(generate_test_mdl_inner(key_manager, key_alias)?)
+    let now = OffsetDateTime::now_utc();
+    let validity_info = ValidityInfo {
+        signed: now,
+        valid_from: now,
+        // mDL valid for thirty days.
+        valid_until: now + Duration::from_secs(60 * 60 * 24 * 30),
+        expected_update: None,
+    };
+
+    Ok(generate_test_mdoc_inner(
+        key_manager,
+        key_alias,
+        "org.iso.18013.5.1.mDL".into(),
+        mdl_namespaces()?,
+        validity_info,
+    )?)
+}
+
+#[uniffi::export]
+/// Generate a new test mdoc with the supplied contents, using the supplied key as the DeviceKey.
+///
+/// Data elements are encoded from their JSON values, so elements requiring other CBOR types,
+/// e.g. byte strings or tagged dates, cannot be expressed.
+pub fn generate_test_mdoc(
+    key_manager: Arc<dyn KeyStore>,
+    key_alias: KeyAlias,
+    config: TestMdocConfig,
+) -> Result<crate::credential::mdoc::Mdoc, MdlUtilError> {
+    let namespaces = test_namespaces_from_json(&config.namespaces)?;
+
+    let now = OffsetDateTime::now_utc();
+    let validity_info = ValidityInfo {
+        signed: now,
+        valid_from: now + time::Duration::seconds(config.valid_from_offset_seconds),
+        valid_until: now + time::Duration::seconds(config.valid_until_offset_seconds),
+        expected_update: None,
+    };
+
+    Ok(generate_test_mdoc_inner(
+        key_manager,
+        key_alias,
+        config.doc_type,
+        namespaces,
+        validity_info,
+    )?)
 }
 
-fn generate_test_mdl_inner(
+fn test_namespaces_from_json(namespaces: &str) -> Result<TestNamespaces> {
+    let namespaces: BTreeMap<String, BTreeMap<String, serde_json::Value>> =
+        serde_json::from_str(namespaces).context("namespaces must be a JSON object of objects")?;
+
+    namespaces
+        .into_iter()
+        .map(|(namespace, elements)| {
+            let elements = elements
+                .into_iter()
+                .map(|(element, value)| {
+                    let value = ciborium::Value::serialized(&value)
+                        .with_context(|| format!("failed to encode {namespace}.{element}"))?;
+                    Ok((element, value))
+                })
+                .collect::<Result<_>>()?;
+            Ok((namespace, elements))
+        })
+        .collect()
+}
+
+fn generate_test_mdoc_inner(
     key_manager: Arc<dyn KeyStore>,
     key_alias: KeyAlias,
+    doc_type: String,
+    namespaces: TestNamespaces,
+    validity_info: ValidityInfo,
 ) -> Result<crate::credential::mdoc::Mdoc> {
     let (certificate, signer) = setup_certificate_chain()?;
     let key = key_manager.get_signing_key(key_alias.clone())?;
     let pk = p256::PublicKey::from_jwk_str(&key.jwk()?)?;
 
-    let mdoc_builder = prepare_mdoc(pk)?;
+    let mdoc_builder = prepare_mdoc(pk, doc_type, namespaces, validity_info)?;
 
     let x5chain = X5Chain::builder().with_certificate(certificate)?.build()?;
 
@@ -122,7 +206,8 @@ fn generate_test_mdl_inner(
     ))
 }
 
-fn prepare_mdoc(pub_key: PublicKey) -> Result<isomdl::issuance::mdoc::Builder> {
+/// The namespaces of the hardcoded test mDL.
+fn mdl_namespaces() -> Result<TestNamespaces> {
     let isomdl_data = serde_json::json!(
         {
           "family_name":"Smith",
@@ -210,28 +295,26 @@ fn prepare_mdoc(pub_key: PublicKey) -> Result<isomdl::issuance::mdoc::Builder> {
         }
     );
 
-    let doc_type = String::from("org.iso.18013.5.1.mDL");
     let isomdl_namespace = String::from("org.iso.18013.5.1");
     let aamva_namespace = String::from("org.iso.18013.5.1.aamva");
 
     let isomdl_data = OrgIso1801351::from_json(&isomdl_data)?.to_ns_map();
     let aamva_data = OrgIso1801351Aamva::from_json(&aamva_isomdl_data)?.to_ns_map();
 
-    let namespaces = [
+    Ok([
         (isomdl_namespace, isomdl_data),
         (aamva_namespace, aamva_data),
     ]
     .into_iter()
-    .collect();
-
-    let validity_info = ValidityInfo {
-        signed: OffsetDateTime::now_utc(),
-        valid_from: OffsetDateTime::now_utc(),
-        // mDL valid for thirty days.
-        valid_until: OffsetDateTime::now_utc() + Duration::from_secs(60 * 60 * 24 * 30),
-        expected_update: None,
-    };
+    .collect())
+}
 
+fn prepare_mdoc(
+    pub_key: PublicKey,
+    doc_type: String,
+    namespaces: TestNamespaces,
+    validity_info: ValidityInfo,
+) -> Result<isomdl::issuance::mdoc::Builder> {
     let digest_algorithm = DigestAlgorithm::SHA256;
 
     let ec = pub_key.to_encoded_point(false);
@@ -328,3 +411,47 @@ where
 
     Ok(builder)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::crypto::RustTestKeyManager;
+
+    #[tokio::test]
+    async fn generates_custom_mdoc() {
+        let key_alias = KeyAlias("pid".to_string());
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let mdoc = generate_test_mdoc(
+            key_manager,
+            key_alias,
+            TestMdocConfig {
+                doc_type: "eu.europa.ec.eudi.pid.1".into(),
+                namespaces: serde_json::json!({
+                    "eu.europa.ec.eudi.pid.1": {
+                        "family_name": "Smith",
+                        "age_over_18": true
+                    }
+                })
+                .to_string(),
+                valid_from_offset_seconds: -60,
+                valid_until_offset_seconds: 60 * 60,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(mdoc.doctype(), "eu.europa.ec.eudi.pid.1");
+        assert_eq!(mdoc.details().values().map(Vec::len).sum::<usize>(), 2);
+        assert!(mdoc.verify_integrity().is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_namespaces() {
+        assert!(test_namespaces_from_json(r#"{"eu.europa.ec.eudi.pid.1": "Smith"}"#).is_err());
+    }
+}
@@ -9,3 +9,39 @@ pub fn context_loader_from_map(
         .with_static_loader()
         .with_context_map_from(map)
 }
+
+/// Returns a loader that never fetches contexts over the network, resolving only the contexts
+/// bundled with the SDK and those in `map`.
+pub fn offline_context_loader(
+    map: Option<HashMap<String, String>>,
+) -> Result<ContextLoader, FromContextMapError> {
+    match map {
+        Some(map) => context_loader_from_map(map),
+        None => Ok(ContextLoader::empty().with_static_loader()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ssi::json_ld::{iref::Iri, Loader};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn offline_contexts() {
+        let bundled = Iri::new("https://www.w3.org/2018/credentials/v1").unwrap();
+        let custom = Iri::new("https://contexts.example/custom/v1").unwrap();
+
+        let loader = offline_context_loader(None).unwrap();
+        assert!(loader.load(bundled).await.is_ok());
+        assert!(loader.load(custom).await.is_err());
+
+        let loader = offline_context_loader(Some(HashMap::from([(
+            custom.to_string(),
+            r#"{ "@context": { "name": "https://schema.org/name" } }"#.to_string(),
+        )])))
+        .unwrap();
+        assert!(loader.load(bundled).await.is_ok());
+        assert!(loader.load(custom).await.is_ok());
+    }
+}
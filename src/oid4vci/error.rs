@@ -1,4 +1,7 @@
-use oid4vci::credential::RequestError;
+use oid4vci::{
+    credential::RequestError,
+    oauth2::{ErrorResponse, RequestTokenError},
+};
 use ssi::{
     claims::data_integrity::DecodeError, claims::ProofValidationError, json_ld::FromContextMapError,
 };
@@ -25,12 +28,34 @@ pub enum Oid4vciError {
     #[error("Invalid parameter: {_0}")]
     InvalidParameter(String),
 
+    #[error("Issuer requires unsupported proof type(s): {}", .0.join(", "))]
+    UnsupportedProofType(Vec<String>),
+
+    #[error("The access token has expired")]
+    TokenExpired,
+
     #[error("Failed to acquire lock for {_0}")]
     LockError(String),
 
     #[error("{vp_request}")]
     VpRequestRequired { vp_request: serde_json::Value },
 
+    /// An OAuth 2.0 error response from the issuer or its authorization server, e.g.
+    /// `invalid_proof` or `invalid_nonce`.
+    #[error("Issuer error {code}{}", .description.as_ref().map(|d| format!(": {d}")).unwrap_or_default())]
+    Issuer {
+        code: String,
+        description: Option<String>,
+    },
+
+    /// The issuer rejected the proof of possession and provided a fresh `c_nonce`, which new
+    /// proofs must be bound to before the credential request is retried.
+    #[error("The issuer rejected the proof of possession, retry with c_nonce {c_nonce}")]
+    InvalidProof {
+        c_nonce: String,
+        description: Option<String>,
+    },
+
     #[error("ProofValidationError: {_0}")]
     ProofValidationError(#[from] ProofValidationError),
 
@@ -47,6 +72,34 @@ pub enum Oid4vciError {
     Generic(String),
 }
 
+impl Oid4vciError {
+    /// Parse an OAuth 2.0 error response body, i.e. a JSON object with an `error` code and an
+    /// optional `error_description`.
+    ///
+    /// An `invalid_proof` or `invalid_nonce` error carrying a new `c_nonce` is returned as
+    /// [Oid4vciError::InvalidProof]. Returns `None` if the body is not an error response.
+    pub(crate) fn from_error_response(body: &[u8]) -> Option<Self> {
+        Self::from_error_json(&serde_json::from_slice(body).ok()?)
+    }
+
+    fn from_error_json(json: &serde_json::Value) -> Option<Self> {
+        let code = json.get("error")?.as_str()?.to_owned();
+        let description = json
+            .get("error_description")
+            .and_then(serde_json::Value::as_str)
+            .map(ToOwned::to_owned);
+
+        let c_nonce = json.get("c_nonce").and_then(serde_json::Value::as_str);
+        match (code.as_str(), c_nonce) {
+            ("invalid_proof" | "invalid_nonce", Some(c_nonce)) => Some(Self::InvalidProof {
+                c_nonce: c_nonce.to_owned(),
+                description,
+            }),
+            _ => Some(Self::Issuer { code, description }),
+        }
+    }
+}
+
 // TODO: some or all of these trait implementations can be converted to macros
 impl From<String> for Oid4vciError {
     fn from(value: String) -> Self {
@@ -74,6 +127,10 @@ where
                     };
                 }
             }
+
+            if let Some(error) = Oid4vciError::from_error_response(body) {
+                return error;
+            }
         }
 
         if let RequestError::Parse(e) = &value {
@@ -84,6 +141,26 @@ where
     }
 }
 
+impl<RE, TE> From<RequestTokenError<RE, TE>> for Oid4vciError
+where
+    RE: std::error::Error + 'static,
+    TE: ErrorResponse + 'static,
+{
+    fn from(value: RequestTokenError<RE, TE>) -> Self {
+        let error = match &value {
+            RequestTokenError::ServerResponse(response) => serde_json::to_value(response)
+                .ok()
+                .and_then(|json| Oid4vciError::from_error_json(&json)),
+            RequestTokenError::Parse(_, body) => Oid4vciError::from_error_response(body),
+            _ => None,
+        };
+
+        error.unwrap_or_else(|| {
+            Oid4vciError::RequestError(format!("failed to exchange code: {value}"))
+        })
+    }
+}
+
 impl From<oid4vci::client::Error> for Oid4vciError {
     fn from(value: oid4vci::client::Error) -> Self {
         Oid4vciError::RequestError(value.to_string())
@@ -3,11 +3,16 @@ use std::{collections::HashMap, future::Future, pin::Pin, str::FromStr, sync::Ar
 use async_trait::async_trait;
 use either::Either;
 use oid4vci::oauth2::{
-    http::{HeaderMap, Method, Request, Response, StatusCode, Uri},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Uri,
+    },
     AsyncHttpClient as ExtAsyncHttpClient, HttpRequest as ExtHttpRequest,
     HttpResponse as ExtHttpResponse, SyncHttpClient as ExtSyncHttpClient,
 };
 
+use crate::credential::CredentialFormat;
+
 #[derive(thiserror::Error, uniffi::Error, Debug)]
 pub enum HttpClientError {
     #[error("failed to build request")]
@@ -195,6 +200,83 @@ impl IHttpClient {
     }
 }
 
+/// Http client wrapper negotiating the content of credential requests, as some issuers
+/// reject requests without an `Accept` header matching the requested credential formats.
+pub(crate) struct ContentNegotiation<'a, C> {
+    client: &'a C,
+    accept: String,
+}
+
+impl<'a, C> ContentNegotiation<'a, C> {
+    /// Negotiate the media types of the requested credential `formats`.
+    pub(crate) fn new(client: &'a C, formats: &[String]) -> Self {
+        let accept = formats
+            .iter()
+            .flat_map(|format| credential_media_types(format))
+            .fold(Vec::<&str>::new(), |mut media_types, media_type| {
+                if !media_types.contains(media_type) {
+                    media_types.push(media_type);
+                }
+                media_types
+            });
+
+        Self {
+            client,
+            accept: match accept.is_empty() {
+                true => "application/json".to_string(),
+                false => accept.join(", "),
+            },
+        }
+    }
+
+    fn negotiate(&self, mut request: ExtHttpRequest) -> ExtHttpRequest {
+        let headers = request.headers_mut();
+        if let Ok(accept) = HeaderValue::from_str(&self.accept) {
+            headers.insert(ACCEPT, accept);
+        }
+        if !headers.contains_key(CONTENT_TYPE) {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        }
+        request
+    }
+}
+
+impl<C: ExtSyncHttpClient> ExtSyncHttpClient for ContentNegotiation<'_, C> {
+    type Error = C::Error;
+
+    fn call(&self, request: ExtHttpRequest) -> Result<ExtHttpResponse, Self::Error> {
+        self.client.call(self.negotiate(request))
+    }
+}
+
+impl<'c, C: ExtAsyncHttpClient<'c>> ExtAsyncHttpClient<'c> for ContentNegotiation<'_, C> {
+    type Error = C::Error;
+    type Future = C::Future;
+
+    fn call(&'c self, request: ExtHttpRequest) -> Self::Future {
+        self.client.call(self.negotiate(request))
+    }
+}
+
+/// Media types accepted in the credential response for a credential format. The credential
+/// response is JSON, unless the issuer returns the credential itself.
+fn credential_media_types(format: &str) -> &'static [&'static str] {
+    match CredentialFormat::from(format.to_string()) {
+        CredentialFormat::JwtVcJson | CredentialFormat::JwtVcJsonLd => {
+            &["application/json", "application/jwt"]
+        }
+        CredentialFormat::LdpVc => &["application/json", "application/ld+json"],
+        CredentialFormat::VCDM2SdJwt => &["application/json", "application/vc+sd-jwt"],
+        CredentialFormat::Other(format) if format == "vc+sd-jwt" => {
+            &["application/json", "application/vc+sd-jwt"]
+        }
+        CredentialFormat::Other(format) if format == "dc+sd-jwt" => {
+            &["application/json", "application/dc+sd-jwt"]
+        }
+        CredentialFormat::MsoMdoc | CredentialFormat::Other(_) => &["application/json"],
+    }
+}
+
 /// Internal Arc Wrapper to be able to impl traits for it
 /// Examples include:
 ///  - `openidconnect::(As|S)yncHttpClient` for `uniffi`'s foreign trait
@@ -232,3 +314,54 @@ pub(crate) fn headermap_to_hashmap(
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingClient(Mutex<Option<HttpRequest>>);
+
+    impl SyncHttpClient for RecordingClient {
+        fn http_client(&self, request: HttpRequest) -> Result<HttpResponse, HttpClientError> {
+            *self.0.lock().unwrap() = Some(request);
+            Ok(HttpResponse {
+                status_code: 200,
+                headers: HashMap::new(),
+                body: b"{}".to_vec(),
+            })
+        }
+    }
+
+    #[test]
+    fn negotiates_credential_media_types() {
+        let recording = Arc::new(RecordingClient::default());
+        let client = IArc::<dyn SyncHttpClient>(recording.clone());
+
+        for (format, accept) in [
+            ("jwt_vc_json", "application/json, application/jwt"),
+            ("ldp_vc", "application/json, application/ld+json"),
+            ("vc+sd-jwt", "application/json, application/vc+sd-jwt"),
+            ("mso_mdoc", "application/json"),
+        ] {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("https://issuer.example/credential")
+                .body(b"{}".to_vec())
+                .unwrap();
+
+            ContentNegotiation::new(&client, &[format.to_string()])
+                .call(request)
+                .unwrap();
+
+            let sent = recording.0.lock().unwrap().take().unwrap();
+            assert_eq!(sent.headers.get("accept").map(String::as_str), Some(accept));
+            assert_eq!(
+                sent.headers.get("content-type").map(String::as_str),
+                Some("application/json")
+            );
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use oid4vci::{
+    metadata::AuthorizationServerMetadata,
+    profiles::metadata::CredentialIssuerMetadata as ICredentialIssuerMetadata,
+};
+
+/// How long discovered metadata is reused by default.
+const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// Credential issuer and authorization server metadata discovered for an issuer.
+///
+/// This is opaque to foreign code, which only stores and returns it.
+#[derive(Clone, uniffi::Object)]
+pub struct CachedIssuerMetadata {
+    pub issuer_metadata: ICredentialIssuerMetadata,
+    pub authorization_metadata: AuthorizationServerMetadata,
+}
+
+/// How issuer metadata was discovered. The authorization server metadata depends on it, so
+/// metadata discovered one way must not be reused for the other.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, uniffi::Enum)]
+pub enum MetadataDiscovery {
+    /// Discovered from the issuer URL, as in `oid4vci_initiate`.
+    Issuer,
+    /// Discovered for the pre-authorized code grant of a credential offer, using the
+    /// authorization server named by the grant, if any.
+    CredentialOffer {
+        authorization_server: Option<String>,
+    },
+}
+
+/// A store for discovered issuer metadata, keyed by issuer URL and how the metadata was
+/// discovered.
+///
+/// Implementations decide how long entries remain valid; `get` must not return stale entries.
+#[uniffi::export(with_foreign)]
+pub trait IssuerMetadataCache: Send + Sync {
+    fn get(
+        &self,
+        issuer: String,
+        discovery: MetadataDiscovery,
+    ) -> Option<Arc<CachedIssuerMetadata>>;
+
+    fn insert(
+        &self,
+        issuer: String,
+        discovery: MetadataDiscovery,
+        metadata: Arc<CachedIssuerMetadata>,
+    );
+}
+
+/// In-memory [IssuerMetadataCache] that expires entries after a fixed TTL.
+#[derive(uniffi::Object)]
+pub struct InMemoryIssuerMetadataCache(
+    TtlMap<(String, MetadataDiscovery), Arc<CachedIssuerMetadata>>,
+);
+
+#[uniffi::export]
+impl InMemoryIssuerMetadataCache {
+    #[uniffi::constructor]
+    pub fn new(ttl_seconds: u64) -> Arc<Self> {
+        Arc::new(Self(TtlMap::new(Duration::from_secs(ttl_seconds))))
+    }
+
+    /// Remove all cached metadata.
+    pub fn clear(&self) {
+        self.0.clear()
+    }
+}
+
+impl Default for InMemoryIssuerMetadataCache {
+    fn default() -> Self {
+        Self(TtlMap::new(DEFAULT_TTL))
+    }
+}
+
+impl IssuerMetadataCache for InMemoryIssuerMetadataCache {
+    fn get(
+        &self,
+        issuer: String,
+        discovery: MetadataDiscovery,
+    ) -> Option<Arc<CachedIssuerMetadata>> {
+        self.0.get(&(issuer, discovery))
+    }
+
+    fn insert(
+        &self,
+        issuer: String,
+        discovery: MetadataDiscovery,
+        metadata: Arc<CachedIssuerMetadata>,
+    ) {
+        self.0.insert((issuer, discovery), metadata)
+    }
+}
+
+/// A map whose entries expire after a fixed TTL.
+struct TtlMap<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlMap<K, V> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        // A poisoned lock only means another thread panicked mid-insert; treat it as a miss.
+        let mut entries = self.entries.lock().ok()?;
+        match entries.get(key) {
+            Some((inserted_at, value)) if inserted_at.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: K, value: V) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, (Instant::now(), value));
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ttl_map_returns_fresh_entries() {
+        let map = TtlMap::new(Duration::from_secs(60));
+        map.insert("https://issuer.example", 1);
+
+        assert_eq!(map.get(&"https://issuer.example"), Some(1));
+        assert_eq!(map.get(&"https://other.example"), None);
+
+        map.clear();
+        assert_eq!(map.get(&"https://issuer.example"), None);
+    }
+
+    #[test]
+    fn ttl_map_keys_on_discovery() {
+        let map = TtlMap::new(Duration::from_secs(60));
+        let issuer = "https://issuer.example".to_string();
+        map.insert((issuer.clone(), MetadataDiscovery::Issuer), 1);
+
+        assert_eq!(
+            map.get(&(issuer.clone(), MetadataDiscovery::Issuer)),
+            Some(1)
+        );
+        assert_eq!(
+            map.get(&(
+                issuer,
+                MetadataDiscovery::CredentialOffer {
+                    authorization_server: None
+                }
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn ttl_map_expires_entries() {
+        let map = TtlMap::new(Duration::ZERO);
+        map.insert("https://issuer.example", 1);
+
+        assert_eq!(map.get(&"https://issuer.example"), None);
+        assert!(map.entries.lock().unwrap().is_empty());
+    }
+}
@@ -4,9 +4,17 @@ use either::Either;
 use oid4vci::{
     client,
     credential::ResponseEnum,
-    credential_offer::CredentialOffer,
-    metadata::{authorization_server::GrantType, AuthorizationServerMetadata, MetadataDiscovery},
-    oauth2::{ClientId, RedirectUrl, TokenResponse as ITokenResponse},
+    credential_offer::{CredentialOffer, CredentialOfferParameters},
+    metadata::{
+        authorization_server::GrantType, AuthorizationServerMetadata, MetadataDiscovery as _,
+    },
+    oauth2::{
+        http::{
+            header::{ACCEPT, CONTENT_TYPE},
+            Method, Request,
+        },
+        ClientId, RedirectUrl, RefreshToken, TokenResponse as ITokenResponse,
+    },
     profiles::{
         core::{
             self,
@@ -17,22 +25,23 @@ use oid4vci::{
         CredentialResponseType, ProfilesCredentialRequest, ProfilesCredentialRequestWithFormat,
     },
     proof_of_possession::Proof,
+    token,
     types::{CredentialOfferRequest, IssuerUrl, PreAuthorizedCode},
 };
-use ssi::{
-    claims::{
-        jwt::ToDecodedJwt, vc::v1::data_integrity::any_credential_from_json_str,
-        VerificationParameters,
-    },
-    dids::{AnyDidMethod, DIDResolver},
+use ssi::claims::{
+    jwt::ToDecodedJwt, vc::v1::data_integrity::any_credential_from_json_str, VerificationParameters,
 };
 use url::Url;
 
-pub use context_loader::context_loader_from_map;
+pub use context_loader::{context_loader_from_map, offline_context_loader};
 pub use error::*;
 pub use http_client::*;
 pub use metadata::*;
+pub use metadata_cache::*;
+pub use offer_preview::*;
+pub use offered_credential::*;
 pub use options::*;
+pub use proof_type::*;
 pub use session::*;
 pub use wrapper::*;
 
@@ -42,7 +51,11 @@ mod context_loader;
 mod error;
 mod http_client;
 mod metadata;
+mod metadata_cache;
+mod offer_preview;
+mod offered_credential;
 mod options;
+mod proof_type;
 mod session;
 mod wrapper;
 
@@ -52,11 +65,35 @@ pub async fn oid4vci_initiate_with_offer(
     client_id: String,
     redirect_url: String,
     http_client: Arc<IHttpClient>,
+) -> Result<Oid4vciSession, Oid4vciError> {
+    initiate_with_offer(credential_offer, client_id, redirect_url, http_client, None).await
+}
+
+/// Initiate a session from a credential offer, reusing issuer metadata from `metadata_cache`
+/// when available.
+pub(crate) async fn initiate_with_offer(
+    credential_offer: String,
+    client_id: String,
+    redirect_url: String,
+    http_client: Arc<IHttpClient>,
+    metadata_cache: Option<&dyn IssuerMetadataCache>,
 ) -> Result<Oid4vciSession, Oid4vciError> {
     let credential_offer = Url::parse(&credential_offer).map_err(|_| {
         Oid4vciError::InvalidParameter("invalid credential_offer: failed to parse url".into())
     })?;
 
+    let credential_offer_uri = credential_offer
+        .query_pairs()
+        .find(|(key, _)| key == "credential_offer_uri")
+        .map(|(_, value)| value.into_owned());
+
+    let credential_offer = match credential_offer_uri {
+        Some(credential_offer_uri) => {
+            dereference_credential_offer_uri(&credential_offer_uri, &http_client).await?
+        }
+        None => credential_offer,
+    };
+
     let credential_offer = CredentialOffer::from_request(
         CredentialOfferRequest::from_url_checked(credential_offer).map_err(|_| {
             Oid4vciError::InvalidParameter("invalid credential_offer: failed to parse offer".into())
@@ -74,59 +111,31 @@ pub async fn oid4vci_initiate_with_offer(
         Oid4vciError::InvalidParameter("invalid credential_offer: failed to resolve offer".into())
     })?;
 
-    let base_url = credential_offer.issuer();
-
-    let issuer_metadata = match &http_client.0 {
-        Either::Left(sync_client) => ICredentialIssuerMetadata::discover(base_url, sync_client),
-        Either::Right(async_client) => {
-            ICredentialIssuerMetadata::discover_async(base_url, async_client).await
-        }
-    }
-    .map_err(|e| Oid4vciError::RequestError(e.to_string()))?;
-
     let grants = credential_offer.grants().map(|g| g.to_owned());
 
-    let authorization_metadata =
-        if let Some(grant) = credential_offer.pre_authorized_code_grant() {
-            // TODO: maybe offer a way for the wallet to pick grant ordering
-            // when multiple options are present
-
-            let authorization_server = grant.authorization_server();
-
-            match &http_client.0 {
-                Either::Left(sync_client) => {
-                    AuthorizationServerMetadata::discover_from_credential_issuer_metadata(
-                        sync_client,
-                        &issuer_metadata,
-                        Some(&GrantType::PreAuthorizedCode),
-                        authorization_server,
-                    )
-                }
-                Either::Right(async_client) => {
-                    AuthorizationServerMetadata::discover_from_credential_issuer_metadata_async(
-                        async_client,
-                        &issuer_metadata,
-                        Some(&GrantType::PreAuthorizedCode),
-                        authorization_server,
-                    )
-                    .await
-                }
+    let issuer = credential_offer.issuer().url().as_str().to_owned();
+    let discovery = MetadataDiscovery::CredentialOffer {
+        authorization_server: credential_offer
+            .pre_authorized_code_grant()
+            .and_then(|grant| grant.authorization_server())
+            .map(|authorization_server| authorization_server.to_string()),
+    };
+    let CachedIssuerMetadata {
+        issuer_metadata,
+        authorization_metadata,
+    } = match metadata_cache.and_then(|cache| cache.get(issuer.clone(), discovery.clone())) {
+        Some(cached) => cached.as_ref().clone(),
+        None => {
+            let discovered = discover_offer_metadata(&credential_offer, &http_client).await?;
+            if let Some(cache) = metadata_cache {
+                cache.insert(issuer, discovery, Arc::new(discovered.clone()));
             }
-        } else {
-            // TODO: if grants isn't present in the credential offer
-            // we must determine the grant type by using the metadata.
-            // Potentially defer to caller using a ForeignTrait after
-            // obtaining `grant_types_supported` from authorization server
-            // metadata. Future solution must keep in mind that the
-            // `authorization_servers` field is an array, so multiple
-            // grant options from different servers may be available.
-            todo!("determine grant type with metadata")
+            discovered
         }
-        .map_err(|_| {
-            Oid4vciError::RequestError("failed to discover authorization server metadata".into())
-        })?;
+    };
 
-    let credential_requests: Vec<ProfilesCredentialRequest> = issuer_metadata
+    // The configurations supported by the issuer that are offered, in the issuer's order.
+    let offered_configurations: Vec<_> = issuer_metadata
         .credential_configurations_supported()
         .iter()
         .filter(|config| {
@@ -134,6 +143,23 @@ pub async fn oid4vci_initiate_with_offer(
                 .credential_configuration_ids()
                 .contains(config.id())
         })
+        .collect();
+
+    let offered_credentials: Vec<OfferedCredential> = offered_configurations
+        .iter()
+        .copied()
+        .map(|config| OfferedCredential::new(config.id().to_string(), config))
+        .collect();
+
+    let proof_types_supported: Vec<Vec<ProofType>> = offered_configurations
+        .iter()
+        .copied()
+        .map(ProofType::supported_by)
+        .collect();
+
+    let credential_requests: Vec<ProfilesCredentialRequest> = offered_configurations
+        .iter()
+        .copied()
         .map(|config| match config.profile_specific_fields() {
             oid4vci::profiles::ProfilesCredentialConfiguration::Core(
                 core_profiles_credential_configuration,
@@ -202,36 +228,211 @@ pub async fn oid4vci_initiate_with_offer(
 
     log::trace!("Credential requests: {:#?}", credential_requests);
 
+    let token_endpoint = authorization_metadata
+        .token_endpoint()
+        .map(|url| url.url().to_string());
+
     let client = client::Client::from_issuer_metadata(
-        ClientId::new(client_id),
+        ClientId::new(client_id.clone()),
         RedirectUrl::new(redirect_url).unwrap(),
         issuer_metadata.clone(),
         authorization_metadata,
     );
 
     let mut session = Oid4vciSession::new(client.into());
+    session.set_client_id(client_id);
     session.set_metadata(issuer_metadata.into());
+    session.set_token_endpoint(token_endpoint);
+    session.set_offered_credentials(offered_credentials);
     session.set_credential_requests(credential_requests)?;
+    session.set_proof_types_supported(proof_types_supported)?;
     session.set_grants(grants)?;
 
     Ok(session)
 }
 
+/// Discover the issuer and authorization server metadata for a resolved credential offer.
+async fn discover_offer_metadata(
+    credential_offer: &CredentialOfferParameters,
+    http_client: &IHttpClient,
+) -> Result<CachedIssuerMetadata, Oid4vciError> {
+    let base_url = credential_offer.issuer();
+
+    let issuer_metadata = match &http_client.0 {
+        Either::Left(sync_client) => ICredentialIssuerMetadata::discover(base_url, sync_client),
+        Either::Right(async_client) => {
+            ICredentialIssuerMetadata::discover_async(base_url, async_client).await
+        }
+    }
+    .map_err(|e| Oid4vciError::RequestError(e.to_string()))?;
+
+    let authorization_metadata =
+        if let Some(grant) = credential_offer.pre_authorized_code_grant() {
+            // TODO: maybe offer a way for the wallet to pick grant ordering
+            // when multiple options are present
+
+            let authorization_server = grant.authorization_server();
+
+            match &http_client.0 {
+                Either::Left(sync_client) => {
+                    AuthorizationServerMetadata::discover_from_credential_issuer_metadata(
+                        sync_client,
+                        &issuer_metadata,
+                        Some(&GrantType::PreAuthorizedCode),
+                        authorization_server,
+                    )
+                }
+                Either::Right(async_client) => {
+                    AuthorizationServerMetadata::discover_from_credential_issuer_metadata_async(
+                        async_client,
+                        &issuer_metadata,
+                        Some(&GrantType::PreAuthorizedCode),
+                        authorization_server,
+                    )
+                    .await
+                }
+            }
+        } else {
+            // TODO: if grants isn't present in the credential offer
+            // we must determine the grant type by using the metadata.
+            // Potentially defer to caller using a ForeignTrait after
+            // obtaining `grant_types_supported` from authorization server
+            // metadata. Future solution must keep in mind that the
+            // `authorization_servers` field is an array, so multiple
+            // grant options from different servers may be available.
+            todo!("determine grant type with metadata")
+        }
+        .map_err(|_| {
+            Oid4vciError::RequestError("failed to discover authorization server metadata".into())
+        })?;
+
+    Ok(CachedIssuerMetadata {
+        issuer_metadata,
+        authorization_metadata,
+    })
+}
+
+/// Fetch a by-reference credential offer (`credential_offer_uri`) and return the equivalent
+/// by-value credential offer URL.
+async fn dereference_credential_offer_uri(
+    credential_offer_uri: &str,
+    http_client: &IHttpClient,
+) -> Result<Url, Oid4vciError> {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(credential_offer_uri)
+        .header(ACCEPT, "application/json")
+        .body(Vec::new())
+        .map_err(|_| {
+            Oid4vciError::InvalidParameter(
+                "invalid credential_offer_uri: failed to build request".into(),
+            )
+        })?;
+
+    let response = http_client.call(request).await.map_err(|e| {
+        Oid4vciError::RequestError(format!("failed to fetch credential_offer_uri: {e}"))
+    })?;
+
+    if !response.status().is_success() {
+        return Err(Oid4vciError::RequestError(format!(
+            "failed to fetch credential_offer_uri: status {}",
+            response.status()
+        )));
+    }
+
+    let offer: serde_json::Value = serde_json::from_slice(response.body()).map_err(|_| {
+        Oid4vciError::InvalidParameter(
+            "invalid credential_offer_uri: response is not a JSON credential offer".into(),
+        )
+    })?;
+
+    if !offer.is_object() || offer.get("credential_issuer").is_none() {
+        return Err(Oid4vciError::InvalidParameter(
+            "invalid credential_offer_uri: credential offer is missing credential_issuer".into(),
+        ));
+    }
+
+    let mut credential_offer = Url::parse("openid-credential-offer://")
+        // Unwrap safety: the URL is a constant and is known to be valid.
+        .unwrap();
+    credential_offer
+        .query_pairs_mut()
+        .append_pair("credential_offer", &offer.to_string());
+
+    Ok(credential_offer)
+}
+
 #[uniffi::export]
 pub async fn oid4vci_initiate(
     base_url: String,
     client_id: String,
     redirect_url: String,
     http_client: Arc<IHttpClient>,
+) -> Result<Oid4vciSession, Oid4vciError> {
+    initiate(base_url, client_id, redirect_url, http_client, None).await
+}
+
+/// Initiate a session with an issuer, reusing issuer metadata from `metadata_cache` when
+/// available.
+pub(crate) async fn initiate(
+    base_url: String,
+    client_id: String,
+    redirect_url: String,
+    http_client: Arc<IHttpClient>,
+    metadata_cache: Option<&dyn IssuerMetadataCache>,
 ) -> Result<Oid4vciSession, Oid4vciError> {
     let base_url = IssuerUrl::new(base_url)
         .map_err(|e| e.to_string())
         .map_err(Oid4vciError::from)?;
 
+    let issuer = base_url.url().as_str().to_owned();
+    let CachedIssuerMetadata {
+        issuer_metadata,
+        authorization_metadata,
+    } = match metadata_cache.and_then(|cache| cache.get(issuer.clone(), MetadataDiscovery::Issuer))
+    {
+        Some(cached) => cached.as_ref().clone(),
+        None => {
+            let discovered = discover_metadata(&base_url, &http_client).await?;
+            if let Some(cache) = metadata_cache {
+                cache.insert(
+                    issuer,
+                    MetadataDiscovery::Issuer,
+                    Arc::new(discovered.clone()),
+                );
+            }
+            discovered
+        }
+    };
+
+    let token_endpoint = authorization_metadata
+        .token_endpoint()
+        .map(|url| url.url().to_string());
+
+    let client = client::Client::from_issuer_metadata(
+        ClientId::new(client_id.clone()),
+        RedirectUrl::new(redirect_url).unwrap(),
+        issuer_metadata.clone(),
+        authorization_metadata,
+    );
+
+    let mut session = Oid4vciSession::new(client.into());
+    session.set_client_id(client_id);
+    session.set_metadata(issuer_metadata.into());
+    session.set_token_endpoint(token_endpoint);
+
+    Ok(session)
+}
+
+/// Discover the issuer and authorization server metadata for an issuer URL.
+async fn discover_metadata(
+    base_url: &IssuerUrl,
+    http_client: &IHttpClient,
+) -> Result<CachedIssuerMetadata, Oid4vciError> {
     let issuer_metadata = match &http_client.0 {
-        Either::Left(sync_client) => ICredentialIssuerMetadata::discover(&base_url, sync_client),
+        Either::Left(sync_client) => ICredentialIssuerMetadata::discover(base_url, sync_client),
         Either::Right(async_client) => {
-            ICredentialIssuerMetadata::discover_async(&base_url, async_client).await
+            ICredentialIssuerMetadata::discover_async(base_url, async_client).await
         }
     }
     .map_err(|_| {
@@ -239,26 +440,19 @@ pub async fn oid4vci_initiate(
     })?;
 
     let authorization_metadata = match &http_client.0 {
-        Either::Left(sync_client) => AuthorizationServerMetadata::discover(&base_url, sync_client),
+        Either::Left(sync_client) => AuthorizationServerMetadata::discover(base_url, sync_client),
         Either::Right(async_client) => {
-            AuthorizationServerMetadata::discover_async(&base_url, async_client).await
+            AuthorizationServerMetadata::discover_async(base_url, async_client).await
         }
     }
     .map_err(|_| {
         Oid4vciError::RequestError("failed to discover authorization server metadata".into())
     })?;
 
-    let client = client::Client::from_issuer_metadata(
-        ClientId::new(client_id),
-        RedirectUrl::new(redirect_url).unwrap(),
-        issuer_metadata.clone(),
+    Ok(CachedIssuerMetadata {
+        issuer_metadata,
         authorization_metadata,
-    );
-
-    let mut session = Oid4vciSession::new(client.into());
-    session.set_metadata(issuer_metadata.into());
-
-    Ok(session)
+    })
 }
 
 #[uniffi::export]
@@ -290,7 +484,67 @@ pub async fn oid4vci_exchange_token(
                 .await
         }
     }
-    .map_err(|_| Oid4vciError::RequestError("failed to exchange code".into()))?;
+    .map_err(Oid4vciError::from)?;
+
+    let nonce = token_response
+        .extra_fields()
+        .c_nonce
+        .clone()
+        .map(|v| v.secret().to_owned());
+
+    session.set_token_response(token_response.into())?;
+
+    Ok(nonce)
+}
+
+/// Obtain a new access token for the session with the OAuth 2.0 refresh token grant.
+///
+/// The session's token response is replaced, keeping `refresh_token` and the session's
+/// `c_nonce` when the issuer does not provide new ones. The session's `client_id` is sent
+/// along, as required for public clients. Returns the new `c_nonce`, if the issuer provided
+/// one.
+#[uniffi::export]
+pub async fn oid4vci_refresh_token(
+    session: Arc<Oid4vciSession>,
+    refresh_token: String,
+    http_client: Arc<IHttpClient>,
+) -> Result<Option<String>, Oid4vciError> {
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("grant_type", "refresh_token")
+        .append_pair("refresh_token", &refresh_token)
+        .append_pair("client_id", session.get_client_id()?)
+        .finish();
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(session.get_token_endpoint()?)
+        .header(ACCEPT, "application/json")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(body.into_bytes())
+        .map_err(|_| Oid4vciError::RequestError("failed to build refresh request".into()))?;
+
+    let response = http_client
+        .call(request)
+        .await
+        .map_err(|e| Oid4vciError::RequestError(format!("failed to refresh token: {e}")))?;
+
+    if !response.status().is_success() {
+        return Err(
+            Oid4vciError::from_error_response(response.body()).unwrap_or_else(|| {
+                Oid4vciError::RequestError(format!(
+                    "failed to refresh token: status {}",
+                    response.status()
+                ))
+            }),
+        );
+    }
+
+    let mut token_response: token::Response = serde_json::from_slice(response.body())
+        .map_err(|e| Oid4vciError::SerdeJsonError(e.to_string()))?;
+
+    if token_response.refresh_token().is_none() {
+        token_response.set_refresh_token(Some(RefreshToken::new(refresh_token)));
+    }
 
     let nonce = token_response
         .extra_fields()
@@ -298,11 +552,105 @@ pub async fn oid4vci_exchange_token(
         .clone()
         .map(|v| v.secret().to_owned());
 
+    let previous_nonce = session.get_c_nonce()?;
     session.set_token_response(token_response.into())?;
+    session.set_c_nonce(nonce.clone().or(previous_nonce))?;
 
     Ok(nonce)
 }
 
+/// Re-issue the session's credentials with a fresh access token obtained from `refresh_token`.
+///
+/// `proofs_of_possession` are built as for [oid4vci_exchange_credential], bound to the
+/// session's current `c_nonce`. If the issuer provides a new `c_nonce` with the refreshed
+/// access token, the proofs are stale and this fails with [Oid4vciError::InvalidProof]:
+/// new proofs bound to that nonce must be passed to [oid4vci_exchange_credential].
+#[uniffi::export]
+pub async fn oid4vci_refresh_credential(
+    session: Arc<Oid4vciSession>,
+    refresh_token: String,
+    proofs_of_possession: Vec<String>,
+    options: Oid4vciExchangeOptions,
+    context_map: Option<HashMap<String, String>>,
+    http_client: Arc<IHttpClient>,
+) -> Result<Vec<CredentialResponse>, Oid4vciError> {
+    let previous_nonce = session.get_c_nonce()?;
+    let nonce = oid4vci_refresh_token(session.clone(), refresh_token, http_client.clone()).await?;
+
+    if let Some(c_nonce) = nonce.filter(|nonce| previous_nonce.as_ref() != Some(nonce)) {
+        return Err(Oid4vciError::InvalidProof {
+            c_nonce,
+            description: Some("a new c_nonce was issued with the refreshed access token".into()),
+        });
+    }
+
+    oid4vci_exchange_credential(
+        session,
+        proofs_of_possession,
+        options,
+        context_map,
+        http_client,
+    )
+    .await
+}
+
+/// Return the session's credential requests and their supported proof types, narrowed to the
+/// `selected` credential configuration ids when given.
+fn selected_credential_requests(
+    session: &Oid4vciSession,
+    selected: Option<&[String]>,
+) -> Result<(Vec<ProfilesCredentialRequest>, Vec<Vec<ProofType>>), Oid4vciError> {
+    let credential_requests = session.get_credential_requests()?;
+    let proof_types_supported = session.get_proof_types_supported()?;
+
+    let Some(selected) = selected else {
+        return Ok((credential_requests, proof_types_supported));
+    };
+
+    Ok(select_offered(
+        &session.offered_credentials(),
+        credential_requests.into_iter().zip(proof_types_supported),
+        selected,
+    )?
+    .into_iter()
+    .unzip())
+}
+
+/// Keep the `items` belonging to the `selected` offered credentials, where `items` are in the
+/// same order as `offered`.
+fn select_offered<T>(
+    offered: &[OfferedCredential],
+    items: impl IntoIterator<Item = T>,
+    selected: &[String],
+) -> Result<Vec<T>, Oid4vciError> {
+    if selected.is_empty() {
+        return Err(Oid4vciError::InvalidParameter(
+            "no credential configurations selected".into(),
+        ));
+    }
+
+    if let Some(id) = selected
+        .iter()
+        .find(|id| !offered.iter().any(|o| &&o.configuration_id == id))
+    {
+        return Err(Oid4vciError::InvalidParameter(format!(
+            "credential configuration '{id}' was not offered"
+        )));
+    }
+
+    Ok(offered
+        .iter()
+        .zip(items)
+        .filter(|(offered, _)| selected.contains(&offered.configuration_id))
+        .map(|(_, item)| item)
+        .collect())
+}
+
+/// Request the session's credentials, with one proof of possession per credential request.
+///
+/// If the issuer rejects the proofs and provides a fresh `c_nonce`, this fails with
+/// [Oid4vciError::InvalidProof] and the nonce is available from [Oid4vciSession::c_nonce]:
+/// new proofs bound to it must be generated before calling this again.
 #[uniffi::export]
 pub async fn oid4vci_exchange_credential(
     session: Arc<Oid4vciSession>,
@@ -314,7 +662,8 @@ pub async fn oid4vci_exchange_credential(
     log::trace!("oid4vci_exchange_credential");
 
     log::trace!("session.get_credential_requests");
-    let credential_requests = session.get_credential_requests()?.clone();
+    let (credential_requests, proof_types_supported) =
+        selected_credential_requests(&session, options.selected_configuration_ids.as_deref())?;
 
     log::trace!("credential_requests.is_empty");
     if credential_requests.is_empty() {
@@ -330,6 +679,34 @@ pub async fn oid4vci_exchange_credential(
         ));
     }
 
+    // Only JWT proofs are currently produced by `generate_pop_prepare`/`generate_pop_complete`,
+    // so fail early instead of sending a proof the issuer is going to reject.
+    log::trace!("check proof types supported by the issuer");
+    if let Some(proof_types) = proof_types_supported
+        .into_iter()
+        .find(|proof_types| !proof_types.is_empty() && !proof_types.contains(&ProofType::Jwt))
+    {
+        return Err(Oid4vciError::UnsupportedProofType(
+            proof_types.iter().map(ToString::to_string).collect(),
+        ));
+    }
+
+    // Fail early instead of making a request the issuer is going to reject.
+    log::trace!("check access token expiry");
+    session.ensure_token_valid()?;
+
+    // Negotiate the content of the credential response with the issuer.
+    let formats = credential_requests
+        .iter()
+        .filter_map(|request| {
+            serde_json::to_value(request)
+                .ok()?
+                .get("format")?
+                .as_str()
+                .map(ToOwned::to_owned)
+        })
+        .collect::<Vec<_>>();
+
     let credential_responses = if credential_requests.len() == 1 {
         log::trace!("processing single request");
 
@@ -346,9 +723,16 @@ pub async fn oid4vci_exchange_credential(
 
         log::trace!("execute with http client");
         let response = match &http_client.0 {
-            Either::Left(sync_client) => request.request(sync_client),
-            Either::Right(async_client) => request.request_async(async_client).await,
-        }?;
+            Either::Left(sync_client) => {
+                request.request(&ContentNegotiation::new(sync_client, &formats))
+            }
+            Either::Right(async_client) => {
+                request
+                    .request_async(&ContentNegotiation::new(async_client, &formats))
+                    .await
+            }
+        }
+        .map_err(|e| session.track_c_nonce(e.into()))?;
 
         log::trace!("match response kind");
         match response.response_kind() {
@@ -375,9 +759,16 @@ pub async fn oid4vci_exchange_credential(
 
         log::trace!("execute with http client");
         let response = match &http_client.0 {
-            Either::Left(sync_client) => request.request(sync_client)?,
-            Either::Right(async_client) => request.request_async(async_client).await?,
-        };
+            Either::Left(sync_client) => {
+                request.request(&ContentNegotiation::new(sync_client, &formats))
+            }
+            Either::Right(async_client) => {
+                request
+                    .request_async(&ContentNegotiation::new(async_client, &formats))
+                    .await
+            }
+        }
+        .map_err(|e| session.track_c_nonce(e.into()))?;
 
         log::trace!("map match response kind");
         response
@@ -444,11 +835,19 @@ pub async fn oid4vci_exchange_credential(
         .await
     } else {
         log::trace!("create vm_resolver");
-        let vm_resolver = AnyDidMethod::default().into_vm_resolver();
+        let vm_resolver = options
+            .did_resolution_policy
+            .clone()
+            .unwrap_or_default()
+            .vm_resolver();
         log::trace!("create verification params");
         let params = match context_map {
             Some(context_map) => VerificationParameters::from_resolver(vm_resolver)
                 .with_json_ld_loader(context_loader_from_map(context_map)?),
+            None if options.offline_contexts.unwrap_or(false) => {
+                VerificationParameters::from_resolver(vm_resolver)
+                    .with_json_ld_loader(offline_context_loader(None)?)
+            }
             None => VerificationParameters::from_resolver(vm_resolver),
         };
 
@@ -517,3 +916,246 @@ pub async fn oid4vci_exchange_credential(
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StaticHttpClient(u16, &'static str);
+
+    impl SyncHttpClient for StaticHttpClient {
+        fn http_client(&self, _request: HttpRequest) -> Result<HttpResponse, HttpClientError> {
+            Ok(HttpResponse {
+                status_code: self.0,
+                headers: HashMap::new(),
+                body: self.1.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    fn client(status_code: u16, body: &'static str) -> IHttpClient {
+        let client: Arc<dyn SyncHttpClient> = Arc::new(StaticHttpClient(status_code, body));
+        client.into()
+    }
+
+    #[test]
+    fn selects_offered_credentials() {
+        let offered: Vec<OfferedCredential> = ["mDL", "PhotoID", "Degree"]
+            .into_iter()
+            .map(|id| OfferedCredential::new(id.into(), &serde_json::json!({ "format": "ldp_vc" })))
+            .collect();
+
+        assert_eq!(
+            select_offered(&offered, [1, 2, 3], &["Degree".into(), "mDL".into()]).unwrap(),
+            vec![1, 3]
+        );
+        assert!(matches!(
+            select_offered(&offered, [1, 2, 3], &["Passport".into()]),
+            Err(Oid4vciError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            select_offered(&offered, [1, 2, 3], &[]),
+            Err(Oid4vciError::InvalidParameter(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn credential_offer_uri_is_dereferenced() {
+        let offer = r#"{"credential_issuer":"https://issuer.example.com","credential_configuration_ids":["UniversityDegree"]}"#;
+
+        let url = dereference_credential_offer_uri(
+            "https://issuer.example.com/offer/123",
+            &client(200, offer),
+        )
+        .await
+        .unwrap();
+
+        let (_, value) = url
+            .query_pairs()
+            .find(|(key, _)| key == "credential_offer")
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&value).unwrap(),
+            serde_json::from_str::<serde_json::Value>(offer).unwrap()
+        );
+    }
+
+    #[test]
+    fn parses_issuer_error_responses() {
+        let error = Oid4vciError::from_error_response(
+            br#"{"error":"invalid_proof","error_description":"the proof has expired"}"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            &error,
+            Oid4vciError::Issuer { code, description }
+                if code == "invalid_proof"
+                    && description.as_deref() == Some("the proof has expired")
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Issuer error invalid_proof: the proof has expired"
+        );
+        assert!(matches!(
+            Oid4vciError::from_error_response(
+                br#"{"error":"invalid_proof","c_nonce":"tZignsnFbp","c_nonce_expires_in":86400}"#,
+            ),
+            Some(Oid4vciError::InvalidProof { c_nonce, description: None }) if c_nonce == "tZignsnFbp"
+        ));
+        assert!(Oid4vciError::from_error_response(b"Internal Server Error").is_none());
+        assert!(Oid4vciError::from_error_response(br#"{"credential":"..."}"#).is_none());
+    }
+
+    /// Serves issuer and authorization server metadata, and records the token requests.
+    #[derive(Default)]
+    struct IssuerHttpClient {
+        token_requests: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl SyncHttpClient for IssuerHttpClient {
+        fn http_client(&self, request: HttpRequest) -> Result<HttpResponse, HttpClientError> {
+            let body = match request.url.as_str() {
+                url if url.ends_with("/.well-known/openid-credential-issuer") => {
+                    serde_json::json!({
+                        "credential_issuer": "https://issuer.example.com",
+                        "credential_endpoint": "https://issuer.example.com/credential",
+                        "credential_configurations_supported": {}
+                    })
+                }
+                url if url.contains("/.well-known/") => serde_json::json!({
+                    "issuer": "https://issuer.example.com",
+                    "authorization_endpoint": "https://issuer.example.com/authorize",
+                    "token_endpoint": "https://issuer.example.com/token",
+                    "response_types_supported": ["code"]
+                }),
+                "https://issuer.example.com/token" => {
+                    self.token_requests
+                        .lock()
+                        .unwrap()
+                        .push(String::from_utf8(request.body).unwrap());
+                    serde_json::json!({
+                        "access_token": "new-access-token",
+                        "token_type": "bearer",
+                        "expires_in": 3600,
+                        "c_nonce": "new-nonce"
+                    })
+                }
+                _ => {
+                    return Ok(HttpResponse {
+                        status_code: 404,
+                        headers: HashMap::new(),
+                        body: Vec::new(),
+                    })
+                }
+            };
+
+            Ok(HttpResponse {
+                status_code: 200,
+                headers: HashMap::from([("content-type".into(), "application/json".into())]),
+                body: serde_json::to_vec(&body).unwrap(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_token_grant() {
+        let issuer = Arc::new(IssuerHttpClient::default());
+        let client: Arc<dyn SyncHttpClient> = issuer.clone();
+        let http_client = Arc::new(IHttpClient::from(client));
+
+        let session = Arc::new(
+            oid4vci_initiate(
+                "https://issuer.example.com".into(),
+                "wallet-client".into(),
+                "https://wallet.example.com/callback".into(),
+                http_client.clone(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        let nonce = oid4vci_refresh_token(session.clone(), "refresh-token".into(), http_client)
+            .await
+            .unwrap();
+        assert_eq!(nonce.as_deref(), Some("new-nonce"));
+
+        let token_requests = issuer.token_requests.lock().unwrap();
+        let form: HashMap<String, String> =
+            url::form_urlencoded::parse(token_requests[0].as_bytes())
+                .into_owned()
+                .collect();
+        assert_eq!(
+            form,
+            HashMap::from([
+                ("grant_type".into(), "refresh_token".into()),
+                ("refresh_token".into(), "refresh-token".into()),
+                ("client_id".into(), "wallet-client".into()),
+            ])
+        );
+
+        let token_response = session.get_token_response().unwrap();
+        assert_eq!(token_response.access_token().secret(), "new-access-token");
+        // The issuer did not rotate the refresh token, so the one used is kept.
+        assert_eq!(
+            token_response
+                .refresh_token()
+                .map(|token| token.secret().as_str()),
+            Some("refresh-token")
+        );
+        assert!(session.is_token_valid());
+        assert_eq!(session.get_c_nonce().unwrap().as_deref(), Some("new-nonce"));
+    }
+
+    #[tokio::test]
+    async fn refresh_credential_rejects_proofs_bound_to_a_stale_nonce() {
+        let issuer = Arc::new(IssuerHttpClient::default());
+        let client: Arc<dyn SyncHttpClient> = issuer.clone();
+        let http_client = Arc::new(IHttpClient::from(client));
+
+        let session = Arc::new(
+            oid4vci_initiate(
+                "https://issuer.example.com".into(),
+                "wallet-client".into(),
+                "https://wallet.example.com/callback".into(),
+                http_client.clone(),
+            )
+            .await
+            .unwrap(),
+        );
+        session.set_c_nonce(Some("old-nonce".into())).unwrap();
+
+        let result = oid4vci_refresh_credential(
+            session.clone(),
+            "refresh-token".into(),
+            vec!["proof-bound-to-old-nonce".into()],
+            Oid4vciExchangeOptions::default(),
+            None,
+            http_client,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Oid4vciError::InvalidProof { ref c_nonce, .. }) if c_nonce == "new-nonce"
+        ));
+        assert_eq!(session.get_c_nonce().unwrap().as_deref(), Some("new-nonce"));
+    }
+
+    #[tokio::test]
+    async fn credential_offer_uri_errors() {
+        let uri = "https://issuer.example.com/offer/123";
+
+        assert!(matches!(
+            dereference_credential_offer_uri(uri, &client(404, "")).await,
+            Err(Oid4vciError::RequestError(_))
+        ));
+        assert!(matches!(
+            dereference_credential_offer_uri(uri, &client(200, "<html></html>")).await,
+            Err(Oid4vciError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            dereference_credential_offer_uri(uri, &client(200, "{}")).await,
+            Err(Oid4vciError::InvalidParameter(_))
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use oid4vci::{credential_offer::CredentialOffer, types::CredentialOfferRequest};
+use url::Url;
+
+use super::Oid4vciError;
+
+/// What a credential offer contains, as far as can be told without any network request.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct CredentialOfferPreview {
+    /// The URL of the credential issuer.
+    ///
+    /// `None` for a by-reference offer, whose content is only known once
+    /// `credential_offer_uri` is fetched.
+    pub credential_issuer: Option<String>,
+    /// The ids of the offered credential configurations, as listed in the issuer metadata.
+    pub credential_configuration_ids: Vec<String>,
+    /// The grant types the offer can be redeemed with, e.g.
+    /// `urn:ietf:params:oauth:grant-type:pre-authorized_code`.
+    pub grant_types: Vec<String>,
+    /// Where the offer is to be fetched from, for a by-reference offer.
+    pub credential_offer_uri: Option<String>,
+}
+
+/// Parse a credential offer URL, without resolving it or discovering the issuer metadata.
+///
+/// This lets a wallet check that a scanned offer is well-formed and show the issuer before
+/// making any network request with [super::oid4vci_initiate_with_offer].
+#[uniffi::export]
+pub fn oid4vci_parse_offer(
+    credential_offer: String,
+) -> Result<CredentialOfferPreview, Oid4vciError> {
+    let credential_offer = Url::parse(&credential_offer).map_err(|_| {
+        Oid4vciError::InvalidParameter("invalid credential_offer: failed to parse url".into())
+    })?;
+
+    if let Some((_, credential_offer_uri)) = credential_offer
+        .query_pairs()
+        .find(|(key, _)| key == "credential_offer_uri")
+    {
+        Url::parse(&credential_offer_uri).map_err(|_| {
+            Oid4vciError::InvalidParameter(
+                "invalid credential_offer_uri: failed to parse url".into(),
+            )
+        })?;
+
+        return Ok(CredentialOfferPreview {
+            credential_issuer: None,
+            credential_configuration_ids: Vec::new(),
+            grant_types: Vec::new(),
+            credential_offer_uri: Some(credential_offer_uri.into_owned()),
+        });
+    }
+
+    // Parse the offer the same way `oid4vci_initiate_with_offer` does.
+    let credential_offer = CredentialOffer::from_request(
+        CredentialOfferRequest::from_url_checked(credential_offer).map_err(|_| {
+            Oid4vciError::InvalidParameter("invalid credential_offer: failed to parse offer".into())
+        })?,
+    )
+    .map_err(|_| {
+        Oid4vciError::InvalidParameter("invalid credential_offer: failed to decode offer".into())
+    })?;
+
+    let CredentialOffer::Value { credential_offer } = credential_offer else {
+        return Err(Oid4vciError::InvalidParameter(
+            "invalid credential_offer: failed to decode offer".into(),
+        ));
+    };
+
+    let grant_types = [
+        credential_offer
+            .authorization_code_grant()
+            .map(|_| "authorization_code"),
+        credential_offer
+            .pre_authorized_code_grant()
+            .map(|_| "urn:ietf:params:oauth:grant-type:pre-authorized_code"),
+    ];
+
+    Ok(CredentialOfferPreview {
+        credential_issuer: Some(credential_offer.issuer().url().as_str().to_owned()),
+        credential_configuration_ids: credential_offer.credential_configuration_ids().clone(),
+        grant_types: grant_types.into_iter().flatten().map(Into::into).collect(),
+        credential_offer_uri: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_offers_offline() {
+        let offer = serde_json::json!({
+            "credential_issuer": "https://issuer.example.com/issuer",
+            "credential_configuration_ids": ["UniversityDegree"],
+            "grants": {
+                "urn:ietf:params:oauth:grant-type:pre-authorized_code": {
+                    "pre-authorized_code": "adhjhdjajkdkhjhdj"
+                }
+            }
+        });
+        let mut url = Url::parse("openid-credential-offer://").unwrap();
+        url.query_pairs_mut()
+            .append_pair("credential_offer", &offer.to_string());
+
+        assert_eq!(
+            oid4vci_parse_offer(url.to_string()).unwrap(),
+            CredentialOfferPreview {
+                credential_issuer: Some("https://issuer.example.com/issuer".into()),
+                credential_configuration_ids: vec!["UniversityDegree".into()],
+                grant_types: vec!["urn:ietf:params:oauth:grant-type:pre-authorized_code".into()],
+                credential_offer_uri: None,
+            }
+        );
+
+        let preview = oid4vci_parse_offer(
+            "openid-credential-offer://?credential_offer_uri=https%3A%2F%2Fissuer.example.com%2Foffer%2F123"
+                .into(),
+        )
+        .unwrap();
+        assert_eq!(preview.credential_issuer, None);
+        assert_eq!(
+            preview.credential_offer_uri.as_deref(),
+            Some("https://issuer.example.com/offer/123")
+        );
+
+        assert!(matches!(
+            oid4vci_parse_offer("not an offer".into()),
+            Err(Oid4vciError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            oid4vci_parse_offer("openid-credential-offer://?credential_offer=%7B%7D".into()),
+            Err(Oid4vciError::InvalidParameter(_))
+        ));
+    }
+}
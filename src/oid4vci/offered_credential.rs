@@ -0,0 +1,158 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::credential::CredentialFormat;
+
+/// A credential configuration included in a credential offer, described for display to the
+/// user before the credential is requested.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct OfferedCredential {
+    /// The credential configuration id, as listed in the issuer metadata.
+    pub configuration_id: String,
+    pub format: CredentialFormat,
+    /// The first display name from the issuer metadata, if any.
+    pub display_name: Option<String>,
+    /// Claims the issuer has described for the credential.
+    pub claims: Vec<OfferedClaim>,
+}
+
+/// A claim described by the issuer metadata for an offered credential.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct OfferedClaim {
+    /// The path to the claim, e.g. `["address", "locality"]`, or `[namespace, element]` for mdocs.
+    pub path: Vec<String>,
+    pub display_name: Option<String>,
+}
+
+impl OfferedCredential {
+    /// Describe a credential configuration from the issuer metadata.
+    pub(crate) fn new(configuration_id: String, configuration: &impl Serialize) -> Self {
+        let configuration = serde_json::to_value(configuration).unwrap_or_default();
+
+        let format = configuration
+            .get("format")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+            .into();
+
+        // Claims are top-level for SD-JWT and mdoc configurations, and part of the credential
+        // definition for VCDM configurations.
+        let mut claims = Vec::new();
+        if let Some(described) = configuration.get("claims").or_else(|| {
+            configuration
+                .get("credential_definition")?
+                .get("credentialSubject")
+        }) {
+            collect_claims(described, &mut vec![], &mut claims);
+        }
+
+        Self {
+            configuration_id,
+            format,
+            display_name: display_name(&configuration),
+            claims,
+        }
+    }
+}
+
+/// Returns the first `display[].name` of a metadata object.
+fn display_name(value: &Value) -> Option<String> {
+    value
+        .get("display")?
+        .as_array()?
+        .iter()
+        .find_map(|display| display.get("name")?.as_str())
+        .map(ToOwned::to_owned)
+}
+
+/// Whether a claims metadata node describes a single claim rather than nesting further claims.
+fn is_claim_description(value: &Value) -> bool {
+    match value.as_object() {
+        Some(object) => {
+            object.is_empty()
+                || ["mandatory", "value_type", "display"]
+                    .iter()
+                    .any(|key| object.contains_key(*key))
+        }
+        None => true,
+    }
+}
+
+fn collect_claims(value: &Value, path: &mut Vec<String>, claims: &mut Vec<OfferedClaim>) {
+    let Some(object) = value.as_object() else {
+        return;
+    };
+
+    for (name, description) in object {
+        path.push(name.clone());
+        if is_claim_description(description) {
+            claims.push(OfferedClaim {
+                path: path.clone(),
+                display_name: display_name(description),
+            });
+        } else {
+            collect_claims(description, path, claims);
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn describes_mdoc_configuration() {
+        let configuration = json!({
+            "format": "mso_mdoc",
+            "doctype": "org.iso.18013.5.1.mDL",
+            "display": [{ "name": "Mobile Driving License", "locale": "en-US" }],
+            "claims": {
+                "org.iso.18013.5.1": {
+                    "given_name": { "display": [{ "name": "Given Name" }] },
+                    "family_name": {}
+                }
+            }
+        });
+
+        let offered = OfferedCredential::new("mDL".into(), &configuration);
+
+        assert_eq!(offered.format, CredentialFormat::MsoMdoc);
+        assert_eq!(
+            offered.display_name.as_deref(),
+            Some("Mobile Driving License")
+        );
+        assert_eq!(offered.claims.len(), 2);
+        assert!(offered.claims.contains(&OfferedClaim {
+            path: vec!["org.iso.18013.5.1".into(), "given_name".into()],
+            display_name: Some("Given Name".into()),
+        }));
+        assert!(offered.claims.contains(&OfferedClaim {
+            path: vec!["org.iso.18013.5.1".into(), "family_name".into()],
+            display_name: None,
+        }));
+    }
+
+    #[test]
+    fn describes_ldp_vc_configuration() {
+        let configuration = json!({
+            "format": "ldp_vc",
+            "credential_definition": {
+                "type": ["VerifiableCredential", "UniversityDegreeCredential"],
+                "credentialSubject": {
+                    "degree": { "display": [{ "name": "Degree" }] }
+                }
+            }
+        });
+
+        let offered = OfferedCredential::new("UniversityDegree".into(), &configuration);
+
+        assert_eq!(offered.format, CredentialFormat::LdpVc);
+        assert_eq!(offered.display_name, None);
+        assert_eq!(offered.claims.len(), 1);
+        assert_eq!(offered.claims[0].path, vec!["degree".to_string()]);
+    }
+}
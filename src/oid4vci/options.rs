@@ -1,4 +1,17 @@
+use crate::did::DidResolutionPolicy;
+
 #[derive(uniffi::Record, Clone, Debug, Default)]
 pub struct Oid4vciExchangeOptions {
     pub verify_after_exchange: Option<bool>,
+    /// Only resolve JSON-LD contexts from the provided context map and the contexts bundled with
+    /// the SDK, failing verification if any other context is referenced instead of fetching it
+    /// over the network. Defaults to `false`.
+    pub offline_contexts: Option<bool>,
+    /// Request only the offered credential configurations with these ids, see
+    /// [Oid4vciSession::offered_credentials](super::Oid4vciSession::offered_credentials).
+    /// Defaults to requesting every offered credential.
+    pub selected_configuration_ids: Option<Vec<String>>,
+    /// Restrict the DIDs resolved when verifying the credentials, see
+    /// `verify_after_exchange`. Defaults to resolving any DID.
+    pub did_resolution_policy: Option<DidResolutionPolicy>,
 }
@@ -0,0 +1,72 @@
+use serde::Serialize;
+
+/// Proof of possession types defined by OID4VCI, as advertised by an issuer in a
+/// credential configuration's `proof_types_supported`.
+#[derive(uniffi::Enum, Clone, Debug, PartialEq, Eq)]
+pub enum ProofType {
+    Jwt,
+    Cwt,
+    LdpVp,
+    Other(String),
+}
+
+impl ProofType {
+    /// Returns the proof types advertised by a credential configuration, or an empty
+    /// list if the configuration does not restrict the proof types.
+    pub(crate) fn supported_by(configuration: &impl Serialize) -> Vec<Self> {
+        serde_json::to_value(configuration)
+            .ok()
+            .as_ref()
+            .and_then(|configuration| configuration.get("proof_types_supported"))
+            .and_then(|proof_types| proof_types.as_object())
+            .map(|proof_types| proof_types.keys().map(|k| k.as_str().into()).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl From<&str> for ProofType {
+    fn from(value: &str) -> Self {
+        match value {
+            "jwt" => Self::Jwt,
+            "cwt" => Self::Cwt,
+            "ldp_vp" => Self::LdpVp,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ProofType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Jwt => write!(f, "jwt"),
+            Self::Cwt => write!(f, "cwt"),
+            Self::LdpVp => write!(f, "ldp_vp"),
+            Self::Other(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn supported_proof_types_from_configuration() {
+        let configuration = json!({
+            "format": "mso_mdoc",
+            "doctype": "org.iso.18013.5.1.mDL",
+            "proof_types_supported": {
+                "cwt": { "proof_signing_alg_values_supported": [-7] }
+            }
+        });
+        assert_eq!(
+            ProofType::supported_by(&configuration),
+            vec![ProofType::Cwt]
+        );
+
+        let unrestricted = json!({ "format": "ldp_vc" });
+        assert!(ProofType::supported_by(&unrestricted).is_empty());
+    }
+}
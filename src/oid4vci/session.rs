@@ -1,16 +1,26 @@
 use futures::lock::Mutex;
-use oid4vci::{credential_offer::CredentialOfferGrants, profiles::metadata, token};
+use oid4vci::{
+    credential_offer::CredentialOfferGrants, oauth2::TokenResponse as ITokenResponse,
+    profiles::metadata, token,
+};
+use time::OffsetDateTime;
 
 use crate::credential::CredentialFormat;
 
-use super::Oid4vciError;
+use super::{OfferedCredential, Oid4vciError, ProofType};
 
 #[derive(uniffi::Object)]
 pub struct Oid4vciSession {
     client: Client,
+    client_id: Option<String>,
     metadata: Option<CredentialIssuerMetadata>,
+    token_endpoint: Option<String>,
+    offered_credentials: Vec<OfferedCredential>,
     token_response: Mutex<Option<TokenResponse>>,
+    token_expires_at: Mutex<Option<OffsetDateTime>>,
+    c_nonce: Mutex<Option<String>>,
     credential_request: Mutex<Option<CredentialRequest>>,
+    proof_types_supported: Mutex<Vec<Vec<ProofType>>>,
     grants: Mutex<Option<Grants>>,
 }
 
@@ -19,9 +29,15 @@ impl Oid4vciSession {
     pub fn new(client: Client) -> Self {
         Self {
             client,
+            client_id: None,
             metadata: None,
+            token_endpoint: None,
+            offered_credentials: Vec::new(),
             token_response: None.into(),
+            token_expires_at: None.into(),
+            c_nonce: None.into(),
             credential_request: None.into(),
+            proof_types_supported: Vec::new().into(),
             grants: None.into(),
         }
     }
@@ -30,6 +46,16 @@ impl Oid4vciSession {
         &self.client.0
     }
 
+    pub fn get_client_id(&self) -> Result<&str, Oid4vciError> {
+        self.client_id
+            .as_deref()
+            .ok_or(Oid4vciError::InvalidSession("client_id unset".into()))
+    }
+
+    pub fn set_client_id(&mut self, client_id: String) {
+        self.client_id = Some(client_id);
+    }
+
     pub fn get_metadata(&self) -> Result<&metadata::CredentialIssuerMetadata, Oid4vciError> {
         self.metadata
             .as_ref()
@@ -41,6 +67,20 @@ impl Oid4vciSession {
         self.metadata = Some(metadata);
     }
 
+    pub fn get_token_endpoint(&self) -> Result<&str, Oid4vciError> {
+        self.token_endpoint
+            .as_deref()
+            .ok_or(Oid4vciError::InvalidSession("token_endpoint unset".into()))
+    }
+
+    pub fn set_token_endpoint(&mut self, token_endpoint: Option<String>) {
+        self.token_endpoint = token_endpoint;
+    }
+
+    pub fn set_offered_credentials(&mut self, offered_credentials: Vec<OfferedCredential>) {
+        self.offered_credentials = offered_credentials;
+    }
+
     pub fn get_token_response(&self) -> Result<token::Response, Oid4vciError> {
         self.token_response
             .try_lock()
@@ -51,6 +91,24 @@ impl Oid4vciSession {
     }
 
     pub fn set_token_response(&self, token_response: TokenResponse) -> Result<(), Oid4vciError> {
+        let expires_at = token_response.0.expires_in().and_then(|expires_in| {
+            OffsetDateTime::now_utc().checked_add(expires_in.try_into().ok()?)
+        });
+
+        *(self
+            .token_expires_at
+            .try_lock()
+            .ok_or(Oid4vciError::LockError("token_expires_at".into()))?) = expires_at;
+
+        self.set_c_nonce(
+            token_response
+                .0
+                .extra_fields()
+                .c_nonce
+                .as_ref()
+                .map(|v| v.secret().to_owned()),
+        )?;
+
         *(self
             .token_response
             .try_lock()
@@ -59,6 +117,52 @@ impl Oid4vciSession {
         Ok(())
     }
 
+    pub fn get_token_expires_at(&self) -> Result<Option<OffsetDateTime>, Oid4vciError> {
+        Ok(*self
+            .token_expires_at
+            .try_lock()
+            .ok_or(Oid4vciError::LockError("token_expires_at".into()))?)
+    }
+
+    /// The latest `c_nonce` provided by the issuer, which proofs of possession must be bound to.
+    pub fn get_c_nonce(&self) -> Result<Option<String>, Oid4vciError> {
+        Ok(self
+            .c_nonce
+            .try_lock()
+            .ok_or(Oid4vciError::LockError("c_nonce".into()))?
+            .clone())
+    }
+
+    pub fn set_c_nonce(&self, c_nonce: Option<String>) -> Result<(), Oid4vciError> {
+        *(self
+            .c_nonce
+            .try_lock()
+            .ok_or(Oid4vciError::LockError("c_nonce".into()))?) = c_nonce;
+
+        Ok(())
+    }
+
+    /// Record the fresh `c_nonce` of an [Oid4vciError::InvalidProof] returned by the issuer,
+    /// so that new proofs can be bound to it.
+    pub(crate) fn track_c_nonce(&self, error: Oid4vciError) -> Oid4vciError {
+        if let Oid4vciError::InvalidProof { c_nonce, .. } = &error {
+            if let Err(e) = self.set_c_nonce(Some(c_nonce.clone())) {
+                return e;
+            }
+        }
+
+        error
+    }
+
+    /// Fail with [Oid4vciError::TokenExpired] if the access token is past its expiry.
+    pub fn ensure_token_valid(&self) -> Result<(), Oid4vciError> {
+        if is_expired(self.get_token_expires_at()?, OffsetDateTime::now_utc()) {
+            return Err(Oid4vciError::TokenExpired);
+        }
+
+        Ok(())
+    }
+
     pub fn get_credential_requests(
         &self,
     ) -> Result<Vec<oid4vci::profiles::ProfilesCredentialRequest>, Oid4vciError> {
@@ -98,6 +202,29 @@ impl Oid4vciSession {
         Ok(())
     }
 
+    /// Proof types supported by the issuer for each credential request, in the same order as
+    /// the requests. An empty list means the issuer did not restrict the proof types.
+    pub fn get_proof_types_supported(&self) -> Result<Vec<Vec<ProofType>>, Oid4vciError> {
+        Ok(self
+            .proof_types_supported
+            .try_lock()
+            .ok_or(Oid4vciError::LockError("proof_types_supported".into()))?
+            .clone())
+    }
+
+    pub fn set_proof_types_supported(
+        &self,
+        proof_types_supported: Vec<Vec<ProofType>>,
+    ) -> Result<(), Oid4vciError> {
+        *(self
+            .proof_types_supported
+            .try_lock()
+            .ok_or(Oid4vciError::LockError("proof_types_supported".into()))?) =
+            proof_types_supported;
+
+        Ok(())
+    }
+
     pub fn get_grants(&self) -> Result<CredentialOfferGrants, Oid4vciError> {
         self.grants
             .try_lock()
@@ -117,6 +244,47 @@ impl Oid4vciSession {
     }
 }
 
+#[uniffi::export]
+impl Oid4vciSession {
+    /// The credentials offered by the issuer, in the order they will be requested.
+    ///
+    /// Empty unless the session was initiated with a credential offer.
+    pub fn offered_credentials(&self) -> Vec<OfferedCredential> {
+        self.offered_credentials.clone()
+    }
+
+    /// When the access token expires, as an RFC 3339 timestamp.
+    ///
+    /// `None` if no token was obtained yet, or if the issuer did not provide `expires_in`.
+    pub fn token_expires_at(&self) -> Option<String> {
+        self.get_token_expires_at()
+            .ok()
+            .flatten()?
+            .format(&time::format_description::well_known::Rfc3339)
+            .ok()
+    }
+
+    /// The latest `c_nonce` provided by the issuer, with the access token or in an
+    /// `invalid_proof` error response.
+    ///
+    /// After [Oid4vciError::InvalidProof], new proofs of possession bound to this nonce must be
+    /// generated, e.g. with `generate_pop_prepare`, before the credential request is retried.
+    pub fn c_nonce(&self) -> Option<String> {
+        self.get_c_nonce().ok().flatten()
+    }
+
+    /// Whether an access token was obtained and has not expired.
+    ///
+    /// Tokens without an `expires_in` are assumed to be valid.
+    pub fn is_token_valid(&self) -> bool {
+        self.get_token_response().is_ok() && self.ensure_token_valid().is_ok()
+    }
+}
+
+fn is_expired(expires_at: Option<OffsetDateTime>, now: OffsetDateTime) -> bool {
+    expires_at.is_some_and(|expires_at| expires_at <= now)
+}
+
 macro_rules! wrap_external_type {
     ($wrap_me:ty, $as:ident) => {
         #[derive(uniffi::Object)]
@@ -149,3 +317,19 @@ pub struct CredentialResponse {
     pub format: CredentialFormat,
     pub payload: Vec<u8>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_expiry() {
+        let now = OffsetDateTime::now_utc();
+        let minute = time::Duration::minutes(1);
+
+        assert!(!is_expired(None, now));
+        assert!(!is_expired(Some(now + minute), now));
+        assert!(is_expired(Some(now), now));
+        assert!(is_expired(Some(now - minute), now));
+    }
+}
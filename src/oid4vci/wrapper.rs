@@ -3,10 +3,13 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use oid4vci::oauth2::TokenResponse as _;
+
 use super::{
-    oid4vci_exchange_credential, oid4vci_exchange_token, oid4vci_get_metadata, oid4vci_initiate,
-    oid4vci_initiate_with_offer, AsyncHttpClient, CredentialResponse, IHttpClient, Oid4vciError,
-    Oid4vciExchangeOptions, Oid4vciMetadata, Oid4vciSession, SyncHttpClient,
+    initiate, initiate_with_offer, oid4vci_exchange_credential, oid4vci_exchange_token,
+    oid4vci_get_metadata, oid4vci_refresh_credential, oid4vci_refresh_token, AsyncHttpClient,
+    CredentialResponse, IHttpClient, InMemoryIssuerMetadataCache, IssuerMetadataCache,
+    Oid4vciError, Oid4vciExchangeOptions, Oid4vciMetadata, Oid4vciSession, SyncHttpClient,
 };
 
 #[derive(uniffi::Object)]
@@ -14,6 +17,7 @@ pub struct Oid4vci {
     http_client: Arc<IHttpClient>,
     session: Mutex<Option<Arc<Oid4vciSession>>>,
     context_map: Mutex<Option<HashMap<String, String>>>,
+    metadata_cache: Mutex<Option<Arc<dyn IssuerMetadataCache>>>,
 }
 
 impl Oid4vci {
@@ -26,6 +30,15 @@ impl Oid4vci {
         Ok(context_map.clone())
     }
 
+    fn metadata_cache(&self) -> Result<Option<Arc<dyn IssuerMetadataCache>>, Oid4vciError> {
+        let metadata_cache = self
+            .metadata_cache
+            .lock()
+            .map_err(|_| Oid4vciError::LockError("metadata_cache".into()))?;
+
+        Ok(metadata_cache.clone())
+    }
+
     fn session(&self) -> Result<Arc<Oid4vciSession>, Oid4vciError> {
         let session = self
             .session
@@ -82,6 +95,7 @@ impl Oid4vci {
         Self {
             session: Mutex::new(None),
             context_map: Mutex::new(None),
+            metadata_cache: Mutex::new(Some(Arc::new(InMemoryIssuerMetadataCache::default()) as _)),
             http_client,
         }
         .into()
@@ -93,6 +107,7 @@ impl Oid4vci {
         Self {
             session: Mutex::new(None),
             context_map: Mutex::new(None),
+            metadata_cache: Mutex::new(Some(Arc::new(InMemoryIssuerMetadataCache::default()) as _)),
             http_client,
         }
         .into()
@@ -109,6 +124,35 @@ impl Oid4vci {
         Ok(())
     }
 
+    /// Reuse discovered issuer metadata from `cache`, for example to share it between
+    /// instances or persist it on the host. By default each instance has its own in-memory
+    /// cache.
+    pub fn set_metadata_cache(
+        &self,
+        cache: Arc<dyn IssuerMetadataCache>,
+    ) -> Result<(), Oid4vciError> {
+        let mut metadata_cache = self
+            .metadata_cache
+            .lock()
+            .map_err(|_| Oid4vciError::LockError("metadata_cache".into()))?;
+
+        *metadata_cache = Some(cache);
+
+        Ok(())
+    }
+
+    /// Always discover issuer metadata, without caching it.
+    pub fn disable_metadata_cache(&self) -> Result<(), Oid4vciError> {
+        let mut metadata_cache = self
+            .metadata_cache
+            .lock()
+            .map_err(|_| Oid4vciError::LockError("metadata_cache".into()))?;
+
+        *metadata_cache = None;
+
+        Ok(())
+    }
+
     fn clear_context_map(&self) -> Result<(), Oid4vciError> {
         let mut context_map = self
             .context_map
@@ -120,13 +164,9 @@ impl Oid4vci {
         Ok(())
     }
 
+    /// Deprecated: use [crate::logger::init_logging] or [crate::logger::init_global_logger].
     fn initiate_logger(&self) {
-        #[cfg(target_os = "android")]
-        android_logger::init_once(
-            android_logger::Config::default()
-                .with_max_level(log::LevelFilter::Trace)
-                .with_tag("MOBILE_SDK_RS"),
-        );
+        crate::logger::init_global_logger();
     }
 
     pub fn get_metadata(&self) -> Result<Oid4vciMetadata, Oid4vciError> {
@@ -139,11 +179,13 @@ impl Oid4vci {
         client_id: String,
         redirect_url: String,
     ) -> Result<(), Oid4vciError> {
-        let session = oid4vci_initiate_with_offer(
+        let metadata_cache = self.metadata_cache()?;
+        let session = initiate_with_offer(
             credential_offer,
             client_id,
             redirect_url,
             self.http_client.clone(),
+            metadata_cache.as_deref(),
         )
         .await?;
         self.set_session(session)
@@ -155,8 +197,15 @@ impl Oid4vci {
         client_id: String,
         redirect_url: String,
     ) -> Result<(), Oid4vciError> {
-        let session =
-            oid4vci_initiate(base_url, client_id, redirect_url, self.http_client.clone()).await?;
+        let metadata_cache = self.metadata_cache()?;
+        let session = initiate(
+            base_url,
+            client_id,
+            redirect_url,
+            self.http_client.clone(),
+            metadata_cache.as_deref(),
+        )
+        .await?;
         self.set_session(session)
     }
 
@@ -178,4 +227,43 @@ impl Oid4vci {
         )
         .await
     }
+
+    /// The latest `c_nonce` provided by the issuer, to bind new proofs of possession to, e.g.
+    /// after [Oid4vciError::InvalidProof].
+    pub fn get_c_nonce(&self) -> Result<Option<String>, Oid4vciError> {
+        self.session()?.get_c_nonce()
+    }
+
+    /// The refresh token issued with the session's current access token, if any.
+    pub fn get_refresh_token(&self) -> Result<Option<String>, Oid4vciError> {
+        Ok(self
+            .session()?
+            .get_token_response()?
+            .refresh_token()
+            .map(|token| token.secret().to_owned()))
+    }
+
+    pub async fn refresh_token(
+        &self,
+        refresh_token: String,
+    ) -> Result<Option<String>, Oid4vciError> {
+        oid4vci_refresh_token(self.session()?, refresh_token, self.http_client.clone()).await
+    }
+
+    pub async fn refresh_credential(
+        &self,
+        refresh_token: String,
+        proofs_of_possession: Vec<String>,
+        options: Oid4vciExchangeOptions,
+    ) -> Result<Vec<CredentialResponse>, Oid4vciError> {
+        oid4vci_refresh_credential(
+            self.session()?,
+            refresh_token,
+            proofs_of_possession,
+            options,
+            self.context_map()?,
+            self.http_client.clone(),
+        )
+        .await
+    }
 }
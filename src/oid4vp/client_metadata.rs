@@ -0,0 +1,241 @@
+//! Metadata of the verifier, passed in an authorization request by value through
+//! `client_metadata` or by reference through `client_metadata_uri`.
+
+use super::error::OID4VPError;
+
+use std::time::Duration;
+
+use openid4vp::core::{
+    authorization_request::{parameters::ResponseMode, AuthorizationRequestObject},
+    object::TypedParameter,
+};
+use serde_json::Value as Json;
+use uniffi::deps::log;
+
+/// The metadata of the verifier that made an authorization request.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct VerifierMetadata {
+    /// The verifier's public keys that the response may be encrypted to, as JSON encoded
+    /// JWKs. Keys restricted to another `use` than `enc` are excluded.
+    pub encryption_jwks: Vec<String>,
+    /// The JWE `alg` the verifier expects the response to be encrypted with, if any.
+    pub authorization_encrypted_response_alg: Option<String>,
+    /// The JWE `enc` the verifier expects the response to be encrypted with, if any.
+    pub authorization_encrypted_response_enc: Option<String>,
+    /// The credential and presentation formats the verifier supports, e.g. `jwt_vp_json`.
+    pub vp_formats: Vec<String>,
+    /// The complete metadata, as a JSON encoded string.
+    pub raw: String,
+}
+
+impl VerifierMetadata {
+    fn from_json(metadata: &Json) -> Result<Self, OID4VPError> {
+        if !metadata.is_object() {
+            return Err(OID4VPError::ClientMetadataResolution(
+                "client metadata is not a JSON object".into(),
+            ));
+        }
+
+        let string = |key: &str| metadata.get(key).and_then(Json::as_str).map(Into::into);
+
+        let encryption_jwks = metadata
+            .pointer("/jwks/keys")
+            .and_then(Json::as_array)
+            .into_iter()
+            .flatten()
+            .filter(|jwk| jwk.get("use").is_none_or(|use_| use_ == "enc"))
+            .map(Json::to_string)
+            .collect();
+
+        let vp_formats = ["vp_formats", "vp_formats_supported"]
+            .iter()
+            .filter_map(|key| metadata.get(key)?.as_object())
+            .flat_map(|formats| formats.keys().cloned())
+            .collect();
+
+        Ok(Self {
+            encryption_jwks,
+            authorization_encrypted_response_alg: string("authorization_encrypted_response_alg"),
+            authorization_encrypted_response_enc: string("authorization_encrypted_response_enc"),
+            vp_formats,
+            raw: metadata.to_string(),
+        })
+    }
+}
+
+/// The raw `client_metadata` authorization request parameter.
+#[derive(Debug, Clone)]
+struct ClientMetadataParameter(Json);
+
+impl TypedParameter for ClientMetadataParameter {
+    const KEY: &'static str = "client_metadata";
+}
+
+impl TryFrom<Json> for ClientMetadataParameter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Json) -> Result<Self, Self::Error> {
+        Ok(Self(value))
+    }
+}
+
+impl From<ClientMetadataParameter> for Json {
+    fn from(value: ClientMetadataParameter) -> Self {
+        value.0
+    }
+}
+
+/// The raw `client_metadata_uri` authorization request parameter.
+#[derive(Debug, Clone)]
+struct ClientMetadataUriParameter(String);
+
+impl TypedParameter for ClientMetadataUriParameter {
+    const KEY: &'static str = "client_metadata_uri";
+}
+
+impl TryFrom<Json> for ClientMetadataUriParameter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Json) -> Result<Self, Self::Error> {
+        Ok(Self(serde_json::from_value(value)?))
+    }
+}
+
+impl From<ClientMetadataUriParameter> for Json {
+    fn from(value: ClientMetadataUriParameter) -> Self {
+        value.0.into()
+    }
+}
+
+/// Resolve the metadata of the verifier that made `request`, fetching it from
+/// `client_metadata_uri` with `client` if it is passed by reference.
+///
+/// Returns `None` if the request carries no metadata. Metadata that cannot be resolved is
+/// only an error for `direct_post.jwt` requests, whose response must be encrypted to the
+/// verifier's keys; otherwise it is logged and ignored.
+pub(crate) async fn resolve_client_metadata(
+    request: &AuthorizationRequestObject,
+    client: &reqwest::Client,
+    timeout: Duration,
+) -> Result<Option<VerifierMetadata>, OID4VPError> {
+    match fetch_client_metadata(request, client, timeout).await {
+        Ok(metadata) => Ok(metadata),
+        Err(e) if matches!(request.response_mode(), ResponseMode::DirectPostJwt) => Err(e),
+        Err(e) => {
+            log::warn!("ignoring verifier metadata: {e}");
+            Ok(None)
+        }
+    }
+}
+
+async fn fetch_client_metadata(
+    request: &AuthorizationRequestObject,
+    client: &reqwest::Client,
+    timeout: Duration,
+) -> Result<Option<VerifierMetadata>, OID4VPError> {
+    if let Some(parameter) = request.get::<ClientMetadataParameter>() {
+        let parameter =
+            parameter.map_err(|e| OID4VPError::ClientMetadataResolution(format!("{e:?}")))?;
+        return VerifierMetadata::from_json(&parameter.0).map(Some);
+    }
+
+    let Some(parameter) = request.get::<ClientMetadataUriParameter>() else {
+        return Ok(None);
+    };
+    let uri = parameter
+        .map_err(|e| OID4VPError::ClientMetadataResolution(format!("{e:?}")))?
+        .0;
+
+    let metadata: Json = client
+        .get(&uri)
+        .timeout(timeout)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| OID4VPError::ClientMetadataResolution(format!("{uri}: {e}")))?
+        .json()
+        .await
+        .map_err(|e| OID4VPError::ClientMetadataResolution(format!("{uri}: {e}")))?;
+
+    VerifierMetadata::from_json(&metadata).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::oid4vp::holder::tests::test_request;
+
+    fn definition() -> Json {
+        serde_json::json!({ "id": "client-metadata-test", "input_descriptors": [] })
+    }
+
+    #[tokio::test]
+    async fn resolves_inline_client_metadata() {
+        let request = test_request(
+            definition(),
+            Some(serde_json::json!({
+                "jwks": { "keys": [
+                    { "kty": "EC", "crv": "P-256", "use": "enc", "x": "x", "y": "y" },
+                    { "kty": "EC", "crv": "P-256", "use": "sig", "x": "x", "y": "y" }
+                ] },
+                "authorization_encrypted_response_alg": "ECDH-ES",
+                "authorization_encrypted_response_enc": "A256GCM",
+                "vp_formats": { "mso_mdoc": { "alg": ["ES256"] } }
+            })),
+        );
+
+        let metadata =
+            resolve_client_metadata(&request, &reqwest::Client::new(), Duration::from_secs(1))
+                .await
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(metadata.encryption_jwks.len(), 1);
+        assert!(metadata.encryption_jwks[0].contains("\"use\":\"enc\""));
+        assert_eq!(
+            metadata.authorization_encrypted_response_alg.as_deref(),
+            Some("ECDH-ES")
+        );
+        assert_eq!(metadata.vp_formats, vec!["mso_mdoc".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn resolves_absent_client_metadata() {
+        let request = test_request(definition(), None);
+
+        assert_eq!(
+            resolve_client_metadata(&request, &reqwest::Client::new(), Duration::from_secs(1))
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_client_metadata_uri_fails_encrypted_requests() {
+        // Nothing listens on the port once the listener is dropped.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/metadata", listener.local_addr().unwrap());
+        drop(listener);
+
+        // Encrypted responses cannot be made without the verifier's metadata.
+        let request: AuthorizationRequestObject = serde_json::from_value(serde_json::json!({
+            "client_id": "https://verifier.example/response",
+            "client_id_scheme": "redirect_uri",
+            "response_type": "vp_token",
+            "response_mode": "direct_post.jwt",
+            "response_uri": "https://verifier.example/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "client_metadata_uri": uri,
+            "presentation_definition": definition()
+        }))
+        .unwrap();
+
+        assert!(matches!(
+            resolve_client_metadata(&request, &reqwest::Client::new(), Duration::from_secs(1))
+                .await,
+            Err(OID4VPError::ClientMetadataResolution(_))
+        ));
+    }
+}
@@ -14,12 +14,26 @@ pub enum OID4VPError {
     RequestValidation(String),
     #[error("Failed to resolve the presentation definition: {0}")]
     PresentationDefinitionResolution(String),
+    #[error("Timed out after {0}ms resolving the presentation definition")]
+    PresentationDefinitionTimeout(u64),
+    #[error("Failed to reach the presentation definition URI: {0}")]
+    PresentationDefinitionUnreachable(String),
+    #[error("Failed to resolve the verifier metadata: {0}")]
+    ClientMetadataResolution(String),
     #[error("Failed to create verifiable presentation token: {0}")]
     Token(String),
     #[error("Unsupported Response Mode for OID4VP Request: {0}")]
     UnsupportedResponseMode(String),
     #[error("Failed to submit OID4VP response: {0}")]
     ResponseSubmission(String),
+    #[error("A response was already sent for this request")]
+    ReplayDetected,
+    #[error("Failed to access used nonces: {0}")]
+    NonceStorage(String),
+    #[error("Invalid nonce tracker configuration: {0}")]
+    NonceTrackerConfiguration(String),
+    #[error("Invalid X.509 trust anchor: {0}")]
+    X509TrustAnchor(String),
     #[error("Credential callback error: {0}")]
     CredentialCallback(String),
     #[error("Failed to create presentation submission: {0}")]
@@ -74,6 +88,10 @@ pub enum OID4VPError {
     SelectiveDisclosureInvalidFields,
     #[error("Selected fields cannot be empty")]
     SelectiveDisclosureEmptySelection,
+    #[error("None of the requested formats are supported: {0}")]
+    UnsupportedRequestedFormat(String),
+    #[error("Invalid transaction data: {0}")]
+    TransactionData(String),
     #[error("Failed to initialize metadata: {0}")]
     Debug(String),
 }
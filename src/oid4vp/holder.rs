@@ -1,28 +1,39 @@
+use super::client_metadata::resolve_client_metadata;
 use super::error::OID4VPError;
+use super::iso_18013_7::prepare_response::RawResponseUri;
 use super::permission_request::*;
-use super::presentation::PresentationSigner;
+use super::presentation::{input_descriptor_definition, PresentationSigner};
+use super::replay::NonceTracker;
+use super::transaction_data::parse_transaction_data;
 use crate::common::*;
 use crate::credential::*;
 use crate::vdc_collection::VdcCollection;
 use crate::UniffiCustomTypeConverter;
 
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use futures::StreamExt;
 use openid4vp::core::authorization_request::parameters::ClientIdScheme;
 use openid4vp::core::credential_format::{ClaimFormatDesignation, ClaimFormatPayload};
 use openid4vp::core::input_descriptor::ConstraintsLimitDisclosure;
 use openid4vp::core::presentation_definition::PresentationDefinition;
+use openid4vp::core::response::AuthorizationResponse;
 use openid4vp::{
     core::{
         authorization_request::{
             parameters::ResponseMode,
-            verification::{did::verify_with_resolver, RequestVerifier},
+            verification::{
+                did::verify_with_resolver, verifier::P256Verifier, x509_san, RequestVerifier,
+            },
             AuthorizationRequestObject,
         },
         metadata::WalletMetadata,
     },
+    verifier::client::X509SanVariant,
     wallet::Wallet as OID4VPWallet,
 };
 
@@ -31,6 +42,7 @@ use ssi::dids::DIDWeb;
 use ssi::dids::VerificationMethodDIDResolver;
 use ssi::prelude::AnyJwkMethod;
 use uniffi::deps::{anyhow, log};
+use x509_cert::{der::DecodePem, Certificate};
 
 pub enum AuthRequest {
     /// Parse the incoming string as a URL.
@@ -62,6 +74,66 @@ impl UniffiCustomTypeConverter for AuthRequest {
     }
 }
 
+/// The outcome of submitting a permission response to the verifier.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct SubmissionResult {
+    /// The URL the wallet should redirect the user to, if the verifier provided one.
+    ///
+    /// This is typically present for same-device flows.
+    pub redirect_uri: Option<Url>,
+    /// The response code issued by the verifier, taken from the response body or the
+    /// `redirect_uri` query or fragment.
+    pub response_code: Option<String>,
+    /// The HTTP status code returned by the verifier.
+    pub status: u16,
+}
+
+impl SubmissionResult {
+    /// Parse the verifier's response to a `direct_post` submission.
+    ///
+    /// The body is expected to be a JSON object, but verifiers may return an empty body, in
+    /// which case only the status is reported.
+    pub(crate) fn from_response(status: u16, body: &[u8]) -> Result<Self, OID4VPError> {
+        if !(200..300).contains(&status) {
+            return Err(OID4VPError::ResponseSubmission(format!(
+                "verifier responded with status {status}: {}",
+                String::from_utf8_lossy(body)
+            )));
+        }
+
+        let json = serde_json::from_slice::<serde_json::Value>(body).ok();
+
+        let redirect_uri = json
+            .as_ref()
+            .and_then(|json| json.get("redirect_uri"))
+            .and_then(|uri| uri.as_str())
+            .map(Url::parse)
+            .transpose()
+            .map_err(|e| OID4VPError::ResponseSubmission(format!("invalid redirect_uri: {e}")))?;
+
+        let response_code = json
+            .as_ref()
+            .and_then(|json| json.get("response_code"))
+            .and_then(|code| code.as_str())
+            .map(ToOwned::to_owned)
+            .or_else(|| {
+                let uri = redirect_uri.as_ref()?;
+                uri.query_pairs()
+                    .chain(url::form_urlencoded::parse(
+                        uri.fragment().unwrap_or_default().as_bytes(),
+                    ))
+                    .find(|(key, _)| key == "response_code")
+                    .map(|(_, code)| code.into_owned())
+            });
+
+        Ok(Self {
+            redirect_uri,
+            response_code,
+            status,
+        })
+    }
+}
+
 /// A Holder is an entity that possesses one or more Verifiable Credentials.
 /// The Holder is typically the subject of the credentials, but not always.
 /// The Holder has the ability to generate Verifiable Presentations from
@@ -88,6 +160,16 @@ pub struct Holder {
 
     /// Optional context map for resolving specific contexts
     pub(crate) context_map: Option<HashMap<String, String>>,
+
+    /// How long to wait for a presentation definition to be fetched, in milliseconds.
+    pub(crate) presentation_definition_timeout_ms: AtomicU64,
+
+    /// Records the nonces of requests that have been responded to, when replay protection
+    /// is enabled.
+    pub(crate) nonce_tracker: Mutex<Option<Arc<NonceTracker>>>,
+
+    /// The root certificates `x509_san_dns` requests must chain to.
+    pub(crate) x509_trust_anchors: Mutex<Vec<Certificate>>,
 }
 
 #[uniffi::export(async_runtime = "tokio")]
@@ -111,6 +193,9 @@ impl Holder {
             provided_credentials: None,
             signer: Arc::new(signer),
             context_map,
+            presentation_definition_timeout_ms: DEFAULT_PRESENTATION_DEFINITION_TIMEOUT_MS.into(),
+            nonce_tracker: Mutex::new(None),
+            x509_trust_anchors: Mutex::new(Vec::new()),
         }))
     }
 
@@ -137,9 +222,93 @@ impl Holder {
             provided_credentials: Some(provided_credentials),
             signer: Arc::new(signer),
             context_map,
+            presentation_definition_timeout_ms: DEFAULT_PRESENTATION_DEFINITION_TIMEOUT_MS.into(),
+            nonce_tracker: Mutex::new(None),
+            x509_trust_anchors: Mutex::new(Vec::new()),
         }))
     }
 
+    /// Return a holder presenting `provided_credentials`, sharing the HTTP client, signer,
+    /// metadata and settings of this holder.
+    ///
+    /// This avoids initializing a new holder for each presentation when only the credentials
+    /// change.
+    pub fn with_credentials(
+        &self,
+        provided_credentials: Vec<Arc<ParsedCredential>>,
+    ) -> Result<Arc<Self>, OID4VPError> {
+        let nonce_tracker = self
+            .nonce_tracker
+            .lock()
+            .map_err(|e| OID4VPError::NonceStorage(e.to_string()))?
+            .clone();
+        let x509_trust_anchors = self
+            .x509_trust_anchors
+            .lock()
+            .map_err(|e| OID4VPError::X509TrustAnchor(e.to_string()))?
+            .clone();
+
+        Ok(Arc::new(Self {
+            client: self.client.clone(),
+            vdc_collection: None,
+            metadata: self.metadata.clone(),
+            trusted_dids: self.trusted_dids.clone(),
+            provided_credentials: Some(provided_credentials),
+            signer: self.signer.clone(),
+            context_map: self.context_map.clone(),
+            presentation_definition_timeout_ms: self
+                .presentation_definition_timeout_ms
+                .load(Ordering::Relaxed)
+                .into(),
+            nonce_tracker: Mutex::new(nonce_tracker),
+            x509_trust_anchors: Mutex::new(x509_trust_anchors),
+        }))
+    }
+
+    /// Set how long to wait for the presentation definition to be fetched from the
+    /// `presentation_definition_uri` of a request, in milliseconds. Defaults to 30 seconds.
+    ///
+    /// The same timeout applies to fetching the verifier metadata from `client_metadata_uri`.
+    pub fn set_presentation_definition_timeout(&self, timeout_ms: u64) {
+        self.presentation_definition_timeout_ms
+            .store(timeout_ms, Ordering::Relaxed);
+    }
+
+    /// Enable replay protection with `tracker`, or disable it with `None`.
+    ///
+    /// While enabled, [Holder::submit_permission_response] rejects a second response to a
+    /// request with the same nonce with [OID4VPError::ReplayDetected]. A response that fails
+    /// to be delivered does not count, so it can be retried.
+    pub fn set_nonce_tracker(&self, tracker: Option<Arc<NonceTracker>>) -> Result<(), OID4VPError> {
+        *self
+            .nonce_tracker
+            .lock()
+            .map_err(|e| OID4VPError::NonceStorage(e.to_string()))? = tracker;
+
+        Ok(())
+    }
+
+    /// Trust `x509_san_dns` requests whose certificate chains to one of `pem_certificates`.
+    ///
+    /// Requests using the `x509_san_dns` client ID scheme are rejected until trust anchors
+    /// are set.
+    pub fn set_x509_trust_anchors(&self, pem_certificates: Vec<String>) -> Result<(), OID4VPError> {
+        let certificates = pem_certificates
+            .iter()
+            .map(|pem| {
+                Certificate::from_pem(pem)
+                    .map_err(|e| OID4VPError::X509TrustAnchor(format!("{e:?}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        *self
+            .x509_trust_anchors
+            .lock()
+            .map_err(|e| OID4VPError::X509TrustAnchor(e.to_string()))? = certificates;
+
+        Ok(())
+    }
+
     /// Given an authorization request URL, return a permission request,
     /// which provides a list of requested credentials and requested fields
     /// that align with the presentation definition of the request.
@@ -174,20 +343,351 @@ impl Holder {
         }
     }
 
+    /// Submit the permission response to the verifier.
+    ///
+    /// The response is POSTed to the request's `response_uri`. A `redirect_uri` returned by the
+    /// verifier is not followed here; it is returned for the host to open.
+    ///
+    /// Returns the verifier's `redirect_uri` and `response_code`, if any, along with the HTTP
+    /// status, so same-device wallets can complete the redirect and cross-device wallets can
+    /// report success.
     pub async fn submit_permission_response(
         &self,
         response: Arc<PermissionResponse>,
-    ) -> Result<Option<Url>, OID4VPError> {
-        let auth_response = response.authorization_response()?;
+    ) -> Result<SubmissionResult, OID4VPError> {
+        let tracker = self
+            .nonce_tracker
+            .lock()
+            .map_err(|e| OID4VPError::NonceStorage(e.to_string()))?
+            .clone();
+        let Some(tracker) = tracker else {
+            return self.post_permission_response(&response).await;
+        };
 
-        self.submit_response(response.authorization_request.clone(), auth_response)
-            .await
-            .map_err(|e| OID4VPError::ResponseSubmission(format!("{e:?}")))
+        let nonce = response.authorization_request.nonce().to_string();
+        tracker.claim(&nonce).await?;
+
+        let result = self.post_permission_response(&response).await;
+        if result.is_err() {
+            // The delivery error is what the caller needs to see; failing to release the
+            // nonce only prevents a retry.
+            if let Err(e) = tracker.release(&nonce).await {
+                log::warn!("failed to release nonce after a failed submission: {e}");
+            }
+        }
+        result
+    }
+
+    /// Given an OpenID4VP request received through the W3C Digital Credentials API, return a
+    /// permission request, as [Holder::authorization_request] does for URL-based requests.
+    ///
+    /// `request` is the JSON request object handed to the wallet by the platform, and `origin`
+    /// is the origin of the web page that made the request, as reported by the platform. The
+    /// request must use the `dc_api` response mode; `dc_api.jwt` is not supported, as the
+    /// response cannot be encrypted yet.
+    ///
+    /// Unsigned requests are always attributed to `web-origin:<origin>`, whatever `client_id`
+    /// they claim. Signed requests, passed as `{"request": "<JWT>"}`, must list `origin` in
+    /// their `expected_origins` and are verified according to their client ID scheme.
+    ///
+    /// The permission response is returned to the browser through [Holder::dc_api_response]
+    /// rather than submitted over HTTP.
+    pub async fn dc_api_request(
+        &self,
+        request: String,
+        origin: String,
+    ) -> Result<Arc<PermissionRequest>, OID4VPError> {
+        let request = match parse_dc_api_request(&request, &origin)? {
+            DcApiRequest::Unsigned(request) => request,
+            DcApiRequest::Signed {
+                request,
+                request_jwt,
+                client_id_scheme,
+            } => {
+                self.verify_dc_api_request(&request, request_jwt, &client_id_scheme)
+                    .await
+                    .map_err(|e| OID4VPError::RequestValidation(format!("{e:?}")))?;
+                request
+            }
+        };
+
+        self.permission_request(request).await
+    }
+
+    /// Encode the permission response to a Digital Credentials API request as the JSON object
+    /// the host returns to the browser.
+    pub fn dc_api_response(
+        &self,
+        response: Arc<PermissionResponse>,
+    ) -> Result<String, OID4VPError> {
+        if !is_dc_api(&response.authorization_request) {
+            return Err(OID4VPError::UnsupportedResponseMode(
+                response.authorization_request.response_mode().to_string(),
+            ));
+        }
+
+        let data = match response.authorization_response()? {
+            AuthorizationResponse::Unencoded(unencoded) => {
+                let mut data = serde_json::json!({
+                    "vp_token": unencoded.vp_token,
+                    "presentation_submission": unencoded.presentation_submission,
+                });
+                if let Some(state) = unencoded.state {
+                    data["state"] = serde_json::to_value(state)
+                        .map_err(|e| OID4VPError::ResponseSubmission(format!("{e:?}")))?;
+                }
+                data
+            }
+            AuthorizationResponse::Jwt(jwt) => serde_json::json!({ "response": jwt.response }),
+        };
+
+        serde_json::to_string(&data).map_err(|e| OID4VPError::ResponseSubmission(format!("{e:?}")))
+    }
+}
+
+/// Default time to wait for a presentation definition to be fetched from the verifier.
+const DEFAULT_PRESENTATION_DEFINITION_TIMEOUT_MS: u64 = 30_000;
+
+/// Map a failure to resolve the presentation definition to an [OID4VPError], telling a
+/// verifier that could not be reached apart from one that served an invalid definition.
+fn presentation_definition_error(e: anyhow::Error) -> OID4VPError {
+    // Connection and DNS failures surface as I/O errors beneath the HTTP client error.
+    if e.chain().any(|cause| cause.is::<std::io::Error>()) {
+        OID4VPError::PresentationDefinitionUnreachable(format!("{e:#}"))
+    } else {
+        OID4VPError::PresentationDefinitionResolution(format!("{e:?}"))
+    }
+}
+
+/// Whether presenting `credential` for `definition` would have to limit disclosure, which
+/// is only supported for mdocs.
+///
+/// This is decided from the input descriptors the credential matches, as the formats an
+/// input descriptor or the verifier claim to accept do not restrict which credentials match
+/// it. A credential matching any input descriptor without `limit_disclosure: required` can
+/// be presented in full for that input descriptor.
+fn requires_limited_disclosure(
+    credential: &ParsedCredential,
+    definition: &PresentationDefinition,
+) -> bool {
+    if matches!(credential.inner, ParsedCredentialInner::MsoMdoc(_)) {
+        return false;
+    }
+
+    let mut matched = definition
+        .input_descriptors()
+        .iter()
+        .filter(|descriptor| {
+            credential.satisfies_presentation_definition(&input_descriptor_definition(
+                definition, descriptor,
+            ))
+        })
+        .peekable();
+
+    matched.peek().is_some()
+        && matched.all(|descriptor| {
+            matches!(
+                descriptor.constraints.limit_disclosure(),
+                Some(ConstraintsLimitDisclosure::Required)
+            )
+        })
+}
+
+/// Response mode of requests made through the W3C Digital Credentials API.
+///
+/// `dc_api.jwt` is not accepted, as [PermissionResponse::authorization_response] cannot
+/// encrypt the response to the verifier.
+const DC_API_RESPONSE_MODE: &str = "dc_api";
+
+/// Whether the response to the request is returned through the Digital Credentials API.
+fn is_dc_api(request: &AuthorizationRequestObject) -> bool {
+    matches!(
+        request.response_mode(),
+        ResponseMode::Unsupported(mode) if mode == DC_API_RESPONSE_MODE
+    )
+}
+
+/// A request received through the Digital Credentials API.
+enum DcApiRequest {
+    /// An unsigned request, attributed to the origin of the caller.
+    Unsigned(AuthorizationRequestObject),
+    /// A request object signed by the verifier, which must be verified before use.
+    Signed {
+        request: AuthorizationRequestObject,
+        request_jwt: String,
+        client_id_scheme: String,
+    },
+}
+
+/// Parse a request received through the Digital Credentials API from `origin`, without
+/// verifying the signature of a signed request.
+fn parse_dc_api_request(request: &str, origin: &str) -> Result<DcApiRequest, OID4VPError> {
+    let request: serde_json::Value =
+        serde_json::from_str(request).map_err(|e| OID4VPError::JsonSyntaxParse(format!("{e}")))?;
+
+    let request_jwt = request
+        .get("request")
+        .and_then(serde_json::Value::as_str)
+        .map(ToOwned::to_owned);
+
+    let mut request = match &request_jwt {
+        Some(jwt) => jwt
+            .split('.')
+            .nth(1)
+            .and_then(|payload| URL_SAFE_NO_PAD.decode(payload).ok())
+            .and_then(|payload| serde_json::from_slice(&payload).ok())
+            .ok_or_else(|| {
+                OID4VPError::RequestValidation("failed to decode the request object".to_string())
+            })?,
+        None => request,
+    };
+    let object = request.as_object_mut().ok_or_else(|| {
+        OID4VPError::RequestValidation("request is not a JSON object".to_string())
+    })?;
+
+    let expected_origins = object
+        .get("expected_origins")
+        .and_then(serde_json::Value::as_array);
+    match expected_origins {
+        Some(expected_origins)
+            if !expected_origins
+                .iter()
+                .any(|expected| expected.as_str() == Some(origin)) =>
+        {
+            return Err(OID4VPError::RequestValidation(format!(
+                "{origin} is not an expected origin of the request"
+            )));
+        }
+        // A signed request could otherwise be replayed from any origin.
+        None if request_jwt.is_some() => {
+            return Err(OID4VPError::RequestValidation(
+                "signed request has no expected_origins".to_string(),
+            ));
+        }
+        _ => {}
+    }
+
+    let client_id_scheme = match &request_jwt {
+        Some(_) => object
+            .get("client_id_scheme")
+            .and_then(serde_json::Value::as_str)
+            .or_else(|| {
+                object
+                    .get("client_id")
+                    .and_then(serde_json::Value::as_str)
+                    .and_then(|client_id| client_id.split(':').next())
+            })
+            .unwrap_or_default()
+            .to_owned(),
+        None => {
+            // Nothing vouches for the client ID an unsigned request claims, so it is
+            // identified by the origin of the caller.
+            object.insert("client_id".into(), format!("web-origin:{origin}").into());
+            object.insert("client_id_scheme".into(), "web-origin".into());
+            String::new()
+        }
+    };
+
+    let request: AuthorizationRequestObject = serde_json::from_value(request)
+        .map_err(|e| OID4VPError::RequestValidation(format!("{e:?}")))?;
+
+    if !is_dc_api(&request) {
+        return Err(OID4VPError::UnsupportedResponseMode(
+            request.response_mode().to_string(),
+        ));
+    }
+
+    Ok(match request_jwt {
+        Some(request_jwt) => DcApiRequest::Signed {
+            request,
+            request_jwt,
+            client_id_scheme,
+        },
+        None => DcApiRequest::Unsigned(request),
+    })
+}
+
+/// The endpoint an authorization response is submitted to.
+///
+/// With `direct_post` and `direct_post.jwt` the response is POSTed to `response_uri`, which is
+/// distinct from any `redirect_uri`: the latter is only where the verifier may send the user
+/// afterwards, and is never a fallback submission endpoint.
+fn submission_uri(request: &AuthorizationRequestObject) -> Result<Url, OID4VPError> {
+    match request.response_mode() {
+        ResponseMode::DirectPost | ResponseMode::DirectPostJwt => {
+            let response_uri = request
+                .get::<RawResponseUri>()
+                .ok_or_else(|| {
+                    OID4VPError::RequestValidation(format!(
+                        "response_uri is required for response mode {}",
+                        request.response_mode()
+                    ))
+                })?
+                .map_err(|e| OID4VPError::RequestValidation(format!("{e:?}")))?;
+
+            Url::parse(&response_uri.0)
+                .map_err(|e| OID4VPError::RequestValidation(format!("invalid response_uri: {e}")))
+        }
+        ResponseMode::Unsupported(mode) => {
+            Err(OID4VPError::UnsupportedResponseMode(mode.to_owned()))
+        }
     }
 }
 
 // Internal methods for the Holder.
 impl Holder {
+    /// Verify the signature of a request object received through the Digital Credentials
+    /// API, according to its client ID scheme.
+    async fn verify_dc_api_request(
+        &self,
+        request: &AuthorizationRequestObject,
+        request_jwt: String,
+        client_id_scheme: &str,
+    ) -> anyhow::Result<()> {
+        match client_id_scheme {
+            "did" => self.did(request, request_jwt).await,
+            "x509_san_dns" => self.x509_san_dns(request, request_jwt).await,
+            scheme => anyhow::bail!("unsupported client_id_scheme for a signed request: {scheme}"),
+        }
+    }
+
+    /// POST the permission response to the request's `response_uri`.
+    async fn post_permission_response(
+        &self,
+        response: &PermissionResponse,
+    ) -> Result<SubmissionResult, OID4VPError> {
+        let auth_response = response.authorization_response()?;
+        let response_uri = submission_uri(&response.authorization_request)?;
+
+        let body =
+            match auth_response {
+                AuthorizationResponse::Unencoded(unencoded) => unencoded
+                    .into_x_www_form_urlencoded()
+                    .map_err(|e| OID4VPError::ResponseSubmission(format!("{e:?}")))?,
+                AuthorizationResponse::Jwt(jwt) => {
+                    format!("response={}", urlencoding::encode(&jwt.response))
+                }
+            };
+
+        let http_response = self
+            .client
+            .as_ref()
+            .post(response_uri)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| OID4VPError::ResponseSubmission(format!("{e:?}")))?;
+
+        let status = http_response.status().as_u16();
+        let body = http_response
+            .bytes()
+            .await
+            .map_err(|e| OID4VPError::ResponseSubmission(format!("{e:?}")))?;
+
+        SubmissionResult::from_response(status, &body)
+    }
+
     /// Return the static metadata for the holder.
     ///
     /// This method is used to initialize the metadata for the holder.
@@ -200,6 +700,9 @@ impl Holder {
             ClaimFormatPayload::AlgValuesSupported(vec!["ES256".into()]),
         );
 
+        // The IETF SD-JWT VC format (`dc+sd-jwt`) is not advertised, as no credential format
+        // matches it yet.
+
         // Insert support for the JSON-LD format.
         metadata.vp_formats_supported_mut().0.insert(
             ClaimFormatDesignation::LdpVp,
@@ -213,8 +716,12 @@ impl Holder {
         );
 
         metadata
-            // Insert support for the DID client ID scheme.
-            .add_client_id_schemes_supported(&[ClientIdScheme::Did, ClientIdScheme::RedirectUri])
+            // Insert support for the DID, redirect URI and X.509 certificate client ID schemes.
+            .add_client_id_schemes_supported(&[
+                ClientIdScheme::Did,
+                ClientIdScheme::RedirectUri,
+                ClientIdScheme::X509SanDns,
+            ])
             .map_err(|e| OID4VPError::MetadataInitialization(format!("{e:?}")))?;
 
         metadata
@@ -222,6 +729,11 @@ impl Holder {
             .add_request_object_signing_alg_values_supported(ssi::jwk::Algorithm::None)
             .map_err(|e| OID4VPError::MetadataInitialization(format!("{e:?}")))?;
 
+        metadata
+            // Allow requests signed with an X.509 certificate key.
+            .add_request_object_signing_alg_values_supported(ssi::jwk::Algorithm::ES256)
+            .map_err(|e| OID4VPError::MetadataInitialization(format!("{e:?}")))?;
+
         Ok(metadata)
     }
 
@@ -238,12 +750,20 @@ impl Holder {
                 Some(vdc_collection) => {
                     futures::stream::iter(vdc_collection.all_entries().await?.into_iter())
                         .filter_map(|id| async move {
-                            vdc_collection
-                                .get(id)
-                                .await
-                                .ok()
-                                .flatten()
-                                .and_then(|cred| cred.try_into_parsed().ok())
+                            match vdc_collection.get(id).await {
+                                Ok(Some(cred)) => match cred.try_into_parsed() {
+                                    Ok(parsed) => Some(parsed),
+                                    Err(e) => {
+                                        log::warn!("skipping credential {id}: {e}");
+                                        None
+                                    }
+                                },
+                                Ok(None) => None,
+                                Err(e) => {
+                                    log::warn!("skipping credential {id}: {e}");
+                                    None
+                                }
+                            }
                         })
                         .collect::<Vec<Arc<ParsedCredential>>>()
                         .await
@@ -262,70 +782,117 @@ impl Holder {
         Ok(credentials)
     }
 
+    /// Return the formats requested through the verifier's `vp_formats` when none of them
+    /// are supported by the holder.
+    ///
+    /// Returns `None` if the verifier did not restrict the formats, or if at least one of them
+    /// is supported.
+    fn unsupported_requested_formats(
+        &self,
+        request: &AuthorizationRequestObject,
+    ) -> Option<String> {
+        let requested = request.vp_formats().ok()?;
+        let supported = &self.metadata.vp_formats_supported().0;
+
+        if requested.0.is_empty()
+            || requested
+                .0
+                .keys()
+                .any(|format| supported.contains_key(format))
+        {
+            return None;
+        }
+
+        Some(
+            requested
+                .0
+                .keys()
+                .filter_map(|format| {
+                    serde_json::to_value(format)
+                        .ok()?
+                        .as_str()
+                        .map(ToOwned::to_owned)
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+
     // Internal method for returning the `PermissionRequest` for an oid4vp request.
     async fn permission_request(
         &self,
         request: AuthorizationRequestObject,
     ) -> Result<Arc<PermissionRequest>, OID4VPError> {
         // Resolve the presentation definition.
-        let mut presentation_definition = request
-            .resolve_presentation_definition(self.http_client())
-            .await
-            .map_err(|e| OID4VPError::PresentationDefinitionResolution(format!("{e:?}")))?
-            .into_parsed();
+        let timeout = Duration::from_millis(
+            self.presentation_definition_timeout_ms
+                .load(Ordering::Relaxed),
+        );
+        let mut presentation_definition = tokio::time::timeout(
+            timeout,
+            request.resolve_presentation_definition(self.http_client()),
+        )
+        .await
+        .map_err(|_| OID4VPError::PresentationDefinitionTimeout(timeout.as_millis() as u64))?
+        .map_err(presentation_definition_error)?
+        .into_parsed();
+
+        let transaction_data = parse_transaction_data(&request)?;
+
+        let client_metadata =
+            resolve_client_metadata(&request, self.client.as_ref(), timeout).await?;
 
         let credentials = self
             .search_credentials_vs_presentation_definition(&mut presentation_definition)
             .await?;
 
         if credentials.is_empty() {
+            // Distinguish a request the wallet can never satisfy from a missing credential.
+            if let Some(formats) = self.unsupported_requested_formats(&request) {
+                return Err(OID4VPError::UnsupportedRequestedFormat(formats));
+            }
+
             return Err(OID4VPError::PermissionRequest(
                 PermissionRequestError::NoCredentialsFound,
             ));
         }
 
         // TODO: Add full support for limit_disclosure, probably this should be thrown at OID4VP
-        if presentation_definition
-            .input_descriptors()
-            .iter()
-            .any(|id| {
-                id.constraints
-                    .limit_disclosure()
-                    .is_some_and(|ld| matches!(ld, ConstraintsLimitDisclosure::Required))
-            })
-        {
-            log::debug!("Limit disclosure required for input descriptor.");
-
-            return Err(OID4VPError::LimitDisclosure(
-                "Limit disclosure required for input descriptor.".to_string(),
-            ));
-        }
-
+        //
+        // Credentials which could only be presented with limited disclosure are dropped, as
+        // other credentials may still satisfy the request.
         let credentials = credentials
             .into_iter()
+            .filter(|c| {
+                let limited = requires_limited_disclosure(c, &presentation_definition);
+                if limited {
+                    log::debug!("Limit disclosure required for credential {}.", c.id());
+                }
+                !limited
+            })
             .map(|c| {
                 Arc::new(PresentableCredential {
                     inner: c.inner.clone(),
-                    limit_disclosure: presentation_definition.input_descriptors().iter().any(
-                        |descriptor| {
-                            !c.requested_fields(&presentation_definition).is_empty()
-                                && matches!(
-                                    descriptor.constraints.limit_disclosure(),
-                                    Some(ConstraintsLimitDisclosure::Required)
-                                )
-                        },
-                    ),
+                    limit_disclosure: false,
                     selected_fields: None,
                 })
             })
             .collect::<Vec<_>>();
 
+        if credentials.is_empty() {
+            return Err(OID4VPError::LimitDisclosure(
+                "required by an input descriptor, but only supported for mso_mdoc".to_string(),
+            ));
+        }
+
         Ok(PermissionRequest::new(
             presentation_definition.clone(),
             credentials.clone(),
             request,
             self.signer.clone(),
             self.context_map.clone(),
+            transaction_data,
+            client_metadata,
         ))
     }
 }
@@ -388,6 +955,34 @@ impl RequestVerifier for Holder {
 
         Ok(())
     }
+
+    /// Performs verification on Authorization Request Objects when `client_id_scheme` is
+    /// `x509_san_dns`, validating the request against the certificate in its `x5c` header.
+    async fn x509_san_dns(
+        &self,
+        decoded_request: &AuthorizationRequestObject,
+        request_jwt: String,
+    ) -> anyhow::Result<()> {
+        log::debug!("Verifying x509_san_dns request.");
+
+        let trust_anchors = self
+            .x509_trust_anchors
+            .lock()
+            .map_err(|e| anyhow::anyhow!("failed to access the X.509 trust anchors: {e}"))?
+            .clone();
+        // Without trust anchors, any self-signed certificate naming the client would pass.
+        if trust_anchors.is_empty() {
+            anyhow::bail!("no X.509 trust anchors are configured for x509_san_dns requests");
+        }
+
+        x509_san::validate::<P256Verifier>(
+            X509SanVariant::Dns,
+            &self.metadata,
+            decoded_request,
+            request_jwt,
+            Some(&trust_anchors),
+        )
+    }
 }
 
 impl OID4VPWallet for Holder {
@@ -407,10 +1002,12 @@ pub(crate) mod tests {
     use super::*;
     use crate::{
         context::default_ld_json_context,
+        crypto::{KeyAlias, RustTestKeyManager},
         did::DidMethod,
         oid4vp::presentation::{PresentationError, PresentationSigner},
         tests::{load_jwk, load_signer},
     };
+    use rstest::rstest;
 
     use json_vc::JsonVc;
     use jwt_vc::JwtVc;
@@ -421,6 +1018,27 @@ pub(crate) mod tests {
     };
     use vcdm2_sd_jwt::VCDM2SdJwt;
 
+    /// A `direct_post` authorization request of a `redirect_uri` verifier for `definition`,
+    /// with the given `client_metadata`.
+    pub(crate) fn test_request(
+        definition: impl serde::Serialize,
+        client_metadata: Option<serde_json::Value>,
+    ) -> AuthorizationRequestObject {
+        let mut request = serde_json::json!({
+            "client_id": "https://verifier.example/response",
+            "client_id_scheme": "redirect_uri",
+            "response_type": "vp_token",
+            "response_mode": "direct_post",
+            "response_uri": "https://verifier.example/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition": definition
+        });
+        if let Some(client_metadata) = client_metadata {
+            request["client_metadata"] = client_metadata;
+        }
+        serde_json::from_value(request).unwrap()
+    }
+
     #[derive(Debug)]
     pub(crate) struct KeySigner {
         pub(crate) jwk: JWK,
@@ -474,11 +1092,631 @@ pub(crate) mod tests {
             CryptosuiteString::new("ecdsa-rdfc-2019".to_string()).unwrap()
         }
 
+        fn supported_cryptosuites(&self) -> Vec<CryptosuiteString> {
+            ["ecdsa-rdfc-2019", "JsonWebSignature2020"]
+                .map(|suite| CryptosuiteString::new(suite.to_string()).unwrap())
+                .to_vec()
+        }
+
         fn jwk(&self) -> String {
             serde_json::to_string(&self.jwk.to_public()).unwrap()
         }
     }
 
+    #[rstest]
+    #[case::empty_body(b"".as_slice(), None, None)]
+    #[case::redirect(
+        br#"{"redirect_uri":"https://verifier.example/cb#response_code=abc"}"#.as_slice(),
+        Some("https://verifier.example/cb#response_code=abc"),
+        Some("abc")
+    )]
+    #[case::redirect_query(
+        br#"{"redirect_uri":"https://verifier.example/cb?response_code=abc"}"#.as_slice(),
+        Some("https://verifier.example/cb?response_code=abc"),
+        Some("abc")
+    )]
+    #[case::response_code(br#"{"response_code":"xyz"}"#.as_slice(), None, Some("xyz"))]
+    fn submission_result_from_response(
+        #[case] body: &[u8],
+        #[case] redirect_uri: Option<&str>,
+        #[case] response_code: Option<&str>,
+    ) {
+        let result = SubmissionResult::from_response(200, body).unwrap();
+        assert_eq!(
+            result,
+            SubmissionResult {
+                redirect_uri: redirect_uri.map(|uri| Url::parse(uri).unwrap()),
+                response_code: response_code.map(ToOwned::to_owned),
+                status: 200,
+            }
+        );
+    }
+
+    #[test]
+    fn submits_to_response_uri() {
+        let request = test_request(
+            serde_json::json!({
+                "id": "response-uri-test",
+                "input_descriptors": []
+            }),
+            None,
+        );
+
+        assert_eq!(
+            submission_uri(&request).unwrap().as_str(),
+            "https://verifier.example/response"
+        );
+    }
+
+    #[tokio::test]
+    async fn posts_response_to_response_uri() {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        // A verifier serving a single request, returning its request line.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let verifier = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            reader.read_exact(&mut vec![0; content_length]).unwrap();
+
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                      Content-Length: 2\r\nConnection: close\r\n\r\n{}",
+                )
+                .unwrap();
+            request_line
+        });
+
+        let definition: PresentationDefinition = serde_json::from_value(serde_json::json!({
+            "id": "response-uri-test",
+            "input_descriptors": [{
+                "id": "alumni",
+                "constraints": {
+                    "fields": [{ "path": ["$.credentialSubject.alumniOf"] }]
+                }
+            }]
+        }))
+        .unwrap();
+        let request: AuthorizationRequestObject = serde_json::from_value(serde_json::json!({
+            "client_id": format!("{verifier}/response"),
+            "client_id_scheme": "redirect_uri",
+            "response_type": "vp_token",
+            "response_mode": "direct_post",
+            "response_uri": format!("{verifier}/response"),
+            "redirect_uri": format!("{verifier}/redirect"),
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition": definition
+        }))
+        .unwrap();
+
+        let json_vc =
+            JsonVc::new_from_json(include_str!("../../tests/examples/alumni_vc.json").into())
+                .unwrap();
+        let credential = Arc::new(PresentableCredential {
+            inner: ParsedCredential::new_ldp_vc(json_vc).inner.clone(),
+            limit_disclosure: false,
+            selected_fields: None,
+        });
+        let signer: Box<dyn PresentationSigner> = Box::new(KeySigner { jwk: load_jwk() });
+        let permission_request = PermissionRequest::new(
+            definition,
+            vec![credential.clone()],
+            request,
+            Arc::new(signer),
+            Some(default_ld_json_context()),
+            vec![],
+            None,
+        );
+        let response = permission_request
+            .create_permission_response_from_selections(
+                vec![credential],
+                ResponseOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        let holder = Holder::new_with_credentials(
+            vec![],
+            vec![],
+            Box::new(KeySigner { jwk: load_jwk() }),
+            None,
+        )
+        .await
+        .unwrap();
+        let result = holder.submit_permission_response(response).await.unwrap();
+
+        assert_eq!(result.status, 200);
+        assert!(server
+            .join()
+            .unwrap()
+            .starts_with("POST /response HTTP/1.1"));
+    }
+
+    fn unsigned_dc_api_request(request: &str, origin: &str) -> AuthorizationRequestObject {
+        match parse_dc_api_request(request, origin).unwrap() {
+            DcApiRequest::Unsigned(request) => request,
+            DcApiRequest::Signed { .. } => panic!("request should be unsigned"),
+        }
+    }
+
+    #[test]
+    fn parses_dc_api_request() {
+        let request = serde_json::json!({
+            "response_type": "vp_token",
+            "response_mode": "dc_api",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition": {
+                "id": "dc-api-test",
+                "input_descriptors": []
+            }
+        })
+        .to_string();
+
+        let parsed = unsigned_dc_api_request(&request, "https://verifier.example");
+        assert!(is_dc_api(&parsed));
+        assert_eq!(parsed.client_id().0, "web-origin:https://verifier.example");
+
+        for mode in ["direct_post", "dc_api.jwt"] {
+            let request = request.replace("\"dc_api\"", &format!("\"{mode}\""));
+            assert!(matches!(
+                parse_dc_api_request(&request, "https://verifier.example"),
+                Err(OID4VPError::UnsupportedResponseMode(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn unsigned_dc_api_request_is_attributed_to_origin() {
+        let request = serde_json::json!({
+            "client_id": "x509_san_dns:verifier.example",
+            "client_id_scheme": "x509_san_dns",
+            "expected_origins": ["https://verifier.example"],
+            "response_type": "vp_token",
+            "response_mode": "dc_api",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition": {
+                "id": "dc-api-test",
+                "input_descriptors": []
+            }
+        })
+        .to_string();
+
+        let parsed = unsigned_dc_api_request(&request, "https://verifier.example");
+        assert_eq!(parsed.client_id().0, "web-origin:https://verifier.example");
+        assert!(matches!(
+            parse_dc_api_request(&request, "https://attacker.example"),
+            Err(OID4VPError::RequestValidation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn signed_dc_api_request_is_verified() {
+        let payload = |extra: serde_json::Value| {
+            let mut payload = serde_json::json!({
+                "client_id": "verifier.example",
+                "client_id_scheme": "x509_san_dns",
+                "response_type": "vp_token",
+                "response_mode": "dc_api",
+                "nonce": "n-0S6_WzA2Mj",
+                "presentation_definition": {
+                    "id": "dc-api-test",
+                    "input_descriptors": []
+                }
+            });
+            payload
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            let jwt = format!(
+                "eyJhbGciOiJFUzI1NiJ9.{}.c2lnbmF0dXJl",
+                URL_SAFE_NO_PAD.encode(payload.to_string())
+            );
+            serde_json::json!({ "request": jwt }).to_string()
+        };
+
+        // Signed requests must name the origins they may be used from.
+        assert!(matches!(
+            parse_dc_api_request(&payload(serde_json::json!({})), "https://verifier.example"),
+            Err(OID4VPError::RequestValidation(_))
+        ));
+
+        let request = payload(serde_json::json!({
+            "expected_origins": ["https://verifier.example"]
+        }));
+        match parse_dc_api_request(&request, "https://verifier.example").unwrap() {
+            DcApiRequest::Signed {
+                request,
+                client_id_scheme,
+                ..
+            } => {
+                assert_eq!(request.client_id().0, "verifier.example");
+                assert_eq!(client_id_scheme, "x509_san_dns");
+            }
+            DcApiRequest::Unsigned(_) => panic!("request should be signed"),
+        }
+
+        // The signature cannot be trusted without trust anchors.
+        let holder = Holder::new_with_credentials(
+            vec![],
+            vec![],
+            Box::new(KeySigner { jwk: load_jwk() }),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            holder
+                .dc_api_request(request, "https://verifier.example".into())
+                .await,
+            Err(OID4VPError::RequestValidation(_))
+        ));
+    }
+
+    #[test]
+    fn metadata_only_advertises_matching_formats() {
+        let metadata = Holder::metadata().unwrap();
+        let formats = serde_json::to_value(metadata.vp_formats_supported()).unwrap();
+
+        assert!(formats.get("vcdm2_sd_jwt").is_some());
+        assert!(formats.get("dc+sd-jwt").is_none());
+    }
+
+    async fn request_with_vp_formats(
+        vp_formats: serde_json::Value,
+    ) -> Result<Arc<PermissionRequest>, OID4VPError> {
+        let request = test_request(
+            serde_json::json!({
+                "id": "vp-formats-test",
+                "input_descriptors": [{
+                    "id": "employee",
+                    "constraints": {
+                        "fields": [{ "path": ["$.credentialSubject.employer"] }]
+                    }
+                }]
+            }),
+            Some(serde_json::json!({ "vp_formats": vp_formats })),
+        );
+
+        let holder = Holder::new_with_credentials(
+            vec![],
+            vec![],
+            Box::new(KeySigner { jwk: load_jwk() }),
+            None,
+        )
+        .await
+        .unwrap();
+
+        holder
+            .authorization_request(AuthRequest::Request(Box::new(request)))
+            .await
+    }
+
+    #[tokio::test]
+    async fn permission_request_rejects_unsupported_formats() {
+        let result = request_with_vp_formats(serde_json::json!({
+            "mso_mdoc": { "alg": ["ES256"] }
+        }))
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(OID4VPError::UnsupportedRequestedFormat(format)) if format.contains("mso_mdoc")
+        ));
+    }
+
+    #[tokio::test]
+    async fn permission_request_reports_missing_credentials() {
+        let result = request_with_vp_formats(serde_json::json!({
+            "ldp_vp": { "proof_type": ["ecdsa-rdfc-2019"] }
+        }))
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(OID4VPError::PermissionRequest(
+                PermissionRequestError::NoCredentialsFound
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn permission_response_is_signed_by_the_override_signer() {
+        let request = test_request(
+            serde_json::json!({
+                "id": "override-signer-test",
+                "input_descriptors": [{
+                    "id": "alumni",
+                    "constraints": {
+                        "fields": [{ "path": ["$.credentialSubject.alumniOf"] }]
+                    }
+                }]
+            }),
+            Some(serde_json::json!({
+                "vp_formats": { "ldp_vp": { "proof_type": ["ecdsa-rdfc-2019"] } }
+            })),
+        );
+        let alumni_vc = ParsedCredential::new_ldp_vc(
+            JsonVc::new_from_json(include_str!("../../tests/examples/alumni_vc.json").into())
+                .unwrap(),
+        );
+
+        // The holder's own key must not be used to sign this response.
+        let holder_signer = KeySigner {
+            jwk: JWK::generate_p256(),
+        };
+        let holder_did = holder_signer.did();
+        let holder = Holder::new_with_credentials(
+            vec![alumni_vc],
+            vec![],
+            Box::new(holder_signer),
+            Some(default_ld_json_context()),
+        )
+        .await
+        .unwrap();
+        let permission_request = holder
+            .authorization_request(AuthRequest::Request(Box::new(request)))
+            .await
+            .unwrap();
+
+        let selected_credentials = permission_request
+            .credentials()
+            .iter()
+            .map(|credential| {
+                let fields = permission_request
+                    .requested_fields(credential)
+                    .iter()
+                    .map(|field| field.path())
+                    .collect();
+                credential.with_selected_fields(fields)
+            })
+            .collect();
+        let override_signer = load_signer();
+        let override_did = override_signer.did();
+        assert_ne!(holder_did, override_did);
+
+        let response = permission_request
+            .create_permission_response_with_signer(
+                selected_credentials,
+                ResponseOptions::default(),
+                Box::new(override_signer),
+            )
+            .await
+            .unwrap();
+
+        let vp_token = serde_json::to_value(&response.vp_token).unwrap();
+        let presentation = vp_token.as_array().map_or(&vp_token, |items| &items[0]);
+        assert_eq!(presentation["holder"], override_did);
+        assert!(presentation["proof"]["verificationMethod"]
+            .as_str()
+            .is_some_and(|vm| vm.starts_with(&override_did)));
+    }
+
+    #[test]
+    fn limit_disclosure_is_decided_by_the_matched_credential() {
+        let definition: PresentationDefinition = serde_json::from_value(serde_json::json!({
+            "id": "limit-disclosure-test",
+            "input_descriptors": [
+                {
+                    "id": "mdl",
+                    "format": { "mso_mdoc": { "alg": ["ES256"] } },
+                    "constraints": {
+                        "limit_disclosure": "required",
+                        "fields": [{ "path": ["$.credentialSubject.alumniOf"] }]
+                    }
+                }
+            ]
+        }))
+        .unwrap();
+
+        // A credential that is not an mdoc cannot limit disclosure, whatever format the
+        // descriptor claims to accept.
+        let json_vc =
+            JsonVc::new_from_json(include_str!("../../tests/examples/alumni_vc.json").into())
+                .unwrap();
+        assert!(requires_limited_disclosure(
+            &ParsedCredential::new_ldp_vc(json_vc),
+            &definition
+        ));
+    }
+
+    #[tokio::test]
+    async fn limit_disclosure_only_applies_to_the_matched_input_descriptors() {
+        let definition: PresentationDefinition = serde_json::from_value(serde_json::json!({
+            "id": "limit-disclosure-test",
+            "input_descriptors": [
+                {
+                    "id": "mdl",
+                    "format": { "mso_mdoc": { "alg": ["ES256"] } },
+                    "constraints": {
+                        "limit_disclosure": "required",
+                        "fields": [{ "path": ["$.credentialSubject.driversLicense"] }]
+                    }
+                },
+                {
+                    "id": "drivers_license",
+                    "constraints": {
+                        "fields": [{ "path": ["$.credentialSubject.driversLicense"] }]
+                    }
+                }
+            ]
+        }))
+        .unwrap();
+
+        // The JWT VC also matches an input descriptor that does not limit disclosure.
+        let jwt_vc = ParsedCredential::new_jwt_vc_json_ld(
+            JwtVc::new_from_compact_jws(include_str!("../../tests/examples/mdl.jwt").into())
+                .unwrap(),
+        );
+        assert!(!requires_limited_disclosure(&jwt_vc, &definition));
+
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        let key_alias = KeyAlias("limit-disclosure".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+        let mdoc = ParsedCredential::new_mso_mdoc(Arc::new(
+            crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap(),
+        ));
+        assert!(!requires_limited_disclosure(&mdoc, &definition));
+    }
+
+    async fn request_with_definition_uri(
+        uri: String,
+        timeout_ms: u64,
+    ) -> Result<Arc<PermissionRequest>, OID4VPError> {
+        let request: AuthorizationRequestObject = serde_json::from_value(serde_json::json!({
+            "client_id": "https://verifier.example/response",
+            "client_id_scheme": "redirect_uri",
+            "response_type": "vp_token",
+            "response_mode": "direct_post",
+            "response_uri": "https://verifier.example/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition_uri": uri
+        }))
+        .unwrap();
+
+        let holder = Holder::new_with_credentials(
+            vec![],
+            vec![],
+            Box::new(KeySigner { jwk: load_jwk() }),
+            None,
+        )
+        .await
+        .unwrap();
+        holder.set_presentation_definition_timeout(timeout_ms);
+
+        holder
+            .authorization_request(AuthRequest::Request(Box::new(request)))
+            .await
+    }
+
+    #[tokio::test]
+    async fn presentation_definition_fetch_times_out() {
+        // The listener accepts connections but never responds.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/definition", listener.local_addr().unwrap());
+
+        assert!(matches!(
+            request_with_definition_uri(uri, 100).await,
+            Err(OID4VPError::PresentationDefinitionTimeout(100))
+        ));
+    }
+
+    #[tokio::test]
+    async fn holder_with_credentials_shares_signer() {
+        let holder = Holder::new_with_credentials(
+            vec![],
+            vec![],
+            Box::new(KeySigner { jwk: load_jwk() }),
+            None,
+        )
+        .await
+        .unwrap();
+        holder.set_presentation_definition_timeout(1_000);
+
+        let json_vc =
+            JsonVc::new_from_json(include_str!("../../tests/examples/alumni_vc.json").into())
+                .unwrap();
+        let reused = holder
+            .with_credentials(vec![ParsedCredential::new_ldp_vc(json_vc)])
+            .unwrap();
+
+        assert!(Arc::ptr_eq(&holder.signer, &reused.signer));
+        assert_eq!(reused.provided_credentials.as_ref().map(Vec::len), Some(1));
+        assert_eq!(
+            reused
+                .presentation_definition_timeout_ms
+                .load(Ordering::Relaxed),
+            1_000
+        );
+    }
+
+    #[tokio::test]
+    async fn presentation_definition_unreachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/definition", listener.local_addr().unwrap());
+        drop(listener);
+
+        assert!(matches!(
+            request_with_definition_uri(uri, 5_000).await,
+            Err(OID4VPError::PresentationDefinitionUnreachable(_))
+        ));
+    }
+
+    #[test]
+    fn metadata_advertises_x509_san_dns() {
+        let metadata = serde_json::to_value(Holder::metadata().unwrap()).unwrap();
+
+        assert!(metadata["client_id_schemes_supported"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("x509_san_dns")));
+        assert!(metadata["request_object_signing_alg_values_supported"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("ES256")));
+    }
+
+    #[tokio::test]
+    async fn x509_san_dns_requires_trust_anchors() {
+        let holder = Holder::new_with_credentials(
+            vec![],
+            vec![],
+            Box::new(KeySigner { jwk: load_jwk() }),
+            None,
+        )
+        .await
+        .unwrap();
+        let request: AuthorizationRequestObject = serde_json::from_value(serde_json::json!({
+            "client_id": "verifier.example",
+            "client_id_scheme": "x509_san_dns",
+            "response_type": "vp_token",
+            "response_mode": "direct_post",
+            "response_uri": "https://verifier.example/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition": { "id": "x509-test", "input_descriptors": [] }
+        }))
+        .unwrap();
+
+        let error = holder
+            .x509_san_dns(&request, String::new())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("trust anchors"));
+
+        assert!(matches!(
+            holder.set_x509_trust_anchors(vec!["not a certificate".into()]),
+            Err(OID4VPError::X509TrustAnchor(_))
+        ));
+    }
+
+    #[test]
+    fn submission_result_rejects_error_status() {
+        assert!(matches!(
+            SubmissionResult::from_response(400, br#"{"error":"invalid_request"}"#),
+            Err(OID4VPError::ResponseSubmission(_))
+        ));
+    }
+
     // NOTE: This test requires the `companion` service to be running and
     // available at localhost:3000.
     //
@@ -27,19 +27,25 @@ const SUPPORTED_ENC: &str = "A256GCM";
 pub fn build_response(
     request: &AuthorizationRequestObject,
     presentation_definition: &PresentationDefinition,
+    input_descriptor_ids: Vec<String>,
     device_response: DeviceResponse,
     mdoc_generated_nonce: String,
 ) -> Result<AuthorizationResponse> {
-    let descriptor_map = DescriptorMap {
-        id: "org.iso.18013.5.1.mDL".to_string(),
-        format: MsoMDoc,
-        path: JsonPath::default(),
-        path_nested: None,
-    };
+    // Every document is carried in the same DeviceResponse, so each descriptor points at the
+    // root of the vp_token.
+    let descriptor_map = input_descriptor_ids
+        .into_iter()
+        .map(|id| DescriptorMap {
+            id,
+            format: MsoMDoc,
+            path: JsonPath::default(),
+            path_nested: None,
+        })
+        .collect();
     let presentation_submission = PresentationSubmission::new(
         Uuid::new_v4(),
         presentation_definition.id().clone(),
-        vec![descriptor_map],
+        descriptor_map,
     );
 
     let device_response = BASE64_URL_SAFE_NO_PAD.encode(
@@ -126,19 +132,7 @@ fn build_jwe(
         jwe_header.set_key_id(kid);
     }
 
-    let mut jwe_payload = JwtPayload::new();
-    jwe_payload.set_claim("vp_token", Some(vp_token))?;
-    jwe_payload.set_claim(
-        "presentation_submission",
-        Some(json!(presentation_submission)),
-    )?;
-
-    if let Some(state) = request.get::<State>() {
-        jwe_payload.set_claim(
-            "state",
-            Some(serde_json::Value::String(state.parsing_error()?.0)),
-        )?;
-    }
+    let jwe_payload = response_payload(request, vp_token, presentation_submission)?;
 
     tracing::debug!(
         "JWE payload:\n{}",
@@ -152,3 +146,76 @@ fn build_jwe(
 
     Ok(jwe)
 }
+
+/// The claims of the encrypted authorization response.
+///
+/// The request's `state` is echoed back so the verifier can correlate the response with its
+/// request.
+fn response_payload(
+    request: &AuthorizationRequestObject,
+    vp_token: Json,
+    presentation_submission: &PresentationSubmission,
+) -> Result<JwtPayload> {
+    let mut payload = JwtPayload::new();
+    payload.set_claim("vp_token", Some(vp_token))?;
+    payload.set_claim(
+        "presentation_submission",
+        Some(json!(presentation_submission)),
+    )?;
+
+    if let Some(state) = request.get::<State>() {
+        payload.set_claim(
+            "state",
+            Some(serde_json::Value::String(state.parsing_error()?.0)),
+        )?;
+    }
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(state: Option<&str>) -> AuthorizationRequestObject {
+        let mut request = json!({
+            "client_id": "verifier.example",
+            "client_id_scheme": "x509_san_dns",
+            "response_type": "vp_token",
+            "response_mode": "direct_post.jwt",
+            "response_uri": "https://verifier.example/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition": {
+                "id": "mdl-test",
+                "input_descriptors": []
+            }
+        });
+        if let Some(state) = state {
+            request["state"] = state.into();
+        }
+        serde_json::from_value(request).unwrap()
+    }
+
+    #[test]
+    fn response_payload_echoes_state() {
+        let presentation_submission =
+            PresentationSubmission::new(Uuid::new_v4(), "mdl-test".to_string(), vec![]);
+
+        let payload = response_payload(
+            &request(Some("session-42")),
+            Json::String("device-response".into()),
+            &presentation_submission,
+        )
+        .unwrap();
+        assert_eq!(payload.claim("state"), Some(&json!("session-42")));
+        assert_eq!(payload.claim("vp_token"), Some(&json!("device-response")));
+
+        let payload = response_payload(
+            &request(None),
+            Json::String("device-response".into()),
+            &presentation_submission,
+        )
+        .unwrap();
+        assert_eq!(payload.claim("state"), None);
+    }
+}
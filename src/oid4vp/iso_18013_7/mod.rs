@@ -1,8 +1,8 @@
 //! CLI test wallet for the 18013-7 Annex B OpenID4VP profile.
 
 mod build_response;
-mod prepare_response;
-mod requested_values;
+pub(crate) mod prepare_response;
+pub(crate) mod requested_values;
 
 use core::fmt;
 use std::sync::Arc;
@@ -11,6 +11,7 @@ use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use base64::prelude::*;
 use build_response::build_response;
+use isomdl::definitions::device_response::DeviceResponse;
 use openid4vp::{
     core::{
         authorization_request::{
@@ -24,7 +25,7 @@ use openid4vp::{
     },
     wallet::Wallet as OpenID4VPWallet,
 };
-use prepare_response::prepare_response;
+use prepare_response::{prepare_response, ApprovedDocument};
 use requested_values::{parse_request, FieldId180137, RequestMatch180137};
 use serde_json::json;
 use ssi::crypto::rand::{thread_rng, Rng};
@@ -33,9 +34,16 @@ use uuid::Uuid;
 
 use crate::{credential::mdoc::Mdoc, crypto::KeyStore};
 
+/// URI scheme advertised as the wallet's `authorization_endpoint`.
+const AUTHORIZATION_ENDPOINT_SCHEME: &str = "mdoc-openid4vp";
+
+/// URI schemes accepted for incoming requests.
+const REQUEST_SCHEMES: &[&str] = &[AUTHORIZATION_ENDPOINT_SCHEME, "openid4vp", "haip"];
+
 /// Handler for OpenID4VP requests according to the profile in ISO/IEC 18013-7 Annex B.
 ///
-/// Notably this supports requests which use the URI scheme `mdoc-openid4vp://`.
+/// Notably this supports requests which use the URI scheme `mdoc-openid4vp://`, as well
+/// as mdoc requests sent with the plain `openid4vp://` and `haip://` schemes.
 #[derive(uniffi::Object, Clone)]
 pub struct OID4VP180137 {
     credentials: Vec<Arc<Mdoc>>,
@@ -121,6 +129,8 @@ impl OID4VP180137 {
 
 impl OID4VP180137 {
     async fn process_request_inner(&self, url: Url) -> Result<InProgressRequest180137> {
+        let url = normalize_request_url(url)?;
+
         let request = self
             .validate_request(url)
             .await
@@ -156,7 +166,18 @@ impl InProgressRequest180137 {
         &self,
         approved_response: ApprovedResponse180137,
     ) -> Result<Option<Url>, OID4VP180137Error> {
-        self.respond_inner(approved_response)
+        self.respond_inner(vec![approved_response])
+            .await
+            .map_err(OID4VP180137Error::response_processing)
+    }
+
+    /// Respond with one document per approved credential, for requests that contain multiple
+    /// input descriptors (e.g. an mDL and a photo ID).
+    pub async fn respond_multiple(
+        &self,
+        approved_responses: Vec<ApprovedResponse180137>,
+    ) -> Result<Option<Url>, OID4VP180137Error> {
+        self.respond_inner(approved_responses)
             .await
             .map_err(OID4VP180137Error::response_processing)
     }
@@ -169,43 +190,17 @@ impl InProgressRequest180137 {
 impl InProgressRequest180137 {
     async fn respond_inner(
         &self,
-        approved_response: ApprovedResponse180137,
+        approved_responses: Vec<ApprovedResponse180137>,
     ) -> Result<Option<Url>> {
-        let credential = self
-            .handler
-            .credentials
-            .iter()
-            .find(|credential| credential.id() == approved_response.credential_id)
-            .context("selected credential not found")?;
-
-        let approved_fields = approved_response.approved_fields;
-        let request_match = self
-            .request_matches
-            .iter()
-            .find(|request_match| request_match.credential_id == approved_response.credential_id)
-            .context("selected credential not found")?;
-
-        request_match.requested_fields.iter()
-            .filter(|field| field.required)
-            .filter(|field| !approved_fields.contains(&field.id))
-            .for_each(|field| log::warn!("required field '{}' was not approved, this may result in an error from the verifier", field.displayable_name));
-
-        let field_map = request_match.field_map.clone();
         let mdoc_generated_nonce = generate_nonce();
 
-        let device_response = prepare_response(
-            self.handler.keystore.clone(),
-            &self.request,
-            credential,
-            approved_fields,
-            &request_match.missing_fields,
-            field_map,
-            mdoc_generated_nonce.clone(),
-        )?;
+        let (input_descriptor_ids, device_response) =
+            self.prepare_device_response(approved_responses, mdoc_generated_nonce.clone())?;
 
         let response = build_response(
             &self.request,
             &self.presentation_definition,
+            input_descriptor_ids,
             device_response,
             mdoc_generated_nonce,
         )?;
@@ -214,6 +209,68 @@ impl InProgressRequest180137 {
             .submit_response(self.request.clone(), response)
             .await
     }
+
+    /// Build a `DeviceResponse` with one document per approved credential, along with the ids
+    /// of the input descriptors the documents respond to, in the same order.
+    fn prepare_device_response(
+        &self,
+        approved_responses: Vec<ApprovedResponse180137>,
+        mdoc_generated_nonce: String,
+    ) -> Result<(Vec<String>, DeviceResponse)> {
+        if approved_responses.is_empty() {
+            bail!("no credentials were approved")
+        }
+
+        let mut input_descriptor_ids = Vec::with_capacity(approved_responses.len());
+        let mut approved_documents = Vec::with_capacity(approved_responses.len());
+
+        for approved_response in approved_responses {
+            let credential = self
+                .handler
+                .credentials
+                .iter()
+                .find(|credential| credential.id() == approved_response.credential_id)
+                .context("selected credential not found")?;
+
+            let approved_fields = approved_response.approved_fields;
+            let request_match = self
+                .request_matches
+                .iter()
+                .find(|request_match| {
+                    request_match.credential_id == approved_response.credential_id
+                })
+                .context("selected credential not found")?;
+
+            if input_descriptor_ids.contains(&request_match.input_descriptor_id) {
+                bail!(
+                    "multiple credentials were approved for input descriptor '{}'",
+                    request_match.input_descriptor_id
+                )
+            }
+
+            request_match.requested_fields.iter()
+                .filter(|field| field.required)
+                .filter(|field| !approved_fields.contains(&field.id))
+                .for_each(|field| log::warn!("required field '{}' was not approved, this may result in an error from the verifier", field.displayable_name));
+
+            input_descriptor_ids.push(request_match.input_descriptor_id.clone());
+            approved_documents.push(ApprovedDocument {
+                credential,
+                approved_fields,
+                missing_fields: &request_match.missing_fields,
+                field_map: request_match.field_map.clone(),
+            });
+        }
+
+        let device_response = prepare_response(
+            self.handler.keystore.clone(),
+            &self.request,
+            approved_documents,
+            mdoc_generated_nonce,
+        )?;
+
+        Ok((input_descriptor_ids, device_response))
+    }
 }
 
 impl OpenID4VPWallet for OID4VP180137 {
@@ -245,6 +302,22 @@ impl RequestVerifier for OID4VP180137 {
     }
 }
 
+/// Check that a request URL uses one of the accepted schemes, and rewrite it to the scheme of the
+/// advertised `authorization_endpoint` so every accepted scheme is validated the same way.
+fn normalize_request_url(mut url: Url) -> Result<Url> {
+    if !REQUEST_SCHEMES.contains(&url.scheme()) {
+        bail!("unsupported request URI scheme '{}'", url.scheme())
+    }
+
+    if url.scheme() != AUTHORIZATION_ENDPOINT_SCHEME
+        && url.set_scheme(AUTHORIZATION_ENDPOINT_SCHEME).is_err()
+    {
+        bail!("failed to normalize request URI scheme '{}'", url.scheme())
+    }
+
+    Ok(url)
+}
+
 fn generate_nonce() -> String {
     let nonce_bytes = thread_rng().gen::<[u8; 16]>();
     BASE64_URL_SAFE_NO_PAD.encode(nonce_bytes)
@@ -253,7 +326,7 @@ fn generate_nonce() -> String {
 fn default_metadata() -> WalletMetadata {
     let metadata_json = json!({
         "issuer": "https://self-issued.me/v2",
-        "authorization_endpoint": "mdoc-openid4vp://",
+        "authorization_endpoint": format!("{AUTHORIZATION_ENDPOINT_SCHEME}://"),
         "response_types_supported": [
             "vp_token"
         ],
@@ -279,9 +352,152 @@ fn default_metadata() -> WalletMetadata {
 
 #[cfg(test)]
 mod test {
+    use std::{fs::File, sync::Arc};
+
+    use openid4vp::core::{
+        authorization_request::AuthorizationRequestObject,
+        presentation_definition::PresentationDefinition,
+    };
+    use serde_json::json;
+    use url::Url;
+
+    use super::{
+        generate_nonce, parse_request, ApprovedResponse180137, InProgressRequest180137,
+        RequestMatch180137, OID4VP180137,
+    };
+    use crate::{
+        crypto::{KeyAlias, RustTestKeyManager},
+        mdl::util::{generate_test_mdl, generate_test_mdoc, TestMdocConfig},
+    };
 
     #[test]
     fn default_metadata() {
         super::default_metadata();
     }
+
+    #[rstest::rstest]
+    #[case::mdoc("mdoc-openid4vp://?request_uri=https%3A%2F%2Fverifier.example%2Frequest")]
+    #[case::plain("openid4vp://?request_uri=https%3A%2F%2Fverifier.example%2Frequest")]
+    #[case::haip("haip://?request_uri=https%3A%2F%2Fverifier.example%2Frequest")]
+    fn normalizes_request_schemes(#[case] url: Url) {
+        let url = super::normalize_request_url(url).unwrap();
+
+        assert_eq!(url.scheme(), "mdoc-openid4vp");
+        assert_eq!(
+            url.query(),
+            Some("request_uri=https%3A%2F%2Fverifier.example%2Frequest")
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_request_scheme() {
+        let url = Url::parse("https://verifier.example/request").unwrap();
+        assert!(super::normalize_request_url(url).is_err());
+    }
+
+    #[tokio::test]
+    async fn responds_with_one_document_per_approved_credential() {
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        let key_alias = KeyAlias("".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let mdl = generate_test_mdl(key_manager.clone(), key_alias.clone()).unwrap();
+        let photo_id = generate_test_mdoc(
+            key_manager.clone(),
+            key_alias,
+            TestMdocConfig {
+                doc_type: "org.iso.23220.photoid.1".into(),
+                namespaces: json!({ "org.iso.23220.1": { "family_name_unicode": "Smith" } })
+                    .to_string(),
+                valid_from_offset_seconds: 0,
+                valid_until_offset_seconds: 3600,
+            },
+        )
+        .unwrap();
+        let credentials = vec![Arc::new(mdl), Arc::new(photo_id)];
+
+        // Request a photo ID alongside the mDL.
+        let mut presentation_definition: serde_json::Value = serde_json::from_reader(
+            File::open("tests/examples/18013_7_presentation_definition.json").unwrap(),
+        )
+        .unwrap();
+        presentation_definition["input_descriptors"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({
+                "id": "org.iso.23220.photoid.1",
+                "constraints": {
+                    "fields": [{
+                        "path": ["$['org.iso.23220.1']['family_name_unicode']"],
+                        "intent_to_retain": false
+                    }],
+                    "limit_disclosure": "required"
+                },
+                "format": { "mso_mdoc": { "alg": ["ES256"] } }
+            }));
+        let request: AuthorizationRequestObject = serde_json::from_value(json!({
+            "client_id": "verifier.example",
+            "client_id_scheme": "x509_san_dns",
+            "response_type": "vp_token",
+            "response_mode": "direct_post.jwt",
+            "response_uri": "https://verifier.example/response",
+            "nonce": "n-0S6_WzA2Mj",
+            "presentation_definition": presentation_definition
+        }))
+        .unwrap();
+        let presentation_definition: PresentationDefinition =
+            serde_json::from_value(presentation_definition).unwrap();
+
+        let request_matches = parse_request(
+            &presentation_definition,
+            credentials.iter().map(|credential| credential.as_ref()),
+        );
+        assert_eq!(request_matches.len(), 2);
+
+        let in_progress = InProgressRequest180137 {
+            request,
+            presentation_definition,
+            request_matches: request_matches.clone(),
+            handler: OID4VP180137::new(credentials, key_manager).unwrap(),
+        };
+        let approve = |request_match: &RequestMatch180137| ApprovedResponse180137 {
+            credential_id: request_match.credential_id,
+            approved_fields: request_match.required_field_ids(),
+        };
+
+        // Documents follow the order of the approvals, not of the input descriptors.
+        let (input_descriptor_ids, device_response) = in_progress
+            .prepare_device_response(
+                request_matches
+                    .iter()
+                    .rev()
+                    .map(|request_match| approve(request_match.as_ref()))
+                    .collect(),
+                generate_nonce(),
+            )
+            .unwrap();
+        let doc_types: Vec<_> = device_response
+            .documents
+            .unwrap()
+            .iter()
+            .map(|document| document.doc_type.clone())
+            .collect();
+        assert_eq!(
+            input_descriptor_ids,
+            vec!["org.iso.23220.photoid.1", "org.iso.18013.5.1.mDL"]
+        );
+        assert_eq!(doc_types, input_descriptor_ids);
+
+        let duplicate = in_progress.prepare_device_response(
+            vec![approve(&request_matches[0]), approve(&request_matches[0])],
+            generate_nonce(),
+        );
+        assert!(duplicate
+            .unwrap_err()
+            .to_string()
+            .contains("multiple credentials were approved"));
+    }
 }
@@ -22,7 +22,7 @@ use serde_json::Value as Json;
 use sha2::{Digest, Sha256};
 use ssi::claims::cose::coset::{self, CoseSign1Builder};
 
-use crate::crypto::KeyStore;
+use crate::crypto::{CryptoCurveUtils, KeyStore};
 
 use super::{
     requested_values::{FieldId180137, FieldMap},
@@ -89,7 +89,7 @@ impl SessionTranscript {
 /// Unprocessed response_uri for use in the Handover. We don't use the default response uri type to
 /// avoid signature errors that could be caused by URL normalisation through the Url type.
 #[derive(Debug, Clone)]
-struct RawResponseUri(String);
+pub(crate) struct RawResponseUri(pub(crate) String);
 
 impl TypedParameter for RawResponseUri {
     const KEY: &'static str = "response_uri";
@@ -113,15 +113,62 @@ impl From<RawResponseUri> for Json {
     }
 }
 
+/// A credential selected to answer one of the input descriptors in the request.
+pub struct ApprovedDocument<'a> {
+    pub credential: &'a Mdoc,
+    pub approved_fields: Vec<FieldId180137>,
+    pub missing_fields: &'a BTreeMap<String, String>,
+    pub field_map: FieldMap,
+}
+
+/// Build a `DeviceResponse` containing one document per approved credential.
 pub fn prepare_response(
     key_store: Arc<dyn KeyStore>,
     request: &AuthorizationRequestObject,
-    credential: &Mdoc,
-    approved_fields: Vec<FieldId180137>,
-    missing_fields: &BTreeMap<String, String>,
-    mut field_map: FieldMap,
+    approved_documents: Vec<ApprovedDocument>,
     mdoc_generated_nonce: String,
 ) -> Result<DeviceResponse> {
+    let client_id = &request.client_id().0;
+    let response_uri = request.get::<RawResponseUri>().parsing_error()?.0;
+
+    let nonce = request.nonce().to_string();
+
+    let handover = Handover::new(client_id.clone(), response_uri, nonce, mdoc_generated_nonce)
+        .context("failed to generate handover")?;
+
+    let session_transcript = SessionTranscript::new(handover);
+
+    let documents = approved_documents
+        .into_iter()
+        .map(|approved_document| {
+            prepare_document(key_store.as_ref(), &session_transcript, approved_document)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let documents = NonEmptyVec::maybe_new(documents).context("no approved documents")?;
+
+    let response = DeviceResponse {
+        version: "1.0".into(),
+        documents: Some(documents),
+        document_errors: None,
+        status: isomdl::definitions::device_response::Status::OK,
+    };
+
+    Ok(response)
+}
+
+fn prepare_document(
+    key_store: &dyn KeyStore,
+    session_transcript: &SessionTranscript,
+    approved_document: ApprovedDocument,
+) -> Result<Document> {
+    let ApprovedDocument {
+        credential,
+        approved_fields,
+        missing_fields,
+        mut field_map,
+    } = approved_document;
+
     let mdoc = credential.document();
 
     let mut revealed_namespaces: BTreeMap<String, NonEmptyVec<Tag24<IssuerSignedItem>>> =
@@ -151,18 +198,8 @@ pub fn prepare_response(
     let device_namespaces = Tag24::new(DeviceNamespaces::new())
         .context("failed to encode device namespaces as CBOR")?;
 
-    let client_id = &request.client_id().0;
-    let response_uri = request.get::<RawResponseUri>().parsing_error()?.0;
-
-    let nonce = request.nonce().to_string();
-
-    let handover = Handover::new(client_id.clone(), response_uri, nonce, mdoc_generated_nonce)
-        .context("failed to generate handover")?;
-
-    let session_transcript = SessionTranscript::new(handover);
-
     let device_authentication_payload = Tag24::new(DeviceAuthentication::new(
-        session_transcript,
+        session_transcript.clone(),
         mdoc.mso.doc_type.clone(),
         device_namespaces.clone(),
     ))
@@ -196,6 +233,13 @@ pub fn prepare_response(
         .sign(prepared_cose_sign1.signature_payload().to_vec())
         .context("failed to generate device_signature")?;
 
+    // COSE signatures are raw fixed-width, and verifiers may require a low S.
+    let utils = CryptoCurveUtils::secp256r1();
+    let signature = utils
+        .ensure_raw_fixed_width_signature_encoding(signature)
+        .map(|signature| utils.normalize_low_s(signature))
+        .context("device_signature has an unsupported encoding")?;
+
     let device_signature = prepared_cose_sign1.finalize(signature);
 
     let device_auth = isomdl::definitions::DeviceAuth::DeviceSignature(device_signature);
@@ -231,14 +275,5 @@ pub fn prepare_response(
         errors: NonEmptyMap::maybe_new(errors),
     };
 
-    let documents = NonEmptyVec::new(document);
-
-    let response = DeviceResponse {
-        version: "1.0".into(),
-        documents: Some(documents),
-        document_errors: None,
-        status: isomdl::definitions::device_response::Status::OK,
-    };
-
-    Ok(response)
+    Ok(document)
 }
@@ -18,6 +18,7 @@ use crate::credential::mdoc::Mdoc;
 /// A viable match for the credential request.
 pub struct RequestMatch180137 {
     pub credential_id: Uuid,
+    pub input_descriptor_id: String,
     pub field_map: FieldMap,
     pub requested_fields: Vec<RequestedField180137>,
     pub missing_fields: BTreeMap<String, String>,
@@ -47,9 +48,40 @@ impl RequestMatch180137 {
         self.credential_id
     }
 
+    /// The ID of the input descriptor (the requested doctype) that this credential matched.
+    pub fn input_descriptor_id(&self) -> String {
+        self.input_descriptor_id.clone()
+    }
+
     pub fn requested_fields(&self) -> Vec<RequestedField180137> {
         self.requested_fields.clone()
     }
+
+    /// The fields the verifier marked as required. These must all be included in the
+    /// `approved_fields` of an `ApprovedResponse180137` for the response to satisfy the request.
+    pub fn required_field_ids(&self) -> Vec<FieldId180137> {
+        self.requested_fields
+            .iter()
+            .filter(|field| field.required)
+            .map(|field| field.id.clone())
+            .collect()
+    }
+
+    /// The smallest set of fields that satisfies the request: every required field and none of
+    /// the optional ones.
+    ///
+    /// Use [RequestMatch180137::all_field_ids] instead to share everything that was requested.
+    pub fn minimal_disclosure_field_ids(&self) -> Vec<FieldId180137> {
+        self.required_field_ids()
+    }
+
+    /// Every field that was requested and can be shared, required or optional.
+    pub fn all_field_ids(&self) -> Vec<FieldId180137> {
+        self.requested_fields
+            .iter()
+            .map(|field| field.id.clone())
+            .collect()
+    }
 }
 
 pub fn parse_request<'l, C>(
@@ -61,29 +93,30 @@ where
 {
     tracing::debug!("processing request: {:#?}", presentation_definition);
 
-    let input_descriptors = presentation_definition.input_descriptors().as_slice();
-    let input_descriptor = match input_descriptors {
-        [] => {
-            tracing::warn!("presentation contained no input descriptors");
-            return vec![];
-        }
-        [input_descriptor] => input_descriptor,
-        [input_descriptor, ..] => {
-            tracing::warn!("only handling the first request");
-            input_descriptor
-        }
-    };
+    let input_descriptors = presentation_definition.input_descriptors();
+    if input_descriptors.is_empty() {
+        tracing::warn!("presentation contained no input descriptors");
+        return vec![];
+    }
+
+    let credentials: Vec<&Mdoc> = credentials.collect();
 
-    credentials
-        .filter_map(
-            |credential| match find_match(input_descriptor, credential) {
-                Ok(m) => Some(Arc::new(m)),
-                Err(e) => {
-                    tracing::info!("credential did not match: {e}");
-                    None
+    input_descriptors
+        .iter()
+        .flat_map(|input_descriptor| {
+            credentials.iter().filter_map(move |credential| {
+                match find_match(input_descriptor, credential) {
+                    Ok(m) => Some(Arc::new(m)),
+                    Err(e) => {
+                        tracing::info!(
+                            "credential did not match input descriptor '{}': {e}",
+                            input_descriptor.id
+                        );
+                        None
+                    }
                 }
-            },
-        )
+            })
+        })
         .collect()
 }
 
@@ -91,7 +124,11 @@ fn find_match(input_descriptor: &InputDescriptor, credential: &Mdoc) -> Result<R
     let mdoc = credential.document();
 
     if mdoc.mso.doc_type != input_descriptor.id {
-        bail!("the request was not for an mDL: {}", input_descriptor.id)
+        bail!(
+            "the request was for doctype '{}', not '{}'",
+            input_descriptor.id,
+            mdoc.mso.doc_type
+        )
     }
 
     let mut age_over_mapping = calculate_age_over_mapping(&mdoc.namespaces);
@@ -233,6 +270,7 @@ fn find_match(input_descriptor: &InputDescriptor, credential: &Mdoc) -> Result<R
 
     Ok(RequestMatch180137 {
         credential_id: credential.id(),
+        input_descriptor_id: input_descriptor.id.clone(),
         field_map,
         requested_fields,
         missing_fields,
@@ -270,7 +308,7 @@ fn cbor_to_string_inner(cbor: &Cbor, allowed_depth: u8) -> Option<String> {
     }
 }
 
-fn age_from_str(s: &str) -> Option<u8> {
+pub(crate) fn age_from_str(s: &str) -> Option<u8> {
     let mut chars = s.chars();
     let first = match chars.next() {
         Some(d @ '0'..='9') => d,
@@ -291,9 +329,10 @@ fn age_from_str(s: &str) -> Option<u8> {
     }
 }
 
-fn calculate_age_over_mapping(
+/// Collect the `age_over_NN` attestations from the `org.iso.18013.5.1` namespace, sorted by age.
+fn age_over_elements(
     namespaces: &NonEmptyMap<String, NonEmptyMap<String, IssuerSignedItemBytes>>,
-) -> BTreeMap<String, Vec<String>> {
+) -> Vec<(u8, bool)> {
     let mut age_over_x_elements: Vec<(u8, bool)> = namespaces
         .iter()
         .filter(|(ns, _)| *ns == "org.iso.18013.5.1")
@@ -307,6 +346,39 @@ fn calculate_age_over_mapping(
 
     age_over_x_elements.sort_by(|a, b| a.0.cmp(&b.0));
 
+    age_over_x_elements
+}
+
+/// Determine whether the holder is over `age`, using the `age_over_NN` attestations present in
+/// the namespaces and the rules defined in ISO/IEC 18013-5 Section 7.2.5.
+///
+/// Returns `None` if no attestation can answer the request.
+pub(crate) fn age_over(
+    namespaces: &NonEmptyMap<String, NonEmptyMap<String, IssuerSignedItemBytes>>,
+    age: u8,
+) -> Option<bool> {
+    age_over_from_elements(age_over_elements(namespaces), age)
+}
+
+fn age_over_from_elements(age_over_x_elements: Vec<(u8, bool)>, age: u8) -> Option<bool> {
+    let responding_age = responding_age(age_over_x_elements.clone(), age)?;
+    age_over_x_elements
+        .into_iter()
+        .find(|(attested_age, _)| *attested_age == responding_age)
+        .map(|(_, over)| over)
+}
+
+/// The age of the `age_over_NN` attestation that answers a request for `age`, following the
+/// rules in ISO/IEC 18013-5 Section 7.2.5. The attestations must be sorted by age.
+pub(crate) fn responding_age(age_over_x_elements: Vec<(u8, bool)>, age: u8) -> Option<u8> {
+    reverse_mapping(age_over_x_elements).get(&age).copied()
+}
+
+fn calculate_age_over_mapping(
+    namespaces: &NonEmptyMap<String, NonEmptyMap<String, IssuerSignedItemBytes>>,
+) -> BTreeMap<String, Vec<String>> {
+    let age_over_x_elements = age_over_elements(namespaces);
+
     // Transform this mapping from (requested_age -> responded_age) into
     // (responded_age -> requested_age[]]) so that virtual elements for every possible requested_age
     // can be constructed.
@@ -407,7 +479,7 @@ mod test {
 
     use crate::crypto::{KeyAlias, RustTestKeyManager};
 
-    use super::{parse_request, reverse_mapping};
+    use super::{age_over_from_elements, parse_request, reverse_mapping};
 
     #[rstest]
     #[case::valid("tests/examples/18013_7_presentation_definition.json", 0)]
@@ -440,6 +512,79 @@ mod test {
         assert_eq!(request.missing_fields.len(), missing_fields);
     }
 
+    #[tokio::test]
+    async fn matches_every_input_descriptor() {
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        let key_alias = KeyAlias("".to_string());
+
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let credentials =
+            vec![crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap()];
+
+        // Request the mDL alongside a photo ID, with the mDL descriptor listed second.
+        let mut presentation_definition: serde_json::Value = serde_json::from_reader(
+            File::open("tests/examples/18013_7_presentation_definition.json").unwrap(),
+        )
+        .unwrap();
+        let input_descriptors = presentation_definition["input_descriptors"]
+            .as_array_mut()
+            .unwrap();
+        let mut photo_id = input_descriptors[0].clone();
+        photo_id["id"] = "org.iso.23220.photoid.1".into();
+        input_descriptors.insert(0, photo_id);
+        let presentation_definition: PresentationDefinition =
+            serde_json::from_value(presentation_definition).unwrap();
+
+        let request = parse_request(&presentation_definition, credentials.iter());
+
+        assert_eq!(request.len(), 1);
+        assert_eq!(request[0].input_descriptor_id, "org.iso.18013.5.1.mDL");
+        assert_eq!(request[0].requested_fields.len(), 12);
+    }
+
+    #[tokio::test]
+    async fn required_and_minimal_field_ids() {
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        let key_alias = KeyAlias("".to_string());
+
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let credentials =
+            vec![crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap()];
+
+        // Make the first requested field optional.
+        let mut presentation_definition: serde_json::Value = serde_json::from_reader(
+            File::open("tests/examples/18013_7_presentation_definition.json").unwrap(),
+        )
+        .unwrap();
+        presentation_definition["input_descriptors"][0]["constraints"]["fields"][0]["optional"] =
+            true.into();
+        let presentation_definition: PresentationDefinition =
+            serde_json::from_value(presentation_definition).unwrap();
+
+        let request = parse_request(&presentation_definition, credentials.iter());
+        let request = &request[0];
+
+        let required = request.required_field_ids();
+        assert_eq!(request.all_field_ids().len(), 12);
+        assert_eq!(required.len(), 11);
+        assert_eq!(request.minimal_disclosure_field_ids(), required);
+
+        let family_name = request
+            .requested_fields
+            .iter()
+            .find(|field| field.displayable_name == "family_name")
+            .unwrap();
+        assert!(!required.contains(&family_name.id));
+    }
+
     #[test]
     fn age_attestation_mapping() {
         let reverse_mapping =
@@ -457,6 +602,38 @@ mod test {
             })
     }
 
+    #[rstest]
+    #[case::below_lowest(16, Some(true))]
+    #[case::attested_true(18, Some(true))]
+    #[case::between_true(19, Some(true))]
+    #[case::between_true_and_false(25, None)]
+    #[case::attested_false(30, Some(false))]
+    #[case::between_false(45, Some(false))]
+    #[case::above_highest(75, Some(false))]
+    #[case::out_of_range(100, None)]
+    fn age_over_derivation(#[case] age: u8, #[case] expected: Option<bool>) {
+        let elements = vec![(18, true), (21, true), (30, false), (60, false)];
+        assert_eq!(age_over_from_elements(elements, age), expected);
+    }
+
+    #[tokio::test]
+    async fn mdoc_age_over() {
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        let key_alias = KeyAlias("".to_string());
+
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        // The test mDL attests `age_over_18: true` and `age_over_21: true`.
+        let mdoc = crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap();
+
+        assert_eq!(mdoc.age_over(20), Some(true));
+        assert_eq!(mdoc.age_over(21), Some(true));
+        assert_eq!(mdoc.age_over(25), None);
+    }
+
     #[rstest]
     #[case::valid("$['namespace']['element_id']", true)]
     #[case::invalid("$.namespace.element_id", false)]
@@ -1,11 +1,19 @@
+pub mod client_metadata;
 pub mod error;
 pub mod holder;
 pub mod iso_18013_7;
 pub mod permission_request;
 pub mod presentation;
+pub mod replay;
+pub mod submission_requirement;
+pub mod transaction_data;
 pub mod verifier;
 
+pub use client_metadata::*;
 pub use holder::*;
 pub use permission_request::*;
 pub use presentation::*;
+pub use replay::*;
+pub use submission_requirement::*;
+pub use transaction_data::*;
 pub use verifier::*;
@@ -1,7 +1,16 @@
+use super::client_metadata::VerifierMetadata;
 use super::error::OID4VPError;
-use super::presentation::{PresentationError, PresentationOptions, PresentationSigner};
+use super::presentation::{
+    input_descriptor_definition, PresentationError, PresentationOptions, PresentationSigner,
+};
+use super::submission_requirement::{
+    is_input_descriptor_required, SubmissionEvaluation, SubmissionRequirement,
+};
+use super::transaction_data::TransactionData;
 use crate::common::*;
-use crate::credential::{Credential, ParsedCredential, PresentableCredential};
+use crate::credential::{
+    Credential, ParsedCredential, ParsedCredentialInner, PresentableCredential,
+};
 
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -42,6 +51,10 @@ pub enum PermissionRequestError {
     #[error("Credential not found for input descriptor id: {0}")]
     CredentialNotFound(String),
 
+    /// Selected credential ID does not match the presentation definition.
+    #[error("Credential {0} does not match the presentation definition.")]
+    UnmatchedCredentialId(String),
+
     /// Input descriptor not found for input descriptor id.
     #[error("Input descriptor not found for input descriptor id: {0}")]
     InputDescriptorNotFound(String),
@@ -76,6 +89,33 @@ pub enum PermissionRequestError {
     Presentation(#[from] PresentationError),
 }
 
+/// The name and purpose an input descriptor gives to a group of requested fields, e.g. to
+/// title each credential request on a consent screen.
+///
+/// These are displayed as provided by the verifier, which is responsible for their language.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct InputDescriptorDisplay {
+    /// The input descriptor id, matching [RequestedField::input_descriptor_id].
+    pub id: String,
+    pub name: Option<String>,
+    pub purpose: Option<String>,
+}
+
+impl InputDescriptorDisplay {
+    /// Describe the input descriptors of a presentation definition, in order.
+    pub(crate) fn from_definition(definition: &PresentationDefinition) -> Vec<Self> {
+        definition
+            .input_descriptors()
+            .iter()
+            .map(|descriptor| Self {
+                id: descriptor.id.clone(),
+                name: descriptor.name.clone(),
+                purpose: descriptor.purpose.clone(),
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, uniffi::Object)]
 pub struct RequestedField {
     /// A unique ID for the requested field
@@ -83,6 +123,8 @@ pub struct RequestedField {
     pub(crate) name: Option<String>,
     pub(crate) path: String,
     pub(crate) required: bool,
+    /// Whether the verifier intends to retain the field's value, as declared by
+    /// `intent_to_retain` on the input descriptor's constraint field.
     pub(crate) retained: bool,
     pub(crate) purpose: Option<String>,
     pub(crate) input_descriptor_id: String,
@@ -139,7 +181,12 @@ impl RequestedField {
         self.required
     }
 
-    /// Return the field retained status
+    /// Return the field retained status.
+    ///
+    /// This is `true` when the presentation definition marks the field with
+    /// `intent_to_retain: true`, meaning the verifier intends to store the value
+    /// after the presentation. It defaults to `false` when the property is absent.
+    /// User interfaces should surface this to the holder before consent.
     pub fn retained(&self) -> bool {
         self.retained
     }
@@ -165,6 +212,8 @@ pub struct PermissionRequest {
     pub(crate) request: AuthorizationRequestObject,
     pub(crate) signer: Arc<Box<dyn PresentationSigner>>,
     pub(crate) context_map: Option<HashMap<String, String>>,
+    pub(crate) transaction_data: Vec<TransactionData>,
+    pub(crate) client_metadata: Option<VerifierMetadata>,
 }
 
 impl PermissionRequest {
@@ -174,6 +223,8 @@ impl PermissionRequest {
         request: AuthorizationRequestObject,
         signer: Arc<Box<dyn PresentationSigner>>,
         context_map: Option<HashMap<String, String>>,
+        transaction_data: Vec<TransactionData>,
+        client_metadata: Option<VerifierMetadata>,
     ) -> Arc<Self> {
         Arc::new(Self {
             definition,
@@ -181,8 +232,192 @@ impl PermissionRequest {
             request,
             signer,
             context_map,
+            transaction_data,
+            client_metadata,
         })
     }
+
+    /// Construct a new permission response for the given credentials, signing the
+    /// presentation with `signer`.
+    async fn respond(
+        &self,
+        selected_credentials: Vec<Arc<PresentableCredential>>,
+        response_options: ResponseOptions,
+        signer: Arc<Box<dyn PresentationSigner>>,
+    ) -> Result<Arc<PermissionResponse>, OID4VPError> {
+        log::debug!("Creating Permission Response");
+
+        // Ensure that the selected credentials are not empty.
+        if selected_credentials.is_empty() {
+            return Err(PermissionRequestError::InvalidSelectedCredential(
+                "No selected credentials".to_string(),
+                self.definition.credential_types_hint().join(", "),
+            )
+            .into());
+        }
+
+        let selected_credentials = selected_credentials
+            .into_iter()
+            .map(|sc| {
+                // If limit disclosure is `required` drop connection
+                if sc.limit_disclosure {
+                    return Err(PermissionRequestError::LimitDisclosure);
+                }
+
+                self.validate_selection(&sc)?;
+                Ok(sc)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Set options for constructing a verifiable presentation.
+        let options = PresentationOptions::for_request(
+            &self.request,
+            signer,
+            self.context_map.clone(),
+            &self.transaction_data,
+            &response_options,
+        );
+
+        // Each credential answers the input descriptor at its position, as in
+        // [PermissionResponse::create_descriptor_map], which selects its transaction data.
+        let credential_options: Vec<_> = self
+            .input_descriptor_ids(selected_credentials.len())
+            .into_iter()
+            .map(|input_descriptor_id| options.for_input_descriptor(input_descriptor_id))
+            .collect();
+
+        let token_items = futures::future::try_join_all(
+            selected_credentials
+                .iter()
+                .zip(&credential_options)
+                .map(|(cred, options): (&Arc<_>, _)| cred.as_vp_token(options)),
+        )
+        .await?;
+
+        let vp_token = VpToken(token_items);
+
+        Ok(Arc::new(PermissionResponse {
+            selected_credentials,
+            presentation_definition: self.definition.clone(),
+            authorization_request: self.request.clone(),
+            vp_token,
+            options: response_options,
+        }))
+    }
+
+    /// Return the ids of the input descriptors answered by `count` selected credentials, in
+    /// order, or `None` for credentials beyond the input descriptors of the definition.
+    fn input_descriptor_ids(&self, count: usize) -> Vec<Option<String>> {
+        let input_descriptors = self.definition.input_descriptors();
+        (0..count)
+            .map(|idx| {
+                input_descriptors
+                    .get(idx)
+                    .map(|descriptor| descriptor.id.clone())
+            })
+            .collect()
+    }
+
+    /// Resolve credential IDs against the credentials matching the presentation definition,
+    /// preserving the order of `credential_ids`.
+    fn credentials_for_ids(
+        &self,
+        credential_ids: &[Uuid],
+    ) -> Result<Vec<Arc<PresentableCredential>>, PermissionRequestError> {
+        credential_ids
+            .iter()
+            .map(|id| {
+                self.credentials
+                    .iter()
+                    .find(|c| c.as_parsed_credential().id() == *id)
+                    .cloned()
+                    .ok_or_else(|| PermissionRequestError::UnmatchedCredentialId(id.to_string()))
+            })
+            .collect()
+    }
+
+    /// Ensure a selected credential satisfies the presentation definition and, for formats
+    /// presenting only the selected fields, that it was selected with all of its required
+    /// fields.
+    fn validate_selection(
+        &self,
+        credential: &Arc<PresentableCredential>,
+    ) -> Result<(), PermissionRequestError> {
+        let parsed = credential.as_parsed_credential();
+        let invalid = |reason: String| {
+            PermissionRequestError::InvalidSelectedCredential(
+                reason,
+                self.definition.credential_types_hint().join(", "),
+            )
+        };
+
+        if !parsed.satisfies_presentation_definition(&self.definition) {
+            return Err(invalid(format!(
+                "Credential {} does not satisfy the presentation definition",
+                parsed.id()
+            )));
+        }
+
+        // JWT and LDP VCs are presented in full, whatever fields are selected, if any.
+        if !matches!(
+            credential.inner,
+            ParsedCredentialInner::VCDM2SdJwt(_) | ParsedCredentialInner::MsoMdoc(_)
+        ) {
+            return Ok(());
+        }
+
+        let Some(selected_fields) = &credential.selected_fields else {
+            return Err(invalid(format!(
+                "Credential {} has no selected fields",
+                parsed.id()
+            )));
+        };
+
+        if let Some(field) = self
+            .requested_fields(credential)
+            .into_iter()
+            .find(|field| field.required && !is_field_selected(field, selected_fields))
+        {
+            return Err(invalid(format!(
+                "Credential {} is missing required field {}",
+                parsed.id(),
+                field.name.as_deref().unwrap_or(&field.path)
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Return the ids of the input descriptors satisfied by at least one of the matched
+    /// credentials, in the order of the presentation definition.
+    fn satisfied_input_descriptors(&self) -> Vec<String> {
+        self.definition
+            .input_descriptors()
+            .iter()
+            .filter(|descriptor| {
+                let definition = input_descriptor_definition(&self.definition, descriptor);
+                self.credentials.iter().any(|credential| {
+                    credential
+                        .as_parsed_credential()
+                        .satisfies_presentation_definition(&definition)
+                })
+            })
+            .map(|descriptor| descriptor.id.clone())
+            .collect()
+    }
+}
+
+/// Whether a requested field is among the selected fields, which use either the encoding of
+/// [RequestedField::path] or a plain JsonPath.
+fn is_field_selected(field: &RequestedField, selected_fields: &[String]) -> bool {
+    selected_fields.iter().any(|selected| {
+        let encoded = match selected.starts_with('$') {
+            true => URL_SAFE.encode(selected),
+            false => selected.split(',').next().unwrap_or_default().to_string(),
+        };
+
+        field.path.split(',').any(|path| path == encoded)
+    })
 }
 
 #[uniffi::export(async_runtime = "tokio")]
@@ -196,6 +431,10 @@ impl PermissionRequest {
     /// Return the requested fields for a given credential.
     ///
     /// NOTE: This will return only the requested fields for a given credential.
+    ///
+    /// Fields of input descriptors that the submission requirements do not
+    /// require, e.g. one of several options in a `pick` rule, are reported as
+    /// not required.
     pub fn requested_fields(
         &self,
         credential: &Arc<PresentableCredential>,
@@ -204,6 +443,54 @@ impl PermissionRequest {
             inner: credential.inner.clone(),
         }
         .requested_fields(&self.definition)
+        .into_iter()
+        .map(|field| {
+            if !field.required
+                || is_input_descriptor_required(&self.definition, &field.input_descriptor_id)
+            {
+                return field;
+            }
+
+            Arc::new(RequestedField {
+                id: field.id,
+                name: field.name.clone(),
+                path: field.path.clone(),
+                required: false,
+                retained: field.retained,
+                purpose: field.purpose.clone(),
+                input_descriptor_id: field.input_descriptor_id.clone(),
+                raw_fields: field.raw_fields.clone(),
+            })
+        })
+        .collect()
+    }
+
+    /// Return the submission requirements of the presentation definition.
+    ///
+    /// This is empty when the definition has no submission requirements, in
+    /// which case every input descriptor must be satisfied.
+    pub fn submission_requirements(&self) -> Vec<SubmissionRequirement> {
+        SubmissionRequirement::from_definition(&self.definition)
+    }
+
+    /// Return the ids of the required input descriptors that none of the matched credentials
+    /// satisfy.
+    ///
+    /// These are the unsatisfied input descriptors selected by the submission requirements
+    /// that cannot be met, e.g. both options of a `pick` rule with a `count` of 1. A response is
+    /// bound to fail unless this is empty.
+    pub fn missing_required_descriptors(&self) -> Vec<String> {
+        SubmissionEvaluation::new(&self.definition, &self.satisfied_input_descriptors()).missing
+    }
+
+    /// Whether the matched credentials can satisfy the request, although optional input
+    /// descriptors may be missing.
+    ///
+    /// This requires every submission requirement to be met: all the input descriptors
+    /// selected by an `all` rule, and enough of those selected by a `pick` rule to meet its
+    /// `count` or `min`.
+    pub fn can_satisfy_required(&self) -> bool {
+        SubmissionEvaluation::new(&self.definition, &self.satisfied_input_descriptors()).satisfiable
     }
 
     /// Return the client ID for the authorization request.
@@ -214,6 +501,15 @@ impl PermissionRequest {
         self.request.client_id().0.clone()
     }
 
+    /// Return the metadata of the verifier, including the keys to encrypt the response to
+    /// and the formats it supports.
+    ///
+    /// This is `None` if the request carries neither `client_metadata` nor
+    /// `client_metadata_uri`.
+    pub fn client_metadata(&self) -> Option<VerifierMetadata> {
+        self.client_metadata.clone()
+    }
+
     /// Return the domain name of the redirect URI.
     ///
     /// This can be used by the user interface to show where
@@ -225,6 +521,12 @@ impl PermissionRequest {
 
     /// Construct a new permission response for the given credential.
     ///
+    /// `selected_fields` is matched to `selected_credentials` by position.
+    ///
+    /// Deprecated: prefer [PermissionRequest::create_permission_response_from_selections]
+    /// with credentials carrying their selection, see
+    /// [PresentableCredential::with_selected_fields].
+    ///
     /// NOTE: `should_strip_quotes` is a non-normative setting to determine
     /// the behavior of removing extra quotations around a JSON
     /// string encoded vp_token, e.g. "'[{ @context: [...] }]'" -> '[{ @context: [...] }]'
@@ -234,17 +536,6 @@ impl PermissionRequest {
         selected_fields: Vec<Vec<String>>,
         response_options: ResponseOptions,
     ) -> Result<Arc<PermissionResponse>, OID4VPError> {
-        log::debug!("Creating Permission Response");
-
-        // Ensure that the selected credentials are not empty.
-        if selected_credentials.is_empty() {
-            return Err(PermissionRequestError::InvalidSelectedCredential(
-                "No selected credentials".to_string(),
-                self.definition.credential_types_hint().join(", "),
-            )
-            .into());
-        }
-
         // Ensure that there are selected fields for all credentials.
         if selected_fields.len() != selected_credentials.len() {
             return Err(PermissionRequestError::InvalidSelectedCredential(
@@ -257,50 +548,101 @@ impl PermissionRequest {
         let selected_credentials = selected_credentials
             .iter()
             .zip(selected_fields)
-            .map(|(sc, sf)| {
-                // If limit disclosure is `required` drop connection
-                if sc.limit_disclosure {
-                    return Err(PermissionRequestError::LimitDisclosure);
-                }
-                Ok(PresentableCredential {
-                    inner: sc.inner.clone(),
-                    limit_disclosure: sc.limit_disclosure,
-                    selected_fields: Some(sf),
-                }
-                .into())
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+            .map(|(credential, fields)| credential.with_selected_fields(fields))
+            .collect();
 
-        // Set options for constructing a verifiable presentation.
-        let options = PresentationOptions {
-            request: &self.request,
-            signer: self.signer.clone(),
-            context_map: self.context_map.clone(),
-            response_options: &response_options,
-        };
+        self.create_permission_response_from_selections(selected_credentials, response_options)
+            .await
+    }
 
-        let token_items = futures::future::try_join_all(
-            selected_credentials
-                .iter()
-                .map(|cred: &Arc<_>| cred.as_vp_token(&options)),
-        )
-        .await?;
+    /// Construct a new permission response for the given credentials, presenting the fields
+    /// each credential was selected with, see [PresentableCredential::with_selected_fields].
+    ///
+    /// SD-JWTs and mdocs must be selected with all of their required fields. JWT and LDP VCs
+    /// are presented in full, so they need no selection.
+    pub async fn create_permission_response_from_selections(
+        &self,
+        selected_credentials: Vec<Arc<PresentableCredential>>,
+        response_options: ResponseOptions,
+    ) -> Result<Arc<PermissionResponse>, OID4VPError> {
+        self.respond(selected_credentials, response_options, self.signer.clone())
+            .await
+    }
 
-        let vp_token = VpToken(token_items);
+    /// Construct a new permission response like
+    /// [PermissionRequest::create_permission_response_from_selections], signing the
+    /// presentation with `signer` instead of the holder's signer.
+    ///
+    /// This lets a wallet holding several keys choose the one a verifier or credential
+    /// requires, for this response only.
+    pub async fn create_permission_response_with_signer(
+        &self,
+        selected_credentials: Vec<Arc<PresentableCredential>>,
+        response_options: ResponseOptions,
+        signer: Box<dyn PresentationSigner>,
+    ) -> Result<Arc<PermissionResponse>, OID4VPError> {
+        self.respond(selected_credentials, response_options, Arc::new(signer))
+            .await
+    }
 
-        Ok(Arc::new(PermissionResponse {
-            selected_credentials,
-            presentation_definition: self.definition.clone(),
-            authorization_request: self.request.clone(),
-            vp_token,
-            options: response_options,
-        }))
+    /// Construct a new permission response for the credentials with the given IDs.
+    ///
+    /// Each ID must refer to a credential that satisfies the presentation definition,
+    /// i.e. one returned by [PermissionRequest::credentials]. IDs of other credentials, even
+    /// ones stored in the wallet, fail with [PermissionRequestError::UnmatchedCredentialId].
+    /// The credentials are presented in the order of `credential_ids`, and `selected_fields`
+    /// is matched to `credential_ids` by position.
+    pub async fn create_permission_response_for_ids(
+        &self,
+        credential_ids: Vec<Uuid>,
+        selected_fields: Vec<Vec<String>>,
+        response_options: ResponseOptions,
+    ) -> Result<Arc<PermissionResponse>, OID4VPError> {
+        let selected_credentials = self.credentials_for_ids(&credential_ids)?;
+
+        self.create_permission_response(selected_credentials, selected_fields, response_options)
+            .await
+    }
+
+    /// Return how many times the presentation signer will be asked to sign when responding
+    /// with the given credentials.
+    ///
+    /// This allows hosts whose keys require user presence for each signature, e.g. keys held
+    /// in the Secure Enclave or StrongBox, to inform the user before the response is created.
+    pub fn signature_operations_required(
+        &self,
+        selected_credentials: Vec<Arc<PresentableCredential>>,
+    ) -> u32 {
+        selected_credentials
+            .iter()
+            .zip(self.input_descriptor_ids(selected_credentials.len()))
+            .map(|(credential, input_descriptor_id)| {
+                let binds_transaction_data = self.transaction_data.iter().any(|transaction_data| {
+                    transaction_data.applies_to(input_descriptor_id.as_deref())
+                });
+                credential.signature_operations(binds_transaction_data)
+            })
+            .sum()
+    }
+
+    /// Return the transactions the holder is asked to authorize with this presentation,
+    /// e.g. a payment confirmation. Each is bound to the presentation when responding.
+    pub fn transaction_data(&self) -> Vec<TransactionData> {
+        self.transaction_data.clone()
     }
 
     /// Return the purpose of the presentation request.
     pub fn purpose(&self) -> Option<String> {
         self.definition.purpose().map(ToOwned::to_owned)
     }
+
+    /// Return the name and purpose of each input descriptor of the presentation definition.
+    ///
+    /// Requested fields can be grouped under these using
+    /// [RequestedField::input_descriptor_id].
+    pub fn input_descriptors(&self) -> Vec<InputDescriptorDisplay> {
+        InputDescriptorDisplay::from_definition(&self.definition)
+    }
 }
 
 /// Non-normative response options used to provide configurable interface
@@ -328,6 +670,17 @@ pub struct ResponseOptions {
     /// Remove the `$.vp` path prefix for the descriptor map for the verifiable credential.
     /// This is non-normative option, e.g. `$.vp` -> `$`
     pub remove_vp_path_prefix: bool,
+    /// Lifetime of a JWT verifiable presentation in seconds, used to set its `exp` claim.
+    /// Defaults to one hour.
+    #[uniffi(default = None)]
+    pub vp_token_lifetime_seconds: Option<u64>,
+    /// Domain of Data Integrity presentation proofs, for verifiers expecting a value other
+    /// than the request's client ID, e.g. its `response_uri`.
+    #[uniffi(default = None)]
+    pub proof_domain: Option<String>,
+    /// Challenge of Data Integrity presentation proofs, used instead of the request's nonce.
+    #[uniffi(default = None)]
+    pub proof_challenge: Option<String>,
 }
 
 /// This struct is used to represent the response to a permission request.
@@ -361,6 +714,17 @@ impl PermissionResponse {
     pub fn vp_token(&self) -> Result<String, OID4VPError> {
         serde_json::to_string(&self.vp_token).map_err(|e| OID4VPError::Token(format!("{e:?}")))
     }
+
+    /// Return the presentation submission describing which input descriptor each selected
+    /// credential answers, and where it is found in the vp token, as a JSON-encoded string.
+    ///
+    /// This is helpful to inspect or log the descriptor map before the response is
+    /// submitted. The `id` of the submission is generated on each call, so it differs from
+    /// the one sent to the verifier.
+    pub fn presentation_submission_json(&self) -> Result<String, OID4VPError> {
+        serde_json::to_string(&self.create_presentation_submission()?)
+            .map_err(|e| OID4VPError::PresentationSubmissionCreation(format!("{e:?}")))
+    }
 }
 
 impl PermissionResponse {
@@ -425,3 +789,517 @@ impl PermissionResponse {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::credential::vcdm2_sd_jwt::VCDM2SdJwt;
+    use crate::oid4vp::holder::tests::test_request;
+    use serde_json::json;
+    use ssi::{
+        claims::{sd_jwt::SdAlg, vc_jose_cose::SdJwtVc},
+        json_pointer, JWK,
+    };
+
+    #[test]
+    fn requested_field_surfaces_intent_to_retain() {
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "retention-test",
+            "input_descriptors": [{
+                "id": "employee",
+                "constraints": {
+                    "fields": [
+                        {
+                            "path": ["$.credentialSubject.name"],
+                            "intent_to_retain": true
+                        },
+                        {
+                            "path": ["$.credentialSubject.email"]
+                        }
+                    ]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let credential = json!({
+            "credentialSubject": {
+                "name": "Alice",
+                "email": "alice@example.com"
+            }
+        });
+
+        let fields: Vec<RequestedField> = definition
+            .requested_fields(&credential)
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        let field_for = |value: &str| {
+            fields
+                .iter()
+                .find(|field| field.raw_fields.contains(&json!(value)))
+                .expect("requested field should be present")
+        };
+
+        assert!(field_for("Alice").retained());
+        assert!(!field_for("alice@example.com").retained());
+    }
+
+    #[test]
+    fn input_descriptor_display() {
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "display-test",
+            "input_descriptors": [
+                {
+                    "id": "employee",
+                    "name": "Employment credential",
+                    "purpose": "To confirm your current employer",
+                    "constraints": {}
+                },
+                { "id": "address", "constraints": {} }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            InputDescriptorDisplay::from_definition(&definition),
+            vec![
+                InputDescriptorDisplay {
+                    id: "employee".into(),
+                    name: Some("Employment credential".into()),
+                    purpose: Some("To confirm your current employer".into()),
+                },
+                InputDescriptorDisplay {
+                    id: "address".into(),
+                    name: None,
+                    purpose: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn permission_response_requires_required_fields() {
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "required-fields-test",
+            "input_descriptors": [{
+                "id": "alumni",
+                "constraints": {
+                    "fields": [
+                        { "path": ["$.credentialSubject.alumniOf"] },
+                        { "path": ["$.credentialSubject.id"], "optional": true }
+                    ]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let request = test_request(&definition, None);
+
+        let claims: SdJwtVc = serde_json::from_value(json!({
+            "@context": ["https://www.w3.org/ns/credentials/v2"],
+            "type": ["VerifiableCredential"],
+            "issuer": "did:example:issuer",
+            "credentialSubject": {
+                "id": "did:example:alice",
+                "alumniOf": "Example University"
+            }
+        }))
+        .unwrap();
+        let sd_jwt = claims
+            .conceal_and_sign(
+                SdAlg::Sha256,
+                &[
+                    json_pointer!("/credentialSubject/id"),
+                    json_pointer!("/credentialSubject/alumniOf"),
+                ],
+                &JWK::generate_ed25519().unwrap(),
+            )
+            .await
+            .unwrap();
+        let credential = Arc::new(PresentableCredential {
+            inner: ParsedCredential::new_sd_jwt(
+                VCDM2SdJwt::new_from_compact_sd_jwt(sd_jwt.to_string()).unwrap(),
+            )
+            .inner
+            .clone(),
+            limit_disclosure: false,
+            selected_fields: None,
+        });
+
+        let signer: Box<dyn PresentationSigner> =
+            Box::new(crate::oid4vp::holder::tests::KeySigner {
+                jwk: crate::tests::load_jwk(),
+            });
+        let permission_request = PermissionRequest::new(
+            definition,
+            vec![credential.clone()],
+            request,
+            Arc::new(signer),
+            None,
+            vec![],
+            None,
+        );
+
+        let optional_fields = permission_request
+            .requested_fields(&credential)
+            .iter()
+            .filter(|field| !field.required())
+            .map(|field| field.path())
+            .collect::<Vec<_>>();
+        assert_eq!(optional_fields.len(), 1);
+
+        // JWT and LDP VCs are presented in full, so their selection may omit required fields.
+        let json_vc = crate::credential::json_vc::JsonVc::new_from_json(
+            include_str!("../../tests/examples/alumni_vc.json").into(),
+        )
+        .unwrap();
+        let ldp_vc = Arc::new(PresentableCredential {
+            inner: ParsedCredential::new_ldp_vc(json_vc).inner.clone(),
+            limit_disclosure: false,
+            selected_fields: None,
+        });
+        permission_request.validate_selection(&ldp_vc).unwrap();
+        permission_request
+            .validate_selection(&ldp_vc.with_selected_fields(optional_fields.clone()))
+            .unwrap();
+
+        let result = permission_request
+            .create_permission_response(
+                vec![credential],
+                vec![optional_fields],
+                ResponseOptions::default(),
+            )
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(OID4VPError::PermissionRequest(
+                PermissionRequestError::InvalidSelectedCredential(reason, _)
+            )) if reason.contains("missing required field")
+        ));
+
+        let result = permission_request
+            .create_permission_response_from_selections(
+                vec![credential.clone()],
+                ResponseOptions::default(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(OID4VPError::PermissionRequest(
+                PermissionRequestError::InvalidSelectedCredential(reason, _)
+            )) if reason.contains("no selected fields")
+        ));
+
+        let optional_only = credential.with_selected_fields(
+            permission_request
+                .requested_fields(&credential)
+                .iter()
+                .filter(|field| !field.required())
+                .map(|field| field.path())
+                .collect(),
+        );
+        let result = permission_request
+            .create_permission_response_from_selections(
+                vec![optional_only],
+                ResponseOptions::default(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(OID4VPError::PermissionRequest(
+                PermissionRequestError::InvalidSelectedCredential(reason, _)
+            )) if reason.contains("missing required field")
+        ));
+
+        let all_fields = credential.with_selected_fields(
+            permission_request
+                .requested_fields(&credential)
+                .iter()
+                .map(|field| field.path())
+                .collect(),
+        );
+        let response = permission_request
+            .create_permission_response_from_selections(
+                vec![all_fields],
+                ResponseOptions::default(),
+            )
+            .await
+            .unwrap();
+        let vp_token = response.vp_token().unwrap();
+        assert!(vp_token.contains(sd_jwt.as_str().split('~').next().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn permission_response_presents_ldp_vc_without_selection() {
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "ldp-vc-response-test",
+            "input_descriptors": [{
+                "id": "alumni",
+                "constraints": {
+                    "fields": [{ "path": ["$.credentialSubject.alumniOf"] }]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let request = test_request(&definition, None);
+
+        let json_vc = crate::credential::json_vc::JsonVc::new_from_json(
+            include_str!("../../tests/examples/alumni_vc.json").into(),
+        )
+        .unwrap();
+        let credential = Arc::new(PresentableCredential {
+            inner: ParsedCredential::new_ldp_vc(json_vc).inner.clone(),
+            limit_disclosure: false,
+            selected_fields: None,
+        });
+
+        let signer: Box<dyn PresentationSigner> =
+            Box::new(crate::oid4vp::holder::tests::KeySigner {
+                jwk: crate::tests::load_jwk(),
+            });
+        let permission_request = PermissionRequest::new(
+            definition,
+            vec![credential.clone()],
+            request,
+            Arc::new(signer),
+            Some(crate::context::default_ld_json_context()),
+            vec![],
+            None,
+        );
+
+        let response = permission_request
+            .create_permission_response_from_selections(
+                vec![credential],
+                ResponseOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        // The credential is presented in full, along with the proof of the holder.
+        let vp_token = response.vp_token().unwrap();
+        assert!(vp_token.contains("Example University"));
+        assert!(vp_token.contains("proof"));
+    }
+
+    #[tokio::test]
+    async fn permission_response_for_ids() {
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "response-for-ids-test",
+            "input_descriptors": [{
+                "id": "alumni",
+                "constraints": {
+                    "fields": [{ "path": ["$.credentialSubject.alumniOf"] }]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let request = test_request(&definition, None);
+
+        let credential = || {
+            let json_vc = crate::credential::json_vc::JsonVc::new_from_json(
+                include_str!("../../tests/examples/alumni_vc.json").into(),
+            )
+            .unwrap();
+            Arc::new(PresentableCredential {
+                inner: ParsedCredential::new_ldp_vc(json_vc).inner.clone(),
+                limit_disclosure: false,
+                selected_fields: None,
+            })
+        };
+        let (first, second) = (credential(), credential());
+        let id = |credential: &Arc<PresentableCredential>| credential.as_parsed_credential().id();
+
+        let signer: Box<dyn PresentationSigner> =
+            Box::new(crate::oid4vp::holder::tests::KeySigner {
+                jwk: crate::tests::load_jwk(),
+            });
+        let permission_request = PermissionRequest::new(
+            definition,
+            vec![first.clone(), second.clone()],
+            request,
+            Arc::new(signer),
+            Some(crate::context::default_ld_json_context()),
+            vec![],
+            None,
+        );
+
+        let resolved = permission_request
+            .credentials_for_ids(&[id(&second), id(&first)])
+            .unwrap();
+        assert_eq!(
+            resolved.iter().map(id).collect::<Vec<_>>(),
+            vec![id(&second), id(&first)]
+        );
+
+        let unknown = Uuid::new_v4();
+        let result = permission_request
+            .create_permission_response_for_ids(
+                vec![id(&first), unknown],
+                vec![vec![], vec![]],
+                ResponseOptions::default(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(OID4VPError::PermissionRequest(
+                PermissionRequestError::UnmatchedCredentialId(ref unmatched)
+            )) if *unmatched == unknown.to_string()
+        ));
+
+        let response = permission_request
+            .create_permission_response_for_ids(
+                vec![id(&second)],
+                vec![vec![]],
+                ResponseOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response
+                .selected_credentials()
+                .iter()
+                .map(id)
+                .collect::<Vec<_>>(),
+            vec![id(&second)]
+        );
+        assert!(response.vp_token().unwrap().contains("Example University"));
+    }
+
+    #[test]
+    fn previews_presentation_submission() {
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "submission-preview-test",
+            "input_descriptors": [{
+                "id": "alumni",
+                "constraints": {
+                    "fields": [{ "path": ["$.credentialSubject.alumniOf"] }]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let request = test_request(&definition, None);
+
+        let json_vc = crate::credential::json_vc::JsonVc::new_from_json(
+            include_str!("../../tests/examples/alumni_vc.json").into(),
+        )
+        .unwrap();
+        let response = PermissionResponse {
+            selected_credentials: vec![Arc::new(PresentableCredential {
+                inner: ParsedCredential::new_ldp_vc(json_vc).inner.clone(),
+                limit_disclosure: false,
+                selected_fields: None,
+            })],
+            presentation_definition: definition,
+            authorization_request: request,
+            vp_token: VpToken(vec![]),
+            options: ResponseOptions::default(),
+        };
+
+        let submission: serde_json::Value =
+            serde_json::from_str(&response.presentation_submission_json().unwrap()).unwrap();
+
+        assert_eq!(submission["definition_id"], "submission-preview-test");
+        assert_eq!(submission["descriptor_map"][0]["id"], "alumni");
+        assert_eq!(
+            submission["descriptor_map"][0]["path_nested"]["id"],
+            "alumni"
+        );
+    }
+
+    #[test]
+    fn reports_missing_required_descriptors() {
+        let json_vc = crate::credential::json_vc::JsonVc::new_from_json(
+            include_str!("../../tests/examples/alumni_vc.json").into(),
+        )
+        .unwrap();
+        let credential = Arc::new(PresentableCredential {
+            inner: ParsedCredential::new_ldp_vc(json_vc).inner.clone(),
+            limit_disclosure: false,
+            selected_fields: None,
+        });
+
+        let permission_request = |submission_requirements: serde_json::Value| {
+            let definition: PresentationDefinition = serde_json::from_value(json!({
+                "id": "partial-response-test",
+                "submission_requirements": submission_requirements,
+                "input_descriptors": [
+                    {
+                        "id": "alumni",
+                        "group": ["A"],
+                        "constraints": {
+                            "fields": [{ "path": ["$.credentialSubject.alumniOf"] }]
+                        }
+                    },
+                    {
+                        "id": "license",
+                        "group": ["B"],
+                        "constraints": {
+                            "fields": [{ "path": ["$.credentialSubject.licenseNumber"] }]
+                        }
+                    }
+                ]
+            }))
+            .unwrap();
+
+            let request = test_request(&definition, None);
+
+            let signer: Box<dyn PresentationSigner> =
+                Box::new(crate::oid4vp::holder::tests::KeySigner {
+                    jwk: crate::tests::load_jwk(),
+                });
+            PermissionRequest::new(
+                definition,
+                vec![credential.clone()],
+                request,
+                Arc::new(signer),
+                None,
+                vec![],
+                None,
+            )
+        };
+
+        // Both input descriptors are required, but no credential has a license number.
+        let all_required = permission_request(json!([
+            { "rule": "all", "from": "A" },
+            { "rule": "all", "from": "B" }
+        ]));
+        assert_eq!(
+            all_required.missing_required_descriptors(),
+            vec!["license".to_string()]
+        );
+        assert!(!all_required.can_satisfy_required());
+
+        let license_optional = permission_request(json!([
+            { "rule": "all", "from": "A" },
+            { "rule": "pick", "max": 1, "from": "B" }
+        ]));
+        assert!(license_optional.missing_required_descriptors().is_empty());
+        assert!(license_optional.can_satisfy_required());
+
+        let license_picked = permission_request(json!([
+            { "rule": "all", "from": "A" },
+            { "rule": "pick", "count": 1, "from": "B" }
+        ]));
+        assert_eq!(
+            license_picked.missing_required_descriptors(),
+            vec!["license".to_string()]
+        );
+        assert!(!license_picked.can_satisfy_required());
+
+        let alumni_picked = permission_request(json!([
+            { "rule": "pick", "count": 1, "from_nested": [
+                { "rule": "all", "from": "A" },
+                { "rule": "all", "from": "B" }
+            ] }
+        ]));
+        assert!(alumni_picked.missing_required_descriptors().is_empty());
+        assert!(alumni_picked.can_satisfy_required());
+    }
+}
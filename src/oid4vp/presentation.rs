@@ -1,13 +1,15 @@
+use crate::credential::{ParsedCredential, PresentableCredential};
 use crate::crypto::CryptoCurveUtils;
 
-use super::{error::OID4VPError, RequestedField, ResponseOptions};
+use super::{error::OID4VPError, RequestedField, ResponseOptions, TransactionData};
 
 use std::{collections::HashMap, ops::Deref, str::FromStr, sync::Arc};
 
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine as _};
 use openid4vp::core::{
     authorization_request::AuthorizationRequestObject, credential_format::ClaimFormatDesignation,
-    presentation_definition::PresentationDefinition, presentation_submission::DescriptorMap,
-    response::parameters::VpTokenItem,
+    input_descriptor::InputDescriptor, presentation_definition::PresentationDefinition,
+    presentation_submission::DescriptorMap, response::parameters::VpTokenItem,
 };
 use serde::Serialize;
 use ssi::{
@@ -41,7 +43,40 @@ pub enum PresentationError {
 
     #[error("Failed to parse public JsonWebKey: {0}")]
     JWK(String),
+
+    #[error("Invalid selected field `{field}`: {reason}")]
+    SelectedField { field: String, reason: String },
+}
+
+/// Check a credential's types against the type hint of a presentation definition.
+///
+/// This is a conservative pre-filter: it only rejects when the definition has a
+/// single input descriptor (so the hint describes every acceptable credential)
+/// and none of the hinted types appear on the credential.
+fn matches_credential_types_hint(definition: &PresentationDefinition, types: &[String]) -> bool {
+    if types.is_empty() || definition.input_descriptors().len() != 1 {
+        return true;
+    }
+
+    let hint = definition.credential_types_hint();
+
+    hint.is_empty()
+        || hint.iter().any(|hinted| {
+            let hinted = AsRef::<str>::as_ref(hinted);
+            hinted == "VerifiableCredential" || types.iter().any(|t| t == hinted)
+        })
 }
+
+/// Restrict a presentation definition to one of its input descriptors, to match credentials
+/// against that input descriptor alone.
+pub(crate) fn input_descriptor_definition(
+    definition: &PresentationDefinition,
+    descriptor: &InputDescriptor,
+) -> PresentationDefinition {
+    PresentationDefinition::new(definition.id().clone(), descriptor.clone())
+        .set_format(definition.format().clone())
+}
+
 /// Credential Presentation trait defines the set of standard methods
 /// each credential format must implement.
 pub trait CredentialPresentation {
@@ -65,6 +100,14 @@ pub trait CredentialPresentation {
     /// Return the credential
     fn credential(&self) -> &Self::Credential;
 
+    /// Return the credential types, excluding the base `VerifiableCredential` type.
+    ///
+    /// These are used to cheaply rule out a credential before it is matched
+    /// against a presentation definition. An empty list skips that check.
+    fn credential_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+
     /// Method to check whether a credential satisfies a given
     /// reference to a presentation definition.
     fn satisfies_presentation_definition(
@@ -85,6 +128,17 @@ pub trait CredentialPresentation {
             return false;
         }
 
+        // If the definition only accepts types the credential does not have,
+        // then skip serializing and matching the whole credential.
+        if !matches_credential_types_hint(presentation_definition, &self.credential_types()) {
+            log::debug!(
+                "Credential types do not match the presentation definition type hint: {:?}.",
+                presentation_definition.credential_types_hint()
+            );
+
+            return false;
+        }
+
         let Ok(json) = serde_json::to_value(self.credential()) else {
             // NOTE: Instead of erroring here, we return false, which will
             // indicate that the credential does not satisfy the presentation
@@ -188,10 +242,23 @@ pub trait PresentationSigner: Send + Sync + std::fmt::Debug {
     /// format of the verifiable presentation, e.g,
     /// `ldp_vp`, `jwt_vp`.
     ///
+    /// This is the preferred suite. If the verifier does not support it,
+    /// another of the `supported_cryptosuites()` may be negotiated instead.
     ///
     /// E.g., JsonWebSignature2020, ecdsa-rdfc-2019
     fn cryptosuite(&self) -> CryptosuiteString;
 
+    /// Data Integrity Cryptographic Suites the signer can produce with its
+    /// `algorithm()`, in order of preference.
+    ///
+    /// These are negotiated when the verifier does not support the preferred
+    /// `cryptosuite()`, e.g. JsonWebSignature2020 for an ES256 key.
+    ///
+    /// Defaults to only the preferred `cryptosuite()`.
+    fn supported_cryptosuites(&self) -> Vec<CryptosuiteString> {
+        vec![self.cryptosuite()]
+    }
+
     /// Return the public JWK of the signing key.
     /// as a String-encoded JSON
     fn jwk(&self) -> String;
@@ -203,59 +270,66 @@ pub trait PresentationSigner: Send + Sync + std::fmt::Debug {
 /// claims in the `vp_token` parameter.
 #[derive(Clone, Debug)]
 pub struct PresentationOptions<'a> {
-    /// Borrowed reference to the authorization request object.
-    pub(crate) request: &'a AuthorizationRequestObject,
+    /// Borrowed reference to the authorization request object, if the presentation
+    /// is made in response to one.
+    pub(crate) request: Option<&'a AuthorizationRequestObject>,
+    /// Intended audience of the presentation, e.g. the verifier's client ID.
+    pub(crate) audience: Option<String>,
+    /// Nonce binding the presentation to a session.
+    pub(crate) nonce: Option<String>,
     /// Signing callback interface that can be used to sign the `vp_token`.
     pub(crate) signer: Arc<Box<dyn PresentationSigner>>,
     /// Optional context map for the presentation.
     pub(crate) context_map: Option<HashMap<String, String>>,
+    /// Transaction data of the request, bound to the presentation when it lists the
+    /// presented credential's input descriptor in its `credential_ids`.
+    pub(crate) transaction_data: &'a [TransactionData],
+    /// The id of the input descriptor answered by the presented credential, if known.
+    pub(crate) input_descriptor_id: Option<String>,
     pub(crate) response_options: &'a ResponseOptions,
 }
 
-impl MessageSigner<WithProtocol<Algorithm, AnyProtocol>> for PresentationOptions<'_> {
+/// Signs Data Integrity proofs for [PresentationOptions] with a given cryptosuite, which may
+/// have been negotiated instead of the signer's preferred [PresentationSigner::cryptosuite].
+#[derive(Clone, Debug)]
+struct CryptosuiteSigner<'a, 'b> {
+    options: &'a PresentationOptions<'b>,
+    cryptosuite: CryptosuiteString,
+}
+
+impl MessageSigner<WithProtocol<Algorithm, AnyProtocol>> for CryptosuiteSigner<'_, '_> {
     #[allow(async_fn_in_trait)]
     async fn sign(
         self,
-        // NOTE: The `protocol` parameter is not used in this implementation, but it would
-        // be preferrable to have a `suite` parameter that would be used here instead.
-        //
-        // For example, `WithSuite` could accept a `AnySuite` type. This might already
-        // exist? But, I tried to change `AnyProtocol` to `AnySuite` to match against
-        // the [PresentationSigner::cryptosuite] method, but alas, this does not work
-        // with the `sign` method.
-        //
-        // TODO: Determine if there is a way to provide a `suite` parameter here.
         WithProtocol(alg, _protocol): WithProtocol<AlgorithmInstance, AnyProtocol>,
         message: &[u8],
     ) -> Result<Vec<u8>, MessageSignatureError> {
-        if !self.signer.algorithm().is_compatible_with(alg.algorithm()) {
+        let signer = &self.options.signer;
+        if !signer.algorithm().is_compatible_with(alg.algorithm()) {
             return Err(MessageSignatureError::UnsupportedAlgorithm(
-                self.signer.algorithm().to_string(),
+                signer.algorithm().to_string(),
             ));
         }
 
-        let signature_bytes = self
-            .signer
+        let signature_bytes = signer
             .sign(message.to_vec())
             .await
             .map_err(|e| MessageSignatureError::signature_failed(format!("{e:?}")))?;
 
-        match self.signer.cryptosuite().as_ref() {
-            "ecdsa-rdfc-2019" => self
-                .curve_utils()
-                .map(|utils| utils.ensure_raw_fixed_width_signature_encoding(signature_bytes))
-                .map_err(|e| MessageSignatureError::UnsupportedAlgorithm(format!("{e:?}")))?
-                .ok_or(MessageSignatureError::UnsupportedAlgorithm(
-                    "Unsupported signature encoding".into(),
-                )),
+        match self.cryptosuite.as_ref() {
+            // Both suites expect a raw, fixed-width signature for the signer's algorithm.
+            "ecdsa-rdfc-2019" | JsonWebSignature2020::NAME => self
+                .options
+                .encode_signature(signature_bytes)
+                .map_err(|e| MessageSignatureError::UnsupportedAlgorithm(format!("{e:?}"))),
             _ => Err(MessageSignatureError::UnsupportedAlgorithm(
-                self.signer.cryptosuite().to_string(),
+                self.cryptosuite.to_string(),
             )),
         }
     }
 }
 
-impl<M> ssi::verification_methods::Signer<M> for PresentationOptions<'_>
+impl<M> ssi::verification_methods::Signer<M> for CryptosuiteSigner<'_, '_>
 where
     M: ssi::verification_methods::VerificationMethod,
 {
@@ -268,12 +342,42 @@ where
     ) -> Result<Option<Self::MessageSigner>, ssi::claims::SignatureError> {
         Ok(method
             .controller()
-            .filter(|ctrl| **ctrl == self.signer.did())
+            .filter(|ctrl| **ctrl == self.options.signer.did())
             .map(|_| self.clone()))
     }
 }
 
-impl PresentationOptions<'_> {
+impl<'a> PresentationOptions<'a> {
+    /// Options for presenting in response to an authorization request, using
+    /// its client ID as the audience and its nonce.
+    pub fn for_request(
+        request: &'a AuthorizationRequestObject,
+        signer: Arc<Box<dyn PresentationSigner>>,
+        context_map: Option<HashMap<String, String>>,
+        transaction_data: &'a [TransactionData],
+        response_options: &'a ResponseOptions,
+    ) -> Self {
+        Self {
+            request: Some(request),
+            audience: Some(request.client_id().0.clone()),
+            nonce: Some(request.nonce().deref().clone()),
+            signer,
+            context_map,
+            transaction_data,
+            input_descriptor_id: None,
+            response_options,
+        }
+    }
+
+    /// Options for presenting the credential answering the input descriptor
+    /// `input_descriptor_id`, which selects the transaction data bound to it.
+    pub fn for_input_descriptor(&self, input_descriptor_id: Option<String>) -> Self {
+        Self {
+            input_descriptor_id,
+            ..self.clone()
+        }
+    }
+
     pub async fn verification_method_id(&self) -> Result<IriBuf, PresentationError> {
         self.signer
             .verification_method()
@@ -282,12 +386,76 @@ impl PresentationOptions<'_> {
             .map_err(|e| PresentationError::VerificationMethod(format!("{e:?}")))
     }
 
-    pub fn audience(&self) -> &String {
-        &self.request.client_id().0
+    pub fn audience(&self) -> Option<&str> {
+        self.audience.as_deref()
+    }
+
+    pub fn nonce(&self) -> Option<&str> {
+        self.nonce.as_deref()
+    }
+
+    /// The domain of a Data Integrity presentation proof, defaulting to the audience.
+    pub fn proof_domain(&self) -> Option<&str> {
+        self.response_options
+            .proof_domain
+            .as_deref()
+            .or(self.audience())
     }
 
-    pub fn nonce(&self) -> &String {
-        self.request.nonce().deref()
+    /// The challenge of a Data Integrity presentation proof, defaulting to the nonce.
+    pub fn proof_challenge(&self) -> Option<&str> {
+        self.response_options
+            .proof_challenge
+            .as_deref()
+            .or(self.nonce())
+    }
+
+    /// The transaction data entries bound to the presentation: those whose `credential_ids`
+    /// include the input descriptor of the presented credential, or all of them if it is
+    /// not known.
+    pub fn bound_transaction_data(&self) -> impl Iterator<Item = &TransactionData> {
+        self.transaction_data.iter().filter(|transaction_data| {
+            transaction_data.applies_to(self.input_descriptor_id.as_deref())
+        })
+    }
+
+    /// Whether any transaction data is bound to the presentation.
+    pub fn binds_transaction_data(&self) -> bool {
+        self.bound_transaction_data().next().is_some()
+    }
+
+    /// The hashes of the transaction data entries bound to the presentation, to be included
+    /// as `transaction_data_hashes` in the presentation.
+    pub fn transaction_data_hashes(&self) -> Vec<String> {
+        self.bound_transaction_data()
+            .map(TransactionData::hash)
+            .collect()
+    }
+
+    /// Sign a compact JWT with the given header and claims, setting the header `alg`
+    /// to the signer's algorithm.
+    pub async fn sign_jwt(
+        &self,
+        mut header: serde_json::Value,
+        claims: &serde_json::Value,
+    ) -> Result<String, PresentationError> {
+        header["alg"] = self.signer.algorithm().to_string().into();
+
+        let encode = |value: &serde_json::Value| {
+            serde_json::to_vec(value)
+                .map(|bytes| BASE64_URL_SAFE_NO_PAD.encode(bytes))
+                .map_err(|e| PresentationError::Signing(format!("{e:?}")))
+        };
+
+        let unsigned = format!("{}.{}", encode(&header)?, encode(claims)?);
+
+        let signature = self.signer.sign(unsigned.as_bytes().to_vec()).await?;
+        let signature = self.encode_signature(signature)?;
+
+        Ok(format!(
+            "{unsigned}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(signature)
+        ))
     }
 
     pub fn issuer(&self) -> String {
@@ -312,34 +480,88 @@ impl PresentationOptions<'_> {
         }
     }
 
+    /// Convert a signature produced by the signer into the raw fixed-width, low-S encoding
+    /// expected by verifiers.
+    pub fn encode_signature(&self, signature: Vec<u8>) -> Result<Vec<u8>, PresentationError> {
+        let utils = self.curve_utils()?;
+
+        utils
+            .ensure_raw_fixed_width_signature_encoding(signature)
+            .map(|signature| utils.normalize_low_s(signature))
+            .ok_or(PresentationError::Signing(
+                "Unsupported signature encoding.".into(),
+            ))
+    }
+
     /// Validate the signing cryptosuite against the supported request algorithms.
     pub fn supports_security_method(
         &self,
         format: impl Into<ClaimFormatDesignation>,
     ) -> Result<(), PresentationError> {
+        self.negotiate_cryptosuite(format).map(|_| ())
+    }
+
+    /// Select the cryptosuite used to sign a presentation of the given format.
+    ///
+    /// The signer's preferred [PresentationSigner::cryptosuite] is used when the verifier
+    /// supports it, otherwise the first of the [PresentationSigner::supported_cryptosuites]
+    /// that the verifier supports is selected.
+    pub fn negotiate_cryptosuite(
+        &self,
+        format: impl Into<ClaimFormatDesignation>,
+    ) -> Result<CryptosuiteString, PresentationError> {
         let format = format.into();
         let suite = self.signer.cryptosuite();
 
+        // Without a request there is no verifier to negotiate with.
+        let Some(request) = self.request else {
+            return Ok(suite);
+        };
+
         // Retrieve the vp_formats from the authorization request object.
-        let vp_formats = self
-            .request
+        let vp_formats = request
             .vp_formats()
             .map_err(|e| PresentationError::CryptographicSuite(format!("{e:?}")))?;
 
-        if !vp_formats.supports_security_method(&format, &suite.to_string()) {
+        let Some(negotiated) = select_cryptosuite(
+            suite.clone(),
+            self.signer.supported_cryptosuites(),
+            |candidate| vp_formats.supports_security_method(&format, &candidate.to_string()),
+        ) else {
             let err_msg = format!("Cryptographic Suite not supported for this request format: {format:?} and suite: {suite:?}. Supported Cryptographic Suites: {vp_formats:?}");
             return Err(PresentationError::CryptographicSuite(err_msg));
+        };
+
+        if negotiated != suite {
+            log::debug!("Negotiated cryptosuite {negotiated} instead of {suite}");
         }
 
-        Ok(())
+        Ok(negotiated)
     }
 
-    /// Sign a JSON presentation type for a v1 OR v2 credential.
+    /// Sign a JSON presentation type for a v1 OR v2 credential, using the signer's
+    /// preferred [PresentationSigner::cryptosuite].
     pub async fn sign_presentation(
         &self,
         // NOTE: the presentation is `unsecured` at this point.
         presentation: AnyJsonPresentation,
     ) -> Result<DataIntegrity<AnyJsonPresentation, AnySuite>, PresentationError> {
+        self.sign_presentation_with_cryptosuite(presentation, &self.signer.cryptosuite())
+            .await
+    }
+
+    /// Sign a JSON presentation type for a v1 OR v2 credential, using `suite`, e.g. the
+    /// cryptosuite negotiated with [PresentationOptions::negotiate_cryptosuite].
+    pub async fn sign_presentation_with_cryptosuite(
+        &self,
+        // NOTE: the presentation is `unsecured` at this point.
+        presentation: AnyJsonPresentation,
+        suite: &CryptosuiteString,
+    ) -> Result<DataIntegrity<AnyJsonPresentation, AnySuite>, PresentationError> {
+        let signer = CryptosuiteSigner {
+            options: self,
+            cryptosuite: suite.clone(),
+        };
         let resolver = VerificationMethodDIDResolver::new(AnyDidMethod::default());
 
         let mut proof_options = ProofOptions::new(
@@ -352,8 +574,12 @@ impl PresentationOptions<'_> {
         // See: https://openid.net/specs/openid-4-verifiable-presentations-1_0.html#section-13.1-14
         //
         // domain is the client_id of the request, in the example above.
-        proof_options.challenge = Some(self.nonce().to_owned());
-        proof_options.domains = vec![self.request.client_id().0.clone()];
+        proof_options.challenge = self.proof_challenge().map(ToOwned::to_owned);
+        proof_options.domains = self
+            .proof_domain()
+            .map(ToOwned::to_owned)
+            .into_iter()
+            .collect();
 
         if let AnyJsonPresentation::V1(_) = presentation {
             let iri_buf = IriRefBuf::new("https://w3id.org/security/data-integrity/v2".into())
@@ -372,8 +598,6 @@ impl PresentationOptions<'_> {
             .map_err(|e| PresentationError::Context(format!("{e:?}")))?
             .unwrap_or_default();
 
-        let suite = self.signer.cryptosuite();
-
         // Use the cryptosuite-specific signing method to sign the presentation.
         match suite.as_ref() {
             "ecdsa-rdfc-2019" => {
@@ -385,7 +609,7 @@ impl PresentationOptions<'_> {
                         },
                         presentation,
                         resolver,
-                        self,
+                        &signer,
                         proof_options,
                         Default::default(),
                     )
@@ -400,7 +624,7 @@ impl PresentationOptions<'_> {
                         },
                         presentation,
                         resolver,
-                        self,
+                        &signer,
                         proof_options,
                         Default::default(),
                     )
@@ -411,3 +635,263 @@ impl PresentationOptions<'_> {
         .map_err(|e| PresentationError::Signing(format!("{e:?}")))
     }
 }
+
+/// Sign a verifiable presentation of a single credential outside of an OID4VP exchange,
+/// e.g. to export it to another system.
+///
+/// The presentation is bound to `audience` and `nonce` when provided. Returns the
+/// presentation as a compact JWT for JWT-based formats, or as JSON for Data Integrity
+/// formats.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn create_standalone_vp(
+    credential: Arc<ParsedCredential>,
+    signer: Box<dyn PresentationSigner>,
+    audience: Option<String>,
+    nonce: Option<String>,
+) -> Result<String, OID4VPError> {
+    let response_options = ResponseOptions::default();
+    let options = PresentationOptions {
+        request: None,
+        audience,
+        nonce,
+        signer: Arc::new(signer),
+        context_map: None,
+        transaction_data: &[],
+        input_descriptor_id: None,
+        response_options: &response_options,
+    };
+
+    let presentable = PresentableCredential {
+        inner: credential.inner.clone(),
+        limit_disclosure: false,
+        selected_fields: None,
+    };
+
+    match presentable.as_vp_token(&options).await? {
+        VpTokenItem::String(vp) => Ok(vp),
+        item => {
+            serde_json::to_string(&item).map_err(|e| OID4VPError::VpTokenCreate(format!("{e:?}")))
+        }
+    }
+}
+
+/// Select the preferred suite if it is supported, otherwise the first supported suite
+/// the signer can produce.
+fn select_cryptosuite(
+    preferred: CryptosuiteString,
+    signer_suites: Vec<CryptosuiteString>,
+    is_supported: impl Fn(&str) -> bool,
+) -> Option<CryptosuiteString> {
+    std::iter::once(preferred)
+        .chain(signer_suites)
+        .find(|suite| is_supported(suite.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::oid4vp::holder::tests::test_request;
+
+    #[test]
+    fn cryptosuite_negotiation() {
+        let suite = |name: &str| CryptosuiteString::new(name.to_string()).unwrap();
+        let signer_suites = || vec![suite("ecdsa-rdfc-2019"), suite(JsonWebSignature2020::NAME)];
+
+        // The preferred suite is used when supported.
+        assert_eq!(
+            select_cryptosuite(suite("ecdsa-rdfc-2019"), signer_suites(), |_| true),
+            Some(suite("ecdsa-rdfc-2019"))
+        );
+
+        // An alternative suite the signer supports is negotiated.
+        assert_eq!(
+            select_cryptosuite(suite("ecdsa-rdfc-2019"), signer_suites(), |candidate| {
+                candidate == JsonWebSignature2020::NAME
+            }),
+            Some(suite(JsonWebSignature2020::NAME))
+        );
+
+        // Signers only supporting their preferred suite are not negotiated with.
+        assert_eq!(
+            select_cryptosuite(
+                suite("ecdsa-rdfc-2019"),
+                vec![suite("ecdsa-rdfc-2019")],
+                |candidate| candidate == JsonWebSignature2020::NAME
+            ),
+            None
+        );
+
+        // No suite of the signer is supported by the verifier.
+        assert_eq!(
+            select_cryptosuite(suite("ecdsa-rdfc-2019"), signer_suites(), |candidate| {
+                candidate == "eddsa-rdfc-2022"
+            }),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn message_signer_uses_negotiated_cryptosuite() {
+        let signer: Arc<Box<dyn PresentationSigner>> =
+            Arc::new(Box::new(crate::tests::load_signer()));
+        let response_options = ResponseOptions::default();
+        let options = PresentationOptions {
+            request: None,
+            audience: None,
+            nonce: None,
+            signer,
+            context_map: None,
+            transaction_data: &[],
+            input_descriptor_id: None,
+            response_options: &response_options,
+        };
+        let sign = |cryptosuite: &str| {
+            CryptosuiteSigner {
+                options: &options,
+                cryptosuite: CryptosuiteString::new(cryptosuite.to_string()).unwrap(),
+            }
+            .sign(
+                WithProtocol(AlgorithmInstance::ES256, AnyProtocol::None),
+                b"message",
+            )
+        };
+
+        // The signer prefers ecdsa-rdfc-2019, but the negotiated suite is used.
+        assert_eq!(sign(JsonWebSignature2020::NAME).await.unwrap().len(), 64);
+        assert!(matches!(
+            sign("eddsa-rdfc-2022").await,
+            Err(MessageSignatureError::UnsupportedAlgorithm(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn standalone_vp_is_signed_by_the_holder() {
+        let signer = crate::tests::load_signer();
+        let holder = signer.did();
+        let credential = ParsedCredential::new_ldp_vc(
+            crate::credential::json_vc::JsonVc::new_from_json(
+                include_str!("../../tests/examples/alumni_vc.json").into(),
+            )
+            .unwrap(),
+        );
+
+        let vp = create_standalone_vp(
+            credential,
+            Box::new(signer),
+            Some("https://verifier.example".into()),
+            Some("standalone-nonce".into()),
+        )
+        .await
+        .unwrap();
+        let vp: serde_json::Value = serde_json::from_str(&vp).unwrap();
+
+        assert_eq!(vp["holder"], holder);
+        let proof = &vp["proof"];
+        assert_eq!(proof["type"], "DataIntegrityProof");
+        assert_eq!(proof["cryptosuite"], "ecdsa-rdfc-2019");
+        assert_eq!(proof["proofPurpose"], "authentication");
+        assert_eq!(proof["challenge"], "standalone-nonce");
+        assert!(proof["verificationMethod"]
+            .as_str()
+            .is_some_and(|vm| vm.starts_with(&holder)));
+        assert!(proof["proofValue"].as_str().is_some_and(|v| !v.is_empty()));
+    }
+
+    #[test]
+    fn transaction_data_hashes_follow_input_descriptor() {
+        let transaction_data = ["pid", "card"].map(|credential_id| {
+            TransactionData::decode(
+                &BASE64_URL_SAFE_NO_PAD.encode(
+                    serde_json::json!({
+                        "type": "payment_data",
+                        "credential_ids": [credential_id],
+                    })
+                    .to_string(),
+                ),
+            )
+            .unwrap()
+        });
+        let response_options = ResponseOptions::default();
+        let options = PresentationOptions {
+            request: None,
+            audience: None,
+            nonce: None,
+            signer: Arc::new(Box::new(crate::tests::load_signer())),
+            context_map: None,
+            transaction_data: &transaction_data,
+            input_descriptor_id: None,
+            response_options: &response_options,
+        };
+
+        assert_eq!(options.transaction_data_hashes().len(), 2);
+        assert_eq!(
+            options
+                .for_input_descriptor(Some("card".into()))
+                .transaction_data_hashes(),
+            vec![transaction_data[1].hash()]
+        );
+        assert!(!options
+            .for_input_descriptor(Some("loyalty".into()))
+            .binds_transaction_data());
+    }
+
+    #[test]
+    fn proof_domain_and_challenge_overrides() {
+        let request = test_request(
+            serde_json::json!({
+                "id": "proof-options-test",
+                "input_descriptors": []
+            }),
+            None,
+        );
+        let signer: Arc<Box<dyn PresentationSigner>> =
+            Arc::new(Box::new(crate::tests::load_signer()));
+
+        let defaults = ResponseOptions::default();
+        let options =
+            PresentationOptions::for_request(&request, signer.clone(), None, &[], &defaults);
+        assert_eq!(
+            options.proof_domain(),
+            Some("https://verifier.example/response")
+        );
+        assert_eq!(options.proof_challenge(), Some("n-0S6_WzA2Mj"));
+
+        let overrides = ResponseOptions {
+            proof_domain: Some("verifier.example".into()),
+            proof_challenge: Some("challenge".into()),
+            ..Default::default()
+        };
+        let options = PresentationOptions::for_request(&request, signer, None, &[], &overrides);
+        assert_eq!(options.proof_domain(), Some("verifier.example"));
+        assert_eq!(options.proof_challenge(), Some("challenge"));
+    }
+
+    #[test]
+    fn credential_types_hint_prefilter() {
+        let definition: PresentationDefinition = serde_json::from_value(serde_json::json!({
+            "id": "type-hint-test",
+            "input_descriptors": [{
+                "id": "degree",
+                "constraints": {
+                    "fields": [{
+                        "path": ["$.type"],
+                        "filter": {
+                            "type": "array",
+                            "contains": { "const": "UniversityDegreeCredential" }
+                        }
+                    }]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let degree = vec!["UniversityDegreeCredential".to_string()];
+        let license = vec!["DriversLicenseCredential".to_string()];
+
+        assert!(matches_credential_types_hint(&definition, &degree));
+        assert!(!matches_credential_types_hint(&definition, &license));
+        // Credentials that do not report their types are never ruled out.
+        assert!(matches_credential_types_hint(&definition, &[]));
+    }
+}
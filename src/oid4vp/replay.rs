@@ -0,0 +1,265 @@
+//! Replay protection for OID4VP responses.
+//!
+//! The nonces of requests that have been responded to are recorded in storage, so that a
+//! second response to the same request, e.g. after the user double-taps, is rejected.
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::common::*;
+use crate::storage_manager::StorageManagerInterface;
+
+use super::error::OID4VPError;
+
+/// Internal prefix for used nonce keys.
+const KEY_PREFIX: &str = "UsedNonce.";
+
+/// Records the nonces of authorization requests that have been responded to.
+///
+/// A nonce is remembered for `window_secs` seconds, and at most `max_entries` nonces are
+/// kept, the least recently claimed being forgotten first.
+#[derive(Debug, uniffi::Object)]
+pub struct NonceTracker {
+    storage: Arc<dyn StorageManagerInterface>,
+    window: Duration,
+    max_entries: u32,
+    /// Serializes the check and record of a nonce, so concurrent responses cannot both pass.
+    lock: tokio::sync::Mutex<()>,
+}
+
+#[uniffi::export]
+impl NonceTracker {
+    /// Fails if `max_entries` is zero, as no nonce could be remembered.
+    #[uniffi::constructor]
+    pub fn new(
+        storage: Arc<dyn StorageManagerInterface>,
+        window_secs: u64,
+        max_entries: u32,
+    ) -> Result<Arc<Self>, OID4VPError> {
+        if max_entries == 0 {
+            return Err(OID4VPError::NonceTrackerConfiguration(
+                "max_entries must be at least 1".into(),
+            ));
+        }
+
+        Ok(Arc::new(Self {
+            storage,
+            window: Duration::from_secs(window_secs),
+            max_entries,
+            lock: tokio::sync::Mutex::new(()),
+        }))
+    }
+}
+
+impl NonceTracker {
+    /// Record that a response to the request with `nonce` is being sent.
+    ///
+    /// Fails with [OID4VPError::ReplayDetected] if a response was already sent for the nonce
+    /// within the window.
+    pub(crate) async fn claim(&self, nonce: &str) -> Result<(), OID4VPError> {
+        let _guard = self.lock.lock().await;
+        let now = unix_time_ms();
+        let key = nonce_key(nonce);
+        let entries = self.entries().await?;
+
+        if entries.iter().any(|(used, used_key)| {
+            *used_key == key && now.saturating_sub(used.used_at) < self.window_ms()
+        }) {
+            return Err(OID4VPError::ReplayDetected);
+        }
+
+        let used = UsedNonce {
+            used_at: now,
+            sequence: entries
+                .iter()
+                .map(|(used, _)| used.sequence)
+                .max()
+                .map_or(0, |sequence| sequence + 1),
+        };
+        self.storage
+            .add(key.clone(), used.to_value())
+            .await
+            .map_err(|e| OID4VPError::NonceStorage(e.to_string()))?;
+
+        self.prune(now, &key, entries).await
+    }
+
+    /// Forget a claimed nonce, e.g. because the response could not be delivered and may be
+    /// retried.
+    pub(crate) async fn release(&self, nonce: &str) -> Result<(), OID4VPError> {
+        let _guard = self.lock.lock().await;
+
+        self.storage
+            .remove(nonce_key(nonce))
+            .await
+            .map_err(|e| OID4VPError::NonceStorage(e.to_string()))
+    }
+
+    fn window_ms(&self) -> u64 {
+        u64::try_from(self.window.as_millis()).unwrap_or(u64::MAX)
+    }
+
+    /// Load the recorded nonces. Entries that cannot be parsed are treated as expired.
+    async fn entries(&self) -> Result<Vec<(UsedNonce, Key)>, OID4VPError> {
+        let keys = self
+            .storage
+            .list()
+            .await
+            .map_err(|e| OID4VPError::NonceStorage(e.to_string()))?;
+
+        let mut entries = Vec::new();
+        for key in keys
+            .into_iter()
+            .filter(|key| key.strip_prefix(KEY_PREFIX).is_some())
+        {
+            let used = self
+                .storage
+                .get(key.clone())
+                .await
+                .map_err(|e| OID4VPError::NonceStorage(e.to_string()))?
+                .and_then(|value| UsedNonce::parse(&value));
+            entries.push((used.unwrap_or_default(), key));
+        }
+
+        Ok(entries)
+    }
+
+    /// Remove nonces outside the window, and the least recently claimed nonces beyond
+    /// `max_entries`. The nonce just claimed, `claimed`, is always kept.
+    ///
+    /// Nonces are ordered by their claim sequence rather than their timestamp, so a clock
+    /// that moves backwards cannot cause the newest nonce to be evicted.
+    async fn prune(
+        &self,
+        now: u64,
+        claimed: &Key,
+        entries: Vec<(UsedNonce, Key)>,
+    ) -> Result<(), OID4VPError> {
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .filter(|(_, key)| key != claimed)
+            .collect();
+
+        // Newest first, so everything past the remaining slots is the oldest.
+        entries.sort_by(|(a, _), (b, _)| b.sequence.cmp(&a.sequence));
+        let remaining = self.max_entries as usize - 1;
+        for (index, (used, key)) in entries.into_iter().enumerate() {
+            if index >= remaining || now.saturating_sub(used.used_at) >= self.window_ms() {
+                self.storage
+                    .remove(key)
+                    .await
+                    .map_err(|e| OID4VPError::NonceStorage(e.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// When a nonce was claimed, stored as `<used_at>:<sequence>`.
+#[derive(Debug, Default, Clone, Copy)]
+struct UsedNonce {
+    /// Milliseconds since the Unix epoch.
+    used_at: u64,
+    /// Increases with every claim, regardless of the clock.
+    sequence: u64,
+}
+
+impl UsedNonce {
+    fn parse(value: &Value) -> Option<Self> {
+        let (used_at, sequence) = std::str::from_utf8(&value.0).ok()?.split_once(':')?;
+
+        Some(Self {
+            used_at: used_at.parse().ok()?,
+            sequence: sequence.parse().ok()?,
+        })
+    }
+
+    fn to_value(self) -> Value {
+        Value(format!("{}:{}", self.used_at, self.sequence).into_bytes())
+    }
+}
+
+/// The storage key of a nonce. Nonces are hashed as they are chosen by the verifier and may
+/// not be valid storage keys.
+fn nonce_key(nonce: &str) -> Key {
+    Key::with_prefix(KEY_PREFIX, &hex::encode(Sha256::digest(nonce.as_bytes())))
+}
+
+/// The current time in milliseconds since the Unix epoch.
+fn unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|elapsed| u64::try_from(elapsed.as_millis()).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_store::LocalStore;
+
+    #[tokio::test]
+    async fn rejects_reused_nonces() {
+        let tracker = NonceTracker::new(Arc::new(LocalStore::new()), 600, 10).unwrap();
+
+        tracker.claim("n-0S6_WzA2Mj").await.unwrap();
+        assert!(matches!(
+            tracker.claim("n-0S6_WzA2Mj").await,
+            Err(OID4VPError::ReplayDetected)
+        ));
+
+        tracker.release("n-0S6_WzA2Mj").await.unwrap();
+        tracker.claim("n-0S6_WzA2Mj").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn keeps_at_most_max_entries() {
+        let storage = Arc::new(LocalStore::new());
+        let tracker = NonceTracker::new(storage.clone(), 600, 2).unwrap();
+
+        for nonce in ["a", "b", "c"] {
+            tracker.claim(nonce).await.unwrap();
+        }
+
+        assert_eq!(storage.list().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn keeps_claimed_nonce_when_clock_goes_back() {
+        let storage = Arc::new(LocalStore::new());
+        let tracker = NonceTracker::new(storage.clone(), 600, 1).unwrap();
+
+        // A nonce claimed earlier, while the clock was ahead.
+        storage
+            .add(
+                nonce_key("ahead"),
+                UsedNonce {
+                    used_at: unix_time_ms() + 60_000,
+                    sequence: 0,
+                }
+                .to_value(),
+            )
+            .await
+            .unwrap();
+
+        tracker.claim("now").await.unwrap();
+
+        assert_eq!(storage.list().await.unwrap(), vec![nonce_key("now")]);
+        assert!(matches!(
+            tracker.claim("now").await,
+            Err(OID4VPError::ReplayDetected)
+        ));
+    }
+
+    #[test]
+    fn rejects_zero_max_entries() {
+        assert!(matches!(
+            NonceTracker::new(Arc::new(LocalStore::new()), 600, 0),
+            Err(OID4VPError::NonceTrackerConfiguration(_))
+        ));
+    }
+}
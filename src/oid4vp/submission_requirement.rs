@@ -0,0 +1,335 @@
+use openid4vp::core::presentation_definition::{
+    PresentationDefinition, SubmissionRequirement as DefinitionRequirement,
+    SubmissionRequirementBase,
+};
+
+/// How many of the input descriptors selected by a submission requirement must be presented.
+#[derive(Debug, Clone, PartialEq, uniffi::Enum)]
+pub enum SubmissionRule {
+    /// Every selected input descriptor must be presented.
+    All,
+    /// A subset of the selected input descriptors must be presented, either exactly `count`
+    /// or between `min` and `max`.
+    Pick {
+        count: Option<u64>,
+        min: Option<u64>,
+        max: Option<u64>,
+    },
+}
+
+/// A submission requirement of a presentation definition, resolved to the input descriptors
+/// it selects from.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct SubmissionRequirement {
+    pub name: Option<String>,
+    pub purpose: Option<String>,
+    pub rule: SubmissionRule,
+    /// Input descriptors selected through `from`, or through every nested requirement of
+    /// `from_nested`.
+    pub input_descriptor_ids: Vec<String>,
+}
+
+impl SubmissionRequirement {
+    /// Resolve the `submission_requirements` of a presentation definition.
+    ///
+    /// Nested requirements are flattened: the result only has [SubmissionRule::All] when the
+    /// requirement and all of its nested requirements use the `all` rule.
+    pub(crate) fn from_definition(definition: &PresentationDefinition) -> Vec<Self> {
+        definition
+            .submission_requirements()
+            .into_iter()
+            .flatten()
+            .map(|requirement| Self::resolve(definition, requirement))
+            .collect()
+    }
+
+    fn resolve(definition: &PresentationDefinition, requirement: &DefinitionRequirement) -> Self {
+        let mut all = matches!(requirement, DefinitionRequirement::All(_));
+        let mut input_descriptor_ids = vec![];
+
+        let object = match requirement_base(requirement) {
+            SubmissionRequirementBase::From {
+                from,
+                submission_requirement_base,
+            } => {
+                input_descriptor_ids.extend(
+                    group_descriptor_ids(definition, from)
+                        .into_iter()
+                        .map(ToOwned::to_owned),
+                );
+                submission_requirement_base
+            }
+            SubmissionRequirementBase::FromNested {
+                from_nested,
+                submission_requirement_base,
+            } => {
+                for nested in from_nested {
+                    let nested = Self::resolve(definition, nested);
+                    all &= nested.rule == SubmissionRule::All;
+                    input_descriptor_ids.extend(nested.input_descriptor_ids);
+                }
+                submission_requirement_base
+            }
+        };
+
+        let rule = match requirement {
+            DefinitionRequirement::All(_) if all => SubmissionRule::All,
+            DefinitionRequirement::All(_) => SubmissionRule::Pick {
+                count: None,
+                min: None,
+                max: None,
+            },
+            DefinitionRequirement::Pick(pick) => SubmissionRule::Pick {
+                count: pick.count.map(u64::from),
+                min: pick.min.map(u64::from),
+                max: pick.max.map(u64::from),
+            },
+        };
+
+        Self {
+            name: object.name.clone(),
+            purpose: object.purpose.clone(),
+            rule,
+            input_descriptor_ids,
+        }
+    }
+}
+
+/// The submission requirements of a presentation definition, evaluated against the input
+/// descriptors that the matched credentials satisfy.
+pub(crate) struct SubmissionEvaluation {
+    /// Whether every submission requirement can be met.
+    pub satisfiable: bool,
+    /// Ids of the unsatisfied input descriptors selected by the requirements that cannot be
+    /// met, in the order of the presentation definition.
+    pub missing: Vec<String>,
+}
+
+impl SubmissionEvaluation {
+    /// Evaluate a presentation definition, given the ids of its satisfied input descriptors.
+    ///
+    /// Without submission requirements every input descriptor must be satisfied.
+    pub(crate) fn new(definition: &PresentationDefinition, satisfied: &[String]) -> Self {
+        let descriptors = definition.input_descriptors();
+        let is_satisfied = |id: &str| satisfied.iter().any(|satisfied| satisfied == id);
+
+        let (satisfiable, missing) = match definition.submission_requirements() {
+            Some(requirements) if !requirements.is_empty() => {
+                let evaluations: Vec<_> = requirements
+                    .iter()
+                    .map(|requirement| evaluate(definition, requirement, &is_satisfied))
+                    .collect();
+                (
+                    evaluations.iter().all(|(met, _)| *met),
+                    evaluations
+                        .into_iter()
+                        .flat_map(|(_, missing)| missing)
+                        .collect(),
+                )
+            }
+            _ => {
+                let missing: Vec<&str> = descriptors
+                    .iter()
+                    .map(|descriptor| descriptor.id.as_str())
+                    .filter(|id| !is_satisfied(id))
+                    .collect();
+                (missing.is_empty(), missing)
+            }
+        };
+
+        Self {
+            satisfiable,
+            missing: descriptors
+                .iter()
+                .filter(|descriptor| missing.contains(&descriptor.id.as_str()))
+                .map(|descriptor| descriptor.id.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Whether a submission requirement can be met, along with the unsatisfied input descriptors
+/// it selects when it cannot.
+fn evaluate<'a>(
+    definition: &'a PresentationDefinition,
+    requirement: &'a DefinitionRequirement,
+    is_satisfied: &impl Fn(&str) -> bool,
+) -> (bool, Vec<&'a str>) {
+    match requirement_base(requirement) {
+        SubmissionRequirementBase::From { from, .. } => {
+            let ids = group_descriptor_ids(definition, from);
+            let available = ids.iter().filter(|id| is_satisfied(id)).count();
+            if available >= needed_choices(requirement, ids.len()) {
+                return (true, vec![]);
+            }
+            (
+                false,
+                ids.into_iter().filter(|id| !is_satisfied(id)).collect(),
+            )
+        }
+        SubmissionRequirementBase::FromNested { from_nested, .. } => {
+            let nested: Vec<_> = from_nested
+                .iter()
+                .map(|nested| evaluate(definition, nested, is_satisfied))
+                .collect();
+            let available = nested.iter().filter(|(met, _)| *met).count();
+            if available >= needed_choices(requirement, nested.len()) {
+                return (true, vec![]);
+            }
+            (
+                false,
+                nested
+                    .into_iter()
+                    .flat_map(|(_, missing)| missing)
+                    .collect(),
+            )
+        }
+    }
+}
+
+fn requirement_base(requirement: &DefinitionRequirement) -> &SubmissionRequirementBase {
+    match requirement {
+        DefinitionRequirement::All(base) => base,
+        DefinitionRequirement::Pick(pick) => &pick.submission_requirement,
+    }
+}
+
+/// How many of the `choices` selected by a submission requirement must be presented.
+fn needed_choices(requirement: &DefinitionRequirement, choices: usize) -> usize {
+    match requirement {
+        DefinitionRequirement::All(_) => choices,
+        DefinitionRequirement::Pick(pick) => pick
+            .count
+            .or(pick.min)
+            .map(|needed| u64::from(needed) as usize)
+            .unwrap_or_default(),
+    }
+}
+
+/// Ids of the input descriptors in a submission requirement group.
+fn group_descriptor_ids<'a>(definition: &'a PresentationDefinition, group: &str) -> Vec<&'a str> {
+    definition
+        .input_descriptors()
+        .iter()
+        .filter(|descriptor| descriptor.group.iter().any(|g| g == group))
+        .map(|descriptor| descriptor.id.as_str())
+        .collect()
+}
+
+/// Whether the input descriptor must be presented to satisfy the presentation definition.
+///
+/// Without submission requirements every input descriptor is required. Otherwise only those
+/// that a requirement cannot do without are: those selected by an `all` rule, or by a `pick`
+/// rule whose `count` or `min` covers every one of its choices.
+pub(crate) fn is_input_descriptor_required(
+    definition: &PresentationDefinition,
+    input_descriptor_id: &str,
+) -> bool {
+    match definition.submission_requirements() {
+        Some(requirements) if !requirements.is_empty() => requirements
+            .iter()
+            .any(|requirement| requires(definition, requirement, input_descriptor_id)),
+        _ => true,
+    }
+}
+
+/// Whether a submission requirement can only be met by presenting the input descriptor.
+fn requires(
+    definition: &PresentationDefinition,
+    requirement: &DefinitionRequirement,
+    input_descriptor_id: &str,
+) -> bool {
+    match requirement_base(requirement) {
+        SubmissionRequirementBase::From { from, .. } => {
+            let ids = group_descriptor_ids(definition, from);
+            ids.contains(&input_descriptor_id)
+                && needed_choices(requirement, ids.len()) >= ids.len()
+        }
+        SubmissionRequirementBase::FromNested { from_nested, .. } => {
+            needed_choices(requirement, from_nested.len()) >= from_nested.len()
+                && from_nested
+                    .iter()
+                    .any(|nested| requires(definition, nested, input_descriptor_id))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_submission_requirements() {
+        let definition: PresentationDefinition = serde_json::from_value(serde_json::json!({
+            "id": "submission-requirements-test",
+            "submission_requirements": [
+                { "name": "Citizenship", "rule": "all", "from": "A" },
+                { "name": "Proof of address", "rule": "pick", "count": 1, "from": "B" }
+            ],
+            "input_descriptors": [
+                { "id": "passport", "group": ["A"], "constraints": {} },
+                { "id": "utility_bill", "group": ["B"], "constraints": {} },
+                { "id": "bank_statement", "group": ["B"], "constraints": {} }
+            ]
+        }))
+        .unwrap();
+
+        let requirements = SubmissionRequirement::from_definition(&definition);
+
+        assert_eq!(
+            requirements,
+            vec![
+                SubmissionRequirement {
+                    name: Some("Citizenship".into()),
+                    purpose: None,
+                    rule: SubmissionRule::All,
+                    input_descriptor_ids: vec!["passport".into()],
+                },
+                SubmissionRequirement {
+                    name: Some("Proof of address".into()),
+                    purpose: None,
+                    rule: SubmissionRule::Pick {
+                        count: Some(1),
+                        min: None,
+                        max: None,
+                    },
+                    input_descriptor_ids: vec!["utility_bill".into(), "bank_statement".into()],
+                },
+            ]
+        );
+
+        assert!(is_input_descriptor_required(&definition, "passport"));
+        assert!(!is_input_descriptor_required(&definition, "utility_bill"));
+    }
+
+    #[test]
+    fn pick_of_every_choice_requires_each_input_descriptor() {
+        let definition: PresentationDefinition = serde_json::from_value(serde_json::json!({
+            "id": "submission-requirements-test",
+            "submission_requirements": [
+                { "rule": "pick", "count": 2, "from": "B" }
+            ],
+            "input_descriptors": [
+                { "id": "utility_bill", "group": ["B"], "constraints": {} },
+                { "id": "bank_statement", "group": ["B"], "constraints": {} }
+            ]
+        }))
+        .unwrap();
+
+        assert!(is_input_descriptor_required(&definition, "utility_bill"));
+        assert!(is_input_descriptor_required(&definition, "bank_statement"));
+
+        let without_requirements: PresentationDefinition =
+            serde_json::from_value(serde_json::json!({
+                "id": "submission-requirements-test",
+                "input_descriptors": [
+                    { "id": "utility_bill", "constraints": {} }
+                ]
+            }))
+            .unwrap();
+        assert!(is_input_descriptor_required(
+            &without_requirements,
+            "utility_bill"
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use super::error::OID4VPError;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use openid4vp::core::{authorization_request::AuthorizationRequestObject, object::TypedParameter};
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+
+/// Hash algorithm used for `transaction_data_hashes`, as registered in the
+/// IANA "Named Information Hash Algorithm" registry.
+pub const TRANSACTION_DATA_HASH_ALG: &str = "sha-256";
+
+/// A `transaction_data` entry of an authorization request, e.g. a payment or
+/// qualified electronic signature the holder is asked to confirm.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct TransactionData {
+    /// The transaction data type, identifying how `details` should be displayed.
+    pub transaction_type: String,
+    /// The input descriptor IDs of the credentials that may authorize the transaction.
+    pub credential_ids: Vec<String>,
+    /// The decoded transaction data object, as a JSON encoded string.
+    pub details: String,
+    /// The base64url-encoded entry, exactly as received in the request.
+    pub encoded: String,
+}
+
+impl TransactionData {
+    /// Decode a base64url-encoded `transaction_data` entry.
+    pub fn decode(encoded: &str) -> Result<Self, OID4VPError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(encoded.trim_end_matches('='))
+            .map_err(|e| OID4VPError::TransactionData(format!("invalid base64url: {e}")))?;
+
+        let details: Json = serde_json::from_slice(&bytes)
+            .map_err(|e| OID4VPError::TransactionData(format!("invalid JSON: {e}")))?;
+
+        let transaction_type = details
+            .get("type")
+            .and_then(Json::as_str)
+            .ok_or_else(|| OID4VPError::TransactionData("missing `type`".into()))?
+            .to_string();
+
+        let credential_ids = details
+            .get("credential_ids")
+            .and_then(Json::as_array)
+            .filter(|ids| !ids.is_empty())
+            .ok_or_else(|| OID4VPError::TransactionData("missing `credential_ids`".into()))?
+            .iter()
+            .map(|id| {
+                id.as_str().map(ToOwned::to_owned).ok_or_else(|| {
+                    OID4VPError::TransactionData("`credential_ids` must be strings".into())
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            transaction_type,
+            credential_ids,
+            details: details.to_string(),
+            encoded: encoded.to_string(),
+        })
+    }
+
+    /// Whether the entry may be authorized by the credential answering the input descriptor
+    /// `input_descriptor_id`. Entries apply to every credential if it is not known.
+    pub fn applies_to(&self, input_descriptor_id: Option<&str>) -> bool {
+        input_descriptor_id.is_none_or(|id| self.credential_ids.iter().any(|cid| cid == id))
+    }
+
+    /// The base64url-encoded SHA-256 hash of the entry, as included in
+    /// `transaction_data_hashes`.
+    pub fn hash(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.encoded.as_bytes()))
+    }
+}
+
+/// The raw `transaction_data` authorization request parameter.
+#[derive(Debug, Clone)]
+struct TransactionDataParameter(Vec<String>);
+
+impl TypedParameter for TransactionDataParameter {
+    const KEY: &'static str = "transaction_data";
+}
+
+impl TryFrom<Json> for TransactionDataParameter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Json) -> Result<Self, Self::Error> {
+        Ok(Self(serde_json::from_value(value)?))
+    }
+}
+
+impl From<TransactionDataParameter> for Json {
+    fn from(value: TransactionDataParameter) -> Self {
+        value.0.into()
+    }
+}
+
+/// Parse the `transaction_data` entries of an authorization request, if any.
+pub fn parse_transaction_data(
+    request: &AuthorizationRequestObject,
+) -> Result<Vec<TransactionData>, OID4VPError> {
+    let Some(parameter) = request.get::<TransactionDataParameter>() else {
+        return Ok(Vec::new());
+    };
+
+    let parameter = parameter.map_err(|e| OID4VPError::TransactionData(format!("{e:?}")))?;
+
+    parameter
+        .0
+        .iter()
+        .map(|encoded| TransactionData::decode(encoded))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_transaction_data() {
+        let details = serde_json::json!({
+            "type": "payment_data",
+            "credential_ids": ["pid"],
+            "payee": "Merchant",
+            "amount": "10.00",
+        });
+        let encoded = URL_SAFE_NO_PAD.encode(details.to_string());
+
+        let transaction_data = TransactionData::decode(&encoded).unwrap();
+
+        assert_eq!(transaction_data.transaction_type, "payment_data");
+        assert_eq!(transaction_data.credential_ids, vec!["pid".to_string()]);
+        assert_eq!(
+            serde_json::from_str::<Json>(&transaction_data.details).unwrap(),
+            details
+        );
+        assert_eq!(
+            transaction_data.hash(),
+            URL_SAFE_NO_PAD.encode(Sha256::digest(encoded.as_bytes()))
+        );
+    }
+
+    #[test]
+    fn transaction_data_applies_to_listed_credentials() {
+        let encoded =
+            URL_SAFE_NO_PAD.encode(r#"{"type":"payment_data","credential_ids":["pid","card"]}"#);
+        let transaction_data = TransactionData::decode(&encoded).unwrap();
+
+        assert!(transaction_data.applies_to(Some("card")));
+        assert!(!transaction_data.applies_to(Some("loyalty")));
+        assert!(transaction_data.applies_to(None));
+    }
+
+    #[test]
+    fn rejects_transaction_data_without_credential_ids() {
+        let encoded = URL_SAFE_NO_PAD.encode(r#"{"type":"payment_data"}"#);
+
+        assert!(TransactionData::decode(&encoded).is_err());
+    }
+}
@@ -13,6 +13,15 @@ pub enum PopError {
     #[error("{_0}")]
     SerializationError(#[from] serde_json::Error),
 
+    #[error("{_0}")]
+    DecodeError(#[from] base64::DecodeError),
+
     #[error("{_0}")]
     ConversionError(#[from] oid4vci::proof_of_possession::ConversionError),
+
+    #[error("{_0}")]
+    CryptoError(#[from] crate::crypto::CryptoError),
+
+    #[error("the signing key did not produce a P-256 ECDSA signature")]
+    InvalidSignature,
 }
@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use base64::prelude::*;
 use oid4vci::{
@@ -12,19 +12,25 @@ use url::Url;
 
 pub use error::*;
 
-use crate::{did, oid4vci::Oid4vciError};
+use crate::{
+    crypto::{CryptoCurveUtils, KeyAlias, KeyStore},
+    did,
+    oid4vci::Oid4vciError,
+};
 
 mod error;
 
 // TODO: consider unifying prepare and complete fns by using a trait for
 // signing/crypto functions similar to `HttpClient` for requests
-#[uniffi::export]
+#[uniffi::export(default(issued_at = None, jti = None))]
 pub async fn generate_pop_prepare(
     audience: String,
     nonce: Option<String>,
     did_method: did::DidMethod,
     public_jwk: String,
     duration_in_secs: Option<i64>,
+    issued_at: Option<i64>,
+    jti: Option<String>,
 ) -> Result<Vec<u8>, PopError> {
     let issuer = did_method.did_from_jwk(&public_jwk)?;
     let vm = did_method.vm_from_jwk(&public_jwk).await?;
@@ -39,16 +45,51 @@ pub async fn generate_pop_prepare(
         nonce: nonce.map(Nonce::new),
     };
 
-    let signing_input = ProofOfPossession::generate(
-        &pop_params,
-        duration_in_secs
-            .map(time::Duration::seconds)
-            .unwrap_or(time::Duration::minutes(5)),
-    )
-    .to_jwt_signing_input()
-    .map_err(PopError::from)?;
+    let duration = duration_in_secs
+        .map(time::Duration::seconds)
+        .unwrap_or(time::Duration::minutes(5));
+
+    let signing_input = ProofOfPossession::generate(&pop_params, duration)
+        .to_jwt_signing_input()
+        .map_err(PopError::from)?;
+
+    if issued_at.is_none() && jti.is_none() {
+        return Ok(signing_input);
+    }
 
-    Ok(signing_input)
+    override_claims(signing_input, issued_at, duration, jti)
+}
+
+/// Set a caller-supplied `iat` (moving `exp` to keep the same validity duration) and `jti` on
+/// the claims of a JWT signing input.
+fn override_claims(
+    signing_input: Vec<u8>,
+    issued_at: Option<i64>,
+    duration: time::Duration,
+    jti: Option<String>,
+) -> Result<Vec<u8>, PopError> {
+    let signing_input = String::from_utf8_lossy(&signing_input);
+    let (header, payload) = signing_input
+        .split_once('.')
+        .unwrap_or((&signing_input, ""));
+
+    let mut claims: serde_json::Value =
+        serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(payload)?)?;
+
+    if let Some(issued_at) = issued_at {
+        claims["iat"] = issued_at.into();
+        if claims.get("exp").is_some() {
+            claims["exp"] = (issued_at + duration.whole_seconds()).into();
+        }
+    }
+
+    if let Some(jti) = jti {
+        claims["jti"] = jti.into();
+    }
+
+    let payload = BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims)?);
+
+    Ok(format!("{header}.{payload}").into_bytes())
 }
 
 #[uniffi::export]
@@ -68,3 +109,132 @@ pub fn generate_pop_complete(
     ]
     .join("."))
 }
+
+/// Generate a complete proof of possession JWT, signed with the key stored in `keystore`
+/// under `key_alias`.
+///
+/// This combines [generate_pop_prepare] and [generate_pop_complete] for hosts whose keys
+/// are available through a [KeyStore]. Hosts signing with an external HSM should use those
+/// instead.
+#[uniffi::export]
+pub async fn generate_pop(
+    audience: String,
+    nonce: Option<String>,
+    did_method: did::DidMethod,
+    keystore: Arc<dyn KeyStore>,
+    key_alias: KeyAlias,
+    duration_in_secs: Option<i64>,
+) -> Result<String, PopError> {
+    let signing_key = keystore.get_signing_key(key_alias)?;
+
+    let signing_input = generate_pop_prepare(
+        audience,
+        nonce,
+        did_method,
+        signing_key.jwk()?,
+        duration_in_secs,
+        None,
+        None,
+    )
+    .await?;
+
+    // The key may produce a DER or a raw fixed-width signature, but the JWT needs the latter,
+    // with a low S as some issuers require.
+    let utils = CryptoCurveUtils::secp256r1();
+    let signature = utils
+        .ensure_raw_fixed_width_signature_encoding(signing_key.sign(signing_input.clone())?)
+        .map(|signature| utils.normalize_low_s(signature))
+        .ok_or(PopError::InvalidSignature)?;
+
+    Ok(format!(
+        "{}.{}",
+        String::from_utf8_lossy(&signing_input),
+        BASE64_URL_SAFE_NO_PAD.encode(signature)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn generates_signed_pop_with_keystore() {
+        use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+
+        use crate::crypto::RustTestKeyManager;
+
+        let key_alias = KeyAlias("pop-key".into());
+        let key_manager = Arc::new(RustTestKeyManager::default());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+
+        let pop = generate_pop(
+            "https://issuer.example".into(),
+            Some("c_nonce".into()),
+            did::DidMethod::Jwk,
+            key_manager.clone(),
+            key_alias.clone(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let (signing_input, signature) = pop.rsplit_once('.').unwrap();
+        let (_, payload) = signing_input.split_once('.').unwrap();
+        let claims: serde_json::Value =
+            serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+        assert!(claims["aud"]
+            .as_str()
+            .unwrap()
+            .starts_with("https://issuer.example"));
+        assert_eq!(claims["nonce"], "c_nonce");
+
+        let public_jwk = key_manager
+            .get_signing_key(key_alias)
+            .unwrap()
+            .jwk()
+            .unwrap();
+        let verifying_key = VerifyingKey::from(p256::PublicKey::from_jwk_str(&public_jwk).unwrap());
+        let signature =
+            Signature::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(signature).unwrap()).unwrap();
+        assert!(
+            signature.normalize_s().is_none(),
+            "signature must have a low S"
+        );
+        verifying_key
+            .verify(signing_input.as_bytes(), &signature)
+            .unwrap();
+    }
+
+    #[test]
+    fn overrides_issued_at_and_jti() {
+        let header =
+            BASE64_URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256","typ":"openid4vci-proof+jwt"}"#);
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(
+            r#"{"iss":"did:example:holder","aud":"https://issuer.example","iat":1,"exp":301}"#,
+        );
+        let signing_input = format!("{header}.{payload}").into_bytes();
+
+        let overridden = override_claims(
+            signing_input,
+            Some(1_700_000_000),
+            time::Duration::minutes(5),
+            Some("pop-1".into()),
+        )
+        .unwrap();
+
+        let overridden = String::from_utf8(overridden).unwrap();
+        let (overridden_header, overridden_payload) = overridden.split_once('.').unwrap();
+        let claims: serde_json::Value =
+            serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(overridden_payload).unwrap())
+                .unwrap();
+
+        assert_eq!(overridden_header, header);
+        assert_eq!(claims["iat"], 1_700_000_000);
+        assert_eq!(claims["exp"], 1_700_000_300);
+        assert_eq!(claims["jti"], "pop-1");
+        assert_eq!(claims["iss"], "did:example:holder");
+    }
+}
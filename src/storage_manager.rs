@@ -32,6 +32,17 @@ pub enum StorageManagerError {
     InternalError,
 }
 
+/// Enum: StorageOp
+///
+/// A write applied as part of a [StorageBatchInterface::batch].
+#[derive(Debug, uniffi::Enum)]
+pub enum StorageOp {
+    /// Add a key-value pair, replacing any existing value.
+    Add { key: Key, value: Value },
+    /// Remove a key. Removing a non-existent key is not an error.
+    Remove { key: Key },
+}
+
 /// Interface: StorageManagerInterface
 ///
 /// The StorageManagerInterface provides access to functions defined in Kotlin and Swift for
@@ -74,3 +85,41 @@ pub trait StorageManagerInterface: Send + Sync + Debug {
     /// expected circumstance, simply returning () and not an error.
     async fn remove(&self, key: Key) -> Result<(), StorageManagerError>;
 }
+
+/// Interface: StorageBatchInterface
+///
+/// An optional extension of [StorageManagerInterface] for storage that can apply several
+/// writes as a single transaction. Hosts that implement it pass it to
+/// [VdcCollection::with_batch_storage](crate::vdc_collection::VdcCollection::with_batch_storage);
+/// without it, writes are applied one at a time with [StorageManagerInterface::add] and
+/// [StorageManagerInterface::remove].
+#[uniffi::export(with_foreign)]
+#[async_trait]
+pub trait StorageBatchInterface: Send + Sync + Debug {
+    /// Function: batch
+    ///
+    /// Applies the operations in order and atomically: either all of them are applied, or
+    /// none are.
+    async fn batch(&self, ops: Vec<StorageOp>) -> Result<(), StorageManagerError>;
+}
+
+/// Apply `ops` with `batch` if the storage supports batches, or otherwise one at a time with
+/// `storage`, stopping at the first failure.
+pub(crate) async fn apply_batch(
+    storage: &dyn StorageManagerInterface,
+    batch: Option<&dyn StorageBatchInterface>,
+    ops: Vec<StorageOp>,
+) -> Result<(), StorageManagerError> {
+    if let Some(batch) = batch {
+        return batch.batch(ops).await;
+    }
+
+    for op in ops {
+        match op {
+            StorageOp::Add { key, value } => storage.add(key, value).await?,
+            StorageOp::Remove { key } => storage.remove(key).await?,
+        }
+    }
+
+    Ok(())
+}
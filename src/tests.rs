@@ -130,8 +130,16 @@ pub async fn test_vc_playground_oid4vci() -> Result<()> {
     let public_jwk = signer.jwk();
     let duration_in_secs = None;
 
-    let pop_prepare =
-        generate_pop_prepare(audience, nonce, did_method, public_jwk, duration_in_secs).await?;
+    let pop_prepare = generate_pop_prepare(
+        audience,
+        nonce,
+        did_method,
+        public_jwk,
+        duration_in_secs,
+        None,
+        None,
+    )
+    .await?;
 
     let signature = signer.sign_jwt(pop_prepare.clone()).await?;
 
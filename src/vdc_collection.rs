@@ -1,16 +1,30 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::common::*;
 use crate::credential::Credential;
+use crate::crypto::KeyAlias;
 use crate::storage_manager::*;
 
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
 use futures::StreamExt;
+use openid4vp::JsonPath;
+use serde_json::Value as Json;
 use thiserror::Error;
-use tracing::info;
+use tracing::{info, warn};
 
 /// Internal prefix for credential keys.
 const KEY_PREFIX: &str = "Credential.";
 
+/// Format version of encrypted backups, stored as their first byte.
+const BACKUP_VERSION: u8 = 1;
+
+/// Length of the AES-GCM nonce following the version of an encrypted backup.
+const BACKUP_NONCE_LEN: usize = 12;
+
 #[derive(uniffi::Object)]
 /// Verifiable Digital Credential Collection
 ///
@@ -18,6 +32,7 @@ const KEY_PREFIX: &str = "Credential.";
 #[derive(Debug)]
 pub struct VdcCollection {
     storage: Arc<dyn StorageManagerInterface>,
+    batch_storage: Option<Arc<dyn StorageBatchInterface>>,
 }
 
 #[derive(Error, Debug, uniffi::Error)]
@@ -41,6 +56,83 @@ pub enum VdcCollectionError {
     /// Attempting to delete a credential from storage failed.
     #[error("Failed to Delete from Storage")]
     DeleteFailed(StorageManagerError),
+
+    /// The JSONPath query could not be parsed.
+    #[error("Invalid JSONPath query: {0}")]
+    InvalidQuery(String),
+
+    /// The backup key is not a 32-byte AES-256 key.
+    #[error("Backup key must be 32 bytes")]
+    InvalidBackupKey,
+
+    /// The backup could not be encrypted.
+    #[error("Failed to encrypt backup")]
+    BackupEncryptionFailed,
+
+    /// The backup could not be decrypted, because the key is wrong or the backup is corrupted.
+    #[error("Failed to decrypt backup")]
+    BackupDecryptionFailed,
+
+    /// The backup was created with an unsupported format version.
+    #[error("Unsupported backup version: {0}")]
+    UnsupportedBackupVersion(u8),
+
+    /// A stored credential cannot be read, so it cannot be backed up. It can be inspected
+    /// with [VdcCollection::get_with_diagnostics], and repaired or deleted.
+    #[error("Credential {0} cannot be read for backup")]
+    BackupCredentialUnreadable(Uuid),
+}
+
+/// How the key aliases of credentials are handled when importing a backup.
+///
+/// Key aliases reference keys held by the device that created the backup, which are not part
+/// of the backup and do not transfer to another device.
+#[derive(Debug, Clone, uniffi::Enum)]
+pub enum BackupKeyAliases {
+    /// Keep the key aliases unchanged, e.g. when restoring on the same device.
+    Keep,
+    /// Remove all key aliases.
+    Strip,
+    /// Replace key aliases using the mapping from old to new alias. Aliases without a
+    /// mapping are removed.
+    Remap { aliases: HashMap<String, String> },
+}
+
+impl BackupKeyAliases {
+    fn apply(&self, key_alias: Option<KeyAlias>) -> Option<KeyAlias> {
+        match self {
+            Self::Keep => key_alias,
+            Self::Strip => None,
+            Self::Remap { aliases } => key_alias
+                .and_then(|alias| aliases.get(&alias.0).cloned())
+                .map(KeyAlias),
+        }
+    }
+}
+
+/// Why a stored credential could not be used.
+#[derive(Error, Debug, Clone, PartialEq, uniffi::Enum)]
+pub enum CredentialPayloadError {
+    /// The stored bytes are not a serialized credential.
+    #[error("Failed to deserialize the stored credential: {0}")]
+    Deserialization(String),
+
+    /// The credential was deserialized, but its payload could not be parsed for its format.
+    #[error("Failed to parse the credential payload: {0}")]
+    Parsing(String),
+}
+
+/// A stored credential along with any problem found when reading it.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct CredentialDiagnostics {
+    /// The ID of the credential.
+    pub id: Uuid,
+    /// The raw bytes held in storage for the credential.
+    pub raw: Vec<u8>,
+    /// The stored credential, if it could be deserialized.
+    pub credential: Option<Credential>,
+    /// Why the credential could not be deserialized or parsed, if it could not.
+    pub error: Option<CredentialPayloadError>,
 }
 
 #[uniffi::export]
@@ -48,7 +140,23 @@ impl VdcCollection {
     #[uniffi::constructor]
     /// Create a new credential set.
     pub fn new(engine: Arc<dyn StorageManagerInterface>) -> VdcCollection {
-        VdcCollection { storage: engine }
+        VdcCollection {
+            storage: engine,
+            batch_storage: None,
+        }
+    }
+
+    #[uniffi::constructor]
+    /// Create a new credential set whose storage can apply several writes atomically with
+    /// `batch`, which usually wraps the same storage as `engine`.
+    pub fn with_batch_storage(
+        engine: Arc<dyn StorageManagerInterface>,
+        batch: Arc<dyn StorageBatchInterface>,
+    ) -> VdcCollection {
+        VdcCollection {
+            storage: engine,
+            batch_storage: Some(batch),
+        }
     }
 
     /// Add a credential to the set.
@@ -68,6 +176,39 @@ impl VdcCollection {
         }
     }
 
+    /// Add a credential to the set unless an identical credential is already stored.
+    ///
+    /// Credentials are compared by their [content hash](crate::credential::credential_content_hash).
+    /// Returns the ID of the stored copy: the existing credential's ID if it was a duplicate,
+    /// otherwise the ID of `credential`.
+    pub async fn add_unique(&self, credential: &Credential) -> Result<Uuid, VdcCollectionError> {
+        if let Some(id) = self
+            .find_by_content_hash(&credential.content_hash())
+            .await?
+        {
+            return Ok(id);
+        }
+
+        self.add(credential).await?;
+        Ok(credential.id)
+    }
+
+    /// Find a stored credential with the given content hash.
+    pub async fn find_by_content_hash(
+        &self,
+        content_hash: &str,
+    ) -> Result<Option<Uuid>, VdcCollectionError> {
+        for id in self.all_entries().await? {
+            if let Ok(Some(credential)) = self.get(id).await {
+                if credential.content_hash() == content_hash {
+                    return Ok(Some(id));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
     /// Get a credential from the store.
     pub async fn get(&self, id: Uuid) -> Result<Option<Credential>, VdcCollectionError> {
         let raw = match self.storage.get(Self::id_to_key(id)).await {
@@ -82,6 +223,48 @@ impl VdcCollection {
         }
     }
 
+    /// Get a credential from the store, reporting whether it can be deserialized and parsed.
+    ///
+    /// Unlike [VdcCollection::get], this does not fail on corrupted entries, so that a wallet can
+    /// detect them and repair or [delete](VdcCollection::delete) them.
+    pub async fn get_with_diagnostics(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<CredentialDiagnostics>, VdcCollectionError> {
+        let raw = match self.storage.get(Self::id_to_key(id)).await {
+            Ok(Some(x)) => x.0,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(VdcCollectionError::LoadFailed(e)),
+        };
+
+        let (credential, error) = match serde_cbor::de::from_slice::<Option<Credential>>(&raw) {
+            Ok(Some(credential)) => {
+                let error = credential
+                    .try_into_parsed()
+                    .err()
+                    .map(|e| CredentialPayloadError::Parsing(e.to_string()));
+                (Some(credential), error)
+            }
+            Ok(None) => (
+                None,
+                Some(CredentialPayloadError::Deserialization(
+                    "stored value is empty".to_string(),
+                )),
+            ),
+            Err(e) => (
+                None,
+                Some(CredentialPayloadError::Deserialization(e.to_string())),
+            ),
+        };
+
+        Ok(Some(CredentialDiagnostics {
+            id,
+            raw,
+            credential,
+            error,
+        }))
+    }
+
     /// Remove a credential from the store.
     pub async fn delete(&self, id: Uuid) -> Result<(), VdcCollectionError> {
         match self.storage.remove(Self::id_to_key(id)).await {
@@ -115,6 +298,143 @@ impl VdcCollection {
             .collect::<Vec<Uuid>>())
     }
 
+    /// Get a list of all the credentials whose claims match a JSONPath query.
+    ///
+    /// Each credential's claims are projected into JSON (mdoc namespaces become
+    /// `{"<namespace>": {"<element>": <value>}}`) and the query is evaluated against them. A
+    /// credential matches if the query selects at least one node and, when `equals` is provided,
+    /// one of the selected nodes equals it. String nodes are compared by their contents, other
+    /// nodes by their JSON encoding.
+    ///
+    /// Credentials that cannot be parsed are skipped.
+    pub async fn query(
+        &self,
+        jsonpath: String,
+        equals: Option<String>,
+    ) -> Result<Vec<Uuid>, VdcCollectionError> {
+        let path = JsonPath::parse(&jsonpath)
+            .map_err(|e| VdcCollectionError::InvalidQuery(e.to_string()))?;
+
+        let all_entries = self.all_entries().await?;
+        Ok(futures::stream::iter(all_entries.into_iter())
+            .filter_map(|id| async move { self.get(id).await.ok().flatten() })
+            .collect::<Vec<Credential>>()
+            .await
+            .iter()
+            .filter(|cred| match Self::claims_as_json(cred) {
+                Some(claims) => path.query(&claims).iter().any(|node| match &equals {
+                    Some(expected) => Self::node_equals(node, expected),
+                    None => true,
+                }),
+                None => false,
+            })
+            .map(|cred| cred.id)
+            .collect::<Vec<Uuid>>())
+    }
+
+    /// Export all credentials as a backup encrypted with the 32-byte AES-256 `key`.
+    ///
+    /// Credential ids and key aliases are preserved. The keys referenced by the aliases are
+    /// not part of the backup, see [BackupKeyAliases].
+    ///
+    /// Fails with [VdcCollectionError::BackupCredentialUnreadable] if a stored credential
+    /// cannot be read, rather than silently leaving it out of the backup.
+    pub async fn export_encrypted(&self, key: Vec<u8>) -> Result<Vec<u8>, VdcCollectionError> {
+        let cipher =
+            Aes256Gcm::new_from_slice(&key).map_err(|_| VdcCollectionError::InvalidBackupKey)?;
+
+        let mut credentials = Vec::new();
+        for id in self.all_entries().await? {
+            match self.get(id).await {
+                Ok(Some(credential)) => credentials.push(credential),
+                Ok(None) => {}
+                Err(VdcCollectionError::DeserializeFailed) => {
+                    return Err(VdcCollectionError::BackupCredentialUnreadable(id))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let plaintext =
+            serde_cbor::to_vec(&credentials).map_err(|_| VdcCollectionError::SerializeFailed)?;
+
+        // The version is authenticated along with the credentials.
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &[BACKUP_VERSION],
+                },
+            )
+            .map_err(|_| VdcCollectionError::BackupEncryptionFailed)?;
+
+        let mut archive = vec![BACKUP_VERSION];
+        archive.extend(nonce);
+        archive.extend(ciphertext);
+        Ok(archive)
+    }
+
+    /// Restore the credentials of a backup created with [VdcCollection::export_encrypted].
+    ///
+    /// Stored credentials with the same ids are replaced. Returns the ids of the imported
+    /// credentials.
+    pub async fn import_encrypted(
+        &self,
+        archive: Vec<u8>,
+        key: Vec<u8>,
+        key_aliases: BackupKeyAliases,
+    ) -> Result<Vec<Uuid>, VdcCollectionError> {
+        let cipher =
+            Aes256Gcm::new_from_slice(&key).map_err(|_| VdcCollectionError::InvalidBackupKey)?;
+
+        let Some((&version, archive)) = archive.split_first() else {
+            return Err(VdcCollectionError::BackupDecryptionFailed);
+        };
+        if version != BACKUP_VERSION {
+            return Err(VdcCollectionError::UnsupportedBackupVersion(version));
+        }
+        if archive.len() < BACKUP_NONCE_LEN {
+            return Err(VdcCollectionError::BackupDecryptionFailed);
+        }
+
+        let (nonce, ciphertext) = archive.split_at(BACKUP_NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &[version],
+                },
+            )
+            .map_err(|_| VdcCollectionError::BackupDecryptionFailed)?;
+
+        let credentials: Vec<Credential> = serde_cbor::from_slice(&plaintext)
+            .map_err(|_| VdcCollectionError::DeserializeFailed)?;
+
+        // Store the credentials in one batch, so an interrupted import leaves no partial state
+        // when the storage supports batches.
+        let mut ids = Vec::with_capacity(credentials.len());
+        let mut ops = Vec::with_capacity(credentials.len());
+        for mut credential in credentials {
+            credential.key_alias = key_aliases.apply(credential.key_alias.take());
+            let value =
+                serde_cbor::to_vec(&credential).map_err(|_| VdcCollectionError::SerializeFailed)?;
+            ops.push(StorageOp::Add {
+                key: Self::id_to_key(credential.id),
+                value: Value(value),
+            });
+            ids.push(credential.id);
+        }
+
+        apply_batch(self.storage.as_ref(), self.batch_storage.as_deref(), ops)
+            .await
+            .map_err(VdcCollectionError::StoreFailed)?;
+
+        Ok(ids)
+    }
+
     /// Dump the contents of the credential set to the logger.
     pub async fn dump(&self) {
         match self.all_entries().await {
@@ -136,6 +456,31 @@ impl VdcCollection {
         Key(format!("{}{}", KEY_PREFIX, id))
     }
 
+    /// Parse a stored credential and project its claims into JSON.
+    fn claims_as_json(credential: &Credential) -> Option<Json> {
+        let claims = credential
+            .try_into_parsed()
+            .map_err(|e| e.to_string())
+            .and_then(|parsed| parsed.claims_as_json_string().map_err(|e| e.to_string()))
+            .and_then(|claims| serde_json::from_str(&claims).map_err(|e| e.to_string()));
+
+        match claims {
+            Ok(claims) => Some(claims),
+            Err(e) => {
+                warn!("Unable to read claims of credential {}: {e}", credential.id);
+                None
+            }
+        }
+    }
+
+    /// Compare a selected node against the expected value from a query.
+    fn node_equals(node: &Json, expected: &str) -> bool {
+        match node {
+            Json::String(value) => value == expected,
+            other => other.to_string() == expected,
+        }
+    }
+
     /// Convert a string ref to a storage key.
     ///
     /// Returns `None` if it's not the right format.
@@ -227,4 +572,265 @@ mod tests {
 
         assert!(vdc.all_entries().await.unwrap().len() == 0);
     }
+
+    #[tokio::test]
+    async fn test_vdc_encrypted_backup() {
+        let source = VdcCollection::new(Arc::new(LocalStore::new()));
+        let credential = Credential {
+            id: Uuid::new_v4(),
+            format: CredentialFormat::MsoMdoc,
+            r#type: CredentialType("org.iso.18013.5.1.mDL".into()),
+            payload: "Some random collection of bytes.".into(),
+            key_alias: Some(KeyAlias("old-device-key".into())),
+        };
+        source.add(&credential).await.unwrap();
+
+        let key = vec![7u8; 32];
+        let archive = source.export_encrypted(key.clone()).await.unwrap();
+
+        assert!(matches!(
+            source
+                .import_encrypted(archive.clone(), vec![8u8; 32], BackupKeyAliases::Keep)
+                .await,
+            Err(VdcCollectionError::BackupDecryptionFailed)
+        ));
+
+        let mut unsupported = archive.clone();
+        unsupported[0] = BACKUP_VERSION + 1;
+        assert!(matches!(
+            source
+                .import_encrypted(unsupported, key.clone(), BackupKeyAliases::Keep)
+                .await,
+            Err(VdcCollectionError::UnsupportedBackupVersion(_))
+        ));
+
+        let target_store = Arc::new(LocalStore::new());
+        let target = VdcCollection::with_batch_storage(target_store.clone(), target_store);
+        let aliases = HashMap::from([("old-device-key".to_string(), "new-device-key".to_string())]);
+        let ids = target
+            .import_encrypted(
+                archive.clone(),
+                key.clone(),
+                BackupKeyAliases::Remap { aliases },
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids, vec![credential.id]);
+
+        let restored = target.get(credential.id).await.unwrap().unwrap();
+        assert_eq!(restored.payload, credential.payload);
+        assert_eq!(restored.key_alias, Some(KeyAlias("new-device-key".into())));
+
+        target
+            .import_encrypted(archive, key, BackupKeyAliases::Strip)
+            .await
+            .unwrap();
+        let restored = target.get(credential.id).await.unwrap().unwrap();
+        assert_eq!(restored.key_alias, None);
+
+        // A credential that cannot be read fails the backup rather than being left out.
+        let corrupted = Uuid::new_v4();
+        source
+            .storage
+            .add(
+                VdcCollection::id_to_key(corrupted),
+                Value(b"not a credential".to_vec()),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            source.export_encrypted(key).await,
+            Err(VdcCollectionError::BackupCredentialUnreadable(id)) if id == corrupted
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_vdc_get_with_diagnostics() {
+        let smi: Arc<dyn StorageManagerInterface> = Arc::new(LocalStore::new());
+        let vdc = VdcCollection::new(smi.clone());
+
+        let sd_jwt = crate::credential::vcdm2_sd_jwt::VCDM2SdJwt::new_from_compact_sd_jwt(
+            include_str!("../tests/examples/sd_vc.jwt").to_string(),
+        )
+        .unwrap();
+        let valid = crate::credential::ParsedCredential::new_sd_jwt(sd_jwt)
+            .into_generic_form()
+            .unwrap();
+        vdc.add(&valid).await.unwrap();
+
+        let diagnostics = vdc.get_with_diagnostics(valid.id).await.unwrap().unwrap();
+        assert_eq!(diagnostics.credential.unwrap().id, valid.id);
+        assert_eq!(diagnostics.error, None);
+
+        let corrupted_payload = Credential {
+            id: Uuid::new_v4(),
+            format: CredentialFormat::VCDM2SdJwt,
+            r#type: CredentialType("OpenBadgeCredential".into()),
+            payload: "not an sd-jwt".into(),
+            key_alias: None,
+        };
+        vdc.add(&corrupted_payload).await.unwrap();
+
+        let diagnostics = vdc
+            .get_with_diagnostics(corrupted_payload.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(diagnostics.credential.is_some());
+        assert!(matches!(
+            diagnostics.error,
+            Some(CredentialPayloadError::Parsing(_))
+        ));
+
+        let corrupted_entry = Uuid::new_v4();
+        smi.add(
+            VdcCollection::id_to_key(corrupted_entry),
+            Value(vec![0xff, 0x00, 0x13]),
+        )
+        .await
+        .unwrap();
+
+        assert!(vdc.get(corrupted_entry).await.is_err());
+        let diagnostics = vdc
+            .get_with_diagnostics(corrupted_entry)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(diagnostics.raw, vec![0xff, 0x00, 0x13]);
+        assert!(diagnostics.credential.is_none());
+        assert!(matches!(
+            diagnostics.error,
+            Some(CredentialPayloadError::Deserialization(_))
+        ));
+
+        assert!(vdc
+            .get_with_diagnostics(Uuid::new_v4())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_vdc_add_unique() {
+        let smi: Arc<dyn StorageManagerInterface> = Arc::new(LocalStore::new());
+        let vdc = VdcCollection::new(smi);
+
+        let credential = Credential {
+            id: Uuid::new_v4(),
+            format: CredentialFormat::MsoMdoc,
+            r#type: CredentialType("org.iso.18013.5.1.mDL".into()),
+            payload: "Some random collection of bytes.".into(),
+            key_alias: None,
+        };
+        let reimported = Credential {
+            id: Uuid::new_v4(),
+            ..credential.clone()
+        };
+        let other = Credential {
+            id: Uuid::new_v4(),
+            payload: "Some other random collection of bytes.".into(),
+            ..credential.clone()
+        };
+
+        assert_eq!(credential.content_hash(), reimported.content_hash());
+        assert_ne!(credential.content_hash(), other.content_hash());
+
+        assert_eq!(vdc.add_unique(&credential).await.unwrap(), credential.id);
+        assert_eq!(vdc.add_unique(&reimported).await.unwrap(), credential.id);
+        assert_eq!(vdc.add_unique(&other).await.unwrap(), other.id);
+
+        assert_eq!(vdc.all_entries().await.unwrap().len(), 2);
+        assert_eq!(
+            vdc.find_by_content_hash(&reimported.content_hash())
+                .await
+                .unwrap(),
+            Some(credential.id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vdc_add_unique_reimported_mdoc() {
+        let smi: Arc<dyn StorageManagerInterface> = Arc::new(LocalStore::new());
+        let vdc = VdcCollection::new(smi);
+
+        let key_manager = Arc::new(crate::crypto::RustTestKeyManager::default());
+        let key_alias = KeyAlias("add-unique-mdoc".to_string());
+        key_manager
+            .generate_p256_signing_key(key_alias.clone())
+            .await
+            .unwrap();
+        let mdoc = Arc::new(crate::mdl::util::generate_test_mdl(key_manager, key_alias).unwrap());
+
+        // Importing the same mdoc again assigns its document a new local ID.
+        let mut document = mdoc.document().clone();
+        document.id = Uuid::new_v4();
+        let reimported_mdoc = crate::credential::mdoc::Mdoc::from_cbor_encoded_document(
+            isomdl::cbor::to_vec(&document).unwrap(),
+            mdoc.key_alias(),
+        )
+        .unwrap();
+
+        let credential = Credential::try_from(mdoc).unwrap();
+        let reimported = Credential::try_from(reimported_mdoc).unwrap();
+        assert_ne!(credential.id, reimported.id);
+        assert_ne!(credential.payload, reimported.payload);
+        assert_eq!(credential.content_hash(), reimported.content_hash());
+
+        assert_eq!(vdc.add_unique(&credential).await.unwrap(), credential.id);
+        assert_eq!(vdc.add_unique(&reimported).await.unwrap(), credential.id);
+        assert_eq!(vdc.all_entries().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_vdc_query() {
+        let smi: Arc<dyn StorageManagerInterface> = Arc::new(LocalStore::new());
+        let vdc = VdcCollection::new(smi);
+
+        let sd_jwt = crate::credential::vcdm2_sd_jwt::VCDM2SdJwt::new_from_compact_sd_jwt(
+            include_str!("../tests/examples/sd_vc.jwt").to_string(),
+        )
+        .unwrap();
+        let credential = crate::credential::ParsedCredential::new_sd_jwt(sd_jwt)
+            .into_generic_form()
+            .unwrap();
+        vdc.add(&credential).await.unwrap();
+
+        // A credential that can't be parsed is skipped rather than failing the query.
+        vdc.add(&Credential {
+            id: Uuid::new_v4(),
+            format: CredentialFormat::MsoMdoc,
+            r#type: CredentialType("org.iso.18013.5.1.mDL".into()),
+            payload: "not an mdoc".into(),
+            key_alias: None,
+        })
+        .await
+        .unwrap();
+
+        let path = "$.credentialSubject.identifier[*].identityHash".to_string();
+
+        assert_eq!(
+            vdc.query(path.clone(), None).await.unwrap(),
+            vec![credential.id]
+        );
+        assert_eq!(
+            vdc.query(path.clone(), Some("john.smith@spruce.com".into()))
+                .await
+                .unwrap(),
+            vec![credential.id]
+        );
+        assert!(vdc
+            .query(path, Some("jane.doe@spruce.com".into()))
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(vdc
+            .query("$.credentialSubject.hasNoSuchClaim".into(), None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            vdc.query("not a path".into(), None).await,
+            Err(VdcCollectionError::InvalidQuery(_))
+        ));
+    }
 }
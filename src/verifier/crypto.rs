@@ -1,5 +1,18 @@
-use uniffi::deps::anyhow::{anyhow, Context};
-use x509_cert::der::{asn1, Encode};
+use cose_rs::CoseSign1;
+use serde_cbor::Value;
+use uniffi::deps::anyhow::{self, anyhow, bail, Context};
+use x509_cert::{
+    der::{
+        asn1::{self, ObjectIdentifier},
+        Encode,
+    },
+    spki::SubjectPublicKeyInfoOwned,
+};
+
+const ID_EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const SECP256R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const SECP384R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.34");
+const ID_ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
 
 // TODO: Replace this with a foreign function interface into native code.
 pub trait Crypto {
@@ -9,6 +22,97 @@ pub trait Crypto {
         payload: Vec<u8>,
         signature: Vec<u8>,
     ) -> VerificationResult;
+
+    /// Verify an ECDSA P-384 signature, DER-encoded, using the key in the certificate.
+    fn p384_verify(
+        &self,
+        certificate_der: Vec<u8>,
+        payload: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> VerificationResult;
+
+    /// Verify an Ed25519 signature using the key in the certificate.
+    fn ed25519_verify(
+        &self,
+        certificate_der: Vec<u8>,
+        payload: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> VerificationResult;
+}
+
+/// The signature scheme of a certificate's public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureScheme {
+    P256,
+    P384,
+    Ed25519,
+}
+
+impl SignatureScheme {
+    /// Determine the signature scheme from a certificate's SubjectPublicKeyInfo.
+    pub fn from_spki(spki: &SubjectPublicKeyInfoOwned) -> anyhow::Result<Self> {
+        match spki.algorithm.oid {
+            ID_EC_PUBLIC_KEY => {
+                let curve: ObjectIdentifier = spki
+                    .algorithm
+                    .parameters
+                    .as_ref()
+                    .context("EC public key is missing the named curve")?
+                    .decode_as()
+                    .context("unable to decode the named curve")?;
+                match curve {
+                    SECP256R1 => Ok(Self::P256),
+                    SECP384R1 => Ok(Self::P384),
+                    curve => bail!("unsupported elliptic curve: {curve}"),
+                }
+            }
+            ID_ED25519 => Ok(Self::Ed25519),
+            oid => bail!("unsupported public key algorithm: {oid}"),
+        }
+    }
+
+    /// The COSE algorithm identifier of signatures made with this scheme: ES256, ES384 or
+    /// EdDSA.
+    fn cose_algorithm(self) -> i128 {
+        match self {
+            Self::P256 => -7,
+            Self::P384 => -35,
+            Self::Ed25519 => -8,
+        }
+    }
+
+    /// Check that the `alg` (label '1') in the protected header of a CWT is the algorithm of
+    /// this scheme, so the CWT is not verified with a different algorithm than it claims.
+    pub fn ensure_cose_algorithm(self, cwt: &CoseSign1) -> anyhow::Result<()> {
+        match cwt
+            .protected()
+            .get_i(1)
+            .context("alg (label '1') is not in the protected header")?
+        {
+            Value::Integer(alg) if *alg == self.cose_algorithm() => Ok(()),
+            Value::Integer(alg) => {
+                bail!("signature algorithm {alg} does not match the {self:?} signer key")
+            }
+            v => bail!("unexpected format for alg: {v:?}"),
+        }
+    }
+
+    /// Verify a signature with the native implementation for this scheme.
+    ///
+    /// ECDSA signatures must be DER-encoded.
+    pub fn verify(
+        self,
+        crypto: &dyn Crypto,
+        certificate_der: Vec<u8>,
+        payload: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> VerificationResult {
+        match self {
+            Self::P256 => crypto.p256_verify(certificate_der, payload, signature),
+            Self::P384 => crypto.p384_verify(certificate_der, payload, signature),
+            Self::Ed25519 => crypto.ed25519_verify(certificate_der, payload, signature),
+        }
+    }
 }
 
 #[derive(Debug, uniffi::Enum)]
@@ -62,27 +166,7 @@ impl cose_rs::algorithm::SignatureAlgorithm for CoseP256Verifier<'_> {
 
 impl signature::Verifier<CoseP256Signature> for CoseP256Verifier<'_> {
     fn verify(&self, msg: &[u8], signature: &CoseP256Signature) -> Result<(), signature::Error> {
-        // Construct DER signature.
-        let mut seq: asn1::SequenceOf<asn1::Uint, 2> = asn1::SequenceOf::new();
-        seq.add(
-            asn1::Uint::new(&signature.r)
-                .context("unable to construct integer from signature parameter 'r'")
-                .map_err(signature::Error::from_source)?,
-        )
-        .context("unable to add signature parameter 'r' to the sequence")
-        .map_err(signature::Error::from_source)?;
-        seq.add(
-            asn1::Uint::new(&signature.s)
-                .context("unable to construct integer from signature parameter 's'")
-                .map_err(signature::Error::from_source)?,
-        )
-        .context("unable to add signature parameter 's' to the sequence")
-        .map_err(signature::Error::from_source)?;
-
-        let der_signature = seq
-            .to_der()
-            .context("unable to encode DER sequence")
-            .map_err(signature::Error::from_source)?;
+        let der_signature = der_ecdsa_signature(&signature.r, &signature.s)?;
 
         self.crypto
             .p256_verify(self.certificate_der.clone(), msg.to_vec(), der_signature)
@@ -90,3 +174,325 @@ impl signature::Verifier<CoseP256Signature> for CoseP256Verifier<'_> {
             .map_err(signature::Error::from_source)
     }
 }
+
+/// A verifier for CoseSign objects with ECDSA + P-384 signatures.
+pub struct CoseP384Verifier<'a> {
+    pub crypto: &'a dyn Crypto,
+    pub certificate_der: Vec<u8>,
+}
+
+/// A CoseSign ECDSA + P-384 signature.
+pub struct CoseP384Signature {
+    r: [u8; 48],
+    s: [u8; 48],
+}
+
+impl TryFrom<&[u8]> for CoseP384Signature {
+    type Error = signature::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() != 96 {
+            return Err(Self::Error::from_source(anyhow!(
+                "expected a 96 byte signature, found {} bytes",
+                value.len()
+            )));
+        }
+        let (r, s) = value.split_at(48);
+        Ok(Self {
+            r: r.try_into().map_err(|e| {
+                Self::Error::from_source(anyhow!("failed to parse 'r' parameter from slice: {e}"))
+            })?,
+            s: s.try_into().map_err(|e| {
+                Self::Error::from_source(anyhow!("failed to parse 's' parameter from slice: {e}"))
+            })?,
+        })
+    }
+}
+
+impl cose_rs::algorithm::SignatureAlgorithm for CoseP384Verifier<'_> {
+    fn algorithm(&self) -> cose_rs::algorithm::Algorithm {
+        cose_rs::algorithm::Algorithm::ES384
+    }
+}
+
+impl signature::Verifier<CoseP384Signature> for CoseP384Verifier<'_> {
+    fn verify(&self, msg: &[u8], signature: &CoseP384Signature) -> Result<(), signature::Error> {
+        let der_signature = der_ecdsa_signature(&signature.r, &signature.s)?;
+
+        self.crypto
+            .p384_verify(self.certificate_der.clone(), msg.to_vec(), der_signature)
+            .into_result()
+            .map_err(signature::Error::from_source)
+    }
+}
+
+/// A verifier for CoseSign objects with EdDSA + Ed25519 signatures.
+pub struct CoseEd25519Verifier<'a> {
+    pub crypto: &'a dyn Crypto,
+    pub certificate_der: Vec<u8>,
+}
+
+/// A CoseSign EdDSA + Ed25519 signature.
+pub struct CoseEd25519Signature([u8; 64]);
+
+impl TryFrom<&[u8]> for CoseEd25519Signature {
+    type Error = signature::Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        value.try_into().map(Self).map_err(|e| {
+            Self::Error::from_source(anyhow!("failed to parse Ed25519 signature from slice: {e}"))
+        })
+    }
+}
+
+impl cose_rs::algorithm::SignatureAlgorithm for CoseEd25519Verifier<'_> {
+    fn algorithm(&self) -> cose_rs::algorithm::Algorithm {
+        cose_rs::algorithm::Algorithm::EdDSA
+    }
+}
+
+impl signature::Verifier<CoseEd25519Signature> for CoseEd25519Verifier<'_> {
+    fn verify(&self, msg: &[u8], signature: &CoseEd25519Signature) -> Result<(), signature::Error> {
+        self.crypto
+            .ed25519_verify(
+                self.certificate_der.clone(),
+                msg.to_vec(),
+                signature.0.to_vec(),
+            )
+            .into_result()
+            .map_err(signature::Error::from_source)
+    }
+}
+
+/// Construct a DER-encoded ECDSA signature from the raw 'r' and 's' parameters.
+fn der_ecdsa_signature(r: &[u8], s: &[u8]) -> Result<Vec<u8>, signature::Error> {
+    let mut seq: asn1::SequenceOf<asn1::Uint, 2> = asn1::SequenceOf::new();
+    seq.add(
+        asn1::Uint::new(r)
+            .context("unable to construct integer from signature parameter 'r'")
+            .map_err(signature::Error::from_source)?,
+    )
+    .context("unable to add signature parameter 'r' to the sequence")
+    .map_err(signature::Error::from_source)?;
+    seq.add(
+        asn1::Uint::new(s)
+            .context("unable to construct integer from signature parameter 's'")
+            .map_err(signature::Error::from_source)?,
+    )
+    .context("unable to add signature parameter 's' to the sequence")
+    .map_err(signature::Error::from_source)?;
+
+    seq.to_der()
+        .context("unable to encode DER sequence")
+        .map_err(signature::Error::from_source)
+}
+
+#[cfg(test)]
+mod test {
+    use p256::pkcs8::EncodePublicKey;
+    use signature::{Signer, Verifier};
+    use ssi::crypto::rand;
+    use x509_cert::{
+        der::{asn1::BitString, Any, Decode},
+        spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned},
+    };
+
+    use super::*;
+
+    fn spki(oid: ObjectIdentifier, curve: Option<ObjectIdentifier>) -> SubjectPublicKeyInfoOwned {
+        SubjectPublicKeyInfoOwned {
+            algorithm: AlgorithmIdentifierOwned {
+                oid,
+                parameters: curve.map(|curve| Any::encode_from(&curve).unwrap()),
+            },
+            subject_public_key: BitString::from_bytes(&[0; 32]).unwrap(),
+        }
+    }
+
+    #[test]
+    fn signature_scheme_from_p256_key() {
+        let key = p256::SecretKey::random(&mut rand::thread_rng()).public_key();
+        let spki = SubjectPublicKeyInfoOwned::from_der(key.to_public_key_der().unwrap().as_bytes())
+            .unwrap();
+
+        assert_eq!(
+            SignatureScheme::from_spki(&spki).unwrap(),
+            SignatureScheme::P256
+        );
+    }
+
+    #[test]
+    fn signature_scheme_from_spki() {
+        assert_eq!(
+            SignatureScheme::from_spki(&spki(ID_EC_PUBLIC_KEY, Some(SECP384R1))).unwrap(),
+            SignatureScheme::P384
+        );
+        assert_eq!(
+            SignatureScheme::from_spki(&spki(ID_ED25519, None)).unwrap(),
+            SignatureScheme::Ed25519
+        );
+        // secp256k1 is not supported.
+        assert!(SignatureScheme::from_spki(&spki(
+            ID_EC_PUBLIC_KEY,
+            Some(ObjectIdentifier::new_unwrap("1.3.132.0.10"))
+        ))
+        .is_err());
+        assert!(SignatureScheme::from_spki(&spki(ID_EC_PUBLIC_KEY, None)).is_err());
+    }
+
+    /// Verifies signatures with fixed keys, standing in for the native implementation.
+    struct KeyCrypto {
+        p256: p256::ecdsa::VerifyingKey,
+        p384: p384::ecdsa::VerifyingKey,
+        ed25519: ed25519_dalek::VerifyingKey,
+    }
+
+    fn result(result: Result<(), signature::Error>) -> VerificationResult {
+        match result {
+            Ok(()) => VerificationResult::Success,
+            Err(e) => VerificationResult::Failure {
+                cause: e.to_string(),
+            },
+        }
+    }
+
+    impl Crypto for KeyCrypto {
+        fn p256_verify(
+            &self,
+            _: Vec<u8>,
+            payload: Vec<u8>,
+            signature: Vec<u8>,
+        ) -> VerificationResult {
+            result(
+                p256::ecdsa::Signature::from_der(&signature)
+                    .and_then(|signature| self.p256.verify(&payload, &signature)),
+            )
+        }
+
+        fn p384_verify(
+            &self,
+            _: Vec<u8>,
+            payload: Vec<u8>,
+            signature: Vec<u8>,
+        ) -> VerificationResult {
+            result(
+                p384::ecdsa::Signature::from_der(&signature)
+                    .and_then(|signature| self.p384.verify(&payload, &signature)),
+            )
+        }
+
+        fn ed25519_verify(
+            &self,
+            _: Vec<u8>,
+            payload: Vec<u8>,
+            signature: Vec<u8>,
+        ) -> VerificationResult {
+            result(
+                ed25519_dalek::Signature::from_slice(&signature)
+                    .and_then(|signature| self.ed25519.verify(&payload, &signature)),
+            )
+        }
+    }
+
+    struct SigningKeys {
+        p256: p256::ecdsa::SigningKey,
+        p384: p384::ecdsa::SigningKey,
+        ed25519: ed25519_dalek::SigningKey,
+    }
+
+    impl SigningKeys {
+        fn generate() -> Self {
+            Self {
+                p256: p256::ecdsa::SigningKey::random(&mut rand::thread_rng()),
+                p384: p384::ecdsa::SigningKey::random(&mut rand::thread_rng()),
+                ed25519: ed25519_dalek::SigningKey::from_bytes(&rand::random()),
+            }
+        }
+
+        fn crypto(&self) -> KeyCrypto {
+            KeyCrypto {
+                p256: *self.p256.verifying_key(),
+                p384: *self.p384.verifying_key(),
+                ed25519: self.ed25519.verifying_key(),
+            }
+        }
+    }
+
+    const MESSAGE: &[u8] = b"signed CWT payload";
+
+    #[test]
+    fn verifies_p256_signatures() {
+        let keys = SigningKeys::generate();
+        let crypto = keys.crypto();
+        let signature: p256::ecdsa::Signature = keys.p256.sign(MESSAGE);
+
+        let verifier = CoseP256Verifier {
+            crypto: &crypto,
+            certificate_der: vec![],
+        };
+        let cose_signature = CoseP256Signature::try_from(signature.to_bytes().as_slice()).unwrap();
+        verifier.verify(MESSAGE, &cose_signature).unwrap();
+        assert!(verifier.verify(b"tampered", &cose_signature).is_err());
+
+        let der = signature.to_der().as_bytes().to_vec();
+        assert!(SignatureScheme::P256
+            .verify(&crypto, vec![], MESSAGE.to_vec(), der.clone())
+            .into_result()
+            .is_ok());
+        // The signature is not accepted as a signature of another scheme.
+        assert!(SignatureScheme::P384
+            .verify(&crypto, vec![], MESSAGE.to_vec(), der)
+            .into_result()
+            .is_err());
+    }
+
+    #[test]
+    fn verifies_p384_signatures() {
+        let keys = SigningKeys::generate();
+        let crypto = keys.crypto();
+        let signature: p384::ecdsa::Signature = keys.p384.sign(MESSAGE);
+
+        let verifier = CoseP384Verifier {
+            crypto: &crypto,
+            certificate_der: vec![],
+        };
+        let cose_signature = CoseP384Signature::try_from(signature.to_bytes().as_slice()).unwrap();
+        verifier.verify(MESSAGE, &cose_signature).unwrap();
+        assert!(verifier.verify(b"tampered", &cose_signature).is_err());
+        assert!(CoseP384Signature::try_from([0; 64].as_slice()).is_err());
+
+        let der = signature.to_der().as_bytes().to_vec();
+        assert!(SignatureScheme::P384
+            .verify(&crypto, vec![], MESSAGE.to_vec(), der.clone())
+            .into_result()
+            .is_ok());
+        assert!(SignatureScheme::P256
+            .verify(&crypto, vec![], MESSAGE.to_vec(), der)
+            .into_result()
+            .is_err());
+    }
+
+    #[test]
+    fn verifies_ed25519_signatures() {
+        let keys = SigningKeys::generate();
+        let crypto = keys.crypto();
+        let signature = keys.ed25519.sign(MESSAGE).to_bytes();
+
+        let verifier = CoseEd25519Verifier {
+            crypto: &crypto,
+            certificate_der: vec![],
+        };
+        let cose_signature = CoseEd25519Signature::try_from(signature.as_slice()).unwrap();
+        verifier.verify(MESSAGE, &cose_signature).unwrap();
+        assert!(verifier.verify(b"tampered", &cose_signature).is_err());
+
+        assert!(SignatureScheme::Ed25519
+            .verify(&crypto, vec![], MESSAGE.to_vec(), signature.to_vec())
+            .into_result()
+            .is_ok());
+        assert!(SignatureScheme::Ed25519
+            .verify(&crypto, vec![], b"tampered".to_vec(), signature.to_vec())
+            .into_result()
+            .is_err());
+    }
+}
@@ -74,6 +74,38 @@ pub fn extract_extensions(certificate: &Certificate) -> Result<(KeyUsage, CrlDis
     Ok((key_usage, crl_dp))
 }
 
+/// Return the integer labels of the claims of an encoded CWT, in the order they are encoded.
+///
+/// Text labels, and payloads that are not a claims map, are ignored.
+pub fn cwt_claim_labels(cwt_bytes: &[u8]) -> Vec<i32> {
+    let Ok(cwt) = serde_cbor::from_slice::<Value>(cwt_bytes) else {
+        return vec![];
+    };
+
+    // COSE_Sign1 is `[protected, unprotected, payload, signature]`, optionally tagged.
+    let cose_sign1 = match cwt {
+        Value::Tag(_, cose_sign1) => *cose_sign1,
+        cose_sign1 => cose_sign1,
+    };
+    let Value::Array(parts) = cose_sign1 else {
+        return vec![];
+    };
+    let Some(Value::Bytes(payload)) = parts.get(2) else {
+        return vec![];
+    };
+    let Ok(Value::Map(claims)) = serde_cbor::from_slice::<Value>(payload) else {
+        return vec![];
+    };
+
+    claims
+        .keys()
+        .filter_map(|label| match label {
+            Value::Integer(label) => i32::try_from(*label).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
 // TODO: Use treeldr instead of manual parsing?
 pub trait Claim: Sized {
     const CWT_LABEL: i32;
@@ -100,9 +132,88 @@ pub trait Claim: Sized {
             })
     }
 
+    /// Parse encoded image bytes, such as a portrait.
+    fn parse_image(value: &Value) -> crate::verifier::outcome::Result<ClaimValue> {
+        match value {
+            Value::Bytes(bytes) => Ok(ClaimValue::Image {
+                value: bytes.clone(),
+            }),
+            _ => Err(Failure::malformed_claim(Self::LABEL, value, "wrong type")),
+        }
+    }
+
+    fn parse_boolean(value: &Value) -> crate::verifier::outcome::Result<ClaimValue> {
+        match value {
+            Value::Bool(b) => Ok(ClaimValue::Boolean { value: *b }),
+            _ => Err(Failure::malformed_claim(Self::LABEL, value, "wrong type")),
+        }
+    }
+
+    fn parse_number(value: &Value) -> crate::verifier::outcome::Result<ClaimValue> {
+        match value {
+            Value::Integer(i) => Ok(ClaimValue::Number { value: *i as f64 }),
+            Value::Float(f) => Ok(ClaimValue::Number { value: *f }),
+            _ => Err(Failure::malformed_claim(Self::LABEL, value, "wrong type")),
+        }
+    }
+
     fn from_value(value: &Value) -> crate::verifier::outcome::Result<Self>;
 }
 
+/// Infer how a claim should be displayed from its CBOR value, for claims without a known
+/// type.
+///
+/// Text in the format `[year]-[month]-[day]` is a date, and byte strings starting with a JPEG,
+/// JPEG 2000 or PNG signature are images. Other byte strings are shown as hex.
+pub fn infer_claim_value(value: &Value) -> ClaimValue {
+    const IMAGE_SIGNATURES: [&[u8]; 3] = [
+        &[0xFF, 0xD8, 0xFF],
+        &[0x00, 0x00, 0x00, 0x0C, 0x6A, 0x50, 0x20, 0x20],
+        &[0x89, 0x50, 0x4E, 0x47],
+    ];
+
+    match value {
+        Value::Text(t) if Date::parse(t, format_description!("[year]-[month]-[day]")).is_ok() => {
+            ClaimValue::Date { value: t.clone() }
+        }
+        Value::Text(t) => ClaimValue::Text { value: t.clone() },
+        Value::Bytes(bytes)
+            if IMAGE_SIGNATURES
+                .iter()
+                .any(|signature| bytes.starts_with(signature)) =>
+        {
+            ClaimValue::Image {
+                value: bytes.clone(),
+            }
+        }
+        Value::Bytes(bytes) => ClaimValue::Text {
+            value: hex::encode(bytes),
+        },
+        Value::Bool(b) => ClaimValue::Boolean { value: *b },
+        Value::Integer(i) => ClaimValue::Number { value: *i as f64 },
+        Value::Float(f) => ClaimValue::Number { value: *f },
+        Value::Map(map) => ClaimValue::MapClaim {
+            value: map
+                .iter()
+                .map(|(k, v)| (cbor_to_string(k), cbor_to_string(v)))
+                .collect(),
+        },
+        v => ClaimValue::Text {
+            value: cbor_to_string(v),
+        },
+    }
+}
+
+fn cbor_to_string(value: &Value) -> String {
+    match value {
+        Value::Text(t) => t.clone(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Bool(b) => b.to_string(),
+        v => format!("{v:?}"),
+    }
+}
+
 pub fn check_validity(validity: &Validity) -> Result<()> {
     let nbf = validity.not_before.to_system_time();
     let exp = validity.not_after.to_system_time();
@@ -115,3 +226,65 @@ pub fn check_validity(validity: &Validity) -> Result<()> {
 
     bail!("certificate is invalid")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_cwt_claim_labels() {
+        let payload = serde_cbor::to_vec(&Value::Map(
+            [
+                (Value::Integer(1), Value::Text("issuer".into())),
+                (Value::Text("name".into()), Value::Text("Jane".into())),
+                (Value::Integer(-65537), Value::Text("schema".into())),
+                (Value::Integer(-70002), Value::Bool(true)),
+            ]
+            .into_iter()
+            .collect(),
+        ))
+        .unwrap();
+        let cwt = serde_cbor::to_vec(&Value::Tag(
+            18,
+            Box::new(Value::Array(vec![
+                Value::Bytes(vec![]),
+                Value::Map(Default::default()),
+                Value::Bytes(payload),
+                Value::Bytes(vec![]),
+            ])),
+        ))
+        .unwrap();
+
+        let mut labels = cwt_claim_labels(&cwt);
+        labels.sort();
+        assert_eq!(labels, vec![-70002, -65537, 1]);
+    }
+
+    #[test]
+    fn infers_claim_display() {
+        assert!(matches!(
+            infer_claim_value(&Value::Text("1990-01-31".into())),
+            ClaimValue::Date { .. }
+        ));
+        assert!(matches!(
+            infer_claim_value(&Value::Text("Jane".into())),
+            ClaimValue::Text { .. }
+        ));
+        assert!(matches!(
+            infer_claim_value(&Value::Bytes(vec![0xFF, 0xD8, 0xFF, 0xE0])),
+            ClaimValue::Image { .. }
+        ));
+        assert!(matches!(
+            infer_claim_value(&Value::Bytes(vec![0x01, 0x02])),
+            ClaimValue::Text { value } if value == "0102"
+        ));
+        assert!(matches!(
+            infer_claim_value(&Value::Bool(true)),
+            ClaimValue::Boolean { value: true }
+        ));
+        assert!(matches!(
+            infer_claim_value(&Value::Integer(42)),
+            ClaimValue::Number { value } if value == 42.0
+        ));
+    }
+}
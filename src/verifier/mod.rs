@@ -5,7 +5,7 @@ pub mod outcome;
 use std::collections::HashMap;
 
 use crate::verifier::{
-    crypto::{CoseP256Verifier, Crypto},
+    crypto::{CoseEd25519Verifier, CoseP256Verifier, CoseP384Verifier, Crypto, SignatureScheme},
     outcome::{ClaimValue, CredentialInfo, Failure, Outcome, Result},
 };
 use cose_rs::{
@@ -25,9 +25,21 @@ pub trait Credential {
     const TITLE: &'static str;
     const IMAGE: &'static [u8];
 
+    /// CWT labels of the claims [Credential::parse_claims] handles, besides the registered
+    /// CWT claims (`iss`, `sub`, `aud`, `exp`, `nbf`, `iat` and `cti`).
+    ///
+    /// When set, other claims are not passed to `parse_claims` but reported in
+    /// [CredentialInfo::extra], so that claims added by issuers do not fail decoding. When
+    /// `None`, every claim is passed to `parse_claims`.
+    const CLAIM_LABELS: Option<&'static [i32]> = None;
+
     fn parse_claims(claims: ClaimsSet) -> Result<HashMap<String, ClaimValue>>;
 }
 
+/// Labels of the claims registered for CWTs in RFC 8392: `iss`, `sub`, `aud`, `exp`, `nbf`,
+/// `iat` and `cti`.
+const REGISTERED_CWT_CLAIM_LABELS: [i32; 7] = [1, 2, 3, 4, 5, 6, 7];
+
 pub fn retrieve_entry_from_status_list(status_list: String, idx: usize) -> Result<u8, Error> {
     let status_list: JsonStatusList = serde_json::from_str(status_list.as_str())
         .map_err(|_: serde_json::Error| anyhow!("Unable to parse JSON String"))?;
@@ -66,6 +78,18 @@ pub trait Verifiable: Credential {
             v => return Err(Failure::incorrect_credential(Self::SCHEMA, v)),
         }
 
+        let mut extra = HashMap::new();
+        if let Some(known) = Self::CLAIM_LABELS {
+            for label in helpers::cwt_claim_labels(&cwt_bytes) {
+                if REGISTERED_CWT_CLAIM_LABELS.contains(&label) || known.contains(&label) {
+                    continue;
+                }
+                if let Some(value) = claims.remove_i(label) {
+                    extra.insert(label.into(), helpers::infer_claim_value(&value));
+                }
+            }
+        }
+
         let claims = Self::parse_claims(claims)?;
 
         Ok((
@@ -74,6 +98,7 @@ pub trait Verifiable: Credential {
                 title: Self::TITLE.to_string(),
                 image: Self::IMAGE.to_vec(),
                 claims,
+                extra,
             },
         ))
     }
@@ -168,8 +193,12 @@ pub trait Verifiable: Credential {
             .to_der()
             .context("unable to encode signer certificate as der")?;
         let signer_signature = signer_certificate.signature.raw_bytes().to_vec();
-        crypto
-            .p256_verify(
+        let root_scheme =
+            SignatureScheme::from_spki(&root_certificate.tbs_certificate.subject_public_key_info)
+                .context("unsupported root certificate key")?;
+        root_scheme
+            .verify(
+                crypto,
                 root_certificate
                     .to_der()
                     .context("unable to encode root certificate as der")?,
@@ -195,13 +224,42 @@ pub trait Verifiable: Credential {
         }
 
         // Validate that Signer issued CWT.
-        let verifier = CoseP256Verifier {
-            crypto,
-            certificate_der: signer_certificate
-                .to_der()
-                .context("unable to encode signer certificate as der")?,
+        let certificate_der = signer_certificate
+            .to_der()
+            .context("unable to encode signer certificate as der")?;
+        let signer_scheme =
+            SignatureScheme::from_spki(&signer_certificate.tbs_certificate.subject_public_key_info)
+                .context("unsupported signer certificate key")?;
+        signer_scheme
+            .ensure_cose_algorithm(cwt)
+            .context("unsupported CWT signature algorithm")?;
+        let result = match signer_scheme {
+            SignatureScheme::P256 => cwt.verify(
+                &CoseP256Verifier {
+                    crypto,
+                    certificate_der,
+                },
+                None,
+                None,
+            ),
+            SignatureScheme::P384 => cwt.verify(
+                &CoseP384Verifier {
+                    crypto,
+                    certificate_der,
+                },
+                None,
+                None,
+            ),
+            SignatureScheme::Ed25519 => cwt.verify(
+                &CoseEd25519Verifier {
+                    crypto,
+                    certificate_der,
+                },
+                None,
+                None,
+            ),
         };
-        match cwt.verify(&verifier, None, None) {
+        match result {
             VerificationResult::Success => Ok(()),
             VerificationResult::Failure(e) => {
                 bail!("failed to verify the CWT signature: {e}")
@@ -237,3 +295,77 @@ pub trait Verifiable: Credential {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_cbor::Value;
+
+    use super::*;
+
+    struct ExampleCredential;
+
+    impl Credential for ExampleCredential {
+        const SCHEMA: &'static str = "example";
+        const TITLE: &'static str = "Example";
+        const IMAGE: &'static [u8] = &[];
+        const CLAIM_LABELS: Option<&'static [i32]> = Some(&[-70001]);
+
+        fn parse_claims(mut claims: ClaimsSet) -> Result<HashMap<String, ClaimValue>> {
+            assert!(
+                claims.remove_i(-70002).is_none(),
+                "unknown claims are not passed to parse_claims"
+            );
+            let name = claims
+                .remove_i(-70001)
+                .ok_or_else(|| Failure::missing_claim("name"))?;
+
+            Ok(HashMap::from([(
+                "name".to_string(),
+                helpers::infer_claim_value(&name),
+            )]))
+        }
+    }
+
+    impl Verifiable for ExampleCredential {}
+
+    /// Encode a CWT with `claims` as a QR code payload: deflated, then base10 with the `9`
+    /// multibase prefix.
+    fn qr_code_payload(claims: Vec<(Value, Value)>) -> String {
+        let payload = serde_cbor::to_vec(&Value::Map(claims.into_iter().collect())).unwrap();
+        let cwt = serde_cbor::to_vec(&Value::Tag(
+            18,
+            Box::new(Value::Array(vec![
+                Value::Bytes(serde_cbor::to_vec(&Value::Map(Default::default())).unwrap()),
+                Value::Map(Default::default()),
+                Value::Bytes(payload),
+                Value::Bytes(vec![]),
+            ])),
+        ))
+        .unwrap();
+        let compressed = miniz_oxide::deflate::compress_to_vec(&cwt, 6);
+
+        format!("9{}", BigUint::from_bytes_be(&compressed).to_str_radix(10))
+    }
+
+    #[test]
+    fn decode_reports_unknown_claims_as_extra() {
+        let payload = qr_code_payload(vec![
+            (Value::Integer(1), Value::Text("issuer.example".into())),
+            (Value::Integer(-65537), Value::Text("example".into())),
+            (Value::Integer(-70001), Value::Text("Jane".into())),
+            (Value::Integer(-70002), Value::Bool(true)),
+        ]);
+
+        let (_, info) = ExampleCredential.decode(payload).unwrap();
+
+        assert!(matches!(
+            info.claims.get("name"),
+            Some(ClaimValue::Text { value }) if value == "Jane"
+        ));
+        assert_eq!(info.extra.len(), 1);
+        assert!(matches!(
+            info.extra.get(&-70002),
+            Some(ClaimValue::Boolean { value: true })
+        ));
+    }
+}
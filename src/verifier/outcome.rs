@@ -24,6 +24,9 @@ pub struct CredentialInfo {
     pub image: Vec<u8>,
     /// The claims decoded from the credential.
     pub claims: HashMap<String, ClaimValue>,
+    /// Claims the credential type does not know, keyed by their CWT label, e.g. claims added
+    /// by the issuer after the type was defined.
+    pub extra: HashMap<i64, ClaimValue>,
 }
 
 /// Credential claim values.
@@ -33,6 +36,12 @@ pub enum ClaimValue {
     Text { value: String },
     /// A date claim in the format `[year]-[month]-[day]`.
     Date { value: String },
+    /// An image claim, such as a portrait, as encoded image bytes (e.g. JPEG or PNG).
+    Image { value: Vec<u8> },
+    /// A boolean claim, e.g. `age_over_18`.
+    Boolean { value: bool },
+    /// A numeric claim.
+    Number { value: f64 },
     /// MapArray
     MapClaim { value: HashMap<String, String> },
 }
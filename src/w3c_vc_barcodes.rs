@@ -1,5 +1,6 @@
-use std::io::Cursor;
+use std::{collections::HashMap, io::Cursor};
 
+use json_syntax::{Parse, Print};
 use ssi::{
     dids::{AnyDidMethod, DIDResolver},
     json_ld::iref::Uri,
@@ -10,6 +11,9 @@ use ssi::{
         client::{MaybeCached, ProviderError, TypedStatusMapProvider},
     },
 };
+
+use crate::oid4vci::context_loader_from_map;
+
 use w3c_vc_barcodes::{
     aamva::{
         dlid::{pdf_417, DlSubfile},
@@ -119,6 +123,58 @@ pub async fn verify_vcb_qrcode_against_mrz(
         .map_err(|_| VCBVerificationError::Verification)
 }
 
+#[derive(thiserror::Error, uniffi::Error, Debug)]
+pub enum CborLdError {
+    #[error("invalid context map: {0}")]
+    ContextMap(String),
+    #[error("invalid JSON-LD document: {0}")]
+    Json(String),
+    #[error("CBOR-LD compression failed: {0}")]
+    Compression(String),
+    #[error("CBOR-LD decompression failed: {0}")]
+    Decompression(String),
+}
+
+/// Compress a JSON-LD document into CBOR-LD bytes.
+///
+/// `contexts` maps context URLs to their JSON-LD definitions and is used in
+/// addition to the contexts bundled with the SDK. Terms are compressed with
+/// the default CBOR-LD registry.
+#[uniffi::export]
+pub async fn cbor_ld_compress(
+    json_ld: String,
+    contexts: HashMap<String, String>,
+) -> Result<Vec<u8>, CborLdError> {
+    let loader =
+        context_loader_from_map(contexts).map_err(|e| CborLdError::ContextMap(e.to_string()))?;
+    let (json, _) =
+        json_syntax::Value::parse_str(&json_ld).map_err(|e| CborLdError::Json(e.to_string()))?;
+
+    cbor_ld::compress_to_bytes(&json, loader, cbor_ld::CompressionOptions::default())
+        .await
+        .map_err(|e| CborLdError::Compression(e.to_string()))
+}
+
+/// Decompress CBOR-LD bytes into a JSON-LD document.
+///
+/// This is the inverse of [cbor_ld_compress] and must be given the same
+/// `contexts` the document was compressed with.
+#[uniffi::export]
+pub async fn cbor_ld_decompress(
+    bytes: Vec<u8>,
+    contexts: HashMap<String, String>,
+) -> Result<String, CborLdError> {
+    let loader =
+        context_loader_from_map(contexts).map_err(|e| CborLdError::ContextMap(e.to_string()))?;
+
+    let json =
+        cbor_ld::decompress_from_bytes(&bytes, loader, cbor_ld::DecompressionOptions::default())
+            .await
+            .map_err(|e| CborLdError::Decompression(e.to_string()))?;
+
+    Ok(json.compact_print().to_string())
+}
+
 pub struct StatusLists;
 
 impl TypedStatusMapProvider<Uri, BitstringStatusListCredential> for StatusLists {
@@ -141,6 +197,29 @@ mod tests {
         verify_pdf417_barcode(pdf417.into()).await.unwrap()
     }
 
+    #[tokio::test]
+    async fn cbor_ld_round_trip() {
+        let json_ld = serde_json::json!({
+            "@context": ["https://www.w3.org/ns/credentials/v2"],
+            "type": ["VerifiableCredential"],
+            "issuer": "did:example:issuer",
+            "credentialSubject": { "id": "did:example:subject" }
+        });
+
+        let compressed = cbor_ld_compress(json_ld.to_string(), HashMap::new())
+            .await
+            .unwrap();
+        assert!(compressed.len() < json_ld.to_string().len());
+
+        let decompressed = cbor_ld_decompress(compressed, HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&decompressed).unwrap(),
+            json_ld
+        );
+    }
+
     #[tokio::test]
     async fn verify_vcb_employment_authorization() {
         let mrz = include_str!("../tests/res/mrz-vcb");